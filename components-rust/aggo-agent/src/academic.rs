@@ -0,0 +1,287 @@
+//! Academic research: papers from arXiv (Atom API) and Semantic Scholar (Graph API), merged
+//! into one list with abstracts and DOIs, then written up as a literature review.
+//!
+//! Both APIs are called directly over `golem_wasi_http`. Semantic Scholar works without a key
+//! at a low shared rate limit; `SEMANTIC_SCHOLAR_API_KEY` raises it. `ACADEMIC_MAX_PAPERS`
+//! caps the papers taken from each source (default 10, at most 25).
+
+use std::time::Duration;
+
+use crate::config;
+use crate::http_cache::ResponseCache;
+use crate::{http_util, site, usage, SearchResult};
+
+const ARXIV_API: &str = "https://export.arxiv.org/api/query";
+const SEMANTIC_SCHOLAR_API: &str = "https://api.semanticscholar.org/graph/v1/paper/search";
+const SEMANTIC_SCHOLAR_FIELDS: &str = "title,abstract,year,authors,venue,externalIds,url,citationCount";
+const DEFAULT_MAX_PAPERS: u32 = 10;
+const MAX_PAPERS: u32 = 25;
+/// Authors named before "et al.".
+const MAX_AUTHORS: usize = 3;
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcademicSource {
+    Arxiv,
+    SemanticScholar,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Paper {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub year: Option<u32>,
+    pub venue: Option<String>,
+    pub abstract_text: Option<String>,
+    pub doi: Option<String>,
+    /// arXiv identifier without its version suffix, e.g. `2101.00001`.
+    pub arxiv_id: Option<String>,
+    pub url: String,
+    pub citation_count: Option<u64>,
+}
+
+impl AcademicSource {
+    pub const ALL: [Self; 2] = [Self::Arxiv, Self::SemanticScholar];
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Arxiv => "arXiv",
+            Self::SemanticScholar => "Semantic Scholar",
+        }
+    }
+
+    /// Papers matching `topic`, most relevant first.
+    pub fn search(
+        &self,
+        topic: &str,
+        cache: &ResponseCache,
+        now: u64,
+        run_usage: &mut usage::RunUsage,
+    ) -> Result<Vec<Paper>, String> {
+        run_usage.search_calls += 1;
+        match self {
+            Self::Arxiv => arxiv_search(topic, max_papers(), cache, now),
+            Self::SemanticScholar => semantic_scholar_search(topic, max_papers(), cache, now),
+        }
+    }
+}
+
+pub fn max_papers() -> u32 {
+    config::env_parse::<u32>("ACADEMIC_MAX_PAPERS")
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_PAPERS)
+        .min(MAX_PAPERS)
+}
+
+/// Interleaves the sources' lists (each in relevance order) and folds duplicates, matched by
+/// DOI, arXiv ID or title, into one paper that keeps the details either source had. Papers
+/// without an abstract are dropped: there would be nothing to review.
+pub fn merge(lists: Vec<Vec<Paper>>) -> Vec<Paper> {
+    let longest = lists.iter().map(Vec::len).max().unwrap_or(0);
+    let mut merged: Vec<Paper> = Vec::new();
+    for rank in 0..longest {
+        for paper in lists.iter().filter_map(|list| list.get(rank)) {
+            match merged.iter_mut().find(|known| same_paper(known, paper)) {
+                Some(known) => fill_from(known, paper),
+                None => merged.push(paper.clone()),
+            }
+        }
+    }
+    merged.retain(|paper| paper.abstract_text.is_some());
+    merged
+}
+
+/// A paper as a prompt source: the DOI link where there is one, with authors, year, venue,
+/// DOI and abstract in the snippet.
+pub fn to_result(paper: &Paper) -> SearchResult {
+    let mut authors = paper.authors.iter().take(MAX_AUTHORS).cloned().collect::<Vec<_>>().join(", ");
+    if paper.authors.len() > MAX_AUTHORS {
+        authors.push_str(" et al.");
+    }
+    let mut snippet = if authors.is_empty() { "Unknown authors".to_string() } else { authors };
+    match paper.year {
+        Some(year) => snippet.push_str(&format!(" ({year})")),
+        None => snippet.push_str(" (n.d.)"),
+    }
+    snippet.push('.');
+    if let Some(venue) = &paper.venue {
+        snippet.push_str(&format!(" {venue}."));
+    }
+    if let Some(doi) = &paper.doi {
+        snippet.push_str(&format!(" DOI: {doi}."));
+    }
+    if let Some(arxiv_id) = &paper.arxiv_id {
+        snippet.push_str(&format!(" arXiv: {arxiv_id}."));
+    }
+    if let Some(count) = paper.citation_count {
+        snippet.push_str(&format!(" Cited by {count}."));
+    }
+    snippet.push_str(&format!(" Abstract: {}", paper.abstract_text.as_deref().unwrap_or_default()));
+
+    SearchResult {
+        url: paper
+            .doi
+            .as_ref()
+            .map(|doi| format!("https://doi.org/{doi}"))
+            .unwrap_or_else(|| paper.url.clone()),
+        title: paper.title.clone(),
+        snippet,
+    }
+}
+
+pub fn academic_prompt(topic: &str, format_instructions: &str, papers: &[SearchResult]) -> String {
+    let papers_json = serde_json::to_string(papers).unwrap_or_else(|_| "[]".to_string());
+    format!(
+        "I'm reviewing the academic literature on \"{topic}\".\n\
+         Below are papers in json format, each with its authors, year, venue, DOI and abstract in the snippet. \
+         Write a literature review: group the papers by theme or approach, compare their methods and findings, \
+         and point out where they agree, where they conflict and what remains open. \
+         Cite each paper by first author and year with its DOI (or its URL when it has no DOI), \
+         and end with a references list in the same form. Use only these papers and their abstracts; \
+         never invent papers, results or DOIs.\n\
+         {format_instructions}\n\
+         Papers: {papers_json}"
+    )
+}
+
+fn arxiv_search(topic: &str, limit: u32, cache: &ResponseCache, now: u64) -> Result<Vec<Paper>, String> {
+    let query = topic
+        .split_whitespace()
+        .map(|term| format!("all:{}", term.trim_matches('"')))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let key = format!("{ARXIV_API}?search_query={query}&max_results={limit}");
+    let request = || {
+        golem_wasi_http::Client::new()
+            .get(ARXIV_API)
+            .query(&[
+                ("search_query", query.as_str()),
+                ("start", "0"),
+                ("max_results", limit.to_string().as_str()),
+                ("sortBy", "relevance"),
+            ])
+            .timeout(http_util::timeout(TIMEOUT))
+    };
+    let (status, feed) = cache
+        .send(&key, request, now)
+        .map_err(|err| format!("arXiv search failed: {err}"))?;
+    if !(200..300).contains(&status) {
+        return Err(format!("arXiv search failed: HTTP {status}"));
+    }
+
+    Ok(site::elements(&feed, "entry")
+        .into_iter()
+        .filter_map(|entry| {
+            let url = site::text_of(entry, "id")?;
+            Some(Paper {
+                title: collapse(&site::text_of(entry, "title")?),
+                authors: site::elements(entry, "author")
+                    .into_iter()
+                    .filter_map(|author| site::text_of(author, "name"))
+                    .collect(),
+                year: site::text_of(entry, "published").and_then(|date| date.get(..4)?.parse().ok()),
+                venue: site::text_of(entry, "arxiv:journal_ref").map(|venue| collapse(&venue)),
+                abstract_text: site::text_of(entry, "summary").map(|summary| collapse(&summary)),
+                doi: site::text_of(entry, "arxiv:doi").map(|doi| doi.trim().to_ascii_lowercase()),
+                arxiv_id: url.rsplit_once("/abs/").map(|(_, id)| unversioned(id).to_string()),
+                url,
+                citation_count: None,
+            })
+        })
+        .collect())
+}
+
+fn semantic_scholar_search(topic: &str, limit: u32, cache: &ResponseCache, now: u64) -> Result<Vec<Paper>, String> {
+    let key = format!("{SEMANTIC_SCHOLAR_API}?query={topic}&limit={limit}");
+    let api_key = std::env::var("SEMANTIC_SCHOLAR_API_KEY").ok().filter(|k| !k.trim().is_empty());
+    let request = || {
+        let request = golem_wasi_http::Client::new()
+            .get(SEMANTIC_SCHOLAR_API)
+            .query(&[
+                ("query", topic),
+                ("limit", limit.to_string().as_str()),
+                ("fields", SEMANTIC_SCHOLAR_FIELDS),
+            ])
+            .timeout(http_util::timeout(TIMEOUT));
+        match &api_key {
+            Some(key) => request.header("x-api-key", key.trim()),
+            None => request,
+        }
+    };
+    let (status, body) = cache
+        .send(&key, request, now)
+        .map_err(|err| format!("Semantic Scholar search failed: {err}"))?;
+    if !(200..300).contains(&status) {
+        return Err(format!("Semantic Scholar search failed: HTTP {status}"));
+    }
+    let body: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|err| format!("Semantic Scholar search returned invalid JSON: {err}"))?;
+
+    Ok(body["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let title = collapse(item["title"].as_str()?);
+            let paper_id = item["paperId"].as_str().unwrap_or_default();
+            Some(Paper {
+                title,
+                authors: item["authors"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|author| author["name"].as_str().map(str::to_string))
+                    .collect(),
+                year: item["year"].as_u64().map(|year| year as u32),
+                venue: item["venue"].as_str().filter(|v| !v.trim().is_empty()).map(str::to_string),
+                abstract_text: item["abstract"].as_str().filter(|a| !a.trim().is_empty()).map(collapse),
+                doi: item["externalIds"]["DOI"].as_str().map(|doi| doi.trim().to_ascii_lowercase()),
+                arxiv_id: item["externalIds"]["ArXiv"].as_str().map(|id| unversioned(id).to_string()),
+                url: item["url"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("https://www.semanticscholar.org/paper/{paper_id}")),
+                citation_count: item["citationCount"].as_u64(),
+            })
+        })
+        .collect())
+}
+
+fn same_paper(a: &Paper, b: &Paper) -> bool {
+    matches!((&a.doi, &b.doi), (Some(x), Some(y)) if x == y)
+        || matches!((&a.arxiv_id, &b.arxiv_id), (Some(x), Some(y)) if x == y)
+        || (!title_key(&a.title).is_empty() && title_key(&a.title) == title_key(&b.title))
+}
+
+/// `2101.00001v2` -> `2101.00001`.
+fn unversioned(arxiv_id: &str) -> &str {
+    match arxiv_id.rsplit_once('v') {
+        Some((id, version)) if !version.is_empty() && version.chars().all(|c| c.is_ascii_digit()) => id,
+        _ => arxiv_id,
+    }
+}
+
+fn fill_from(known: &mut Paper, other: &Paper) {
+    if known.authors.is_empty() {
+        known.authors = other.authors.clone();
+    }
+    known.year = known.year.or(other.year);
+    known.venue = known.venue.take().or_else(|| other.venue.clone());
+    known.abstract_text = known.abstract_text.take().or_else(|| other.abstract_text.clone());
+    known.doi = known.doi.take().or_else(|| other.doi.clone());
+    known.arxiv_id = known.arxiv_id.take().or_else(|| other.arxiv_id.clone());
+    known.citation_count = known.citation_count.or(other.citation_count);
+}
+
+/// Lowercased alphanumerics only, so punctuation and spacing differences still match.
+fn title_key(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
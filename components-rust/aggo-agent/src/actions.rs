@@ -0,0 +1,87 @@
+//! Recommended next steps for a caller-described persona or goal, kept separate from the
+//! descriptive report.
+
+use golem_rust::Schema;
+
+use crate::citations::Citation;
+use crate::prompt;
+
+const MAX_ACTION_ITEMS: usize = 10;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub enum ActionPriority {
+    High,
+    Medium,
+    Low,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ActionItem {
+    pub action: String,
+    /// Why this step matters for the persona, grounded in the report.
+    pub rationale: String,
+    pub priority: ActionPriority,
+    /// Report sources the recommendation relies on.
+    pub sources: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ExtractedActions {
+    actions: Vec<ExtractedAction>,
+}
+
+#[derive(serde::Deserialize)]
+struct ExtractedAction {
+    action: String,
+    #[serde(default)]
+    rationale: String,
+    #[serde(default)]
+    priority: String,
+    #[serde(default)]
+    sources: Vec<usize>,
+}
+
+pub fn actions_prompt(persona: &str, report: &str, sources: &[Citation]) -> String {
+    let sources = sources
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{}. {}", i + 1, c.url))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "You are advising the following reader: {persona}\n\
+         Based only on the research report below, recommend 3 to 7 concrete next steps for this reader, most important first.\n\
+         Each step needs a short rationale tied to the report, a priority (high, medium or low) and the numbers of the sources it relies on.\n\
+         Reply with JSON only, in this shape: {{\"actions\": [{{\"action\": \"...\", \"rationale\": \"...\", \"priority\": \"high\", \"sources\": [1]}}]}}\n\n\
+         Sources:\n{sources}\n\n\
+         Report:\n{report}"
+    )
+}
+
+pub fn parse_actions(reply: &str, sources: &[Citation]) -> Result<Vec<ActionItem>, String> {
+    let extracted: ExtractedActions = serde_json::from_str(prompt::json_object(reply))
+        .map_err(|err| format!("The model did not return valid action-item JSON: {err}"))?;
+
+    Ok(extracted
+        .actions
+        .into_iter()
+        .filter(|a| !a.action.trim().is_empty())
+        .take(MAX_ACTION_ITEMS)
+        .map(|a| ActionItem {
+            action: a.action.trim().to_string(),
+            rationale: a.rationale.trim().to_string(),
+            priority: match a.priority.trim().to_ascii_lowercase().as_str() {
+                "high" => ActionPriority::High,
+                "low" => ActionPriority::Low,
+                _ => ActionPriority::Medium,
+            },
+            sources: a
+                .sources
+                .iter()
+                .filter_map(|n| n.checked_sub(1).and_then(|i| sources.get(i)))
+                .map(|c| c.url.clone())
+                .collect(),
+        })
+        .collect())
+}
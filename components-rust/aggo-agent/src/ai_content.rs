@@ -0,0 +1,121 @@
+//! Flags web results that read like AI-generated content-farm pages and ranks them after the
+//! rest, so they only reach the prompt when the context has room to spare.
+//!
+//! The check is a heuristic over the title and the snippet or page text; no LLM call. Signals:
+//! stock phrases that generated filler leans on, listicle/guide title templates, and (for page
+//! text) sentences of unusually uniform length. Corpus documents and connector results are
+//! the organisation's own and are not checked. Set `AI_CONTENT_DETECTION=false` to disable.
+
+use golem_rust::Schema;
+
+use crate::{compress, SearchResult};
+
+/// Phrases common in generated filler; each distinct one found adds `PHRASE_WEIGHT`.
+const STOCK_PHRASES: &[&str] = &[
+    "as an ai language model",
+    "in today's fast-paced world",
+    "in today's digital age",
+    "in the ever-evolving",
+    "ever-changing landscape",
+    "it's important to note that",
+    "it is important to note that",
+    "delve into",
+    "dive into the world of",
+    "unlock the power",
+    "unleash the",
+    "a testament to",
+    "rich tapestry",
+    "navigating the complexities",
+    "game-changer",
+    "in conclusion,",
+    "whether you're a beginner or",
+    "look no further",
+    "without further ado",
+    "embark on a journey",
+];
+const TITLE_TEMPLATES: &[&str] = &[
+    "ultimate guide",
+    "comprehensive guide",
+    "everything you need to know",
+    "a complete guide",
+    "you need to know in",
+];
+const PHRASE_WEIGHT: f64 = 0.4;
+const TITLE_WEIGHT: f64 = 0.3;
+const UNIFORM_WEIGHT: f64 = 0.4;
+/// Results at or above this score are flagged.
+const THRESHOLD: f64 = 0.8;
+/// Sentence-length uniformity is only judged on this many sentences or more.
+const MIN_SENTENCES: usize = 8;
+/// Coefficient of variation of sentence lengths below which text counts as uniform; human
+/// prose usually varies far more.
+const UNIFORM_VARIATION: f64 = 0.3;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct AiContentFlag {
+    pub url: String,
+    /// Heuristic score; flagged from 0.8.
+    pub score: f64,
+    /// What triggered the flag, e.g. `phrase: "delve into"` or `uniform sentence lengths`.
+    pub signals: Vec<String>,
+}
+
+pub fn enabled() -> bool {
+    std::env::var("AI_CONTENT_DETECTION")
+        .map(|v| !matches!(v.trim().to_ascii_lowercase().as_str(), "false" | "0" | "off" | "no"))
+        .unwrap_or(true)
+}
+
+/// Flags for the results that look generated, in result order.
+pub fn flag(results: &[SearchResult]) -> Vec<AiContentFlag> {
+    results.iter().filter_map(assess).collect()
+}
+
+/// Moves flagged results after the others, keeping the order within each group.
+pub fn demote(results: &mut [SearchResult], flags: &[AiContentFlag]) {
+    if flags.is_empty() {
+        return;
+    }
+    results.sort_by_key(|result| flags.iter().any(|flag| flag.url == result.url));
+}
+
+fn assess(result: &SearchResult) -> Option<AiContentFlag> {
+    let text = result.snippet.to_lowercase();
+    let title = result.title.to_lowercase();
+    let mut score = 0.0;
+    let mut signals = Vec::new();
+
+    for phrase in STOCK_PHRASES {
+        if text.contains(phrase) || title.contains(phrase) {
+            score += PHRASE_WEIGHT;
+            signals.push(format!("phrase: {phrase:?}"));
+        }
+    }
+    if let Some(template) = TITLE_TEMPLATES.iter().find(|t| title.contains(*t)) {
+        score += TITLE_WEIGHT;
+        signals.push(format!("title: {template:?}"));
+    }
+    if uniform_sentences(&result.snippet) {
+        score += UNIFORM_WEIGHT;
+        signals.push("uniform sentence lengths".to_string());
+    }
+
+    (score >= THRESHOLD).then(|| AiContentFlag {
+        url: result.url.clone(),
+        score: (score * 100.0).round() / 100.0,
+        signals,
+    })
+}
+
+fn uniform_sentences(text: &str) -> bool {
+    let lengths: Vec<f64> = compress::split_sentences(text)
+        .iter()
+        .map(|sentence| compress::words(sentence).count() as f64)
+        .collect();
+    if lengths.len() < MIN_SENTENCES {
+        return false;
+    }
+    let mean = lengths.iter().sum::<f64>() / lengths.len() as f64;
+    let variance = lengths.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / lengths.len() as f64;
+    mean > 0.0 && variance.sqrt() / mean < UNIFORM_VARIATION
+}
@@ -0,0 +1,108 @@
+//! Wayback Machine fallback for sources that are gone or unreachable.
+//!
+//! When a page fetch fails, the closest archive.org snapshot is fetched instead and its
+//! capture date travels with the page, so citations can say which version was read.
+//! Set `WAYBACK_FALLBACK=false` to disable. Private sources (domains with configured fetch
+//! credentials) are never looked up.
+
+use std::time::Duration;
+
+use golem_rust::Schema;
+
+use crate::credentials;
+use crate::fetch::{self, FetchedPage};
+use crate::http_util;
+
+const AVAILABILITY_API: &str = "https://archive.org/wayback/available";
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ArchivedSnapshot {
+    /// The Wayback Machine URL of the snapshot.
+    pub url: String,
+    /// Capture date, `YYYY-MM-DD` (UTC).
+    pub captured_on: String,
+}
+
+#[derive(serde::Deserialize)]
+struct Availability {
+    #[serde(default)]
+    archived_snapshots: Snapshots,
+}
+
+#[derive(Default, serde::Deserialize)]
+struct Snapshots {
+    closest: Option<Closest>,
+}
+
+#[derive(serde::Deserialize)]
+struct Closest {
+    #[serde(default)]
+    available: bool,
+    url: String,
+    timestamp: String,
+}
+
+pub fn enabled() -> bool {
+    std::env::var("WAYBACK_FALLBACK")
+        .map(|v| !matches!(v.trim().to_ascii_lowercase().as_str(), "false" | "0" | "off" | "no"))
+        .unwrap_or(true)
+}
+
+/// Fetches the live page, falling back to its latest archived snapshot when the fetch fails.
+pub fn fetch_page_or_snapshot(url: &str, timeout: Duration) -> Result<FetchedPage, String> {
+    let err = match fetch::fetch_page(url, timeout) {
+        Ok(page) => return Ok(page),
+        Err(err) => err,
+    };
+    let web_url = url.trim().starts_with("http://") || url.trim().starts_with("https://");
+    if !enabled() || !web_url || credentials::is_private(url) {
+        return Err(err);
+    }
+
+    let (raw_url, snapshot) = match closest_snapshot(url.trim(), timeout) {
+        Ok(Some(found)) => found,
+        Ok(None) => return Err(format!("{err} (no Wayback Machine snapshot either)")),
+        Err(archive_err) => return Err(format!("{err} (Wayback Machine lookup failed: {archive_err})")),
+    };
+    log::info!("Using Wayback Machine snapshot from {} for {url}", snapshot.captured_on);
+
+    let mut page = fetch::fetch_page(&raw_url, timeout)
+        .map_err(|archive_err| format!("{err} (snapshot fetch failed: {archive_err})"))?;
+    page.url = url.trim().to_string();
+    // Re-extract under the original URL so its domain's extraction rules apply.
+    if let Some(html) = &page.html {
+        page.text = fetch::html_to_text(&page.url, html).1;
+    }
+    page.snapshot = Some(snapshot);
+    Ok(page)
+}
+
+/// The raw-content URL (without the Wayback toolbar) and the public record of the closest
+/// snapshot, if archive.org has one.
+fn closest_snapshot(url: &str, timeout: Duration) -> Result<Option<(String, ArchivedSnapshot)>, String> {
+    let response = http_util::send(|| {
+        golem_wasi_http::Client::new()
+            .get(AVAILABILITY_API)
+            .query(&[("url", url)])
+            .timeout(timeout)
+    })?;
+    if !response.is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let availability: Availability = response.json().map_err(|err| err.to_string())?;
+
+    let Some(closest) = availability.archived_snapshots.closest.filter(|c| c.available) else {
+        return Ok(None);
+    };
+    let timestamp = closest.timestamp.trim();
+    if timestamp.len() < 8 || !timestamp.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let raw_url = format!("https://web.archive.org/web/{timestamp}id_/{url}");
+    let snapshot = ArchivedSnapshot {
+        url: closest.url.replacen("http://", "https://", 1),
+        captured_on: format!("{}-{}-{}", &timestamp[..4], &timestamp[4..6], &timestamp[6..8]),
+    };
+    Ok(Some((raw_url, snapshot)))
+}
@@ -0,0 +1,180 @@
+//! Monthly spend limits per workspace, enforced against the usage ledger's cost estimates.
+//!
+//! Defaults come from `DEFAULT_SOFT_SPEND_LIMIT_USD` / `DEFAULT_HARD_SPEND_LIMIT_USD`;
+//! `set_spend_limits` overrides them per workspace, limit by limit: a limit a workspace leaves
+//! unset keeps the default. Crossing the soft limit adds a warning
+//! to responses; crossing the hard limit blocks research until the month rolls over or an
+//! operator overrides it for the current period.
+
+use golem_rust::Schema;
+
+use crate::config;
+
+#[derive(Clone, Debug, Default, Schema, serde::Serialize, serde::Deserialize)]
+pub struct SpendLimits {
+    pub soft_limit_usd: Option<f64>,
+    pub hard_limit_usd: Option<f64>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct BudgetExceeded {
+    pub workspace: String,
+    pub period: String,
+    pub spent_usd: f64,
+    pub hard_limit_usd: f64,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct BudgetStatus {
+    pub workspace: String,
+    pub period: String,
+    pub spent_usd: f64,
+    pub limits: SpendLimits,
+    /// Whether the hard limit is overridden for this period.
+    pub override_active: bool,
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct BudgetBook {
+    limits: Vec<(String, SpendLimits)>,
+    /// (workspace, period) pairs whose hard limit is lifted.
+    overrides: Vec<(String, String)>,
+}
+
+impl SpendLimits {
+    fn from_env() -> Self {
+        Self {
+            soft_limit_usd: env_limit("DEFAULT_SOFT_SPEND_LIMIT_USD"),
+            hard_limit_usd: env_limit("DEFAULT_HARD_SPEND_LIMIT_USD"),
+        }
+    }
+
+    /// These limits, with each unset one taken from `defaults`.
+    fn or(&self, defaults: SpendLimits) -> SpendLimits {
+        SpendLimits {
+            soft_limit_usd: self.soft_limit_usd.or(defaults.soft_limit_usd),
+            hard_limit_usd: self.hard_limit_usd.or(defaults.hard_limit_usd),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, limit) in [("soft", self.soft_limit_usd), ("hard", self.hard_limit_usd)] {
+            if limit.is_some_and(|l| !l.is_finite() || l < 0.0) {
+                return Err(format!("The {name} spend limit must be a non-negative number"));
+            }
+        }
+        if let (Some(soft), Some(hard)) = (self.soft_limit_usd, self.hard_limit_usd) {
+            if soft > hard {
+                return Err("The soft spend limit must not exceed the hard limit".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BudgetBook {
+    pub fn set_limits(&mut self, workspace: &str, limits: SpendLimits) -> Result<(), String> {
+        limits.validate()?;
+        limits.or(SpendLimits::from_env()).validate()?;
+        match self.limits.iter_mut().find(|(ws, _)| ws == workspace) {
+            Some((_, existing)) => *existing = limits,
+            None => self.limits.push((workspace.to_string(), limits)),
+        }
+        Ok(())
+    }
+
+    pub fn set_override(&mut self, workspace: &str, period: &str, active: bool) {
+        self.overrides.retain(|(ws, p)| !(ws == workspace && p == period));
+        if active {
+            self.overrides.push((workspace.to_string(), period.to_string()));
+        }
+    }
+
+    pub fn status(&self, workspace: &str, period: &str, spent_usd: f64) -> BudgetStatus {
+        BudgetStatus {
+            workspace: workspace.to_string(),
+            period: period.to_string(),
+            spent_usd,
+            limits: self.limits_for(workspace),
+            override_active: self.is_overridden(workspace, period),
+        }
+    }
+
+    /// Blocks when the hard limit is reached; otherwise returns a warning once the soft limit is.
+    pub fn check(&self, workspace: &str, period: &str, spent_usd: f64) -> Result<Option<String>, BudgetExceeded> {
+        let limits = self.limits_for(workspace);
+
+        if let Some(hard) = limits.hard_limit_usd {
+            if spent_usd >= hard && !self.is_overridden(workspace, period) {
+                return Err(BudgetExceeded {
+                    workspace: workspace.to_string(),
+                    period: period.to_string(),
+                    spent_usd,
+                    hard_limit_usd: hard,
+                });
+            }
+        }
+
+        Ok(limits.soft_limit_usd.filter(|soft| spent_usd >= *soft).map(|soft| {
+            format!(
+                "Budget warning: workspace {workspace:?} has spent ${spent_usd:.2} of its ${soft:.2} soft limit for {period}."
+            )
+        }))
+    }
+
+    fn limits_for(&self, workspace: &str) -> SpendLimits {
+        self.limits
+            .iter()
+            .find(|(ws, _)| ws == workspace)
+            .map(|(_, limits)| limits.or(SpendLimits::from_env()))
+            .unwrap_or_else(SpendLimits::from_env)
+    }
+
+    fn is_overridden(&self, workspace: &str, period: &str) -> bool {
+        self.overrides.iter().any(|(ws, p)| ws == workspace && p == period)
+    }
+}
+
+fn env_limit(var: &str) -> Option<f64> {
+    config::env_parse::<f64>(var).filter(|limit| limit.is_finite() && *limit >= 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(soft: Option<f64>, hard: Option<f64>) -> SpendLimits {
+        SpendLimits {
+            soft_limit_usd: soft,
+            hard_limit_usd: hard,
+        }
+    }
+
+    #[test]
+    fn validate_accepts_unset_and_ordered_limits() {
+        assert!(limits(None, None).validate().is_ok());
+        assert!(limits(Some(0.0), None).validate().is_ok());
+        assert!(limits(None, Some(10.0)).validate().is_ok());
+        assert!(limits(Some(5.0), Some(10.0)).validate().is_ok());
+        assert!(limits(Some(10.0), Some(10.0)).validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_bad_numbers_and_a_soft_limit_over_the_hard_one() {
+        assert!(limits(Some(-1.0), None).validate().is_err());
+        assert!(limits(None, Some(f64::NAN)).validate().is_err());
+        assert!(limits(Some(f64::INFINITY), None).validate().is_err());
+        assert!(limits(Some(10.0), Some(5.0)).validate().is_err());
+    }
+
+    #[test]
+    fn or_fills_each_unset_limit_from_the_defaults() {
+        let merged = limits(Some(20.0), None).or(limits(Some(5.0), Some(10.0)));
+        assert_eq!((merged.soft_limit_usd, merged.hard_limit_usd), (Some(20.0), Some(10.0)));
+        // Valid alone, but not once merged with the default hard limit.
+        assert!(merged.validate().is_err());
+
+        let merged = limits(None, None).or(limits(None, Some(10.0)));
+        assert_eq!((merged.soft_limit_usd, merged.hard_limit_usd), (None, Some(10.0)));
+    }
+}
@@ -0,0 +1,298 @@
+//! Report cache with stale-while-revalidate semantics for repeated topics.
+//!
+//! `research_with_options` (and `research_news`) keep their last report per workspace, topic and
+//! options. Within `REPORT_CACHE_FRESH_SECONDS` a repeat request gets the cached report at once.
+//! For `REPORT_CACHE_STALE_SECONDS` after that it still does, but the agent refreshes the report
+//! in a background invocation; older entries are researched again in the request. Both default
+//! to 0, which turns the cache off. `REPORT_CACHE_MAX_ENTRIES` (default 100) bounds it, oldest
+//! first out. Every response carries `freshness` so callers can tell a cached report's age.
+
+use golem_rust::Schema;
+
+use crate::{config, ResearchOptions, ResearchReport};
+
+const DEFAULT_MAX_ENTRIES: usize = 100;
+
+#[derive(Clone, Debug, Default, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ReportFreshness {
+    /// When the report was researched (unix seconds).
+    pub generated_at: u64,
+    pub age_seconds: u64,
+    /// Served from the cache rather than researched for this request.
+    pub cached: bool,
+    /// A background refresh of this cached report is running.
+    pub refreshing: bool,
+}
+
+/// What `clear_cache` emptied, in entries per cache.
+#[derive(Clone, Debug, Default, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ClearedCaches {
+    pub search_results: u32,
+    pub llm_responses: u32,
+    pub reports: u32,
+    pub pages: u32,
+    pub http_responses: u32,
+}
+
+pub struct CachePolicy {
+    fresh_seconds: u64,
+    stale_seconds: u64,
+    max_entries: usize,
+}
+
+impl CachePolicy {
+    pub fn from_env() -> Self {
+        Self {
+            fresh_seconds: config::env_parse::<u64>("REPORT_CACHE_FRESH_SECONDS").unwrap_or(0),
+            stale_seconds: config::env_parse::<u64>("REPORT_CACHE_STALE_SECONDS").unwrap_or(0),
+            max_entries: config::env_parse::<u64>("REPORT_CACHE_MAX_ENTRIES").map_or(DEFAULT_MAX_ENTRIES, |n| n as usize),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.fresh_seconds + self.stale_seconds > 0 && self.max_entries > 0
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CacheEntry {
+    pub key: String,
+    pub workspace: String,
+    pub topic: String,
+    pub options: ResearchOptions,
+    pub report: ResearchReport,
+    pub generated_at: u64,
+    pub refreshing: bool,
+}
+
+/// How a cached report may be used.
+pub enum Lookup {
+    /// Serve it as is.
+    Fresh(ResearchReport),
+    /// Serve it and start a background refresh unless one is running.
+    Stale(ResearchReport),
+    Miss,
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct ReportCache {
+    entries: Vec<CacheEntry>,
+}
+
+/// Workspace, case-folded topic and the options that shape the report.
+pub fn key(workspace: &str, topic: &str, options: &ResearchOptions) -> String {
+    // Priority decides whether a request is shed, fields which parts are returned and
+    // bypass_cache whether caches are read, not what is researched.
+    let options = ResearchOptions {
+        priority: None,
+        fields: None,
+        bypass_cache: None,
+        ..options.clone()
+    };
+    format!(
+        "{workspace}\n{}\n{}",
+        topic.trim().to_lowercase(),
+        serde_json::to_string(&options).unwrap_or_default()
+    )
+}
+
+impl ReportCache {
+    pub fn lookup(&self, key: &str, now: u64, policy: &CachePolicy) -> Lookup {
+        if !policy.enabled() {
+            return Lookup::Miss;
+        }
+        let Some(entry) = self.entries.iter().find(|e| e.key == key) else {
+            return Lookup::Miss;
+        };
+        let age = now.saturating_sub(entry.generated_at);
+        let mut report = entry.report.clone();
+        report.freshness = ReportFreshness {
+            generated_at: entry.generated_at,
+            age_seconds: age,
+            cached: true,
+            refreshing: entry.refreshing,
+        };
+        if age < policy.fresh_seconds {
+            Lookup::Fresh(report)
+        } else if age < policy.fresh_seconds + policy.stale_seconds {
+            Lookup::Stale(report)
+        } else {
+            Lookup::Miss
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&CacheEntry> {
+        self.entries.iter().find(|e| e.key == key)
+    }
+
+    /// Marks a background refresh as started; false if one already is.
+    pub fn begin_refresh(&mut self, key: &str) -> bool {
+        match self.entries.iter_mut().find(|e| e.key == key) {
+            Some(entry) if !entry.refreshing => {
+                entry.refreshing = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn end_refresh(&mut self, key: &str) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.key == key) {
+            entry.refreshing = false;
+        }
+    }
+
+    /// Keeps `entry` in place of any earlier report for its key; the entry arrives unrefreshed.
+    pub fn store(&mut self, entry: CacheEntry, policy: &CachePolicy) {
+        if !policy.enabled() {
+            return;
+        }
+        self.entries.retain(|e| e.key != entry.key);
+        self.entries.push(entry);
+        if self.entries.len() > policy.max_entries {
+            let excess = self.entries.len() - policy.max_entries;
+            self.entries.drain(..excess);
+        }
+    }
+
+    /// Empties the cache and returns how many reports it had. A refresh still running stores
+    /// its report when it finishes.
+    pub fn clear(&mut self) -> u32 {
+        std::mem::take(&mut self.entries).len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(fresh_seconds: u64, stale_seconds: u64, max_entries: usize) -> CachePolicy {
+        CachePolicy {
+            fresh_seconds,
+            stale_seconds,
+            max_entries,
+        }
+    }
+
+    fn entry(key: &str, generated_at: u64) -> CacheEntry {
+        let report = serde_json::from_value(serde_json::json!({
+            "report_id": "rpt-1",
+            "topic": "rust",
+            "summary": "",
+            "key_findings": [],
+            "sources": [],
+            "report": key,
+            "model": "m",
+        }))
+        .unwrap();
+        CacheEntry {
+            key: key.to_string(),
+            workspace: "default".to_string(),
+            topic: "rust".to_string(),
+            options: ResearchOptions::default(),
+            report,
+            generated_at,
+            refreshing: false,
+        }
+    }
+
+    fn served(lookup: Lookup) -> Option<(bool, ResearchReport)> {
+        match lookup {
+            Lookup::Fresh(report) => Some((true, report)),
+            Lookup::Stale(report) => Some((false, report)),
+            Lookup::Miss => None,
+        }
+    }
+
+    #[test]
+    fn serves_fresh_then_stale_then_misses() {
+        let policy = policy(60, 300, 10);
+        let mut cache = ReportCache::default();
+        cache.store(entry("k", 1000), &policy);
+
+        let (fresh, report) = served(cache.lookup("k", 1059, &policy)).unwrap();
+        assert!(fresh);
+        assert!(report.freshness.cached && !report.freshness.refreshing);
+        assert_eq!((report.freshness.generated_at, report.freshness.age_seconds), (1000, 59));
+
+        let (fresh, report) = served(cache.lookup("k", 1060, &policy)).unwrap();
+        assert!(!fresh);
+        assert_eq!(report.freshness.age_seconds, 60);
+        assert!(served(cache.lookup("k", 1359, &policy)).is_some_and(|(fresh, _)| !fresh));
+        assert!(served(cache.lookup("k", 1360, &policy)).is_none());
+        assert!(served(cache.lookup("other", 1000, &policy)).is_none());
+    }
+
+    #[test]
+    fn stale_only_policy_always_refreshes() {
+        let policy = policy(0, 60, 10);
+        let mut cache = ReportCache::default();
+        cache.store(entry("k", 1000), &policy);
+        assert!(served(cache.lookup("k", 1000, &policy)).is_some_and(|(fresh, _)| !fresh));
+    }
+
+    #[test]
+    fn a_disabled_cache_keeps_nothing() {
+        let mut cache = ReportCache::default();
+        cache.store(entry("k", 1000), &policy(0, 0, 10));
+        assert!(cache.get("k").is_none());
+
+        cache.store(entry("k", 1000), &policy(60, 0, 10));
+        assert!(served(cache.lookup("k", 1000, &policy(0, 0, 10))).is_none());
+        assert!(served(cache.lookup("k", 1000, &policy(60, 0, 0))).is_none());
+    }
+
+    #[test]
+    fn one_refresh_at_a_time() {
+        let policy = policy(0, 60, 10);
+        let mut cache = ReportCache::default();
+        assert!(!cache.begin_refresh("k"));
+        cache.store(entry("k", 1000), &policy);
+        assert!(cache.begin_refresh("k"));
+        assert!(!cache.begin_refresh("k"));
+        assert!(served(cache.lookup("k", 1000, &policy)).is_some_and(|(_, report)| report.freshness.refreshing));
+        cache.end_refresh("k");
+        assert!(cache.begin_refresh("k"));
+
+        // A refreshed report replaces the old one, unrefreshed.
+        cache.store(entry("k", 1030), &policy);
+        assert_eq!(cache.get("k").map(|e| (e.generated_at, e.refreshing)), Some((1030, false)));
+    }
+
+    #[test]
+    fn drops_the_oldest_entries_beyond_the_limit() {
+        let policy = policy(60, 0, 2);
+        let mut cache = ReportCache::default();
+        for key in ["a", "b", "c"] {
+            cache.store(entry(key, 1000), &policy);
+        }
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_some() && cache.get("c").is_some());
+        assert_eq!(cache.clear(), 2);
+        assert!(cache.get("c").is_none());
+    }
+
+    #[test]
+    fn key_covers_workspace_topic_and_report_options() {
+        let options = ResearchOptions::default();
+        assert_eq!(key("default", " Rust Async ", &options), key("default", "rust async", &options));
+        assert_ne!(key("default", "rust", &options), key("team", "rust", &options));
+
+        let deeper = ResearchOptions {
+            max_results: Some(25),
+            ..ResearchOptions::default()
+        };
+        assert_ne!(key("default", "rust", &options), key("default", "rust", &deeper));
+    }
+    #[test]
+    fn key_ignores_options_that_dont_shape_the_report() {
+        let options = ResearchOptions::default();
+        let request = ResearchOptions {
+            priority: Some(crate::options::RequestPriority::Low),
+            fields: Some(Vec::new()),
+            bypass_cache: Some(true),
+            ..ResearchOptions::default()
+        };
+        assert_eq!(key("default", "rust", &options), key("default", "rust", &request));
+    }
+}
@@ -0,0 +1,235 @@
+//! Charts for reports: with `ResearchOptions::charts` set, a second LLM pass picks the numeric
+//! series in the report and its sources (a metric over years, a comparison across vendors) and
+//! they are drawn into a "Charts" section, as Mermaid `xychart-beta` blocks or as text bar
+//! charts with a sparkline. No image service is involved, so the report stays plain markdown.
+//!
+//! The model only picks and labels the numbers. A series is drawn only when every value occurs
+//! in the report or the sources, so a chart can't show figures the research didn't find.
+//!
+//! Text charts are laid out in monospace columns (see `locale`), so CJK labels line up, and
+//! show their values in the report language's number format.
+
+use golem_rust::Schema;
+
+use crate::locale;
+use crate::prompt;
+use crate::truncate;
+use crate::SearchResult;
+
+const MAX_CHARTS: usize = 3;
+const MIN_POINTS: usize = 2;
+const MAX_POINTS: usize = 24;
+const MAX_LABEL_CHARS: usize = 40;
+/// Source text shown to the model per source.
+const SOURCE_CHARS: usize = 1500;
+/// Width of the longest bar in text charts.
+const BAR_WIDTH: usize = 30;
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Schema, serde::Serialize, serde::Deserialize)]
+pub enum ChartFormat {
+    /// Mermaid `xychart-beta` code blocks, drawn by GitHub, GitLab and most markdown viewers.
+    Mermaid,
+    /// Bar charts and a sparkline in a plain text code block.
+    Ascii,
+}
+
+#[derive(serde::Deserialize)]
+struct ExtractedCharts {
+    #[serde(default)]
+    charts: Vec<Series>,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Series {
+    pub title: String,
+    #[serde(default)]
+    pub unit: String,
+    /// `line` for a trend over time, anything else draws bars.
+    #[serde(default)]
+    pub kind: String,
+    pub points: Vec<Point>,
+    /// Number of the source the values come from, if one.
+    #[serde(default)]
+    pub source: Option<usize>,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Point {
+    pub label: String,
+    pub value: f64,
+}
+
+pub fn extraction_prompt(report: &str, sources: &[SearchResult]) -> String {
+    let sources = sources
+        .iter()
+        .enumerate()
+        .map(|(i, s)| format!("{}. {} ({})\n{}", i + 1, s.title, s.url, truncate::chars(&s.snippet, SOURCE_CHARS)))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    format!(
+        "Find up to {MAX_CHARTS} numeric series in the research report and the numbered sources below that a reader would understand better as a chart, \
+         such as one metric over several years or the same metric compared across several items. Each series needs {MIN_POINTS} to {MAX_POINTS} points \
+         that share one unit. Copy each value exactly as it is stated (as a plain number, without thousands separators or units); don't compute, \
+         convert or estimate values. Leave out anything that isn't stated as a number.\n\
+         Use kind \"line\" for a series over time and \"bar\" otherwise, and give the number of the source the values come from, or null if they are from the report.\n\
+         Reply with JSON only, in this shape: {{\"charts\": [{{\"title\": \"...\", \"unit\": \"...\", \"kind\": \"line\", \"points\": [{{\"label\": \"2022\", \"value\": 1.5}}], \"source\": 1}}]}}. \
+         Reply with {{\"charts\": []}} if there are no such series.\n\n\
+         Sources:\n{sources}\n\n\
+         Report:\n{report}"
+    )
+}
+
+/// The series in the model's reply that are fit to draw: enough points, and every value found
+/// in the report or the sources.
+pub fn parse_series(reply: &str, report: &str, sources: &[SearchResult]) -> Result<Vec<Series>, String> {
+    let extracted: ExtractedCharts = serde_json::from_str(prompt::json_object(reply))
+        .map_err(|err| format!("The model did not return valid chart JSON: {err}"))?;
+    let mut stated = numbers_in(report);
+    for source in sources {
+        stated.extend(numbers_in(&source.snippet));
+    }
+
+    Ok(extracted
+        .charts
+        .into_iter()
+        .filter(|series| (MIN_POINTS..=MAX_POINTS).contains(&series.points.len()))
+        .filter(|series| {
+            let found = series.points.iter().all(|point| {
+                point.value.is_finite() && stated.iter().any(|n| (n - point.value).abs() <= point.value.abs() * 1e-9)
+            });
+            if !found {
+                log::info!("Dropping chart {:?}: not all of its values occur in the research", series.title);
+            }
+            found
+        })
+        .map(|mut series| {
+            series.source = series.source.filter(|n| (1..=sources.len()).contains(n));
+            series
+        })
+        .take(MAX_CHARTS)
+        .collect())
+}
+
+/// The numbers written in `text`, with `,` thousands separators and a leading minus allowed.
+fn numbers_in(text: &str) -> Vec<f64> {
+    let mut numbers = Vec::new();
+    let mut current = String::new();
+    let mut previous = ' ';
+    for c in text.chars().chain(std::iter::once(' ')) {
+        let starts_negative = c == '-' && current.is_empty() && !previous.is_alphanumeric();
+        let separator = (c == '.' || c == ',') && current.chars().any(|d| d.is_ascii_digit());
+        if c.is_ascii_digit() || starts_negative || separator {
+            current.push(c);
+        } else if !current.is_empty() {
+            let number = current.trim_end_matches(['.', ',']);
+            // `1,200` is a thousand two hundred, but `1,2,3` is a list.
+            let parts = number.split(',').map(str::to_string);
+            for candidate in std::iter::once(number.replace(',', "")).chain(parts) {
+                if let Ok(number) = candidate.parse::<f64>() {
+                    numbers.push(number);
+                }
+            }
+            current.clear();
+        }
+        previous = c;
+    }
+    numbers
+}
+
+/// The "Charts" section for `series`, linking each chart to its source.
+pub fn render(series: &[Series], format: ChartFormat, sources: &[SearchResult], language: &str) -> String {
+    let charts: Vec<String> = series
+        .iter()
+        .map(|series| {
+            let chart = match format {
+                ChartFormat::Mermaid => mermaid(series),
+                ChartFormat::Ascii => text_chart(series, language),
+            };
+            let source = series
+                .source
+                .and_then(|n| sources.get(n - 1))
+                .map(|s| format!("\n\nSource: [{}]({})", s.title, s.url))
+                .unwrap_or_default();
+            format!("{chart}{source}")
+        })
+        .collect();
+    format!("## Charts\n\n{}", charts.join("\n\n"))
+}
+
+fn mermaid(series: &Series) -> String {
+    let labels: Vec<String> = series.points.iter().map(|p| quoted(&p.label)).collect();
+    let values: Vec<String> = series.points.iter().map(|p| number(p.value)).collect();
+    let kind = if series.kind.eq_ignore_ascii_case("line") { "line" } else { "bar" };
+    let mut chart = format!(
+        "```mermaid\nxychart-beta\n    title {}\n    x-axis [{}]\n",
+        quoted(&series.title),
+        labels.join(", ")
+    );
+    if !series.unit.trim().is_empty() {
+        chart.push_str(&format!("    y-axis {}\n", quoted(&series.unit)));
+    }
+    chart.push_str(&format!("    {kind} [{}]\n```", values.join(", ")));
+    chart
+}
+
+fn text_chart(series: &Series, language: &str) -> String {
+    let labels: Vec<String> = series
+        .points
+        .iter()
+        .map(|p| locale::truncate_width(p.label.trim(), MAX_LABEL_CHARS))
+        .collect();
+    let label_width = labels.iter().map(|l| locale::display_width(l)).max().unwrap_or(0);
+    let largest = series.points.iter().map(|p| p.value.abs()).fold(0.0, f64::max);
+    let unit = match series.unit.trim() {
+        "" => String::new(),
+        unit => format!(" ({unit})"),
+    };
+    let mut lines = vec![format!("{}{unit}  {}", locale::isolate(series.title.trim()), sparkline(&series.points))];
+    for (label, point) in labels.iter().zip(&series.points) {
+        let width = if largest > 0.0 {
+            (point.value.abs() / largest * BAR_WIDTH as f64).round() as usize
+        } else {
+            0
+        };
+        lines.push(format!(
+            "{} │{}{} {}",
+            locale::isolate(&locale::pad_to_width(label, label_width)),
+            "█".repeat(width),
+            " ".repeat(BAR_WIDTH - width),
+            locale::format_number(point.value, language)
+        ));
+    }
+    format!("```text\n{}\n```", lines.join("\n"))
+}
+
+/// One block character per point, scaled between the smallest and the largest value.
+fn sparkline(points: &[Point]) -> String {
+    let min = points.iter().map(|p| p.value).fold(f64::INFINITY, f64::min);
+    let max = points.iter().map(|p| p.value).fold(f64::NEG_INFINITY, f64::max);
+    points
+        .iter()
+        .map(|p| {
+            let level = if max > min {
+                ((p.value - min) / (max - min) * (SPARK_LEVELS.len() - 1) as f64).round() as usize
+            } else {
+                SPARK_LEVELS.len() / 2
+            };
+            SPARK_LEVELS[level]
+        })
+        .collect()
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", truncate::chars(text, MAX_LABEL_CHARS).replace('"', "'"))
+}
+
+/// `value` without a trailing `.0`.
+fn number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{value}")
+    }
+}
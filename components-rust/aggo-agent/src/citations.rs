@@ -0,0 +1,118 @@
+//! Source links cited in report text, used to build deduplicated source lists.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Citation {
+    pub title: Option<String>,
+    pub url: String,
+}
+
+/// Markdown links and bare http(s) URLs in `text`, in order of first appearance, deduplicated
+/// by normalized URL. A titled occurrence wins over a bare one.
+pub fn extract(text: &str) -> Vec<Citation> {
+    let mut citations: Vec<Citation> = Vec::new();
+    let mut add = |title: Option<String>, url: &str| {
+        let url = url.trim_end_matches(['.', ',', ';', ':', ')', ']', '>', '"', '\'']);
+        if url.len() <= "https://".len() {
+            return;
+        }
+        let key = normalize_url(url);
+        match citations.iter_mut().find(|c| normalize_url(&c.url) == key) {
+            Some(existing) if existing.title.is_none() => existing.title = title,
+            Some(_) => {}
+            None => citations.push(Citation {
+                title,
+                url: url.to_string(),
+            }),
+        }
+    };
+
+    let mut rest = text;
+    while let Some(start) = rest.find("http") {
+        let before = &rest[..start];
+        let candidate = &rest[start..];
+        if !(candidate.starts_with("http://") || candidate.starts_with("https://")) {
+            rest = &rest[start + 4..];
+            continue;
+        }
+        let end = candidate
+            .find(|c: char| c.is_whitespace() || matches!(c, ')' | '<' | '>' | '"' | '`' | ']'))
+            .unwrap_or(candidate.len());
+
+        // `[title](url)`: the link text sits just before the `](`.
+        let title = before
+            .strip_suffix("](")
+            .and_then(|b| b.rfind('[').map(|open| b[open + 1..].trim().to_string()))
+            .filter(|t| !t.is_empty());
+        add(title, &candidate[..end]);
+        rest = &candidate[end..];
+    }
+
+    citations
+}
+
+/// Query parameters that only track the click, besides `utm_*`.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "msclkid", "yclid", "mc_cid", "mc_eid", "igshid", "_ga", "_gl", "ref_src",
+];
+
+/// Treats http and https alike, lowercases the host, drops `www.`, default ports, fragments,
+/// tracking parameters and trailing slashes, and sorts the remaining parameters, so the same
+/// page cited in different forms compares equal.
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    let url = url.split('#').next().unwrap_or(url);
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (host, path_and_query) = match rest.find(['/', '?']) {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, ""),
+    };
+    let host = host.to_ascii_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let host = host
+        .strip_suffix(":443")
+        .or_else(|| host.strip_suffix(":80"))
+        .unwrap_or(host);
+
+    let (path, query) = path_and_query.split_once('?').unwrap_or((path_and_query, ""));
+    let mut query: Vec<&str> = query
+        .split('&')
+        .filter(|p| {
+            let name = p.split('=').next().unwrap_or(p).to_ascii_lowercase();
+            !p.is_empty() && !name.starts_with("utm_") && !TRACKING_PARAMS.contains(&name.as_str())
+        })
+        .collect();
+    query.sort_unstable();
+
+    let mut normalized = format!("https://{}{}", host, path.trim_end_matches('/'));
+    if !query.is_empty() {
+        normalized.push('?');
+        normalized.push_str(&query.join("&"));
+    }
+    normalized
+}
+
+/// Lowercased host of a URL without `www.` or port, e.g. `example.com`.
+pub fn host(url: &str) -> Option<String> {
+    let rest = url.trim().split_once("://").map_or(url.trim(), |(_, rest)| rest);
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = host.rsplit('@').next().unwrap_or(host);
+    let host = host.split(':').next().unwrap_or(host).to_ascii_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    if host.contains('.') {
+        Some(host.to_string())
+    } else {
+        None
+    }
+}
+
+/// A numbered markdown source list.
+pub fn render_sources(citations: &[Citation]) -> String {
+    let mut sources = String::from("## Sources\n");
+    for (index, citation) in citations.iter().enumerate() {
+        match &citation.title {
+            Some(title) => sources.push_str(&format!("{}. [{}]({})\n", index + 1, title, citation.url)),
+            None => sources.push_str(&format!("{}. {}\n", index + 1, citation.url)),
+        }
+    }
+    sources
+}
@@ -0,0 +1,114 @@
+//! Extractive compression of page text before it enters the prompt.
+//!
+//! Sentences are scored by how densely they mention the topic's terms (with a small bonus
+//! for appearing early in the page), and the best ones are kept in their original order until
+//! the text is down to `PROMPT_COMPRESSION_RATIO` of its length (default 0.25). No LLM call.
+
+use crate::config;
+
+const STOPWORDS: &[&str] = &[
+    "about", "after", "also", "and", "are", "but", "can", "for", "from", "has", "have", "how",
+    "into", "its", "not", "of", "on", "or", "that", "the", "their", "this", "was", "were", "what",
+    "when", "which", "who", "why", "will", "with",
+];
+
+/// Sentences shorter than this are usually navigation or captions.
+const MIN_SENTENCE_CHARS: usize = 25;
+
+pub fn compression_ratio() -> f64 {
+    config::env_parse::<f64>("PROMPT_COMPRESSION_RATIO")
+        .filter(|r| r.is_finite() && *r > 0.0 && *r <= 1.0)
+        .unwrap_or(0.25)
+}
+
+/// Reduces `text` to its most topic-relevant sentences, about `ratio` of the original length.
+pub fn compress(text: &str, topic: &str, ratio: f64) -> String {
+    let sentences = split_sentences(text);
+    if sentences.len() <= 1 {
+        return text.trim().to_string();
+    }
+
+    let terms = terms(topic);
+    let total_chars: usize = sentences.iter().map(|s| s.len()).sum();
+    let target_chars = ((total_chars as f64) * ratio).ceil() as usize;
+
+    let mut ranked: Vec<(usize, f64)> = sentences
+        .iter()
+        .enumerate()
+        .map(|(index, sentence)| (index, score(sentence, &terms, index, sentences.len())))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut keep = Vec::new();
+    let mut kept_chars = 0;
+    for (index, _) in ranked {
+        if kept_chars >= target_chars {
+            break;
+        }
+        kept_chars += sentences[index].len();
+        keep.push(index);
+    }
+    keep.sort_unstable();
+
+    keep.into_iter()
+        .map(|index| sentences[index])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let boundary = match c {
+            '\n' => true,
+            '.' | '!' | '?' => chars.peek().is_none_or(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if boundary {
+            let end = i + c.len_utf8();
+            push_sentence(&mut sentences, &text[start..end]);
+            start = end;
+        }
+    }
+    push_sentence(&mut sentences, &text[start..]);
+
+    sentences
+}
+
+fn push_sentence<'a>(sentences: &mut Vec<&'a str>, candidate: &'a str) {
+    let candidate = candidate.trim();
+    if candidate.chars().count() >= MIN_SENTENCE_CHARS {
+        sentences.push(candidate);
+    }
+}
+
+/// Lowercased content words of `topic`, without stopwords and very short words.
+pub fn terms(topic: &str) -> Vec<String> {
+    words(topic)
+        .filter(|w| w.chars().count() > 2 && !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+}
+
+/// Topic-term hits normalised by sentence length, plus a bonus that decays down the page.
+fn score(sentence: &str, terms: &[String], index: usize, count: usize) -> f64 {
+    let words: Vec<String> = words(sentence).collect();
+    if words.is_empty() {
+        return 0.0;
+    }
+
+    let hits = words.iter().filter(|w| terms.iter().any(|t| w.starts_with(t.as_str()))).count();
+    let distinct = terms.iter().filter(|t| words.iter().any(|w| w.starts_with(t.as_str()))).count();
+    let density = hits as f64 / (words.len() as f64).sqrt();
+    let position = 1.0 - index as f64 / count as f64;
+
+    density + distinct as f64 * 0.5 + position * 0.3
+}
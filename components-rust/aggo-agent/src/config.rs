@@ -0,0 +1,85 @@
+//! Configuration checks at startup. Problems that would leave the agent unable to research (no
+//! LLM model, an unknown web search provider, a summarizer without its key) are collected into a
+//! `ConfigReport` instead of stopping the worker, which callers would only see as an opaque trap.
+//! `validate_config` returns the report, and research methods fail with a `config-error` that
+//! carries it until the env is fixed.
+
+use golem_rust::Schema;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Schema, serde::Serialize, serde::Deserialize)]
+pub enum ConfigProblem {
+    /// Unset, empty or still the `changeme` placeholder.
+    Missing,
+    /// Set to a value the agent can't use.
+    Invalid,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ConfigIssue {
+    /// The env var, e.g. `LLM_MODEL`.
+    pub variable: String,
+    pub problem: ConfigProblem,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ConfigReport {
+    /// Whether research can run, i.e. there are no `errors`.
+    pub ok: bool,
+    /// Problems that block research.
+    pub errors: Vec<ConfigIssue>,
+    /// Problems the agent works around, e.g. a web search provider dropped from the chain
+    /// because its API key is missing.
+    pub warnings: Vec<ConfigIssue>,
+}
+
+impl Default for ConfigReport {
+    fn default() -> Self {
+        Self {
+            ok: true,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl ConfigReport {
+    pub fn error(&mut self, variable: &str, problem: ConfigProblem, message: impl Into<String>) {
+        let message = message.into();
+        log::error!("{message} Check the component env in golem.yaml.");
+        self.errors.push(ConfigIssue {
+            variable: variable.to_string(),
+            problem,
+            message,
+        });
+        self.ok = false;
+    }
+
+    pub fn warn(&mut self, variable: &str, problem: ConfigProblem, message: impl Into<String>) {
+        let message = message.into();
+        log::warn!("{message} Check the component env in golem.yaml.");
+        self.warnings.push(ConfigIssue {
+            variable: variable.to_string(),
+            problem,
+            message,
+        });
+    }
+}
+
+impl std::fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors: Vec<&str> = self.errors.iter().map(|issue| issue.message.as_str()).collect();
+        write!(f, "{}", errors.join(" "))
+    }
+}
+
+/// Whether an env value is unset in effect: empty or the `changeme` placeholder.
+pub fn is_placeholder(value: &str) -> bool {
+    value.trim().is_empty() || value.trim() == "changeme"
+}
+
+/// The env var `name` parsed as a `T`, surrounding whitespace ignored; `None` if it is unset or
+/// doesn't parse. Callers decide what 0 or an out-of-range value means.
+pub fn env_parse<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok().and_then(|v| v.trim().parse::<T>().ok())
+}
@@ -0,0 +1,891 @@
+//! External search connectors: internal search systems (intranet search, wikis, document
+//! stores) queried alongside the web search provider.
+//!
+//! Connectors are declared in `SEARCH_CONNECTORS` as a JSON array. Each has a `name`, a
+//! `kind` and kind-specific settings; secrets are referenced by env var name, never inlined.
+//! The generic `http` kind maps any JSON search API:
+//!
+//! ```json
+//! [{"name": "intranet", "kind": "http", "endpoint": "https://search.internal/api/query",
+//!   "method": "POST", "body": {"q": "{query}", "size": "{limit}"},
+//!   "auth": {"type": "bearer", "token_env": "INTRANET_SEARCH_TOKEN"},
+//!   "results_path": "hits", "title_field": "title", "url_field": "link", "snippet_field": "summary"}]
+//! ```
+//!
+//! The `elasticsearch` kind (also for OpenSearch) runs a `multi_match` query on an index and
+//! uses highlighted fragments of the body field as snippets:
+//!
+//! ```json
+//! [{"name": "docs", "kind": "elasticsearch", "endpoint": "https://es.internal:9200",
+//!   "index": "docs-*", "title_field": "title", "body_field": "content",
+//!   "url_template": "https://docs.internal/view/{id}",
+//!   "auth": {"type": "api_key", "key_env": "DOCS_ES_API_KEY"}}]
+//! ```
+//!
+//! The `postgrest` kind runs Postgres full-text search on a table through PostgREST (or calls
+//! an RPC function on an HTTP-SQL gateway), mapping configurable title/url/body columns.
+//!
+//! The `google_drive` kind searches a Drive folder and reads the text of matching Google Docs.
+//! The `confluence` and `jira` kinds search pages and issues, scoped to spaces or projects.
+//! The `slack` kind searches messages in allowlisted channels, with PII redacted.
+//! The `mail` kind searches configured mail folders (Microsoft Graph or an IMAP gateway); it is
+//! opt-in per request and redacted.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use golem_rust::Schema;
+
+use crate::SearchResult;
+
+const DEFAULT_MAX_RESULTS: u32 = 5;
+const MAX_RESULTS: u32 = 50;
+const TIMEOUT: Duration = Duration::from_secs(15);
+/// Snippets built from whole document bodies are cut to this length.
+const MAX_SNIPPET_CHARS: usize = 1000;
+/// Text kept per fetched document (e.g. a Google Doc), used as the result snippet.
+const MAX_DOCUMENT_CHARS: usize = 4000;
+const DRIVE_API: &str = "https://www.googleapis.com/drive/v3/files";
+const GOOGLE_DOC_MIME: &str = "application/vnd.google-apps.document";
+const SLACK_SEARCH_API: &str = "https://slack.com/api/search.messages";
+const GRAPH_API: &str = "https://graph.microsoft.com/v1.0";
+
+/// A configured connector, as reported by `list_search_connectors`.
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ConnectorInfo {
+    pub name: String,
+    pub kind: String,
+    /// Whether research queries it unless the caller picks connectors explicitly.
+    pub default_enabled: bool,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct ExternalSearchConnector {
+    pub name: String,
+    /// Results taken per query (default 5, at most 50).
+    #[serde(default)]
+    pub max_results: Option<u32>,
+    /// Queried by every research run unless the caller names connectors (default true).
+    #[serde(default = "default_true")]
+    pub default_enabled: bool,
+    #[serde(default)]
+    pub auth: Option<ConnectorAuth>,
+    #[serde(flatten)]
+    pub kind: ConnectorKind,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConnectorKind {
+    Http(HttpConnector),
+    /// Elasticsearch or OpenSearch (same `_search` API).
+    Elasticsearch(ElasticsearchConnector),
+    /// Postgres full-text search through a PostgREST-compatible gateway.
+    Postgrest(PostgrestConnector),
+    /// Documents in a Google Drive folder.
+    GoogleDrive(GoogleDriveConnector),
+    /// Confluence page search (CQL).
+    Confluence(ConfluenceConnector),
+    /// Jira issue search (JQL).
+    Jira(JiraConnector),
+    /// Slack message search.
+    Slack(SlackConnector),
+    /// Mail folders, read through Microsoft Graph or an IMAP gateway.
+    Mail(MailConnector),
+}
+
+/// Credentials, each read from the named env var at query time.
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConnectorAuth {
+    Bearer { token_env: String },
+    Basic { username_env: String, password_env: String },
+    Header { name: String, value_env: String },
+    /// Elasticsearch API key, sent as `Authorization: ApiKey <key>`.
+    ApiKey { key_env: String },
+}
+
+/// A JSON search API described by its request shape and where the results live.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct HttpConnector {
+    pub endpoint: String,
+    /// `GET` (default) or `POST`.
+    #[serde(default)]
+    pub method: Option<String>,
+    /// GET: the query string parameter carrying the query, e.g. `q`.
+    #[serde(default)]
+    pub query_param: Option<String>,
+    /// GET: fixed extra query parameters.
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+    /// POST: JSON body; string values `{query}` and `{limit}` are filled in.
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+    /// Dotted path to the result array in the response, e.g. `hits.hits`; empty for the root.
+    #[serde(default)]
+    pub results_path: String,
+    /// Dotted paths within each result.
+    pub title_field: String,
+    pub url_field: String,
+    pub snippet_field: String,
+    /// Prepended to relative URLs, e.g. `https://wiki.internal`.
+    #[serde(default)]
+    pub url_prefix: Option<String>,
+}
+
+/// An Elasticsearch/OpenSearch index searched with a `multi_match` query.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct ElasticsearchConnector {
+    /// Cluster URL, e.g. `https://es.internal:9200`.
+    pub endpoint: String,
+    /// Index, alias or pattern, e.g. `docs-*`.
+    pub index: String,
+    /// `_source` field holding the title.
+    #[serde(default = "default_title_field")]
+    pub title_field: String,
+    /// `_source` field holding the document text; highlighted fragments of it become the snippet.
+    #[serde(default = "default_body_field")]
+    pub body_field: String,
+    /// `_source` field holding the document URL.
+    #[serde(default)]
+    pub url_field: Option<String>,
+    /// Builds the URL from the hit instead, e.g. `https://docs.internal/view/{id}` (`{id}` is
+    /// the document `_id`).
+    #[serde(default)]
+    pub url_template: Option<String>,
+    /// Fields searched, with optional boosts. Default: title (boosted) and body.
+    #[serde(default)]
+    pub search_fields: Vec<String>,
+}
+
+/// A Postgres table (or view) searched with full-text search through PostgREST, or an RPC
+/// function on an HTTP-SQL gateway that takes `{query, limit}` and returns rows.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct PostgrestConnector {
+    /// Gateway URL, e.g. `https://pg-gateway.internal`.
+    pub endpoint: String,
+    /// Table or view queried as `GET /{table}`.
+    #[serde(default)]
+    pub table: Option<String>,
+    /// Function called as `POST /rpc/{function}` instead of querying a table.
+    #[serde(default)]
+    pub function: Option<String>,
+    /// Column the query is matched against with `websearch_to_tsquery` (a `tsvector` column,
+    /// or a text column Postgres converts). Defaults to the body column.
+    #[serde(default)]
+    pub search_column: Option<String>,
+    /// Text search configuration, e.g. `english` (default) or `simple`.
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default = "default_title_field")]
+    pub title_column: String,
+    #[serde(default = "default_url_column")]
+    pub url_column: String,
+    #[serde(default = "default_body_field")]
+    pub body_column: String,
+    /// Builds the URL from other columns instead, e.g. `https://docs.internal/doc/{id}`.
+    #[serde(default)]
+    pub url_template: Option<String>,
+}
+
+/// A Google Drive folder searched with Drive full-text search; Google Docs and plain-text
+/// files among the hits are downloaded so their text grounds the research.
+///
+/// Needs `bearer` auth holding an OAuth access token for a service account the folder is
+/// shared with (minted and refreshed outside the agent, e.g. by a secrets sidecar).
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct GoogleDriveConnector {
+    pub folder_id: String,
+    /// Download the text of matching documents (default true); otherwise only titles and links
+    /// are returned.
+    #[serde(default = "default_true")]
+    pub fetch_text: bool,
+}
+
+/// Confluence pages found with a CQL text search, optionally limited to some spaces.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct ConfluenceConnector {
+    /// Site URL including the context path, e.g. `https://acme.atlassian.net/wiki`.
+    pub base_url: String,
+    /// Space keys searched; empty for every space the credentials can read.
+    #[serde(default)]
+    pub spaces: Vec<String>,
+}
+
+/// Jira issues found with a JQL text search, optionally limited to some projects.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct JiraConnector {
+    /// Site URL, e.g. `https://acme.atlassian.net`.
+    pub base_url: String,
+    /// Project keys searched; empty for every project the credentials can read.
+    #[serde(default)]
+    pub projects: Vec<String>,
+    /// Search endpoint; defaults to `/rest/api/2/search` (Jira Cloud: `/rest/api/2/search/jql`).
+    #[serde(default)]
+    pub search_path: Option<String>,
+}
+
+/// Slack messages found with `search.messages`, limited to allowlisted channels. Message text
+/// is PII-redacted and direct messages are never returned.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct SlackConnector {
+    /// Channel names (without `#`) whose messages may be used; required, so a token's
+    /// access to every channel doesn't leak into reports by default.
+    pub channels: Vec<String>,
+}
+
+/// Read-only search over configured mail folders. Always opt-in: it is only queried when a
+/// request names it in `ResearchOptions.connectors`. Subjects and bodies are PII-redacted and
+/// senders are never included.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct MailConnector {
+    #[serde(flatten)]
+    pub source: MailSource,
+    /// Folders searched; required. Graph accepts folder ids or well-known names such as `inbox`.
+    pub folders: Vec<String>,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum MailSource {
+    /// Microsoft Graph `$search` over one mailbox's folders.
+    Graph { mailbox: String },
+    /// An IMAP gateway answering `POST {endpoint}` with `{query, folders, limit}` by
+    /// `{"messages": [{id, folder, subject, date, body, url}]}`.
+    Gateway { endpoint: String },
+}
+
+fn default_url_column() -> String {
+    "url".to_string()
+}
+
+fn default_title_field() -> String {
+    "title".to_string()
+}
+
+fn default_body_field() -> String {
+    "body".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl ExternalSearchConnector {
+    pub fn info(&self) -> ConnectorInfo {
+        ConnectorInfo {
+            name: self.name.clone(),
+            kind: match &self.kind {
+                ConnectorKind::Http(_) => "http",
+                ConnectorKind::Elasticsearch(_) => "elasticsearch",
+                ConnectorKind::Postgrest(_) => "postgrest",
+                ConnectorKind::GoogleDrive(_) => "google_drive",
+                ConnectorKind::Confluence(_) => "confluence",
+                ConnectorKind::Jira(_) => "jira",
+                ConnectorKind::Slack(_) => "slack",
+                ConnectorKind::Mail(_) => "mail",
+            }
+            .to_string(),
+            default_enabled: self.default_enabled(),
+        }
+    }
+
+    /// Whether research uses it when the caller doesn't name connectors. Mail never is.
+    fn default_enabled(&self) -> bool {
+        self.default_enabled && !matches!(self.kind, ConnectorKind::Mail(_))
+    }
+
+    fn limit(&self) -> u32 {
+        self.max_results.unwrap_or(DEFAULT_MAX_RESULTS).clamp(1, MAX_RESULTS)
+    }
+
+    pub fn search(&self, query: &str) -> Result<Vec<SearchResult>, String> {
+        match &self.kind {
+            ConnectorKind::Http(http) => http.search(query, self.limit(), self.auth.as_ref()),
+            ConnectorKind::Elasticsearch(es) => es.search(query, self.limit(), self.auth.as_ref()),
+            ConnectorKind::Postgrest(pg) => pg.search(query, self.limit(), self.auth.as_ref()),
+            ConnectorKind::GoogleDrive(drive) => drive.search(query, self.limit(), self.auth.as_ref()),
+            ConnectorKind::Confluence(confluence) => confluence.search(query, self.limit(), self.auth.as_ref()),
+            ConnectorKind::Jira(jira) => jira.search(query, self.limit(), self.auth.as_ref()),
+            ConnectorKind::Slack(slack) => slack.search(query, self.limit(), self.auth.as_ref()),
+            ConnectorKind::Mail(mail) => mail.search(query, self.limit(), self.auth.as_ref()),
+        }
+    }
+}
+
+impl HttpConnector {
+    fn search(&self, query: &str, limit: u32, auth: Option<&ConnectorAuth>) -> Result<Vec<SearchResult>, String> {
+        let client = golem_wasi_http::Client::new();
+        let post = self.method.as_deref().is_some_and(|m| m.eq_ignore_ascii_case("post"));
+        let request = if post {
+            let body = self
+                .body
+                .as_ref()
+                .map(|body| fill_placeholders(body, query, limit))
+                .unwrap_or_else(|| serde_json::json!({ "query": query, "limit": limit }));
+            client.post(&self.endpoint).json(&body)
+        } else {
+            let mut params: Vec<(String, String)> = self.params.clone().into_iter().collect();
+            params.push((self.query_param.clone().unwrap_or_else(|| "q".to_string()), query.to_string()));
+            client.get(&self.endpoint).query(&params)
+        };
+        let json = send_json(authorize(request.timeout(TIMEOUT), auth)?)?;
+
+        let items = lookup(&json, &self.results_path)
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| format!("no result array at {:?}", self.results_path))?;
+        Ok(items
+            .iter()
+            .filter_map(|item| {
+                let url = text_at(item, &self.url_field)?;
+                let url = match &self.url_prefix {
+                    Some(prefix) if !url.contains("://") => {
+                        format!("{}/{}", prefix.trim_end_matches('/'), url.trim_start_matches('/'))
+                    }
+                    _ => url,
+                };
+                Some(SearchResult {
+                    title: text_at(item, &self.title_field).unwrap_or_else(|| url.clone()),
+                    snippet: text_at(item, &self.snippet_field).unwrap_or_default(),
+                    url,
+                })
+            })
+            .take(limit as usize)
+            .collect())
+    }
+}
+
+impl ElasticsearchConnector {
+    fn search(&self, query: &str, limit: u32, auth: Option<&ConnectorAuth>) -> Result<Vec<SearchResult>, String> {
+        let fields = if self.search_fields.is_empty() {
+            vec![format!("{}^2", self.title_field), self.body_field.clone()]
+        } else {
+            self.search_fields.clone()
+        };
+        let mut source = vec![self.title_field.clone(), self.body_field.clone()];
+        source.extend(self.url_field.clone());
+        let body = serde_json::json!({
+            "size": limit,
+            "query": { "multi_match": { "query": query, "fields": fields } },
+            "_source": source,
+            "highlight": { "fields": { self.body_field.clone(): { "fragment_size": 200, "number_of_fragments": 3 } } },
+        });
+
+        let url = format!("{}/{}/_search", self.endpoint.trim_end_matches('/'), self.index.trim());
+        let request = golem_wasi_http::Client::new().post(url).json(&body).timeout(TIMEOUT);
+        let json = send_json(authorize(request, auth)?)?;
+
+        let hits = lookup(&json, "hits.hits")
+            .and_then(|v| v.as_array())
+            .ok_or("no hits in the response")?;
+        Ok(hits
+            .iter()
+            .filter_map(|hit| {
+                let id = text_at(hit, "_id").unwrap_or_default();
+                let url = match (&self.url_template, &self.url_field) {
+                    (Some(template), _) => template.replace("{id}", &id),
+                    (None, Some(field)) => text_at(hit, &format!("_source.{field}"))?,
+                    (None, None) => format!("{}/{}/_doc/{id}", self.endpoint.trim_end_matches('/'), self.index.trim()),
+                };
+                let snippet = text_at(hit, &format!("highlight.{}", self.body_field))
+                    .or_else(|| text_at(hit, &format!("_source.{}", self.body_field)))
+                    .map(|text| crate::truncate::chars(&text, MAX_SNIPPET_CHARS))
+                    .unwrap_or_default();
+                Some(SearchResult {
+                    title: text_at(hit, &format!("_source.{}", self.title_field)).unwrap_or_else(|| url.clone()),
+                    snippet,
+                    url,
+                })
+            })
+            .collect())
+    }
+}
+
+impl PostgrestConnector {
+    fn search(&self, query: &str, limit: u32, auth: Option<&ConnectorAuth>) -> Result<Vec<SearchResult>, String> {
+        let endpoint = self.endpoint.trim_end_matches('/');
+        let client = golem_wasi_http::Client::new();
+        let request = match (&self.function, &self.table) {
+            (Some(function), _) => client
+                .post(format!("{endpoint}/rpc/{}", function.trim()))
+                .json(&serde_json::json!({ "query": query, "limit": limit })),
+            (None, Some(table)) => {
+                let language = self.language.as_deref().unwrap_or("english");
+                let column = self.search_column.as_deref().unwrap_or(&self.body_column);
+                let params = [
+                    ("select".to_string(), self.columns().join(",")),
+                    (column.to_string(), format!("wfts({language}).{query}")),
+                    ("limit".to_string(), limit.to_string()),
+                ];
+                client.get(format!("{endpoint}/{}", table.trim())).query(&params)
+            }
+            (None, None) => return Err("needs a table or a function".to_string()),
+        };
+        let response = authorize(request.timeout(TIMEOUT), auth)?
+            .send()
+            .map_err(|err| err.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+        let rows: Vec<serde_json::Value> = response.json().map_err(|err| format!("invalid JSON response: {err}"))?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let url = match &self.url_template {
+                    Some(template) => template_columns(template).iter().fold(template.clone(), |url, column| {
+                        url.replace(&format!("{{{column}}}"), &text_at(row, column).unwrap_or_default())
+                    }),
+                    None => text_at(row, &self.url_column)?,
+                };
+                Some(SearchResult {
+                    title: text_at(row, &self.title_column).unwrap_or_else(|| url.clone()),
+                    snippet: text_at(row, &self.body_column)
+                        .map(|text| crate::truncate::chars(&text, MAX_SNIPPET_CHARS))
+                        .unwrap_or_default(),
+                    url,
+                })
+            })
+            .take(limit as usize)
+            .collect())
+    }
+
+    /// Columns selected from the table: title, body, and the URL column or template columns.
+    fn columns(&self) -> Vec<String> {
+        let mut columns = vec![self.title_column.clone(), self.body_column.clone()];
+        match &self.url_template {
+            Some(template) => columns.extend(template_columns(template)),
+            None => columns.push(self.url_column.clone()),
+        }
+        columns.sort();
+        columns.dedup();
+        columns
+    }
+}
+
+impl GoogleDriveConnector {
+    fn search(&self, query: &str, limit: u32, auth: Option<&ConnectorAuth>) -> Result<Vec<SearchResult>, String> {
+        if !matches!(auth, Some(ConnectorAuth::Bearer { .. })) {
+            return Err("needs bearer auth with a service-account access token".to_string());
+        }
+        let q = format!(
+            "'{}' in parents and fullText contains '{}' and trashed = false",
+            drive_escape(self.folder_id.trim()),
+            drive_escape(query)
+        );
+        let params = [
+            ("q", q.as_str()),
+            ("fields", "files(id,name,mimeType,webViewLink,description)"),
+            ("pageSize", &limit.to_string()),
+            ("supportsAllDrives", "true"),
+            ("includeItemsFromAllDrives", "true"),
+        ];
+        let request = golem_wasi_http::Client::new().get(DRIVE_API).query(&params).timeout(TIMEOUT);
+        let json = send_json(authorize(request, auth)?)?;
+        let files = lookup(&json, "files").and_then(|v| v.as_array()).ok_or("no files in the response")?;
+
+        Ok(files
+            .iter()
+            .filter_map(|file| {
+                let id = text_at(file, "id")?;
+                let title = text_at(file, "name").unwrap_or_else(|| id.clone());
+                let url = text_at(file, "webViewLink").unwrap_or_else(|| format!("https://drive.google.com/open?id={id}"));
+                let mime = text_at(file, "mimeType").unwrap_or_default();
+                let text = if self.fetch_text {
+                    self.document_text(&id, &mime, auth).unwrap_or_else(|err| {
+                        log::warn!("Could not read Drive file {title:?}: {err}");
+                        None
+                    })
+                } else {
+                    None
+                };
+                Some(SearchResult {
+                    snippet: text.or_else(|| text_at(file, "description")).unwrap_or_default(),
+                    title,
+                    url,
+                })
+            })
+            .take(limit as usize)
+            .collect())
+    }
+
+    /// Plain text of a Google Doc (exported) or a text file (downloaded); `None` for other types.
+    fn document_text(&self, id: &str, mime: &str, auth: Option<&ConnectorAuth>) -> Result<Option<String>, String> {
+        let client = golem_wasi_http::Client::new();
+        let request = if mime == GOOGLE_DOC_MIME {
+            client.get(format!("{DRIVE_API}/{id}/export")).query(&[("mimeType", "text/plain")])
+        } else if mime.starts_with("text/") {
+            client.get(format!("{DRIVE_API}/{id}")).query(&[("alt", "media"), ("supportsAllDrives", "true")])
+        } else {
+            return Ok(None);
+        };
+        let response = authorize(request.timeout(TIMEOUT), auth)?
+            .send()
+            .map_err(|err| err.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+        let body = response.bytes().map_err(|err| err.to_string())?;
+        let text = String::from_utf8_lossy(&body);
+        let text = text.trim();
+        Ok((!text.is_empty()).then(|| crate::truncate::chars(text, MAX_DOCUMENT_CHARS)))
+    }
+}
+
+impl ConfluenceConnector {
+    fn search(&self, query: &str, limit: u32, auth: Option<&ConnectorAuth>) -> Result<Vec<SearchResult>, String> {
+        let mut cql = format!("type = page AND text ~ {}", quoted(query));
+        if !self.spaces.is_empty() {
+            cql.push_str(&format!(" AND space IN ({})", quoted_list(&self.spaces)));
+        }
+        let base = self.base_url.trim_end_matches('/');
+        let params = [("cql", cql), ("limit", limit.to_string()), ("excerpt", "highlight".to_string())];
+        let request = golem_wasi_http::Client::new()
+            .get(format!("{base}/rest/api/search"))
+            .query(&params)
+            .timeout(TIMEOUT);
+        let json = send_json(authorize(request, auth)?)?;
+        let results = lookup(&json, "results").and_then(|v| v.as_array()).ok_or("no results in the response")?;
+
+        Ok(results
+            .iter()
+            .filter_map(|result| {
+                let path = text_at(result, "url").or_else(|| text_at(result, "content._links.webui"))?;
+                let url = if path.contains("://") {
+                    path
+                } else {
+                    format!("{base}/{}", path.trim_start_matches('/'))
+                };
+                let excerpt = text_at(result, "excerpt").unwrap_or_default();
+                Some(SearchResult {
+                    title: text_at(result, "title")
+                        .or_else(|| text_at(result, "content.title"))
+                        .map(|t| strip_highlight(&t))
+                        .unwrap_or_else(|| url.clone()),
+                    snippet: strip_highlight(&excerpt),
+                    url,
+                })
+            })
+            .take(limit as usize)
+            .collect())
+    }
+}
+
+impl JiraConnector {
+    fn search(&self, query: &str, limit: u32, auth: Option<&ConnectorAuth>) -> Result<Vec<SearchResult>, String> {
+        let mut jql = format!("text ~ {}", quoted(query));
+        if !self.projects.is_empty() {
+            jql.push_str(&format!(" AND project IN ({})", quoted_list(&self.projects)));
+        }
+        jql.push_str(" ORDER BY updated DESC");
+        let base = self.base_url.trim_end_matches('/');
+        let path = self.search_path.as_deref().unwrap_or("/rest/api/2/search");
+        let params = [
+            ("jql", jql),
+            ("maxResults", limit.to_string()),
+            ("fields", "summary,description,status".to_string()),
+        ];
+        let request = golem_wasi_http::Client::new()
+            .get(format!("{base}/{}", path.trim_start_matches('/')))
+            .query(&params)
+            .timeout(TIMEOUT);
+        let json = send_json(authorize(request, auth)?)?;
+        let issues = lookup(&json, "issues").and_then(|v| v.as_array()).ok_or("no issues in the response")?;
+
+        Ok(issues
+            .iter()
+            .filter_map(|issue| {
+                let key = text_at(issue, "key")?;
+                let summary = text_at(issue, "fields.summary").unwrap_or_default();
+                let status = text_at(issue, "fields.status.name");
+                let description = text_at(issue, "fields.description").unwrap_or_default();
+                let snippet = match status {
+                    Some(status) => format!("[{status}] {description}"),
+                    None => description,
+                };
+                Some(SearchResult {
+                    title: format!("{key}: {summary}"),
+                    snippet: crate::truncate::chars(snippet.trim(), MAX_SNIPPET_CHARS),
+                    url: format!("{base}/browse/{key}"),
+                })
+            })
+            .take(limit as usize)
+            .collect())
+    }
+}
+
+impl SlackConnector {
+    fn search(&self, query: &str, limit: u32, auth: Option<&ConnectorAuth>) -> Result<Vec<SearchResult>, String> {
+        let allowed: Vec<String> = self
+            .channels
+            .iter()
+            .map(|c| c.trim().trim_start_matches('#').to_lowercase())
+            .filter(|c| !c.is_empty())
+            .collect();
+        if allowed.is_empty() {
+            return Err("needs a channels allowlist".to_string());
+        }
+        // Over-fetch, since matches outside the allowlist are dropped below.
+        let count = (limit * 4).min(100);
+        let params = [("query", query.to_string()), ("count", count.to_string()), ("highlight", "false".to_string())];
+        let request = golem_wasi_http::Client::new()
+            .get(SLACK_SEARCH_API)
+            .query(&params)
+            .timeout(TIMEOUT);
+        let json = send_json(authorize(request, auth)?)?;
+        if json.get("ok").and_then(|v| v.as_bool()) != Some(true) {
+            return Err(format!("Slack error: {}", text_at(&json, "error").unwrap_or_else(|| "unknown".to_string())));
+        }
+        let matches = lookup(&json, "messages.matches")
+            .and_then(|v| v.as_array())
+            .ok_or("no messages in the response")?;
+
+        Ok(matches
+            .iter()
+            .filter(|m| {
+                let direct = ["channel.is_im", "channel.is_mpim"]
+                    .iter()
+                    .any(|flag| lookup(m, flag).and_then(|v| v.as_bool()) == Some(true));
+                let channel = text_at(m, "channel.name").unwrap_or_default().to_lowercase();
+                !direct && allowed.contains(&channel)
+            })
+            .filter_map(|m| {
+                let url = text_at(m, "permalink")?;
+                let channel = text_at(m, "channel.name").unwrap_or_default();
+                let date = text_at(m, "ts")
+                    .and_then(|ts| ts.split('.').next()?.parse::<u64>().ok())
+                    .map(crate::clock::date_key);
+                let text = crate::redact::redact(&text_at(m, "text").unwrap_or_default());
+                Some(SearchResult {
+                    title: match date {
+                        Some(date) => format!("Slack #{channel}, {date}"),
+                        None => format!("Slack #{channel}"),
+                    },
+                    snippet: crate::truncate::chars(&text, MAX_SNIPPET_CHARS),
+                    url,
+                })
+            })
+            .take(limit as usize)
+            .collect())
+    }
+}
+
+impl MailConnector {
+    fn search(&self, query: &str, limit: u32, auth: Option<&ConnectorAuth>) -> Result<Vec<SearchResult>, String> {
+        let folders: Vec<&str> = self.folders.iter().map(|f| f.trim()).filter(|f| !f.is_empty()).collect();
+        if folders.is_empty() {
+            return Err("needs at least one folder".to_string());
+        }
+        let client = golem_wasi_http::Client::new();
+        let mut results = Vec::new();
+        match &self.source {
+            MailSource::Graph { mailbox } => {
+                for folder in folders {
+                    let url = format!("{GRAPH_API}/users/{}/mailFolders/{folder}/messages", mailbox.trim());
+                    let params = [
+                        ("$search", format!("\"{}\"", query.replace('"', ""))),
+                        ("$top", limit.to_string()),
+                        ("$select", "subject,bodyPreview,receivedDateTime,webLink".to_string()),
+                    ];
+                    let json = send_json(authorize(client.get(url).query(&params).timeout(TIMEOUT), auth)?)?;
+                    let messages = lookup(&json, "value").and_then(|v| v.as_array()).ok_or("no messages in the response")?;
+                    results.extend(messages.iter().filter_map(|m| {
+                        Some(mail_result(
+                            text_at(m, "webLink")?,
+                            text_at(m, "subject"),
+                            text_at(m, "receivedDateTime"),
+                            text_at(m, "bodyPreview"),
+                        ))
+                    }));
+                }
+            }
+            MailSource::Gateway { endpoint } => {
+                let body = serde_json::json!({ "query": query, "folders": folders, "limit": limit });
+                let json = send_json(authorize(client.post(endpoint).json(&body).timeout(TIMEOUT), auth)?)?;
+                let messages = lookup(&json, "messages").and_then(|v| v.as_array()).ok_or("no messages in the response")?;
+                results.extend(messages.iter().filter_map(|m| {
+                    let url = text_at(m, "url").or_else(|| {
+                        Some(format!("{}/messages/{}", endpoint.trim_end_matches('/'), text_at(m, "id")?))
+                    })?;
+                    Some(mail_result(url, text_at(m, "subject"), text_at(m, "date"), text_at(m, "body")))
+                }));
+            }
+        }
+        results.truncate(limit as usize);
+        Ok(results)
+    }
+}
+
+/// A redacted mail search result; the sender is deliberately left out.
+fn mail_result(url: String, subject: Option<String>, date: Option<String>, body: Option<String>) -> SearchResult {
+    let subject = crate::redact::redact(subject.as_deref().unwrap_or("(no subject)"));
+    let title = match date {
+        // ISO timestamps: keep the date.
+        Some(date) => format!("Email: {subject} ({})", date.get(..10).unwrap_or(&date)),
+        None => format!("Email: {subject}"),
+    };
+    SearchResult {
+        title,
+        snippet: crate::truncate::chars(&crate::redact::redact(&body.unwrap_or_default()), MAX_SNIPPET_CHARS),
+        url,
+    }
+}
+
+fn send_json(request: golem_wasi_http::RequestBuilder) -> Result<serde_json::Value, String> {
+    let response = request.send().map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    response.json().map_err(|err| format!("invalid JSON response: {err}"))
+}
+
+/// A double-quoted CQL/JQL string.
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn quoted_list(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|v| quoted(v.trim()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Drops Confluence's `@@@hl@@@` / `@@@endhl@@@` highlight markers.
+fn strip_highlight(text: &str) -> String {
+    text.replace("@@@hl@@@", "").replace("@@@endhl@@@", "")
+}
+
+/// Escapes a value for a single-quoted Drive query string.
+fn drive_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+/// `{column}` placeholders in a URL template.
+fn template_columns(template: &str) -> Vec<String> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(name, _)| name.trim().to_string()))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Connectors from `SEARCH_CONNECTORS`; invalid config is logged and yields none.
+pub fn from_env() -> Vec<ExternalSearchConnector> {
+    let Ok(raw) = std::env::var("SEARCH_CONNECTORS") else {
+        return Vec::new();
+    };
+    if raw.trim().is_empty() {
+        return Vec::new();
+    }
+    match serde_json::from_str::<Vec<ExternalSearchConnector>>(&raw) {
+        Ok(connectors) => connectors,
+        Err(err) => {
+            log::warn!("SEARCH_CONNECTORS is not a valid JSON connector list: {err}");
+            Vec::new()
+        }
+    }
+}
+
+/// The connectors a request uses: the named ones, or every default-enabled one.
+pub fn select<'a>(
+    connectors: &'a [ExternalSearchConnector],
+    requested: Option<&[String]>,
+) -> Result<Vec<&'a ExternalSearchConnector>, String> {
+    let Some(requested) = requested else {
+        return Ok(connectors.iter().filter(|c| c.default_enabled()).collect());
+    };
+    requested
+        .iter()
+        .map(|name| {
+            connectors
+                .iter()
+                .find(|c| c.name.eq_ignore_ascii_case(name.trim()))
+                .ok_or_else(|| format!("Unknown search connector {name:?}"))
+        })
+        .collect()
+}
+
+/// Alternates web and connector results so both survive the prompt's source limit.
+pub fn interleave(web: Vec<SearchResult>, internal: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut merged = Vec::with_capacity(web.len() + internal.len());
+    let mut web = web.into_iter();
+    let mut internal = internal.into_iter();
+    loop {
+        match (web.next(), internal.next()) {
+            (None, None) => break,
+            (a, b) => merged.extend(a.into_iter().chain(b)),
+        }
+    }
+    merged
+}
+
+fn authorize(
+    request: golem_wasi_http::RequestBuilder,
+    auth: Option<&ConnectorAuth>,
+) -> Result<golem_wasi_http::RequestBuilder, String> {
+    let secret = |var: &str| {
+        std::env::var(var)
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .ok_or_else(|| format!("{var} is not set"))
+    };
+    Ok(match auth {
+        None => request,
+        Some(ConnectorAuth::Bearer { token_env }) => request.bearer_auth(secret(token_env)?),
+        Some(ConnectorAuth::Basic {
+            username_env,
+            password_env,
+        }) => request.basic_auth(secret(username_env)?, Some(secret(password_env)?)),
+        Some(ConnectorAuth::Header { name, value_env }) => request.header(name, secret(value_env)?),
+        Some(ConnectorAuth::ApiKey { key_env }) => {
+            request.header("Authorization", format!("ApiKey {}", secret(key_env)?))
+        }
+    })
+}
+
+/// Replaces `"{query}"`/`"{limit}"` string values (and `{query}` inside strings) in a body.
+fn fill_placeholders(value: &serde_json::Value, query: &str, limit: u32) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) if s == "{limit}" => serde_json::Value::from(limit),
+        serde_json::Value::String(s) => serde_json::Value::String(s.replace("{query}", query)),
+        serde_json::Value::Array(items) => items.iter().map(|v| fill_placeholders(v, query, limit)).collect(),
+        serde_json::Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| (k.clone(), fill_placeholders(v, query, limit)))
+            .collect(),
+        other => other.clone(),
+    }
+}
+
+/// Value at a dotted path such as `hits.hits` or `_source.title`; array indexes are numbers.
+pub fn lookup<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |current, segment| match current {
+            serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => current.get(segment),
+        })
+}
+
+/// Text at a dotted path; arrays (e.g. highlight fragments) are joined.
+pub fn text_at(value: &serde_json::Value, path: &str) -> Option<String> {
+    let text = match lookup(value, path)? {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect::<Vec<_>>()
+            .join(" … "),
+        serde_json::Value::Null => return None,
+        other => other.to_string(),
+    };
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
@@ -0,0 +1,156 @@
+//! Full page content for the top web results, so the model reads more than a search snippet.
+//!
+//! The first `CONTENT_FETCH_PAGES` web results (default 3, 0 turns fetching off;
+//! `ResearchOptions.fetch_pages` overrides it per request) are downloaded, reduced to their
+//! readable text and appended to the snippet, cut to `CONTENT_FETCH_MAX_PAGE_TOKENS` (default
+//! 1000) each. Each page gets `CONTENT_FETCH_TIMEOUT_SECONDS` (default 10); a page that can't
+//! be fetched keeps its snippet.
+//!
+//! Fetched pages are cached by content hash (see `page_cache`), so repeat research on a topic
+//! doesn't download or extract unchanged pages again.
+//!
+//! Extraction is readability-style: the page's `<article>` or `<main>` (or the domain's
+//! `EXTRACTION_RULES` entry) is stripped to text, then lines that look like navigation, bylines
+//! or link lists rather than prose are dropped. Results that link to a PDF (by content type or
+//! signature) get the document's text instead (see `pdf`), so papers and reports can be quoted.
+
+use std::time::Duration;
+
+use crate::config;
+use crate::fetch::{self, Conditional};
+use crate::page_cache::{self, CachedPage, PageCache};
+use crate::{differential, prompt, truncate, SearchResult};
+
+const DEFAULT_PAGES: u32 = 3;
+pub const MAX_PAGES: u32 = 10;
+const DEFAULT_MAX_PAGE_TOKENS: usize = 1000;
+const DEFAULT_TIMEOUT_SECONDS: u64 = 10;
+/// Lines with fewer words are kept only when they end a sentence.
+const MIN_LINE_WORDS: usize = 8;
+/// Snippets this long already carry page text (full-text providers) and aren't fetched again.
+const PAGE_TEXT_CHARS: usize = 1000;
+
+pub struct FetchSettings {
+    pages: u32,
+    max_page_tokens: usize,
+    timeout: Duration,
+}
+
+impl FetchSettings {
+    pub fn from_env() -> Self {
+        Self {
+            pages: config::env_parse::<u64>("CONTENT_FETCH_PAGES").map_or(DEFAULT_PAGES, |n| (n as u32).min(MAX_PAGES)),
+            max_page_tokens: config::env_parse::<u64>("CONTENT_FETCH_MAX_PAGE_TOKENS").map_or(DEFAULT_MAX_PAGE_TOKENS, |n| n as usize),
+            timeout: Duration::from_secs(config::env_parse::<u64>("CONTENT_FETCH_TIMEOUT_SECONDS").unwrap_or(DEFAULT_TIMEOUT_SECONDS)),
+        }
+    }
+
+    /// Pages to fetch for a request: its `fetch_pages`, else the configured default.
+    pub fn pages(&self, requested: Option<u32>) -> u32 {
+        requested.map_or(self.pages, |n| n.min(MAX_PAGES))
+    }
+}
+
+/// Appends the readable text of each result's page to its snippet, in order, until `pages`
+/// pages have been fetched. Pages come from `cache` where it still has them (see `page_cache`).
+/// Returns the failures, one message per page.
+pub fn enrich<'a>(
+    results: impl IntoIterator<Item = &'a mut SearchResult>,
+    pages: u32,
+    settings: &FetchSettings,
+    cache: &PageCache,
+    policy: &page_cache::CachePolicy,
+    now: u64,
+) -> Vec<String> {
+    let max_chars = prompt::chars_for_tokens(settings.max_page_tokens);
+    let mut failures = Vec::new();
+    for result in results
+        .into_iter()
+        .filter(|result| result.snippet.chars().count() < PAGE_TEXT_CHARS)
+        .take(pages as usize)
+    {
+        match page_text(&result.url, settings, cache, policy, now) {
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => {
+                let text = truncate::chars(&text, max_chars);
+                result.snippet = if result.snippet.trim().is_empty() {
+                    text
+                } else {
+                    format!("{}\n\nPage text: {text}", result.snippet.trim())
+                };
+            }
+            Err(err) => failures.push(err),
+        }
+    }
+    failures
+}
+
+/// The readable text of the page at `url`, from the cache while it is fresh or unchanged.
+fn page_text(
+    url: &str,
+    settings: &FetchSettings,
+    cache: &PageCache,
+    policy: &page_cache::CachePolicy,
+    now: u64,
+) -> Result<String, String> {
+    let cached = cache.get(url).filter(|_| policy.enabled());
+    if let Some(page) = &cached {
+        if now.saturating_sub(page.checked_at) < policy.fresh_seconds {
+            return Ok(page.text.clone());
+        }
+    }
+
+    let validators = cached.as_ref().map(|page| page.validators.clone()).unwrap_or_default();
+    let (content_type, body, validators) = match fetch::fetch_if_modified(url, settings.timeout, &validators)? {
+        Conditional::Modified {
+            content_type,
+            body,
+            validators,
+        } => (content_type, body, validators),
+        Conditional::NotModified => {
+            return match cached {
+                Some(page) => {
+                    cache.confirm(url, None, now);
+                    Ok(page.text)
+                }
+                None => Err(format!("Could not fetch {url}: HTTP 304 for a page not in the cache")),
+            };
+        }
+    };
+
+    let content_hash = differential::digest(&String::from_utf8_lossy(&body));
+    if let Some(page) = cached.filter(|page| page.content_hash == content_hash) {
+        cache.confirm(url, Some(validators), now);
+        return Ok(page.text);
+    }
+    let text = readable_text(&fetch::page_from_body(url, &content_type, body)?.text);
+    cache.store(
+        CachedPage {
+            url: url.to_string(),
+            content_hash,
+            validators,
+            text: text.clone(),
+            changed_at: now,
+            checked_at: now,
+        },
+        policy,
+    );
+    Ok(text)
+}
+
+/// The prose of extracted page text: short lines that don't end a sentence (menus, bylines,
+/// share buttons, link lists) and repeated lines are dropped.
+pub fn readable_text(text: &str) -> String {
+    let mut seen: Vec<&str> = Vec::new();
+    let mut lines = Vec::new();
+    for line in text.lines().map(str::trim) {
+        let words = line.split_whitespace().count();
+        let sentence = line.ends_with(['.', '!', '?', ':', '"', '”']);
+        if words == 0 || (words < MIN_LINE_WORDS && !sentence) || seen.contains(&line) {
+            continue;
+        }
+        seen.push(line);
+        lines.push(line);
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,58 @@
+//! Context window of the active model, used to scale the prompt budget.
+//!
+//! Resolution order: `LLM_CONTEXT_WINDOW` (tokens), then for Ollama the model's `num_ctx`
+//! parameter from `/api/show` or the server's default context size, then a table of well-known hosted
+//! models, then a conservative 8k default. Part of the window is kept free for the answer:
+//! `LLM_MAX_OUTPUT_TOKENS` if set (also sent as the request's `max_tokens`), otherwise a
+//! quarter of the window up to 4096 tokens.
+
+use crate::config;
+use crate::ollama;
+
+const DEFAULT_CONTEXT_WINDOW: u32 = 8_192;
+const MAX_DEFAULT_OUTPUT_RESERVE: u32 = 4_096;
+
+/// Model-name prefixes of hosted models and their context windows, most specific first.
+const KNOWN_WINDOWS: &[(&str, u32)] = &[
+    ("gpt-4o", 128_000),
+    ("gpt-4.1", 1_000_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("claude", 200_000),
+    ("grok", 131_072),
+];
+
+pub fn resolve(model: &str) -> u32 {
+    if let Some(window) = config::env_parse::<u32>("LLM_CONTEXT_WINDOW").filter(|n| *n > 0) {
+        return window;
+    }
+    if ollama::is_configured() {
+        if let Some(window) = ollama::context_window(model) {
+            return window;
+        }
+    }
+
+    let model = model.trim().to_ascii_lowercase();
+    let name = model.rsplit('/').next().unwrap_or(&model);
+    KNOWN_WINDOWS
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map(|(_, window)| *window)
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+/// `max_tokens` to send with the request, if the operator configured one.
+pub fn max_output_tokens() -> Option<u32> {
+    config::env_parse::<u32>("LLM_MAX_OUTPUT_TOKENS").filter(|n| *n > 0)
+}
+
+/// Tokens kept free for the model's answer.
+pub fn output_reserve(window: u32) -> u32 {
+    max_output_tokens()
+        .unwrap_or_else(|| (window / 4).min(MAX_DEFAULT_OUTPUT_RESERVE))
+        .min(window / 2)
+}
@@ -0,0 +1,95 @@
+//! Conversation memory for `ask_followup`. The last `research` or `research_with_options` run
+//! is kept in worker state (its topic, the sources the model saw and its answer), and
+//! follow-up questions are answered from that material and the earlier follow-ups instead of a
+//! new search. A new research run starts a new conversation. Research sessions (`sessions`) keep
+//! a conversation of their own each.
+
+use crate::truncate;
+use crate::SearchResult;
+
+/// Follow-ups kept per conversation; the oldest are dropped first.
+const MAX_TURNS: usize = 20;
+/// Earlier follow-ups repeated in the prompt.
+const PROMPT_TURNS: usize = 4;
+/// Length at which the research answer and earlier follow-up answers are cut in the prompt.
+const MAX_ANSWER_CHARS: usize = 6000;
+const MAX_TURN_ANSWER_CHARS: usize = 1500;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct FollowupTurn {
+    pub question: String,
+    pub answer: String,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Conversation {
+    pub workspace: String,
+    pub topic: String,
+    /// The model that wrote the research answer; follow-ups use it too.
+    pub model: String,
+    /// Sources the research answer was written from, with snippets as sent. A report served
+    /// from the report cache has its cited sources here, without snippets.
+    pub sources: Vec<SearchResult>,
+    pub answer: String,
+    pub turns: Vec<FollowupTurn>,
+    pub updated_at: u64,
+}
+
+impl Conversation {
+    pub fn new(workspace: &str, topic: &str, model: &str, sources: Vec<SearchResult>, answer: &str, at: u64) -> Self {
+        Self {
+            workspace: workspace.to_string(),
+            topic: topic.to_string(),
+            model: model.to_string(),
+            sources,
+            answer: answer.to_string(),
+            turns: Vec::new(),
+            updated_at: at,
+        }
+    }
+
+    pub fn record(&mut self, question: &str, answer: &str, at: u64) {
+        self.turns.push(FollowupTurn {
+            question: question.to_string(),
+            answer: answer.to_string(),
+        });
+        if self.turns.len() > MAX_TURNS {
+            self.turns.remove(0);
+        }
+        self.updated_at = at;
+    }
+
+    /// The follow-up prompt for `question`, with `sources` (the conversation's, fitted to the
+    /// context window by the caller).
+    pub fn prompt(&self, question: &str, sources: &[SearchResult]) -> String {
+        let sources_json = serde_json::to_string(sources).unwrap_or_else(|_| "[]".to_string());
+        let earlier = self.turns[self.turns.len().saturating_sub(PROMPT_TURNS)..]
+            .iter()
+            .map(|turn| {
+                format!(
+                    "Q: {}\nA: {}",
+                    turn.question,
+                    truncate::chars(&turn.answer, MAX_TURN_ANSWER_CHARS)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let earlier = if earlier.is_empty() {
+            String::new()
+        } else {
+            format!("Earlier follow-up questions and your answers:\n{earlier}\n\n")
+        };
+        format!(
+            "You researched the topic \"{}\" and wrote the report below from the search results at the bottom.\n\
+             Answer the follow-up question, or carry out the follow-up instruction, from the report and the search results, going into more detail where they allow. \
+             Cite the sources you rely on as inline markdown links. If they don't cover the question, say so and answer from \
+             your own knowledge, marked as such.\n\n\
+             Report:\n{}\n\n\
+             {earlier}\
+             Follow-up: {question}\n\n\
+             Search results: {sources_json}",
+            self.topic,
+            truncate::chars(&self.answer, MAX_ANSWER_CHARS),
+        )
+    }
+}
@@ -0,0 +1,592 @@
+//! Private document corpus per workspace, filled by external systems through
+//! `ingest_document` (e.g. a webhook from a CMS or a document pipeline). Research runs in the
+//! workspace retrieve the best-matching documents alongside web results.
+//!
+//! Documents are split into passages ("chunks") on ingest. Each chunk is tokenized for keyword
+//! search and, when `EMBEDDING_MODEL` is set, embedded right away; the vectors live in agent
+//! state with the documents, so they persist without an external vector store.
+//!
+//! Retrieval is hybrid by default: the BM25 and embedding-similarity rankings of the chunks are
+//! merged with reciprocal rank fusion, so passages either method finds make it in.
+//! `CORPUS_RETRIEVAL=keyword|vector` uses one method alone, e.g. to compare recall.
+
+use golem_rust::Schema;
+
+use crate::config;
+use crate::embed::{self, Embedder};
+use crate::explain::CorpusHit;
+use crate::schema::SCHEMA_VERSION;
+use crate::{compress, rank, truncate, SearchResult};
+
+/// Documents kept per workspace when `CORPUS_DOCUMENT_LIMIT` is unset or 0.
+const DEFAULT_DOCUMENT_LIMIT: usize = 5000;
+/// Longer documents are cut; split very long sources into several documents instead.
+const MAX_DOCUMENT_CHARS: usize = 200_000;
+const MAX_METADATA_ENTRIES: usize = 50;
+const MAX_TAGS: usize = 20;
+const DEFAULT_RESULTS_PER_QUERY: usize = 3;
+/// Chunks taken from each ranking before fusion.
+const FUSION_CANDIDATES: usize = 50;
+/// Target chunk size; chunks end at a paragraph or sentence break where possible.
+const CHUNK_BYTES: usize = 1500;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct MetadataEntry {
+    pub key: String,
+    pub value: String,
+}
+
+/// A document pushed by an external system.
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct IngestDocument {
+    /// The sender's id for the document; ingesting the same `external_id` again replaces it.
+    pub external_id: Option<String>,
+    pub title: String,
+    /// Where readers can open the original; cited as `corpus://{workspace}/{id}` when absent.
+    pub url: Option<String>,
+    pub text: String,
+    pub metadata: Vec<MetadataEntry>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct CorpusDocument {
+    /// See `schema`; version 0 is a record from before versioning.
+    #[serde(default)]
+    pub schema_version: u32,
+    pub id: String,
+    pub workspace: String,
+    pub external_id: Option<String>,
+    pub title: String,
+    pub url: String,
+    pub metadata: Vec<MetadataEntry>,
+    pub chars: u32,
+    /// Whether the text was cut to the 200,000-character limit.
+    pub truncated: bool,
+    /// Passages the document was split into for retrieval.
+    pub chunks: u32,
+    /// Whether every chunk has an embedding (vector search covers the document).
+    pub embedded: bool,
+    pub tags: Vec<String>,
+    pub ingested_at: u64,
+    pub updated_at: u64,
+}
+
+/// Narrows `list_documents`; unset fields match everything.
+#[derive(Clone, Debug, Default, Schema, serde::Serialize, serde::Deserialize)]
+pub struct DocumentFilter {
+    /// Documents carrying this tag.
+    pub tag: Option<String>,
+    /// Documents with this metadata key, and value when it's not empty.
+    pub metadata: Option<MetadataEntry>,
+    /// Case-insensitive substring of the title.
+    pub title_contains: Option<String>,
+    /// Documents ingested or updated at or after this time (unix seconds).
+    pub updated_since: Option<u64>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub documents: u32,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct CorpusStats {
+    pub documents: u32,
+    pub total_chars: u64,
+    pub truncated_documents: u32,
+    pub chunks: u32,
+    pub embedded_documents: u32,
+    /// Most used first.
+    pub tags: Vec<TagCount>,
+    pub oldest_ingested_at: Option<u64>,
+    pub last_updated_at: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetrievalMode {
+    Hybrid,
+    Keyword,
+    Vector,
+}
+
+impl RetrievalMode {
+    /// `CORPUS_RETRIEVAL`; vector mode falls back to keyword search when there are no vectors.
+    pub fn from_env() -> Self {
+        match std::env::var("CORPUS_RETRIEVAL")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "keyword" | "bm25" => Self::Keyword,
+            "vector" | "embedding" => Self::Vector,
+            _ => Self::Hybrid,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Hybrid => "hybrid",
+            Self::Keyword => "keyword",
+            Self::Vector => "vector",
+        }
+    }
+}
+
+/// A retrieved chunk (as indices into the corpus) and how it scored.
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkScore {
+    pub document: usize,
+    pub chunk: usize,
+    pub bm25: f64,
+    /// 1-based position among the BM25 fusion candidates, if it was one.
+    pub bm25_rank: Option<usize>,
+    pub similarity: Option<f64>,
+    pub vector_rank: Option<usize>,
+    /// What results are ordered by: the fused score in hybrid mode, else the one method's.
+    pub score: f64,
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Corpus {
+    documents: Vec<StoredDocument>,
+    next_id: u64,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct StoredDocument {
+    info: CorpusDocument,
+    text: String,
+    chunks: Vec<Chunk>,
+}
+
+/// A passage of a document: the unit that is indexed and retrieved.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct Chunk {
+    /// Byte range in the document text.
+    start: usize,
+    end: usize,
+    /// Title and passage tokens for keyword search; left out of snapshots and rebuilt on load.
+    #[serde(skip)]
+    words: Vec<String>,
+    /// Unit-length embedding; `None` until embedded.
+    vector: Option<Vec<f32>>,
+}
+
+impl StoredDocument {
+    fn new(info: CorpusDocument, text: String) -> Self {
+        let mut document = StoredDocument {
+            info,
+            text,
+            chunks: Vec::new(),
+        };
+        document.index();
+        document
+    }
+
+    /// Re-chunks and re-tokenizes the text; embeddings are dropped.
+    fn index(&mut self) {
+        self.chunks = chunk_ranges(&self.text)
+            .into_iter()
+            .map(|(start, end)| Chunk {
+                start,
+                end,
+                words: Vec::new(),
+                vector: None,
+            })
+            .collect();
+        self.tokenize();
+        self.info.chunks = self.chunks.len() as u32;
+        self.info.embedded = false;
+    }
+
+    /// Fills in the keyword tokens of the existing chunks, keeping their embeddings.
+    fn tokenize(&mut self) {
+        let title_words: Vec<String> = compress::words(&self.info.title).collect();
+        for chunk in &mut self.chunks {
+            chunk.words = title_words
+                .iter()
+                .cloned()
+                .chain(compress::words(&self.text[chunk.start..chunk.end]))
+                .collect();
+        }
+    }
+
+    fn chunk_text(&self, chunk: &Chunk) -> &str {
+        self.text[chunk.start..chunk.end].trim()
+    }
+
+    fn result(&self, chunk: &Chunk) -> SearchResult {
+        SearchResult {
+            url: self.info.url.clone(),
+            title: self.info.title.clone(),
+            snippet: self.chunk_text(chunk).to_string(),
+        }
+    }
+
+    fn matches(&self, filter: &DocumentFilter) -> bool {
+        let tag_ok = filter
+            .tag
+            .as_deref()
+            .map(normalize_tag)
+            .is_none_or(|tag| self.info.tags.contains(&tag));
+        let metadata_ok = filter.metadata.as_ref().is_none_or(|wanted| {
+            self.info.metadata.iter().any(|m| {
+                m.key == wanted.key.trim() && (wanted.value.trim().is_empty() || m.value == wanted.value.trim())
+            })
+        });
+        let title_ok = filter
+            .title_contains
+            .as_deref()
+            .is_none_or(|needle| self.info.title.to_lowercase().contains(&needle.trim().to_lowercase()));
+        let since_ok = filter.updated_since.is_none_or(|since| self.info.updated_at >= since);
+        tag_ok && metadata_ok && title_ok && since_ok
+    }
+}
+
+impl Corpus {
+    /// Adds a document, or replaces the one with the same `external_id`.
+    pub fn ingest(&mut self, workspace: &str, doc: IngestDocument, now: u64) -> Result<CorpusDocument, String> {
+        let title = doc.title.trim().to_string();
+        let text = doc.text.trim();
+        if title.is_empty() {
+            return Err("Document title must not be empty".to_string());
+        }
+        if text.is_empty() {
+            return Err("Document text must not be empty".to_string());
+        }
+        if doc.metadata.len() > MAX_METADATA_ENTRIES {
+            return Err(format!("At most {MAX_METADATA_ENTRIES} metadata entries are allowed"));
+        }
+        let url = doc.url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+        if let Some(url) = &url {
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                return Err(format!("Document url must be http(s), got {url:?}"));
+            }
+        }
+        let external_id = doc.external_id.map(|id| id.trim().to_string()).filter(|id| !id.is_empty());
+
+        let kept = truncate::prefix(text, MAX_DOCUMENT_CHARS);
+        let (text, truncated) = (kept.to_string(), kept.len() < text.len());
+        let existing = external_id.as_ref().and_then(|external| {
+            self.documents
+                .iter()
+                .position(|d| d.info.workspace == workspace && d.info.external_id.as_ref() == Some(external))
+        });
+        // Re-ingesting keeps the document's id and curation.
+        let (id, ingested_at, tags) = match existing {
+            Some(i) => {
+                let info = &self.documents[i].info;
+                (info.id.clone(), info.ingested_at, info.tags.clone())
+            }
+            None => {
+                self.next_id += 1;
+                (format!("doc-{}", self.next_id), now, Vec::new())
+            }
+        };
+
+        let info = CorpusDocument {
+            schema_version: SCHEMA_VERSION,
+            url: url.unwrap_or_else(|| format!("corpus://{workspace}/{id}")),
+            id,
+            workspace: workspace.to_string(),
+            external_id,
+            title,
+            metadata: doc.metadata,
+            chars: text.chars().count() as u32,
+            truncated,
+            chunks: 0,
+            embedded: false,
+            tags,
+            ingested_at,
+            updated_at: now,
+        };
+        let stored = StoredDocument::new(info.clone(), text);
+        match existing {
+            Some(i) => self.documents[i] = stored,
+            None => {
+                self.documents.push(stored);
+                self.enforce_limit(workspace);
+            }
+        }
+        Ok(info)
+    }
+
+    pub fn get(&self, workspace: &str, id: &str) -> Option<&CorpusDocument> {
+        self.documents
+            .iter()
+            .find(|d| d.info.workspace == workspace && d.info.id == id)
+            .map(|d| &d.info)
+    }
+
+    pub fn is_empty(&self, workspace: &str) -> bool {
+        !self.documents.iter().any(|d| d.info.workspace == workspace)
+    }
+
+    /// A ranked chunk as a search result, with the chunk text as the snippet.
+    pub fn result(&self, score: &ChunkScore) -> SearchResult {
+        let document = &self.documents[score.document];
+        document.result(&document.chunks[score.chunk])
+    }
+
+    pub fn hit(&self, score: &ChunkScore, used: bool) -> CorpusHit {
+        let info = &self.documents[score.document].info;
+        CorpusHit {
+            document_id: info.id.clone(),
+            title: info.title.clone(),
+            url: info.url.clone(),
+            chunk: score.chunk as u32,
+            bm25: score.bm25,
+            bm25_rank: score.bm25_rank.map(|r| r as u32),
+            similarity: score.similarity,
+            vector_rank: score.vector_rank.map(|r| r as u32),
+            score: score.score,
+            used,
+        }
+    }
+
+    /// The workspace's documents that match `query`, best first, each represented by its
+    /// best-scoring chunk.
+    pub fn rank(&self, workspace: &str, query: &str, query_vector: Option<&[f32]>, mode: RetrievalMode) -> Vec<ChunkScore> {
+        let chunks: Vec<(usize, usize)> = self
+            .documents
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.info.workspace == workspace)
+            .flat_map(|(i, d)| (0..d.chunks.len()).map(move |c| (i, c)))
+            .collect();
+        let chunk = |&(d, c): &(usize, usize)| &self.documents[d].chunks[c];
+
+        let terms = compress::terms(query);
+        let words: Vec<&[String]> = chunks.iter().map(|key| chunk(key).words.as_slice()).collect();
+        let bm25 = rank::bm25(&words, &terms);
+        let similarity: Option<Vec<f64>> = query_vector
+            .filter(|_| mode != RetrievalMode::Keyword && chunks.iter().any(|key| chunk(key).vector.is_some()))
+            .map(|query| {
+                chunks
+                    .iter()
+                    .map(|key| chunk(key).vector.as_deref().map_or(0.0, |v| f64::from(embed::similarity(query, v))))
+                    .collect()
+            });
+
+        let keyword_ranking = rank::top_indices(&bm25, FUSION_CANDIDATES);
+        let vector_ranking = similarity
+            .as_deref()
+            .map(|s| rank::top_indices(s, FUSION_CANDIDATES))
+            .unwrap_or_default();
+        let scores = match (mode, &similarity) {
+            (RetrievalMode::Keyword, _) | (_, None) => bm25.clone(),
+            (RetrievalMode::Vector, Some(similarity)) => similarity.clone(),
+            (RetrievalMode::Hybrid, Some(_)) => {
+                rank::reciprocal_rank_fusion(&[keyword_ranking.clone(), vector_ranking.clone()], chunks.len())
+            }
+        };
+        let position = |ranking: &[usize], i: usize| ranking.iter().position(|&r| r == i).map(|p| p + 1);
+
+        let mut seen = vec![false; self.documents.len()];
+        let mut best: Vec<ChunkScore> = Vec::new();
+        for i in rank::top_indices(&scores, usize::MAX) {
+            let (document, chunk) = chunks[i];
+            if !std::mem::replace(&mut seen[document], true) {
+                best.push(ChunkScore {
+                    document,
+                    chunk,
+                    bm25: bm25[i],
+                    bm25_rank: position(&keyword_ranking, i),
+                    similarity: similarity.as_ref().map(|s| s[i]),
+                    vector_rank: position(&vector_ranking, i),
+                    score: scores[i],
+                });
+            }
+        }
+        best
+    }
+
+    /// Embeds the chunks that have no vector yet, for one document or the whole workspace;
+    /// returns how many chunks were embedded.
+    pub fn embed(&mut self, workspace: &str, id: Option<&str>, embedder: &Embedder) -> Result<u32, String> {
+        let mut embedded = 0;
+        for document in self
+            .documents
+            .iter_mut()
+            .filter(|d| d.info.workspace == workspace && id.is_none_or(|id| d.info.id == id))
+        {
+            let pending: Vec<usize> = (0..document.chunks.len())
+                .filter(|&i| document.chunks[i].vector.is_none())
+                .collect();
+            if pending.is_empty() {
+                continue;
+            }
+            let inputs: Vec<String> = pending
+                .iter()
+                .map(|&i| format!("{}\n\n{}", document.info.title, document.chunk_text(&document.chunks[i])))
+                .collect();
+            let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+            let vectors = embedder.embed(&inputs)?;
+            for (i, vector) in pending.into_iter().zip(vectors) {
+                document.chunks[i].vector = Some(vector);
+                embedded += 1;
+            }
+            document.info.embedded = true;
+        }
+        Ok(embedded)
+    }
+
+    /// Newest (last updated) first.
+    pub fn list(&self, workspace: &str, filter: &DocumentFilter) -> Vec<CorpusDocument> {
+        let mut documents: Vec<CorpusDocument> = self
+            .documents
+            .iter()
+            .filter(|d| d.info.workspace == workspace && d.matches(filter))
+            .map(|d| d.info.clone())
+            .collect();
+        documents.sort_by_key(|d| std::cmp::Reverse(d.updated_at));
+        documents
+    }
+
+    /// Adds and removes tags; tags are trimmed and lowercased.
+    pub fn tag(&mut self, workspace: &str, id: &str, add: &[String], remove: &[String]) -> Result<CorpusDocument, String> {
+        let document = self
+            .documents
+            .iter_mut()
+            .find(|d| d.info.workspace == workspace && d.info.id == id)
+            .ok_or_else(|| format!("Unknown document {id:?}"))?;
+        let remove: Vec<String> = remove.iter().map(|t| normalize_tag(t)).collect();
+        document.info.tags.retain(|t| !remove.contains(t));
+        for tag in add.iter().map(|t| normalize_tag(t)).filter(|t| !t.is_empty()) {
+            if !document.info.tags.contains(&tag) {
+                if document.info.tags.len() >= MAX_TAGS {
+                    return Err(format!("A document can have at most {MAX_TAGS} tags"));
+                }
+                document.info.tags.push(tag);
+            }
+        }
+        Ok(document.info.clone())
+    }
+
+    pub fn delete(&mut self, workspace: &str, id: &str) -> bool {
+        let before = self.documents.len();
+        self.documents
+            .retain(|d| !(d.info.workspace == workspace && d.info.id == id));
+        self.documents.len() != before
+    }
+
+    /// Rebuilds the chunks and keyword index of one document, or of the whole workspace when
+    /// `id` is `None`, dropping embeddings; returns how many documents were reindexed.
+    pub fn reindex(&mut self, workspace: &str, id: Option<&str>) -> Result<u32, String> {
+        let mut count = 0;
+        for document in self
+            .documents
+            .iter_mut()
+            .filter(|d| d.info.workspace == workspace && id.is_none_or(|id| d.info.id == id))
+        {
+            document.index();
+            count += 1;
+        }
+        match id {
+            Some(id) if count == 0 => Err(format!("Unknown document {id:?}")),
+            _ => Ok(count),
+        }
+    }
+
+    /// Rebuilds the keyword index of every document after a snapshot restore.
+    pub fn restore_index(&mut self) {
+        for document in &mut self.documents {
+            document.tokenize();
+        }
+    }
+
+    pub fn stats(&self, workspace: &str) -> CorpusStats {
+        let documents: Vec<&CorpusDocument> = self
+            .documents
+            .iter()
+            .filter(|d| d.info.workspace == workspace)
+            .map(|d| &d.info)
+            .collect();
+        let mut tags: Vec<TagCount> = Vec::new();
+        for tag in documents.iter().flat_map(|d| &d.tags) {
+            match tags.iter_mut().find(|t| &t.tag == tag) {
+                Some(count) => count.documents += 1,
+                None => tags.push(TagCount {
+                    tag: tag.clone(),
+                    documents: 1,
+                }),
+            }
+        }
+        tags.sort_by(|a, b| b.documents.cmp(&a.documents).then_with(|| a.tag.cmp(&b.tag)));
+
+        CorpusStats {
+            documents: documents.len() as u32,
+            total_chars: documents.iter().map(|d| u64::from(d.chars)).sum(),
+            truncated_documents: documents.iter().filter(|d| d.truncated).count() as u32,
+            chunks: documents.iter().map(|d| d.chunks).sum(),
+            embedded_documents: documents.iter().filter(|d| d.embedded).count() as u32,
+            tags,
+            oldest_ingested_at: documents.iter().map(|d| d.ingested_at).min(),
+            last_updated_at: documents.iter().map(|d| d.updated_at).max(),
+        }
+    }
+
+    /// Drops the oldest documents beyond the workspace's limit.
+    fn enforce_limit(&mut self, workspace: &str) {
+        let count = self.documents.iter().filter(|d| d.info.workspace == workspace).count();
+        let mut excess = count.saturating_sub(document_limit());
+        self.documents.retain(|d| {
+            if excess > 0 && d.info.workspace == workspace {
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+/// Documents kept per workspace (`CORPUS_DOCUMENT_LIMIT`, default 5000); 0 counts as unset.
+fn document_limit() -> usize {
+    config::env_parse::<usize>("CORPUS_DOCUMENT_LIMIT")
+        .filter(|limit| *limit > 0)
+        .unwrap_or(DEFAULT_DOCUMENT_LIMIT)
+}
+
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Corpus documents added to each research query (`CORPUS_RESULTS_PER_QUERY`, default 3); 0
+/// counts as unset, like `CORPUS_DOCUMENT_LIMIT`.
+pub fn results_per_query() -> usize {
+    config::env_parse::<usize>("CORPUS_RESULTS_PER_QUERY")
+        .filter(|limit| *limit > 0)
+        .unwrap_or(DEFAULT_RESULTS_PER_QUERY)
+}
+
+/// Byte ranges of roughly `CHUNK_BYTES`, cut at a paragraph break, else a sentence end, else
+/// whitespace in the second half of each window.
+fn chunk_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let rest = &text[start..];
+        if rest.len() <= CHUNK_BYTES {
+            ranges.push((start, text.len()));
+            break;
+        }
+        let window = truncate::prefix_bytes(rest, CHUNK_BYTES);
+        let half = window.len() / 2;
+        let cut = [
+            window.rfind("\n\n").map(|i| i + 2),
+            window.rfind(". ").map(|i| i + 2),
+            window.rfind([' ', '\n']).map(|i| i + 1),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|&i| i >= half)
+        .unwrap_or(window.len());
+        ranges.push((start, start + cut));
+        start += cut;
+    }
+    ranges.retain(|&(start, end)| !text[start..end].trim().is_empty());
+    ranges
+}
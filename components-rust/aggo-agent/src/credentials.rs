@@ -0,0 +1,86 @@
+//! Per-domain request headers and cookies for fetching authenticated sources such as internal
+//! wikis or APIs.
+//!
+//! `FETCH_CREDENTIALS` holds a JSON array, normally filled from secrets:
+//!
+//! ```json
+//! [{"domain": "wiki.internal.example.com", "headers": {"Authorization": "Bearer ..."},
+//!   "cookies": {"session": "..."}}]
+//! ```
+//!
+//! Credentials are only sent over https unless the entry sets `"allow_http": true`, and only
+//! to the entry's domain and its subdomains. Values are never logged.
+
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, serde::Deserialize)]
+struct DomainCredentials {
+    domain: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    cookies: BTreeMap<String, String>,
+    #[serde(default)]
+    allow_http: bool,
+}
+
+/// Extra headers to send when fetching `url`, including a `Cookie` header built from the
+/// configured cookies. Empty when nothing is configured for the URL's host.
+pub fn headers_for(url: &str) -> Vec<(String, String)> {
+    let Some(entry) = entry_for(url) else {
+        return Vec::new();
+    };
+    if !entry.allow_http && !url.trim().starts_with("https://") {
+        log::warn!(
+            "Not sending credentials for {} over plain http; set allow_http to permit it",
+            entry.domain.trim()
+        );
+        return Vec::new();
+    }
+
+    let mut headers: Vec<(String, String)> = entry.headers.into_iter().collect();
+    if !entry.cookies.is_empty() {
+        let cookie = entry
+            .cookies
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("; ");
+        headers.push(("Cookie".to_string(), cookie));
+    }
+    headers
+}
+
+/// Whether `url` is on a domain with configured credentials, i.e. a private source whose
+/// URLs should not be sent to third parties such as the Wayback Machine.
+pub fn is_private(url: &str) -> bool {
+    entry_for(url).is_some()
+}
+
+fn entry_for(url: &str) -> Option<DomainCredentials> {
+    let host = crate::citations::host(url)?;
+    let raw = std::env::var("FETCH_CREDENTIALS").ok()?;
+    if raw.trim().is_empty() {
+        return None;
+    }
+    let entries: Vec<DomainCredentials> = match serde_json::from_str(&raw) {
+        Ok(entries) => entries,
+        Err(err) => {
+            // serde's message can quote input, so only its position is reported.
+            log::warn!(
+                "FETCH_CREDENTIALS is not a valid JSON credential list (line {}, column {})",
+                err.line(),
+                err.column()
+            );
+            return None;
+        }
+    };
+
+    entries
+        .into_iter()
+        .filter(|e| {
+            let domain = e.domain.trim().to_ascii_lowercase();
+            !domain.is_empty() && (host == domain || host.ends_with(&format!(".{domain}")))
+        })
+        .max_by_key(|e| e.domain.trim().len())
+}
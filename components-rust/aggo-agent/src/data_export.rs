@@ -0,0 +1,245 @@
+//! Structured data from a deep research job as CSV and JSONL attachments, for spreadsheets and
+//! data pipelines: the markdown tables in the report, its key findings, or its cited sources.
+//!
+//! Tables and sources are read from the report text as written; only findings need an LLM pass
+//! (the same one as `key_findings`). CSV follows RFC 4180, and cells that a spreadsheet would
+//! run as a formula (`=`, `+`, `@`, or `-` not starting a number) are prefixed with `'`.
+//! Attachments over `PAYLOAD_COMPRESS_THRESHOLD_BYTES` are gzipped (see `payload`).
+
+use golem_rust::Schema;
+
+use crate::citations;
+use crate::findings::KeyFinding;
+use crate::payload;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Schema, serde::Serialize, serde::Deserialize)]
+pub enum DataKind {
+    /// Markdown tables in the report: one CSV per table, and one JSONL line per row.
+    Tables,
+    /// Key findings with the URLs that support them.
+    Findings,
+    /// Cited sources with the section that first cites them.
+    Sources,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct DataAttachment {
+    /// e.g. `sources.csv`, `table-2.csv`.
+    pub filename: String,
+    /// `text/csv` or `application/x-ndjson`.
+    pub media_type: String,
+    /// The file, or with `encoding` set, the file in that encoding.
+    pub content: String,
+    /// `gzip+base64` when the file was over the compression threshold; unset for plain text.
+    pub encoding: Option<String>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct DataExport {
+    pub job_id: String,
+    pub kind: DataKind,
+    /// CSV files first, then the JSONL file with the same records.
+    pub attachments: Vec<DataAttachment>,
+}
+
+/// A markdown table and the heading it appears under.
+struct Table {
+    section: Option<String>,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+pub fn tables(report: &str) -> Vec<DataAttachment> {
+    let tables = parse_tables(report);
+    let mut attachments: Vec<DataAttachment> = tables
+        .iter()
+        .enumerate()
+        .map(|(i, table)| {
+            let mut rows = vec![table.headers.clone()];
+            rows.extend(table.rows.iter().cloned());
+            csv(&format!("table-{}.csv", i + 1), &rows)
+        })
+        .collect();
+    let records = tables.iter().enumerate().flat_map(|(i, table)| {
+        table.rows.iter().map(move |row| {
+            let cells: serde_json::Map<String, serde_json::Value> = table
+                .headers
+                .iter()
+                .zip(row)
+                .enumerate()
+                .map(|(j, (header, cell))| {
+                    let name = if header.is_empty() { format!("column {}", j + 1) } else { header.clone() };
+                    (name, serde_json::Value::from(cell.as_str()))
+                })
+                .collect();
+            serde_json::json!({ "table": i + 1, "section": table.section, "cells": cells })
+        })
+    });
+    attachments.push(jsonl("tables.jsonl", records));
+    attachments
+}
+
+pub fn findings(findings: &[KeyFinding]) -> Vec<DataAttachment> {
+    let mut rows = vec![vec!["finding".to_string(), "statement".to_string(), "sources".to_string()]];
+    rows.extend(
+        findings
+            .iter()
+            .enumerate()
+            .map(|(i, f)| vec![(i + 1).to_string(), f.statement.clone(), f.sources.join(" ")]),
+    );
+    let records = findings.iter().enumerate().map(|(i, f)| {
+        serde_json::json!({ "finding": i + 1, "statement": f.statement, "sources": f.sources })
+    });
+    vec![csv("findings.csv", &rows), jsonl("findings.jsonl", records)]
+}
+
+pub fn sources(report: &str) -> Vec<DataAttachment> {
+    // Each source with the heading above its first citation.
+    let mut cited: Vec<(citations::Citation, Option<String>)> = Vec::new();
+    for (section, text) in sections(report) {
+        for citation in citations::extract(&text) {
+            let key = citations::normalize_url(&citation.url);
+            if !cited.iter().any(|(c, _)| citations::normalize_url(&c.url) == key) {
+                cited.push((citation, section.clone()));
+            }
+        }
+    }
+    let mut rows = vec![vec![
+        "source".to_string(),
+        "title".to_string(),
+        "url".to_string(),
+        "section".to_string(),
+    ]];
+    rows.extend(cited.iter().enumerate().map(|(i, (c, section))| {
+        vec![
+            (i + 1).to_string(),
+            c.title.clone().unwrap_or_default(),
+            c.url.clone(),
+            section.clone().unwrap_or_default(),
+        ]
+    }));
+    let records = cited.iter().enumerate().map(|(i, (c, section))| {
+        serde_json::json!({ "source": i + 1, "title": c.title, "url": c.url, "section": section })
+    });
+    vec![csv("sources.csv", &rows), jsonl("sources.jsonl", records)]
+}
+
+/// The report split at its markdown headings: each heading's text (none before the first)
+/// with the text under it.
+fn sections(report: &str) -> Vec<(Option<String>, String)> {
+    let mut sections = vec![(None, String::new())];
+    for line in report.lines() {
+        if let Some(heading) = heading(line) {
+            sections.push((Some(heading), String::new()));
+        } else if let Some((_, text)) = sections.last_mut() {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    sections
+}
+
+fn heading(line: &str) -> Option<String> {
+    let rest = line.trim_start().strip_prefix('#')?;
+    let text = rest.trim_start_matches('#');
+    (text.starts_with(' ') && !text.trim().is_empty()).then(|| text.trim().to_string())
+}
+
+/// Pipe tables: a header row, a `|---|` delimiter row, and the rows after it.
+fn parse_tables(report: &str) -> Vec<Table> {
+    let mut tables = Vec::new();
+    for (section, text) in sections(report) {
+        let lines: Vec<&str> = text.lines().map(str::trim).collect();
+        let mut i = 0;
+        while i + 1 < lines.len() {
+            if !(lines[i].contains('|') && is_delimiter(lines[i + 1])) {
+                i += 1;
+                continue;
+            }
+            let headers = cells(lines[i]);
+            let mut rows = Vec::new();
+            let mut next = i + 2;
+            while next < lines.len() && lines[next].contains('|') {
+                let mut row = cells(lines[next]);
+                row.resize(headers.len(), String::new());
+                rows.push(row);
+                next += 1;
+            }
+            tables.push(Table {
+                section: section.clone(),
+                headers,
+                rows,
+            });
+            i = next;
+        }
+    }
+    tables
+}
+
+fn is_delimiter(line: &str) -> bool {
+    let cells = cells(line);
+    !cells.is_empty()
+        && cells.iter().all(|cell| {
+            let dashes = cell.trim_matches(':');
+            !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+        })
+}
+
+/// The cells of a table row, with `\|` kept as a literal pipe and `**` emphasis removed.
+fn cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                chars.next();
+                cell.push('|');
+            }
+            '|' => cells.push(std::mem::take(&mut cell)),
+            _ => cell.push(c),
+        }
+    }
+    cells.push(cell);
+    cells.into_iter().map(|cell| cell.replace("**", "").trim().to_string()).collect()
+}
+
+fn csv(filename: &str, rows: &[Vec<String>]) -> DataAttachment {
+    let content: String = rows
+        .iter()
+        .map(|row| row.iter().map(|cell| csv_cell(cell)).collect::<Vec<_>>().join(",") + "\r\n")
+        .collect();
+    attachment(filename, "text/csv", content)
+}
+
+fn csv_cell(cell: &str) -> String {
+    let formula = cell.starts_with(['=', '+', '@', '\t', '\r'])
+        || (cell.starts_with('-') && cell[1..].parse::<f64>().is_err());
+    let cell = if formula { format!("'{cell}") } else { cell.to_string() };
+    if cell.contains([',', '"', '\n', '\r']) || cell.trim() != cell {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell
+    }
+}
+
+fn jsonl(filename: &str, records: impl Iterator<Item = serde_json::Value>) -> DataAttachment {
+    let content: String = records.map(|record| format!("{record}\n")).collect();
+    attachment(filename, "application/x-ndjson", content)
+}
+
+fn attachment(filename: &str, media_type: &str, content: String) -> DataAttachment {
+    let (content, encoding) = match payload::compress(&content) {
+        Some(compressed) => (compressed, Some(payload::GZIP_BASE64.to_string())),
+        None => (content, None),
+    };
+    DataAttachment {
+        filename: filename.to_string(),
+        media_type: media_type.to_string(),
+        content,
+        encoding,
+    }
+}
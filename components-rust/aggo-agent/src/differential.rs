@@ -0,0 +1,108 @@
+//! Differential updates for scheduled (monitored) topics.
+//!
+//! Every scheduled run stores fingerprints (URL plus a hash of the text as retrieved, before
+//! prompt trimming) of the sources its report was written from. When a schedule runs again, sources matching the last good run's
+//! fingerprints are dropped and the model updates that report from the new and changed ones
+//! instead of writing it from scratch; with nothing new the report is carried over without an
+//! LLM call. `MONITOR_DIFFERENTIAL_UPDATES=false` turns this off.
+
+use golem_rust::Schema;
+
+use crate::{citations, SearchResult};
+
+/// Fingerprints kept per report, newest first; older sources count as new if they return.
+const MAX_FINGERPRINTS: usize = 200;
+
+#[derive(Clone, Debug, PartialEq, Eq, Schema, serde::Serialize, serde::Deserialize)]
+pub struct SourceFingerprint {
+    /// Normalized as in `citations::normalize_url`.
+    pub url: String,
+    /// FNV-1a hash of the snippet as retrieved, in hex.
+    pub digest: String,
+}
+
+pub fn enabled() -> bool {
+    std::env::var("MONITOR_DIFFERENTIAL_UPDATES")
+        .map(|v| !matches!(v.trim().to_ascii_lowercase().as_str(), "false" | "0" | "off" | "no"))
+        .unwrap_or(true)
+}
+
+pub fn fingerprint(sources: &[SearchResult]) -> Vec<SourceFingerprint> {
+    sources.iter().map(fingerprint_of).collect()
+}
+
+/// The sources whose URL is new or whose text differs from the previous run's.
+pub fn changed(sources: Vec<SearchResult>, previous: &[SourceFingerprint]) -> Vec<SearchResult> {
+    sources
+        .into_iter()
+        .filter(|source| !previous.contains(&fingerprint_of(source)))
+        .collect()
+}
+
+/// `current` followed by the previous fingerprints it doesn't replace, capped.
+pub fn merge(previous: &[SourceFingerprint], current: Vec<SourceFingerprint>) -> Vec<SourceFingerprint> {
+    let mut merged = current;
+    for fingerprint in previous {
+        if !merged.iter().any(|f| f.url == fingerprint.url) {
+            merged.push(fingerprint.clone());
+        }
+    }
+    merged.truncate(MAX_FINGERPRINTS);
+    merged
+}
+
+/// A stored report's text as the model wrote it: without the "Finished research" line and the
+/// retrieval notes and footer appended after `---` rules.
+pub fn report_body(stored: &str) -> &str {
+    let mut body = match stored.split_once('\n') {
+        Some((first, rest)) if first.starts_with("Finished research for topic ") => rest,
+        _ => stored,
+    };
+    while let Some((before, after)) = body.rsplit_once("\n\n---\n") {
+        let appended = after
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .all(|line| line.starts_with("<sub>") || line.starts_with('_'));
+        if !appended {
+            break;
+        }
+        body = before;
+    }
+    body.trim_end()
+}
+
+pub fn update_prompt(
+    topic: &str,
+    previous_report: &str,
+    previous_date: &str,
+    format_instructions: &str,
+    sources: &[SearchResult],
+) -> String {
+    let sources_json = serde_json::to_string(sources).unwrap_or_else(|_| "[]".to_string());
+    format!(
+        "I'm monitoring the topic \"{topic}\". Below is the report from {previous_date}, followed by sources \
+         that are new or have changed since then, in json format.\n\
+         Update the report rather than rewriting it: add what the new sources report, correct or replace statements \
+         they contradict or supersede, and keep the rest of the report, including its citations, as it is. \
+         Cite the new sources by URL. Start with a short \"What's new\" section listing the changes since {previous_date}.\n\
+         {format_instructions}\n\
+         Previous report:\n{previous_report}\n\n\
+         New and changed sources: {sources_json}"
+    )
+}
+
+fn fingerprint_of(source: &SearchResult) -> SourceFingerprint {
+    SourceFingerprint {
+        url: citations::normalize_url(&source.url),
+        digest: digest(&source.snippet),
+    }
+}
+
+/// 64-bit FNV-1a of `text` in hex: stable across builds, unlike `std`'s hasher.
+pub fn digest(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
@@ -0,0 +1,105 @@
+//! Text embeddings for the corpus vector index, from an OpenAI-compatible `/v1/embeddings`
+//! endpoint (OpenAI, Ollama, vLLM, LiteLLM, ...).
+//!
+//! Enabled by `EMBEDDING_MODEL`. `EMBEDDING_ENDPOINT` defaults to the Ollama server when Ollama
+//! is configured, else to OpenAI; `EMBEDDING_API_KEY` is sent as a bearer token when set.
+
+use std::time::Duration;
+
+use crate::{http_util, sanitize};
+
+const OPENAI_ENDPOINT: &str = "https://api.openai.com/v1/embeddings";
+/// Inputs per request.
+const BATCH_SIZE: usize = 32;
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+pub struct Embedder {
+    pub model: String,
+    endpoint: String,
+}
+
+#[derive(serde::Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(serde::Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl Embedder {
+    pub fn from_env() -> Option<Self> {
+        let model = std::env::var("EMBEDDING_MODEL").ok()?.trim().to_string();
+        if model.is_empty() {
+            return None;
+        }
+        let endpoint = std::env::var("EMBEDDING_ENDPOINT")
+            .ok()
+            .map(|e| e.trim().to_string())
+            .filter(|e| !e.is_empty())
+            .unwrap_or_else(|| {
+                if crate::ollama::is_configured() {
+                    format!("{}/v1/embeddings", crate::ollama::base_url())
+                } else {
+                    OPENAI_ENDPOINT.to_string()
+                }
+            });
+        Some(Embedder { model, endpoint })
+    }
+
+    /// Unit-length embeddings of `texts`, in order, so cosine similarity is a dot product.
+    pub fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, String> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(BATCH_SIZE) {
+            let api_key = std::env::var("EMBEDDING_API_KEY").ok().filter(|k| !k.trim().is_empty());
+            let request = || {
+                let request = golem_wasi_http::Client::new()
+                    .post(&self.endpoint)
+                    .json(&serde_json::json!({ "model": self.model, "input": batch }))
+                    .timeout(TIMEOUT);
+                match &api_key {
+                    Some(key) => request.bearer_auth(key.trim()),
+                    None => request,
+                }
+            };
+            let endpoint = sanitize::sanitize(&self.endpoint);
+            let response =
+                http_util::send(request).map_err(|err| format!("Embedding request to {endpoint} failed: {err}"))?;
+            if !response.is_success() {
+                return Err(format!("Embedding request to {endpoint} failed: HTTP {}", response.status()));
+            }
+            let mut parsed: EmbeddingResponse = response
+                .json()
+                .map_err(|err| format!("Invalid embedding response: {err}"))?;
+            if parsed.data.len() != batch.len() {
+                return Err(format!(
+                    "Embedding response has {} vectors for {} inputs",
+                    parsed.data.len(),
+                    batch.len()
+                ));
+            }
+            parsed.data.sort_by_key(|d| d.index);
+            vectors.extend(parsed.data.into_iter().map(|d| normalized(d.embedding)));
+        }
+        Ok(vectors)
+    }
+}
+
+/// Cosine similarity of two unit vectors.
+pub fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
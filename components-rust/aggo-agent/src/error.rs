@@ -0,0 +1,86 @@
+use golem_rust::Schema;
+
+use crate::budget::BudgetExceeded;
+use crate::config::ConfigReport;
+use crate::ollama::ModelUnavailable;
+use crate::refusal::Refusal;
+use crate::shedding::Overloaded;
+
+/// Typed failures returned by agent methods, so callers can branch on the kind of error.
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub enum AgentError {
+    /// The request itself is invalid: unknown template, bad workspace ID, and so on.
+    InvalidRequest(String),
+    /// The workspace has reached its hard monthly spend limit.
+    BudgetExceeded(BudgetExceeded),
+    /// The configured model can't be served (e.g. not pulled on the Ollama host).
+    ModelUnavailable(ModelUnavailable),
+    /// The LLM provider call failed.
+    LlmError(String),
+    /// The model's reply could not be parsed into the requested structure.
+    ParseError(String),
+    /// Web search failed and the fallback policy left no sources to research with. In report
+    /// metadata: a search provider that failed while another one answered.
+    SearchFailed(String),
+    /// A page could not be fetched or read.
+    FetchError(String),
+    /// The worker is over its load-shedding thresholds and turned away a low-priority request.
+    Overloaded(Overloaded),
+    /// The model declined the request, the provider's content filter stopped its reply, or the
+    /// topic safety check rejected the topic before the run started (see `safety`).
+    Refused(Refusal),
+    /// The component env is missing or has invalid variables (see `validate_config`).
+    ConfigError(ConfigReport),
+}
+
+impl std::fmt::Display for AgentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidRequest(message) => write!(f, "Invalid request: {message}"),
+            Self::BudgetExceeded(budget) => write!(
+                f,
+                "Workspace {:?} has spent ${:.2} of its ${:.2} hard limit for {}; new research is blocked until the limit is raised, overridden, or the month rolls over",
+                budget.workspace, budget.spent_usd, budget.hard_limit_usd, budget.period
+            ),
+            Self::ModelUnavailable(unavailable) => write!(
+                f,
+                "Model {:?} is unavailable at {}. {}",
+                unavailable.model, unavailable.endpoint, unavailable.hint
+            ),
+            Self::LlmError(message) => write!(f, "{message}"),
+            Self::ParseError(message) => write!(f, "Could not parse the model's reply: {message}"),
+            Self::SearchFailed(message) => write!(f, "No sources to research with: {message}"),
+            Self::FetchError(message) => write!(f, "{message}"),
+            Self::Overloaded(overloaded) => write!(
+                f,
+                "The agent is overloaded ({}); low-priority requests are rejected for now, retry in {} seconds",
+                overloaded.reason, overloaded.retry_after_seconds
+            ),
+            Self::Refused(refusal) => write!(f, "The model declined the request: {}", refusal.reason),
+            Self::ConfigError(report) => write!(
+                f,
+                "The agent is not configured for research: {report} Check the component env in golem.yaml."
+            ),
+        }
+    }
+}
+
+impl AgentError {
+    /// The variant's name in kebab case, as in the WIT interface.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::InvalidRequest(_) => "invalid-request",
+            Self::BudgetExceeded(_) => "budget-exceeded",
+            Self::ModelUnavailable(_) => "model-unavailable",
+            Self::LlmError(_) => "llm-error",
+            Self::ParseError(_) => "parse-error",
+            Self::SearchFailed(_) => "search-failed",
+            Self::FetchError(_) => "fetch-error",
+            Self::Overloaded(_) => "overloaded",
+            Self::Refused(_) => "refused",
+            Self::ConfigError(_) => "config-error",
+        }
+    }
+}
+
+impl std::error::Error for AgentError {}
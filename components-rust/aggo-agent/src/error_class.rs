@@ -0,0 +1,181 @@
+//! Error taxonomy: provider and LLM failures are mapped to a class here, once, and retry and
+//! fallback decisions are made on the class rather than on error text.
+//!
+//! | class                 | examples                                   | retried | next provider / model |
+//! |-----------------------|--------------------------------------------|---------|-----------------------|
+//! | `retryable-transient` | timeouts, 5xx, 408, malformed replies      | yes     | yes                   |
+//! | `quota`               | 429, 402, exhausted quota windows          | no¹     | yes                   |
+//! | `auth`                | 401, 403, missing or rejected API keys     | no      | yes                   |
+//! | `permanent`           | unsupported features, unavailable models   | no      | yes                   |
+//! | `invalid-request`     | 400, 404, 413, 422, rejected queries       | no      | no                    |
+//!
+//! ¹ `http_util` does repeat a 429 whose `Retry-After` is short; a long one is left to fail over.
+//!
+//! An invalid request would fail the same way with any provider or model, so it ends the
+//! fallback chain at once. Errors that reach the classifier only as text are classified by the
+//! HTTP status they mention and a few well-known phrases; text that matches nothing is
+//! `permanent`.
+
+use golem_rust::golem_ai::golem::llm::llm;
+use golem_rust::golem_ai::golem::web_search::types;
+
+use crate::error::AgentError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorClass {
+    Transient,
+    Quota,
+    Auth,
+    Permanent,
+    InvalidRequest,
+}
+
+impl ErrorClass {
+    /// The class's name as in `heartbeat` and trace notes.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Transient => "retryable-transient",
+            Self::Quota => "quota",
+            Self::Auth => "auth",
+            Self::Permanent => "permanent",
+            Self::InvalidRequest => "invalid-request",
+        }
+    }
+
+    /// Whether the same call may succeed when repeated.
+    pub fn retryable(self) -> bool {
+        self == Self::Transient
+    }
+
+    /// Whether the next provider in the chain, or the next fallback model, is worth trying.
+    pub fn falls_back(self) -> bool {
+        self != Self::InvalidRequest
+    }
+
+    pub fn of_status(status: u16) -> Self {
+        match status {
+            401 | 403 | 407 => Self::Auth,
+            402 | 429 => Self::Quota,
+            408 | 425 => Self::Transient,
+            400 | 404 | 405 | 411 | 413 | 414 | 415 | 422 => Self::InvalidRequest,
+            500.. => Self::Transient,
+            _ => Self::Permanent,
+        }
+    }
+
+    pub fn of_llm(err: &llm::Error) -> Self {
+        match err.code {
+            llm::ErrorCode::AuthenticationFailed => Self::Auth,
+            // Token-per-minute limits pass; a used-up balance or plan doesn't.
+            llm::ErrorCode::RateLimitExceeded if mentions_quota(&err.message) => Self::Quota,
+            llm::ErrorCode::RateLimitExceeded | llm::ErrorCode::InternalError => Self::Transient,
+            llm::ErrorCode::InvalidRequest => Self::InvalidRequest,
+            llm::ErrorCode::Unsupported => Self::Permanent,
+            llm::ErrorCode::Unknown => Self::of_message(&err.message),
+        }
+    }
+
+    pub fn of_search(err: &types::SearchError) -> Self {
+        match err {
+            types::SearchError::InvalidQuery => Self::InvalidRequest,
+            types::SearchError::RateLimited(_) => Self::Quota,
+            types::SearchError::UnsupportedFeature(_) => Self::Permanent,
+            types::SearchError::BackendError(message) => Self::of_message(message),
+        }
+    }
+
+    pub fn of_error(err: &AgentError) -> Self {
+        match err {
+            AgentError::InvalidRequest(_) => Self::InvalidRequest,
+            AgentError::BudgetExceeded(_) => Self::Quota,
+            AgentError::ModelUnavailable(_) => Self::Permanent,
+            AgentError::LlmError(message) | AgentError::SearchFailed(message) | AgentError::FetchError(message) => {
+                Self::of_message(message)
+            }
+            // Another attempt gets another reply.
+            AgentError::ParseError(_) => Self::Transient,
+            AgentError::Overloaded(_) => Self::Transient,
+            // Declined for its content; another model may answer, but the request stands as asked.
+            AgentError::Refused(_) => Self::InvalidRequest,
+            AgentError::ConfigError(_) => Self::Permanent,
+        }
+    }
+
+    /// The class of an error known only by its text: the `HTTP <status>` it mentions, else
+    /// well-known phrases.
+    pub fn of_message(message: &str) -> Self {
+        if let Some(status) = http_status(message) {
+            return Self::of_status(status);
+        }
+        let message = message.to_ascii_lowercase();
+        let mentions = |phrases: &[&str]| phrases.iter().any(|phrase| message.contains(phrase));
+        if mentions(&["ratelimitexceeded", "rate limit", "rate-limit", "too many requests"]) {
+            if mentions_quota(&message) {
+                Self::Quota
+            } else {
+                Self::Transient
+            }
+        } else if mentions_quota(&message) {
+            Self::Quota
+        } else if mentions(&["authenticationfailed", "unauthorized", "forbidden", "api key", "api_key", "is not set"]) {
+            Self::Auth
+        } else if mentions(&["invalidrequest", "invalid request", "invalidquery", "invalid url"]) {
+            Self::InvalidRequest
+        } else if mentions(&[
+            "internalerror",
+            "timed out",
+            "timeout",
+            "connection",
+            "error sending request",
+            "temporarily",
+            "unavailable",
+            "invalid json",
+            "could not read",
+        ]) {
+            Self::Transient
+        } else {
+            Self::Permanent
+        }
+    }
+}
+
+fn mentions_quota(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    ["quota", "billing", "credit", "exhausted"].iter().any(|phrase| message.contains(phrase))
+}
+
+/// The status code after the first `HTTP ` in `message`.
+fn http_status(message: &str) -> Option<u16> {
+    let (_, rest) = message.split_once("HTTP ")?;
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok().filter(|status| (100..600).contains(status))
+}
+
+/// A provider failure and its class.
+#[derive(Clone, Debug)]
+pub struct ProviderError {
+    pub class: ErrorClass,
+    pub message: String,
+}
+
+impl ProviderError {
+    pub fn new(class: ErrorClass, message: impl Into<String>) -> Self {
+        Self {
+            class,
+            message: message.into(),
+        }
+    }
+}
+
+/// Classifies an error known only by its text (see `ErrorClass::of_message`).
+impl From<String> for ProviderError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorClass::of_message(&message), message)
+    }
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
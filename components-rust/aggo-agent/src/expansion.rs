@@ -0,0 +1,164 @@
+//! Query expansion for `ResearchOptions::expand_queries`: the model rewrites a raw topic such
+//! as "rust async" into 2-4 targeted search queries, each is searched, and the result lists are
+//! merged round-robin (the `results` pass then drops duplicate URLs). A topic written in
+//! another language (see `multilingual`) gets queries in its language and in English, each
+//! searched with results in its language preferred.
+
+use crate::multilingual;
+use crate::prompt;
+use crate::truncate;
+use crate::SearchResult;
+
+const MIN_QUERIES: usize = 2;
+const MAX_QUERIES: usize = 4;
+const MAX_QUERY_CHARS: usize = 200;
+
+/// A search query from the plan, with the language whose results it should prefer; `None`
+/// keeps the request's language.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlannedQuery {
+    pub query: String,
+    pub language: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ExpandedQueries {
+    #[serde(default)]
+    queries: Vec<ExpandedQuery>,
+}
+
+#[derive(serde::Deserialize)]
+struct ExpandedQuery {
+    query: String,
+    #[serde(default)]
+    language: Option<String>,
+}
+
+/// The planning prompt for `topic`, written in `language` if that isn't English.
+pub fn expansion_prompt(topic: &str, language: Option<&str>) -> String {
+    let languages = match language {
+        Some(code) => format!(
+            "The topic is written in {name}. Write about half of the queries in {name} with language \"{code}\" and the rest in English \
+             with language \"en\".",
+            name = multilingual::language_name(code)
+        ),
+        None => "Write the queries in the language of the topic, with language null.".to_string(),
+    };
+    format!(
+        "Rewrite the research topic below into {MIN_QUERIES} to {MAX_QUERIES} web search queries that together find the best sources on it. \
+         Make each query specific, covering a different aspect (for example the main concept, how it is used in practice, comparisons \
+         or recent developments), and use the terms an expert would search for. Don't add search operators.\n\
+         {languages}\n\
+         Reply with JSON only, in this shape: {{\"queries\": [{{\"query\": \"...\", \"language\": null}}]}}.\n\n\
+         Topic: {topic}"
+    )
+}
+
+/// The planned queries in the model's reply: trimmed, without duplicates, at most four. A
+/// language other than English or the topic's is dropped.
+pub fn parse_queries(reply: &str, language: Option<&str>) -> Result<Vec<PlannedQuery>, String> {
+    let expanded: ExpandedQueries = serde_json::from_str(prompt::json_object(reply))
+        .map_err(|err| format!("The model did not return valid query JSON: {err}"))?;
+    let mut planned: Vec<PlannedQuery> = Vec::new();
+    for query in expanded.queries {
+        let text = query.query.replace('\n', " ");
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let text = truncate::chars(text, MAX_QUERY_CHARS);
+        if planned.iter().any(|p| p.query.eq_ignore_ascii_case(&text)) {
+            continue;
+        }
+        let query_language = query
+            .language
+            .map(|l| l.trim().to_ascii_lowercase())
+            .filter(|l| language.is_some() && (l == "en" || Some(l.as_str()) == language));
+        planned.push(PlannedQuery {
+            query: text,
+            language: query_language,
+        });
+    }
+    planned.truncate(MAX_QUERIES);
+    if planned.len() < MIN_QUERIES {
+        return Err(format!(
+            "The model planned {} search queries, fewer than {MIN_QUERIES}",
+            planned.len()
+        ));
+    }
+    Ok(planned)
+}
+
+/// Result lists merged round-robin, so every query's best results come first.
+pub fn merge(lists: Vec<Vec<SearchResult>>) -> Vec<SearchResult> {
+    let mut lists: Vec<std::vec::IntoIter<SearchResult>> = lists.into_iter().map(Vec::into_iter).collect();
+    let mut merged = Vec::new();
+    loop {
+        let mut any = false;
+        for list in &mut lists {
+            if let Some(result) = list.next() {
+                any = true;
+                merged.push(result);
+            }
+        }
+        if !any {
+            return merged;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queries(planned: &[PlannedQuery]) -> Vec<&str> {
+        planned.iter().map(|p| p.query.as_str()).collect()
+    }
+
+    #[test]
+    fn trims_and_drops_blank_and_duplicate_queries() {
+        let reply = r#"Sure: {"queries": [
+            {"query": "  rust async runtimes  "},
+            {"query": "   "},
+            {"query": "\n"},
+            {"query": "RUST ASYNC RUNTIMES"},
+            {"query": "tokio vs\nasync-std"}
+        ]}"#;
+        let planned = parse_queries(reply, None).unwrap();
+        assert_eq!(queries(&planned), ["rust async runtimes", "tokio vs async-std"]);
+    }
+
+    #[test]
+    fn fails_below_the_minimum_and_keeps_at_most_the_maximum() {
+        let blank = r#"{"queries": [{"query": "rust"}, {"query": " \n "}]}"#;
+        assert!(parse_queries(blank, None).is_err());
+        assert!(parse_queries("not json", None).is_err());
+
+        let many = r#"{"queries": [{"query": "a"}, {"query": "b"}, {"query": "c"}, {"query": "d"}, {"query": "e"}]}"#;
+        assert_eq!(queries(&parse_queries(many, None).unwrap()), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn caps_query_length() {
+        let long = "x".repeat(MAX_QUERY_CHARS * 2);
+        let reply = format!(r#"{{"queries": [{{"query": "{long}"}}, {{"query": "y"}}]}}"#);
+        let planned = parse_queries(&reply, None).unwrap();
+        assert_eq!(planned[0].query.chars().count(), MAX_QUERY_CHARS + 1);
+        assert!(planned[0].query.ends_with('…'));
+    }
+
+    #[test]
+    fn keeps_only_english_or_the_topic_language() {
+        let reply = r#"{"queries": [
+            {"query": "a", "language": " DE "},
+            {"query": "b", "language": "en"},
+            {"query": "c", "language": "fr"}
+        ]}"#;
+        let planned = parse_queries(reply, Some("de")).unwrap();
+        let languages: Vec<Option<&str>> = planned.iter().map(|p| p.language.as_deref()).collect();
+        assert_eq!(languages, [Some("de"), Some("en"), None]);
+
+        let planned = parse_queries(reply, None).unwrap();
+        assert!(planned.iter().all(|p| p.language.is_none()));
+    }
+}
@@ -0,0 +1,90 @@
+//! `explain_retrieval`: what a research run retrieves for a topic and why, without the LLM
+//! call, for diagnosing "why did the report miss X".
+
+use golem_rust::Schema;
+
+use crate::ai_content::AiContentFlag;
+use crate::error::AgentError;
+use crate::relevance::RelevanceScore;
+use crate::SearchResult;
+
+#[derive(Clone, Debug, Default, Schema, serde::Serialize, serde::Deserialize)]
+pub struct RetrievalExplanation {
+    pub topic: String,
+    pub queries: Vec<IssuedQuery>,
+    /// Web search provider and each search connector, with how many results they returned.
+    pub providers: Vec<ProviderCount>,
+    /// `hybrid`, `keyword` or `vector`, plus whether a query embedding was available.
+    pub corpus_mode: String,
+    /// Corpus documents retrieved, best first, each with the scores of its best chunk.
+    pub corpus_hits: Vec<CorpusHit>,
+    /// Context tokens left for sources after instructions, exemplars and the answer reserve.
+    pub available_tokens: u32,
+    /// Sources sent to the model, in prompt order, with snippets as sent.
+    pub selected: Vec<SearchResult>,
+    /// URLs of candidates cut by the source limit or the context budget.
+    pub dropped: Vec<String>,
+    /// URLs of results dropped as copies of an earlier result (see `results`).
+    #[serde(default)]
+    pub duplicates: Vec<String>,
+    /// Relevance score of each web, connector and corpus result, kept ones first (see
+    /// `relevance`).
+    #[serde(default)]
+    pub relevance: Vec<RelevanceScore>,
+    /// Web results that look AI-generated and were ranked after the others.
+    pub ai_content: Vec<AiContentFlag>,
+    /// Retrieval problems that are noted at the end of the report, e.g. failed search providers.
+    pub notes: Vec<String>,
+    /// Failures the run got past, by kind: search providers (`search-failed`) and pages
+    /// (`fetch-error`).
+    #[serde(default)]
+    pub errors: Vec<AgentError>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct IssuedQuery {
+    pub provider: String,
+    pub query: String,
+    pub include_domains: Vec<String>,
+    pub exclude_domains: Vec<String>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ProviderCount {
+    pub provider: String,
+    /// Results as returned by the provider.
+    pub raw_results: u32,
+    /// Results left after exclusion terms were applied.
+    pub kept_results: u32,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct CorpusHit {
+    pub document_id: String,
+    pub title: String,
+    pub url: String,
+    /// Index of the document's best chunk.
+    pub chunk: u32,
+    pub bm25: f64,
+    /// 1-based position among the top BM25 chunks, if it was one.
+    pub bm25_rank: Option<u32>,
+    /// Cosine similarity to the query embedding, when both exist.
+    pub similarity: Option<f64>,
+    pub vector_rank: Option<u32>,
+    /// The score hits are ordered by: fused in hybrid mode, else the one method's score.
+    pub score: f64,
+    /// Whether it was among the corpus results added to the sources.
+    pub used: bool,
+}
+
+impl RetrievalExplanation {
+    pub fn provider(&mut self, provider: &str, raw: usize, kept: usize, error: Option<String>) {
+        self.providers.push(ProviderCount {
+            provider: provider.to_string(),
+            raw_results: raw as u32,
+            kept_results: kept as u32,
+            error,
+        });
+    }
+}
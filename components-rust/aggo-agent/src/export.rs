@@ -0,0 +1,484 @@
+//! Repurposing finished research into other formats.
+//!
+//! Each export asks the model for a JSON structure and renders any text form (e.g. Marp
+//! markdown) locally, so the typed data and the rendering always agree.
+
+use golem_rust::Schema;
+
+use crate::citations::{self, Citation};
+use crate::prompt;
+use crate::truncate;
+
+/// Typical narration pace used to size scripts.
+const WORDS_PER_MINUTE: u32 = 150;
+pub const MAX_SCRIPT_MINUTES: u32 = 60;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct Slide {
+    pub title: String,
+    pub bullets: Vec<String>,
+    pub speaker_notes: String,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct SlideDeck {
+    pub job_id: String,
+    pub title: String,
+    pub slides: Vec<Slide>,
+    /// Marp markdown (`---` slide separators, speaker notes as HTML comments); reveal.js's
+    /// markdown plugin accepts the same file.
+    pub markdown: String,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ShowNote {
+    pub title: Option<String>,
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct NarrationScript {
+    pub job_id: String,
+    pub title: String,
+    pub target_minutes: u32,
+    /// Spoken length at ~150 words per minute.
+    pub estimated_minutes: f64,
+    pub word_count: u32,
+    /// Plain spoken text; links and citations are moved to `show_notes`.
+    pub script: String,
+    pub show_notes: Vec<ShowNote>,
+}
+
+#[derive(Clone, Copy, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub enum SocialPlatform {
+    X,
+    Bluesky,
+    Mastodon,
+    Threads,
+    Linkedin,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct SocialPost {
+    /// 1-based position in the thread.
+    pub index: u32,
+    /// Post text including its `n/total` prefix.
+    pub text: String,
+    pub char_count: u32,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct SocialThread {
+    pub job_id: String,
+    pub platform: SocialPlatform,
+    pub max_chars: u32,
+    pub posts: Vec<SocialPost>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ReadMoreLink {
+    pub title: Option<String>,
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct NewsletterSection {
+    pub heading: String,
+    pub teaser: String,
+    pub bullets: Vec<String>,
+    pub links: Vec<ReadMoreLink>,
+    /// The section rendered through the template.
+    pub html: String,
+}
+
+#[derive(serde::Deserialize)]
+struct ExtractedNewsletter {
+    heading: String,
+    #[serde(default)]
+    teaser: String,
+    #[serde(default)]
+    bullets: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ExtractedThread {
+    posts: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ExtractedDeck {
+    #[serde(default)]
+    title: String,
+    slides: Vec<ExtractedSlide>,
+}
+
+#[derive(serde::Deserialize)]
+struct ExtractedSlide {
+    title: String,
+    #[serde(default)]
+    bullets: Vec<String>,
+    #[serde(default)]
+    speaker_notes: String,
+}
+
+pub fn slides_prompt(report: &str) -> String {
+    format!(
+        "Turn the research report below into a slide outline for a 10-15 minute talk.\n\
+         Use 6 to 12 slides: a title slide first, a closing slide with the key sources last. Give each slide a short title, \
+         at most 5 concise bullets, and speaker notes of 2-4 sentences saying what the presenter should explain.\n\
+         Reply with JSON only, in this shape: {{\"title\": \"...\", \"slides\": [{{\"title\": \"...\", \"bullets\": [\"...\"], \"speaker_notes\": \"...\"}}]}}\n\n\
+         Report:\n{report}"
+    )
+}
+
+pub fn parse_slides(job_id: &str, fallback_title: &str, reply: &str) -> Result<SlideDeck, String> {
+    let extracted: ExtractedDeck = serde_json::from_str(prompt::json_object(reply))
+        .map_err(|err| format!("The model did not return valid slide JSON: {err}"))?;
+
+    let slides: Vec<Slide> = extracted
+        .slides
+        .into_iter()
+        .filter(|s| !s.title.trim().is_empty())
+        .map(|s| Slide {
+            title: s.title.trim().to_string(),
+            bullets: s
+                .bullets
+                .into_iter()
+                .map(|b| b.trim().to_string())
+                .filter(|b| !b.is_empty())
+                .collect(),
+            speaker_notes: s.speaker_notes.trim().to_string(),
+        })
+        .collect();
+    if slides.is_empty() {
+        return Err("The model returned no slides".to_string());
+    }
+
+    let title = match extracted.title.trim() {
+        "" => fallback_title.to_string(),
+        title => title.to_string(),
+    };
+    let markdown = render_marp(&title, &slides);
+    Ok(SlideDeck {
+        job_id: job_id.to_string(),
+        title,
+        slides,
+        markdown,
+    })
+}
+
+fn render_marp(title: &str, slides: &[Slide]) -> String {
+    let mut markdown = format!("---\nmarp: true\ntitle: {}\npaginate: true\n---\n", title.replace('\n', " "));
+    for (index, slide) in slides.iter().enumerate() {
+        if index > 0 {
+            markdown.push_str("\n---\n");
+        }
+        let heading = if index == 0 { "#" } else { "##" };
+        markdown.push_str(&format!("\n{heading} {}\n", slide.title));
+        if !slide.bullets.is_empty() {
+            markdown.push('\n');
+            for bullet in &slide.bullets {
+                markdown.push_str(&format!("- {bullet}\n"));
+            }
+        }
+        if !slide.speaker_notes.is_empty() {
+            markdown.push_str(&format!("\n<!--\n{}\n-->\n", slide.speaker_notes.replace("-->", "—>")));
+        }
+    }
+    markdown
+}
+
+pub fn script_prompt(report: &str, minutes: u32) -> String {
+    let words = minutes * WORDS_PER_MINUTE;
+    format!(
+        "Rewrite the research report below as a spoken-word script for a single narrator, about {words} words long ({minutes} minutes read aloud).\n\
+         Write for the ear: short sentences, natural transitions, no headings, bullet points, tables, URLs or markdown. \
+         Mention sources by name where it helps (\"according to ...\"); links go in the show notes, not the script.\n\
+         Reply with the script text only.\n\n\
+         Report:\n{report}"
+    )
+}
+
+pub fn build_script(job_id: &str, title: &str, minutes: u32, reply: &str, sources: &[Citation]) -> NarrationScript {
+    let script = strip_links(reply.trim());
+    let word_count = script.split_whitespace().count() as u32;
+    NarrationScript {
+        job_id: job_id.to_string(),
+        title: title.to_string(),
+        target_minutes: minutes,
+        estimated_minutes: (f64::from(word_count) / f64::from(WORDS_PER_MINUTE) * 10.0).round() / 10.0,
+        word_count,
+        script,
+        show_notes: sources
+            .iter()
+            .map(|c| ShowNote {
+                title: c.title.clone(),
+                url: c.url.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Replaces markdown links with their text and drops bare URLs, which read badly aloud.
+fn strip_links(text: &str) -> String {
+    let mut spoken = text.to_string();
+    for citation in citations::extract(text) {
+        let label = citation.title.clone().unwrap_or_default();
+        spoken = spoken.replace(&format!("[{label}]({})", citation.url), &label);
+        spoken = spoken.replace(&citation.url, "");
+    }
+    spoken
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Room kept in every post for the `n/total ` prefix.
+const THREAD_PREFIX_CHARS: usize = 8;
+
+impl SocialPlatform {
+    pub fn max_chars(&self) -> usize {
+        match self {
+            Self::X => 280,
+            Self::Bluesky => 300,
+            Self::Mastodon | Self::Threads => 500,
+            Self::Linkedin => 3000,
+        }
+    }
+
+    fn display_name(&self) -> &'static str {
+        match self {
+            Self::X => "X (Twitter)",
+            Self::Bluesky => "Bluesky",
+            Self::Mastodon => "Mastodon",
+            Self::Threads => "Threads",
+            Self::Linkedin => "LinkedIn",
+        }
+    }
+}
+
+pub fn thread_prompt(report: &str, platform: SocialPlatform) -> String {
+    let limit = platform.max_chars() - THREAD_PREFIX_CHARS;
+    format!(
+        "Summarize the research report below as a {} thread of 4 to 10 posts.\n\
+         The first post must hook the reader and say what the thread covers; each following post makes one point. \
+         Keep every post under {limit} characters, plain text, no numbering, no hashtags, no links (links are added at the end separately).\n\
+         Reply with JSON only, in this shape: {{\"posts\": [\"...\"]}}\n\n\
+         Report:\n{report}",
+        platform.display_name()
+    )
+}
+
+/// Numbers the model's posts, splits any that are too long, and appends the source links. A
+/// link too long for a post of its own is shortened with `…` rather than left out.
+pub fn build_thread(
+    job_id: &str,
+    platform: SocialPlatform,
+    reply: &str,
+    sources: &[Citation],
+) -> Result<SocialThread, String> {
+    let extracted: ExtractedThread = serde_json::from_str(prompt::json_object(reply))
+        .map_err(|err| format!("The model did not return valid thread JSON: {err}"))?;
+    let limit = platform.max_chars() - THREAD_PREFIX_CHARS;
+
+    let mut bodies: Vec<String> = extracted
+        .posts
+        .iter()
+        .map(|p| strip_links(p.trim()))
+        .filter(|p| !p.is_empty())
+        .flat_map(|p| split_to_fit(&p, limit))
+        .collect();
+    if bodies.is_empty() {
+        return Err("The model returned no posts".to_string());
+    }
+
+    const CONTINUED: &str = "Sources (cont.):";
+    let max_url_chars = limit - CONTINUED.len() - 1;
+    let mut links = String::from("Sources:");
+    for citation in sources {
+        let line = format!("\n{}", truncate::chars(&citation.url, max_url_chars));
+        if links.contains('\n') && links.chars().count() + line.chars().count() > limit {
+            bodies.push(links);
+            links = String::from(CONTINUED);
+        }
+        links.push_str(&line);
+    }
+    if links.contains('\n') {
+        bodies.push(links);
+    }
+
+    let total = bodies.len();
+    let posts = bodies
+        .into_iter()
+        .enumerate()
+        .map(|(i, body)| {
+            let text = format!("{}/{total} {body}", i + 1);
+            SocialPost {
+                index: i as u32 + 1,
+                char_count: text.chars().count() as u32,
+                text,
+            }
+        })
+        .collect();
+
+    Ok(SocialThread {
+        job_id: job_id.to_string(),
+        platform,
+        max_chars: platform.max_chars() as u32,
+        posts,
+    })
+}
+
+/// Splits text into chunks of at most `limit` characters, preferring sentence and then word
+/// boundaries.
+fn split_to_fit(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+    while rest.chars().count() > limit {
+        let window = truncate::prefix(rest, limit);
+        let window_end = window.len();
+        let cut = window
+            .rfind(". ")
+            .map(|i| i + 1)
+            .filter(|i| *i > window.len() / 3)
+            .or_else(|| window.rfind(' '))
+            .filter(|i| *i > 0)
+            .unwrap_or(window_end);
+        chunks.push(rest[..cut].trim().to_string());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() {
+        chunks.push(rest.to_string());
+    }
+    chunks
+}
+
+/// Read-more links shown per newsletter section.
+const MAX_NEWSLETTER_LINKS: usize = 5;
+
+/// Used when neither the caller nor `NEWSLETTER_SECTION_TEMPLATE` provides one.
+const DEFAULT_NEWSLETTER_TEMPLATE: &str = "<section class=\"research-digest\">\n  <h2>{{heading}}</h2>\n  <p>{{teaser}}</p>\n  <ul>\n{{bullets}}\n  </ul>\n  <p>Read more:</p>\n  <ul>\n{{links}}\n  </ul>\n</section>";
+
+pub fn newsletter_prompt(report: &str) -> String {
+    format!(
+        "Write a newsletter section summarizing the research report below for busy readers.\n\
+         Give a short, specific heading (under 10 words), a one or two sentence teaser, and 3 to 5 bullets with the most newsworthy points. Plain text only, no links or markdown.\n\
+         Reply with JSON only, in this shape: {{\"heading\": \"...\", \"teaser\": \"...\", \"bullets\": [\"...\"]}}\n\n\
+         Report:\n{report}"
+    )
+}
+
+/// The template to render with: the caller's, else `NEWSLETTER_SECTION_TEMPLATE`, else the
+/// built-in one. Placeholders: `{{heading}}`, `{{teaser}}`, `{{bullets}}` and `{{links}}`.
+pub fn newsletter_template(requested: Option<String>) -> String {
+    requested
+        .filter(|t| !t.trim().is_empty())
+        .or_else(|| std::env::var("NEWSLETTER_SECTION_TEMPLATE").ok().filter(|t| !t.trim().is_empty()))
+        .unwrap_or_else(|| DEFAULT_NEWSLETTER_TEMPLATE.to_string())
+}
+
+pub fn build_newsletter(template: &str, reply: &str, sources: &[Citation]) -> Result<NewsletterSection, String> {
+    let extracted: ExtractedNewsletter = serde_json::from_str(prompt::json_object(reply))
+        .map_err(|err| format!("The model did not return valid newsletter JSON: {err}"))?;
+
+    let heading = extracted.heading.trim().to_string();
+    let teaser = extracted.teaser.trim().to_string();
+    let bullets: Vec<String> = extracted
+        .bullets
+        .iter()
+        .map(|b| b.trim().to_string())
+        .filter(|b| !b.is_empty())
+        .collect();
+    let links: Vec<ReadMoreLink> = sources
+        .iter()
+        .take(MAX_NEWSLETTER_LINKS)
+        .map(|c| ReadMoreLink {
+            title: c.title.clone(),
+            url: c.url.clone(),
+        })
+        .collect();
+
+    let bullets_html = bullets
+        .iter()
+        .map(|b| format!("    <li>{}</li>", escape_html(b)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let links_html = links
+        .iter()
+        .map(|l| {
+            format!(
+                "    <li><a href=\"{}\">{}</a></li>",
+                escape_html(&l.url),
+                escape_html(l.title.as_deref().unwrap_or(&l.url))
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let html = template
+        .replace("{{heading}}", &escape_html(&heading))
+        .replace("{{teaser}}", &escape_html(&teaser))
+        .replace("{{bullets}}", &bullets_html)
+        .replace("{{links}}", &links_html);
+
+    Ok(NewsletterSection {
+        heading,
+        teaser,
+        bullets,
+        links,
+        html,
+    })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_to_fit_prefers_sentences_then_words() {
+        assert_eq!(split_to_fit("  short post ", 20), ["short post"]);
+        assert_eq!(
+            split_to_fit("One two three. Four five six seven.", 20),
+            ["One two three.", "Four five six seven."]
+        );
+        assert_eq!(split_to_fit("alpha beta gamma delta", 12), ["alpha beta", "gamma delta"]);
+        assert_eq!(split_to_fit("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn split_to_fit_counts_characters() {
+        assert_eq!(split_to_fit("ééééé", 2), ["éé", "éé", "é"]);
+        let text = "Über die Straße gehen wir schnell. Danach fahren wir mit dem Zug nach Köln. 日本語 ".repeat(10);
+        let chunks = split_to_fit(&text, 40);
+        assert!(chunks.iter().all(|c| !c.is_empty() && c.chars().count() <= 40));
+        assert_eq!(chunks.join(" ").split_whitespace().collect::<Vec<_>>(), text.split_whitespace().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn thread_keeps_over_long_source_links() {
+        let sources = [
+            Citation {
+                title: None,
+                url: "https://example.com/a".to_string(),
+            },
+            Citation {
+                title: None,
+                url: format!("https://example.com/{}", "x".repeat(400)),
+            },
+        ];
+        let thread = build_thread("job", SocialPlatform::X, r#"{"posts": ["The point."]}"#, &sources).unwrap();
+        assert!(thread.posts.iter().all(|p| p.char_count <= thread.max_chars));
+        let links: Vec<&str> = thread.posts.iter().flat_map(|p| p.text.lines().skip(1)).collect();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0], "https://example.com/a");
+        assert!(links[1].starts_with("https://example.com/x") && links[1].ends_with('…'));
+    }
+}
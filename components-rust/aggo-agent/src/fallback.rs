@@ -0,0 +1,65 @@
+//! What the research pipeline does when one of its stages fails, set with `FALLBACK_POLICY`
+//! (JSON), e.g. `{"search": "corpus_only", "fetch": "snippet_only", "synthesis_models": ["llama3.2:3b"]}`.
+//!
+//! | stage     | default                                          | alternative               |
+//! |-----------|--------------------------------------------------|---------------------------|
+//! | search    | `corpus_only`: go on with corpus and connector results | `fail`              |
+//! | fetch     | `snippet_only`: use the snippet when a result has no page text | `skip` the result |
+//! | synthesis | try each of `synthesis_models` in order          | no models: fail           |
+//!
+//! Once a stage has nothing left to fall back on the run fails with a typed `AgentError`:
+//! `SearchFailed` when web search failed and no other source returned anything, or the last
+//! model's `LlmError` / `ModelUnavailable`.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchFallback {
+    #[default]
+    CorpusOnly,
+    Fail,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchFallback {
+    #[default]
+    SnippetOnly,
+    Skip,
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct FallbackPolicy {
+    pub search: SearchFallback,
+    /// Only applies in full-text mode, where results are expected to carry page text.
+    pub fetch: FetchFallback,
+    /// Models tried in order when the request's model fails.
+    pub synthesis_models: Vec<String>,
+}
+
+impl FallbackPolicy {
+    pub fn from_env() -> Self {
+        let Ok(raw) = std::env::var("FALLBACK_POLICY") else {
+            return Self::default();
+        };
+        if raw.trim().is_empty() {
+            return Self::default();
+        }
+
+        match serde_json::from_str::<FallbackPolicy>(&raw) {
+            Ok(mut policy) => {
+                policy.synthesis_models = policy
+                    .synthesis_models
+                    .iter()
+                    .map(|m| m.trim().to_string())
+                    .filter(|m| !m.is_empty())
+                    .collect();
+                policy
+            }
+            Err(err) => {
+                log::warn!("FALLBACK_POLICY is not a valid fallback policy, using the defaults: {err}");
+                Self::default()
+            }
+        }
+    }
+}
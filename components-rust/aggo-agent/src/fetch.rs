@@ -0,0 +1,403 @@
+//! Direct page fetching and readable-text extraction, for URL-based features that bypass
+//! the search provider.
+
+use std::time::Duration;
+
+use crate::archive::ArchivedSnapshot;
+use crate::credentials;
+use crate::http_util;
+use crate::pdf;
+use crate::rules;
+
+/// Bodies beyond this are cut before extraction.
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+/// PDFs are larger for the same amount of text, and their text may come late in the file.
+const MAX_PDF_BYTES: usize = 16 * 1024 * 1024;
+
+/// Page fetch timeout for calls without a tighter budget of their own.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+
+const USER_AGENT: &str = "aggo-agent/0.1 (+https://github.com/haymant/AGGO-AGENT)";
+
+/// Elements whose content is never readable text.
+const SKIPPED_ELEMENTS: [&str; 7] = ["script", "style", "noscript", "svg", "template", "iframe", "head"];
+
+/// Elements that start a new line in the extracted text.
+const BLOCK_ELEMENTS: [&str; 22] = [
+    "p", "div", "br", "li", "ul", "ol", "h1", "h2", "h3", "h4", "h5", "h6", "tr", "table", "section",
+    "article", "header", "footer", "blockquote", "pre", "hr", "dt",
+];
+
+#[derive(Clone, Debug)]
+pub struct FetchedPage {
+    pub url: String,
+    pub title: Option<String>,
+    /// Readable text: the page's `<article>` or `<main>` when present, else its body.
+    pub text: String,
+    /// The raw document, for HTML pages.
+    pub html: Option<String>,
+    /// Set when the live page could not be fetched and an archived copy was read instead.
+    pub snapshot: Option<ArchivedSnapshot>,
+}
+
+/// HTTP cache validators from an earlier response, sent back to ask whether it changed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// The outcome of a conditional GET.
+pub enum Conditional {
+    /// 304: the copy the validators came from is still current.
+    NotModified,
+    Modified {
+        content_type: String,
+        /// Raw bytes, capped; see `fetch_if_modified`.
+        body: Vec<u8>,
+        validators: Validators,
+    },
+}
+
+/// Fetches an http(s) page and extracts its title and readable text.
+pub fn fetch_page(url: &str, timeout: Duration) -> Result<FetchedPage, String> {
+    fetch_page_if_modified(url, timeout, &Validators::default())?
+        .map(|(page, _)| page)
+        .ok_or_else(|| format!("Could not fetch {}: HTTP 304 without validators", url.trim()))
+}
+
+/// `fetch_page` as a conditional GET; `None` when the server says the page is unchanged since
+/// the response `validators` came from. Returns the new response's validators with the page.
+pub fn fetch_page_if_modified(
+    url: &str,
+    timeout: Duration,
+    validators: &Validators,
+) -> Result<Option<(FetchedPage, Validators)>, String> {
+    match fetch_if_modified(url, timeout, validators)? {
+        Conditional::NotModified => Ok(None),
+        Conditional::Modified {
+            content_type,
+            body,
+            validators,
+        } => Ok(Some((page_from_body(url, &content_type, body)?, validators))),
+    }
+}
+
+/// A fetched document's title and readable text; fails for content that isn't a web page or
+/// a PDF with extractable text.
+pub fn page_from_body(url: &str, content_type: &str, body: Vec<u8>) -> Result<FetchedPage, String> {
+    if pdf::is_pdf(content_type, &body) {
+        let document = pdf::extract(&body).map_err(|err| format!("Could not read the PDF at {}: {err}", url.trim()))?;
+        return Ok(FetchedPage {
+            url: url.trim().to_string(),
+            title: document.title,
+            text: document.text,
+            html: None,
+            snapshot: None,
+        });
+    }
+    let body = String::from_utf8_lossy(&body).into_owned();
+    if !content_type.is_empty() && !content_type.contains("html") && !content_type.starts_with("text/") {
+        return Err(format!("{} is not a web page (content type {content_type})", url.trim()));
+    }
+
+    if content_type.starts_with("text/plain") {
+        return Ok(FetchedPage {
+            url: url.trim().to_string(),
+            title: None,
+            text: collapse_whitespace(&body),
+            html: None,
+            snapshot: None,
+        });
+    }
+
+    let (title, text) = html_to_text(url.trim(), &body);
+    Ok(FetchedPage {
+        url: url.trim().to_string(),
+        title,
+        text,
+        html: Some(body),
+        snapshot: None,
+    })
+}
+
+/// GETs an http(s) URL and returns its lowercased content type and body (lossy UTF-8, capped
+/// at 2 MiB).
+pub fn fetch_text(url: &str, timeout: Duration) -> Result<(String, String), String> {
+    match fetch_if_modified(url, timeout, &Validators::default())? {
+        Conditional::Modified { content_type, body, .. } => {
+            Ok((content_type, String::from_utf8_lossy(&body).into_owned()))
+        }
+        Conditional::NotModified => Err(format!("Could not fetch {}: HTTP 304 without validators", url.trim())),
+    }
+}
+
+/// `fetch_text` as a conditional GET returning the raw body (capped at 2 MiB, 16 MiB for
+/// PDFs): with `validators` from an earlier response the server may answer 304 instead of
+/// sending the page again.
+pub fn fetch_if_modified(url: &str, timeout: Duration, validators: &Validators) -> Result<Conditional, String> {
+    let url = url.trim();
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("Invalid URL {url:?}: expected http(s)://"));
+    }
+
+    let credentials = credentials::headers_for(url);
+    let request = || {
+        let mut request = golem_wasi_http::Client::new()
+            .get(url)
+            .header("User-Agent", USER_AGENT)
+            .header("Accept", "text/html,application/xhtml+xml,application/xml,text/plain;q=0.9,application/pdf;q=0.8")
+            .timeout(timeout);
+        for (name, value) in &credentials {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        if let Some(etag) = &validators.etag {
+            request = request.header("If-None-Match", etag.as_str());
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header("If-Modified-Since", last_modified.as_str());
+        }
+        request
+    };
+    let response = http_util::send(request).map_err(|err| format!("Could not fetch {url}: {err}"))?;
+    if response.status() == 304 {
+        return Ok(Conditional::NotModified);
+    }
+    if !response.is_success() {
+        return Err(format!("Could not fetch {url}: HTTP {}", response.status()));
+    }
+
+    let content_type = response.header("content-type").unwrap_or_default().to_ascii_lowercase();
+    let validators = Validators {
+        etag: response.header("etag").map(str::to_string),
+        last_modified: response.header("last-modified").map(str::to_string),
+    };
+    let mut body = response.into_body();
+    body.truncate(if pdf::is_pdf(&content_type, &body) {
+        MAX_PDF_BYTES
+    } else {
+        MAX_BODY_BYTES
+    });
+    Ok(Conditional::Modified {
+        content_type,
+        body,
+        validators,
+    })
+}
+
+/// The `<title>` and the readable text of an HTML document, using the `EXTRACTION_RULES`
+/// entry for the page's domain when there is one.
+pub fn html_to_text(url: &str, html: &str) -> (Option<String>, String) {
+    let title = element_content(html, "title")
+        .map(|t| collapse_whitespace(&decode_entities(t)))
+        .filter(|t| !t.is_empty());
+    let text = match rules::rule_for(url) {
+        Some(rule) => rule.extract(html),
+        None => strip_tags(readable_region(html)),
+    };
+    (title, text)
+}
+
+/// The page's `<article>` or `<main>` when present, else its `<body>`.
+pub fn readable_region(html: &str) -> &str {
+    element_content(html, "article")
+        .or_else(|| element_content(html, "main"))
+        .or_else(|| element_content(html, "body"))
+        .unwrap_or(html)
+}
+
+/// Inner HTML of the first `<name ...>...</name>`, matched case-insensitively.
+fn element_content<'a>(html: &'a str, name: &str) -> Option<&'a str> {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{name}");
+    let mut from = 0;
+    let start = loop {
+        let at = from + lower[from..].find(&open)?;
+        let next = lower[at + open.len()..].chars().next();
+        if next.is_some_and(|c| c == '>' || c.is_whitespace()) {
+            break at;
+        }
+        from = at + open.len();
+    };
+    let inner = start + lower[start..].find('>')? + 1;
+    let end = lower[inner..]
+        .find(&format!("</{name}"))
+        .map_or(html.len(), |i| inner + i);
+    Some(&html[inner..end])
+}
+
+/// Text of an HTML fragment, one line per block element.
+pub fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        text.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |i| &comment[i + 3..]);
+            continue;
+        }
+        let Some(close) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = rest[1..close].trim_start_matches('/').to_ascii_lowercase();
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("");
+        rest = &rest[close + 1..];
+
+        if SKIPPED_ELEMENTS.contains(&name) && !rest.is_empty() {
+            rest = match find_close_tag(rest, name) {
+                Some(i) => rest[i..].find('>').map_or("", |j| &rest[i + j + 1..]),
+                None => "",
+            };
+        } else if BLOCK_ELEMENTS.contains(&name) {
+            text.push('\n');
+        } else {
+            text.push(' ');
+        }
+    }
+    text.push_str(rest);
+
+    decode_entities(&text)
+        .lines()
+        .map(collapse_whitespace)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Byte offset of the first `</name` in `html`, matched case-insensitively.
+pub fn find_close_tag(html: &str, name: &str) -> Option<usize> {
+    let mut from = 0;
+    while let Some(i) = html[from..].find("</") {
+        let at = from + i;
+        let candidate = &html.as_bytes()[at + 2..];
+        if candidate.len() >= name.len()
+            && candidate[..name.len()].eq_ignore_ascii_case(name.as_bytes())
+            && candidate
+                .get(name.len())
+                .is_none_or(|c| *c == b'>' || c.is_ascii_whitespace())
+        {
+            return Some(at);
+        }
+        from = at + 2;
+    }
+    None
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Resolves `href` against the page URL; `None` for fragments and non-http(s) schemes.
+pub fn resolve_url(base: &str, href: &str) -> Option<String> {
+    let href = href.trim();
+    if href.is_empty() || href.starts_with('#') {
+        return None;
+    }
+    if href.starts_with("http://") || href.starts_with("https://") {
+        return Some(href.to_string());
+    }
+    let has_scheme = href.split_once(':').is_some_and(|(scheme, _)| {
+        !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    if has_scheme {
+        return None;
+    }
+
+    let (scheme, rest) = base.split_once("://")?;
+    let origin_end = rest.find('/').unwrap_or(rest.len());
+    let origin = format!("{scheme}://{}", &rest[..origin_end]);
+    if let Some(path) = href.strip_prefix("//") {
+        return Some(format!("{scheme}://{path}"));
+    }
+    if href.starts_with('/') {
+        return Some(format!("{origin}{href}"));
+    }
+    let path = rest[origin_end..].split(['?', '#']).next().unwrap_or("");
+    let dir = path.rfind('/').map_or("/", |i| &path[..=i]);
+    Some(format!("{origin}{dir}{href}"))
+}
+
+/// Value of `name="..."` (single-quoted or unquoted also work) in a tag's attribute text,
+/// matched case-insensitively and entity-decoded; `None` when absent or blank.
+pub fn attribute(attrs: &str, name: &str) -> Option<String> {
+    let lower = attrs.to_ascii_lowercase();
+    let name = name.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(&name) {
+        let at = from + i;
+        from = at + name.len();
+        let preceded = attrs[..at].chars().next_back().is_none_or(|c| c.is_whitespace());
+        let Some(after) = attrs[from..].trim_start().strip_prefix('=') else {
+            continue;
+        };
+        if !preceded {
+            continue;
+        }
+        let after = after.trim_start();
+        let value = match after.chars().next()? {
+            quote @ ('"' | '\'') => {
+                let value = &after[1..];
+                &value[..value.find(quote).unwrap_or(value.len())]
+            }
+            _ => after.split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or(""),
+        };
+        let value = decode_entities(value.trim());
+        return if value.is_empty() { None } else { Some(value) };
+    }
+    None
+}
+
+/// Decodes the named entities common in body text plus numeric references.
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..=end]);
+        let replacement = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "rsquo" | "lsquo" => Some('\''),
+            "rdquo" | "ldquo" => Some('"'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        });
+        match (entity, replacement) {
+            (Some(entity), Some(c)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
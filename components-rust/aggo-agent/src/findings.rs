@@ -0,0 +1,193 @@
+//! Findings extracted from a report and linked to the sources it cites.
+//!
+//! The model is shown the report and a numbered list of its cited sources and asked for JSON
+//! findings with the source numbers that support or contradict each one. Source numbers are
+//! validated here, so every edge in the graph points at a real, cited URL.
+
+use golem_rust::Schema;
+
+use crate::citations::Citation;
+use crate::prompt;
+
+#[derive(Clone, Debug, PartialEq, Schema, serde::Serialize, serde::Deserialize)]
+pub enum EvidenceRelation {
+    Supports,
+    Contradicts,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct FindingNode {
+    /// `f1`, `f2`, … in report order.
+    pub id: String,
+    pub statement: String,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct SourceNode {
+    /// `s1`, `s2`, … in order of first citation.
+    pub id: String,
+    pub url: String,
+    pub title: Option<String>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct EvidenceEdge {
+    pub finding_id: String,
+    pub source_id: String,
+    pub relation: EvidenceRelation,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct CitationGraph {
+    pub report_id: String,
+    pub findings: Vec<FindingNode>,
+    pub sources: Vec<SourceNode>,
+    pub edges: Vec<EvidenceEdge>,
+}
+
+/// One atomic finding and the URLs that support it.
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct KeyFinding {
+    pub statement: String,
+    pub sources: Vec<String>,
+}
+
+/// What `key_findings` should read: a deep research job, a stored report, or a fresh topic.
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub enum FindingsSubject {
+    Job(String),
+    Report(String),
+    Topic(String),
+}
+
+/// Upper bound on key findings returned; the prompt asks for 5–10.
+const MAX_KEY_FINDINGS: usize = 10;
+
+#[derive(serde::Deserialize)]
+struct ExtractedFindings {
+    findings: Vec<ExtractedFinding>,
+}
+
+#[derive(serde::Deserialize)]
+struct ExtractedFinding {
+    statement: String,
+    #[serde(default)]
+    supports: Vec<usize>,
+    #[serde(default)]
+    contradicts: Vec<usize>,
+}
+
+pub fn extraction_prompt(report: &str, sources: &[Citation]) -> String {
+    let sources = sources
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{}. {}", i + 1, c.url))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "Extract the distinct factual findings from the research report below. Each finding must be one atomic, self-contained claim.\n\
+         For each finding list the numbers of the sources (from the numbered list) that support it and any that contradict it.\n\
+         Reply with JSON only, in this shape: {{\"findings\": [{{\"statement\": \"...\", \"supports\": [1], \"contradicts\": []}}]}}\n\n\
+         Sources:\n{sources}\n\n\
+         Report:\n{report}"
+    )
+}
+
+pub fn key_findings_prompt(report: &str, sources: &[Citation]) -> String {
+    let sources = sources
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{}. {}", i + 1, c.url))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "List the 5 to 10 most important findings of the research report below, most important first.\n\
+         Each finding must be one atomic, self-contained claim that a reader could record in a decision log, backed by at least one source from the numbered list.\n\
+         Reply with JSON only, in this shape: {{\"findings\": [{{\"statement\": \"...\", \"supports\": [1]}}]}}\n\n\
+         Sources:\n{sources}\n\n\
+         Report:\n{report}"
+    )
+}
+
+/// Key findings from the model's reply. When the report cites sources, findings the model
+/// couldn't tie to one are dropped.
+pub fn parse_key_findings(reply: &str, sources: &[Citation]) -> Result<Vec<KeyFinding>, String> {
+    let graph = parse_graph("", reply, sources)?;
+
+    Ok(graph
+        .findings
+        .iter()
+        .map(|finding| KeyFinding {
+            statement: finding.statement.clone(),
+            sources: graph
+                .edges
+                .iter()
+                .filter(|e| e.finding_id == finding.id && e.relation == EvidenceRelation::Supports)
+                .filter_map(|e| graph.sources.iter().find(|s| s.id == e.source_id))
+                .map(|s| s.url.clone())
+                .collect(),
+        })
+        .filter(|finding| sources.is_empty() || !finding.sources.is_empty())
+        .take(MAX_KEY_FINDINGS)
+        .collect())
+}
+
+/// Builds the graph from the model's JSON reply; out-of-range source numbers are dropped.
+pub fn parse_graph(report_id: &str, reply: &str, sources: &[Citation]) -> Result<CitationGraph, String> {
+    let extracted: ExtractedFindings = serde_json::from_str(prompt::json_object(reply))
+        .map_err(|err| format!("The model did not return valid findings JSON: {err}"))?;
+
+    let source_nodes: Vec<SourceNode> = sources
+        .iter()
+        .enumerate()
+        .map(|(i, c)| SourceNode {
+            id: format!("s{}", i + 1),
+            url: c.url.clone(),
+            title: c.title.clone(),
+        })
+        .collect();
+
+    let mut findings = Vec::new();
+    let mut edges = Vec::new();
+    for finding in extracted.findings {
+        let statement = finding.statement.trim();
+        if statement.is_empty() {
+            continue;
+        }
+        let finding_id = format!("f{}", findings.len() + 1);
+
+        let relations = finding
+            .supports
+            .iter()
+            .map(|n| (*n, EvidenceRelation::Supports))
+            .chain(finding.contradicts.iter().map(|n| (*n, EvidenceRelation::Contradicts)));
+        for (number, relation) in relations {
+            if let Some(source) = number.checked_sub(1).and_then(|i| source_nodes.get(i)) {
+                let duplicate = edges.iter().any(|e: &EvidenceEdge| {
+                    e.finding_id == finding_id && e.source_id == source.id && e.relation == relation
+                });
+                if !duplicate {
+                    edges.push(EvidenceEdge {
+                        finding_id: finding_id.clone(),
+                        source_id: source.id.clone(),
+                        relation,
+                    });
+                }
+            }
+        }
+
+        findings.push(FindingNode {
+            id: finding_id,
+            statement: statement.to_string(),
+        });
+    }
+
+    Ok(CitationGraph {
+        report_id: report_id.to_string(),
+        findings,
+        sources: source_nodes,
+        edges,
+    })
+}
@@ -0,0 +1,105 @@
+//! `heartbeat`: liveness and load for orchestrators deciding when to recycle or scale workers.
+//!
+//! Each external dependency call (a web search provider, an LLM model) records its outcome
+//! here. There is no circuit breaker that skips dependencies; the state is the signal one
+//! would key on: `failing` after `FAILING_AFTER` consecutive failures, `degraded` after fewer,
+//! `healthy` once a call succeeds again.
+
+use std::cell::RefCell;
+
+use golem_rust::Schema;
+
+use crate::error_class::ErrorClass;
+use crate::quota::QuotaStatus;
+
+/// Consecutive failures after which a dependency counts as failing.
+const FAILING_AFTER: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Schema, serde::Serialize, serde::Deserialize)]
+pub enum DependencyState {
+    Healthy,
+    Degraded,
+    Failing,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct DependencyStatus {
+    /// `search:<provider>` or `llm:<model>`.
+    pub name: String,
+    pub state: DependencyState,
+    pub consecutive_failures: u32,
+    pub last_success_at: Option<u64>,
+    pub last_failure_at: Option<u64>,
+    /// Class of the last failure (e.g. `quota`; see `error_class`), never its message.
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct Heartbeat {
+    pub now: u64,
+    /// When this worker instance was created or restored.
+    pub started_at: u64,
+    pub uptime_seconds: u64,
+    /// Deep research jobs with sections still to write.
+    pub in_flight_jobs: u32,
+    /// Streams still receiving text.
+    pub in_flight_streams: u32,
+    /// Work waiting to run: due schedules and watch checks plus deferred notifications.
+    pub queue_depth: u32,
+    pub due_schedules: u32,
+    pub due_watches: u32,
+    pub pending_notifications: u32,
+    /// Every dependency called since the worker started, by name.
+    pub dependencies: Vec<DependencyStatus>,
+    /// Web search providers with quota windows, configured or reported (see `quota`).
+    pub quotas: Vec<QuotaStatus>,
+}
+
+/// Dependency outcomes; recorded from `&self` methods, hence the `RefCell`.
+#[derive(Default)]
+pub struct DependencyHealth {
+    dependencies: RefCell<Vec<DependencyStatus>>,
+}
+
+impl DependencyHealth {
+    pub fn record(&self, name: &str, error: Option<ErrorClass>, now: u64) {
+        let mut dependencies = self.dependencies.borrow_mut();
+        let index = match dependencies.iter().position(|d| d.name == name) {
+            Some(index) => index,
+            None => {
+                dependencies.push(DependencyStatus {
+                    name: name.to_string(),
+                    state: DependencyState::Healthy,
+                    consecutive_failures: 0,
+                    last_success_at: None,
+                    last_failure_at: None,
+                    last_error: None,
+                });
+                dependencies.len() - 1
+            }
+        };
+        let dependency = &mut dependencies[index];
+        match error {
+            None => {
+                dependency.consecutive_failures = 0;
+                dependency.last_success_at = Some(now);
+            }
+            Some(class) => {
+                dependency.consecutive_failures += 1;
+                dependency.last_failure_at = Some(now);
+                dependency.last_error = Some(class.name().to_string());
+            }
+        }
+        dependency.state = match dependency.consecutive_failures {
+            0 => DependencyState::Healthy,
+            n if n < FAILING_AFTER => DependencyState::Degraded,
+            _ => DependencyState::Failing,
+        };
+    }
+
+    pub fn statuses(&self) -> Vec<DependencyStatus> {
+        let mut statuses = self.dependencies.borrow().clone();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+}
@@ -0,0 +1,265 @@
+//! Stored research reports, kept in agent state per workspace.
+
+use golem_rust::Schema;
+
+use crate::config;
+use crate::differential::SourceFingerprint;
+use crate::error::AgentError;
+use crate::schema::SCHEMA_VERSION;
+
+/// Reports kept per workspace when `REPORT_HISTORY_LIMIT` is unset; oldest are dropped first.
+const DEFAULT_HISTORY_LIMIT: usize = 200;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ReportRecord {
+    /// See `schema`; version 0 is a record from before versioning.
+    #[serde(default)]
+    pub schema_version: u32,
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub workspace: String,
+    pub topic: String,
+    #[serde(default)]
+    pub created_at: u64,
+    /// Set when the report was produced by a schedule.
+    pub schedule_id: Option<String>,
+    pub report: String,
+    /// Missing from version 0 records.
+    #[serde(default)]
+    pub metadata: ReportMetadata,
+}
+
+/// How a report was generated, so a run can be reproduced.
+#[derive(Clone, Debug, Default, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ReportMetadata {
+    pub model: String,
+    /// Sampling seed passed to the provider, if the caller set one.
+    pub seed: Option<u64>,
+    /// Sources a scheduled report was written from, for the next run's differential update.
+    #[serde(default)]
+    pub sources: Vec<SourceFingerprint>,
+    /// Failures the run got past, by kind; see `RetrievalExplanation::errors`. A report whose
+    /// text is an LLM failure message has that failure here.
+    #[serde(default)]
+    pub errors: Vec<AgentError>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ReportSummary {
+    pub id: String,
+    pub topic: String,
+    pub created_at: u64,
+    pub schedule_id: Option<String>,
+}
+
+/// `export_history` output.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct HistoryExport {
+    #[serde(default)]
+    schema_version: u32,
+    records: Vec<ReportRecord>,
+}
+
+/// Exports from before versioning are a bare array of records.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StoredHistory {
+    Versioned(HistoryExport),
+    Legacy(Vec<ReportRecord>),
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct ReportHistory {
+    records: Vec<ReportRecord>,
+    next_id: u64,
+}
+
+impl ReportHistory {
+    pub fn record(
+        &mut self,
+        workspace: &str,
+        topic: &str,
+        schedule_id: Option<String>,
+        created_at: u64,
+        report: String,
+        metadata: ReportMetadata,
+    ) -> String {
+        self.next_id += 1;
+        let id = format!("rpt-{}", self.next_id);
+        self.records.push(ReportRecord {
+            schema_version: SCHEMA_VERSION,
+            id: id.clone(),
+            workspace: workspace.to_string(),
+            topic: topic.to_string(),
+            created_at,
+            schedule_id,
+            report,
+            metadata,
+        });
+        self.enforce_limit(workspace);
+        id
+    }
+
+    /// Newest first.
+    pub fn list(&self, workspace: &str) -> Vec<ReportSummary> {
+        self.records
+            .iter()
+            .rev()
+            .filter(|r| r.workspace == workspace)
+            .map(|r| ReportSummary {
+                id: r.id.clone(),
+                topic: r.topic.clone(),
+                created_at: r.created_at,
+                schedule_id: r.schedule_id.clone(),
+            })
+            .collect()
+    }
+
+    pub fn get(&self, workspace: &str, id: &str) -> Option<&ReportRecord> {
+        self.records
+            .iter()
+            .find(|r| r.workspace == workspace && r.id == id)
+    }
+
+    /// The schedule's newest report that recorded its sources, i.e. the last one that was
+    /// written successfully.
+    pub fn latest_for_schedule(&self, workspace: &str, schedule_id: &str) -> Option<&ReportRecord> {
+        self.records.iter().rev().find(|r| {
+            r.workspace == workspace
+                && r.schedule_id.as_deref() == Some(schedule_id)
+                && !r.metadata.sources.is_empty()
+        })
+    }
+
+    /// The workspace's reports as `export_history` JSON, oldest first.
+    pub fn export(&self, workspace: &str) -> String {
+        let export = HistoryExport {
+            schema_version: SCHEMA_VERSION,
+            records: self
+                .records
+                .iter()
+                .filter(|r| r.workspace == workspace)
+                .cloned()
+                .collect(),
+        };
+        serde_json::to_string(&export).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Appends imported records under new IDs, keeping their topics, times and metadata.
+    /// The workspace's history limit still applies, so the oldest may be dropped.
+    pub fn import(&mut self, workspace: &str, records: Vec<ReportRecord>) -> u32 {
+        let count = records.len() as u32;
+        for record in records {
+            self.next_id += 1;
+            self.records.push(ReportRecord {
+                schema_version: SCHEMA_VERSION,
+                id: format!("rpt-{}", self.next_id),
+                workspace: workspace.to_string(),
+                ..record
+            });
+        }
+        self.enforce_limit(workspace);
+        count
+    }
+
+    fn enforce_limit(&mut self, workspace: &str) {
+        let limit = history_limit();
+        let count = self.records.iter().filter(|r| r.workspace == workspace).count();
+        let mut excess = count.saturating_sub(limit);
+        self.records.retain(|r| {
+            if excess > 0 && r.workspace == workspace {
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+/// Records from `export_history` JSON of any schema version, upgraded to the current one.
+pub fn parse_export(data: &str) -> Result<Vec<ReportRecord>, String> {
+    let (version, records) = match serde_json::from_str::<StoredHistory>(data.trim()) {
+        Ok(StoredHistory::Versioned(export)) => (export.schema_version, export.records),
+        Ok(StoredHistory::Legacy(records)) => (0, records),
+        Err(err) => return Err(format!("Not a report history export: {err}")),
+    };
+    if version > SCHEMA_VERSION {
+        log::warn!("Importing history from schema version {version} (this component writes {SCHEMA_VERSION}); unknown fields are dropped");
+    }
+    Ok(records
+        .into_iter()
+        .filter(|r| !r.report.trim().is_empty())
+        .map(|mut record| {
+            if record.metadata.model.is_empty() {
+                record.metadata.model = "unknown".to_string();
+            }
+            record
+        })
+        .collect())
+}
+
+fn history_limit() -> usize {
+    config::env_parse::<usize>("REPORT_HISTORY_LIMIT")
+        .filter(|limit| *limit > 0)
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_a_legacy_bare_array() {
+        let records = parse_export(r#" [{"topic": "rust", "report": "Findings", "schedule_id": null}] "#).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].schema_version, 0);
+        assert_eq!(records[0].topic, "rust");
+        assert_eq!(records[0].metadata.model, "unknown");
+    }
+
+    #[test]
+    fn imports_a_versioned_export_and_drops_blank_reports() {
+        let data = r#"{"schema_version": 1, "records": [
+            {"schema_version": 1, "id": "rpt-1", "topic": "a", "report": "A", "metadata": {"model": "m", "seed": 7}},
+            {"schema_version": 1, "id": "rpt-2", "topic": "b", "report": "  \n"}
+        ]}"#;
+        let records = parse_export(data).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, "rpt-1");
+        assert_eq!(records[0].metadata.model, "m");
+        assert_eq!(records[0].metadata.seed, Some(7));
+    }
+
+    #[test]
+    fn imports_a_newer_version_without_its_unknown_fields() {
+        let data = format!(
+            r#"{{"schema_version": {}, "records": [{{"topic": "a", "report": "A", "rating": 5}}], "owner": "x"}}"#,
+            SCHEMA_VERSION + 1
+        );
+        assert_eq!(parse_export(&data).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rejects_other_json() {
+        assert!(parse_export("").is_err());
+        assert!(parse_export(r#"{"reports": []}"#).is_err());
+        assert!(parse_export("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn round_trips_its_own_export() {
+        let mut history = ReportHistory::default();
+        let metadata = ReportMetadata {
+            model: "m".to_string(),
+            ..ReportMetadata::default()
+        };
+        history.record("default", "topic", Some("sch-1".to_string()), 42, "Report".to_string(), metadata);
+        let records = parse_export(&history.export("default")).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].schema_version, SCHEMA_VERSION);
+        assert_eq!(records[0].created_at, 42);
+        assert_eq!(records[0].schedule_id.as_deref(), Some("sch-1"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Conditional GETs for provider endpoints that send validators: the Wikipedia REST API, the
+//! arXiv and Semantic Scholar APIs and site sitemaps.
+//!
+//! A response that came with an `ETag` or `Last-Modified` is kept with them, and the next GET
+//! of the same URL and query sends them back as `If-None-Match` / `If-Modified-Since`. A 304
+//! answer is served from the kept body, so an unchanged response costs a round trip but no
+//! download. Page fetches (the content fetcher and watches) keep their validators with the page
+//! instead; see `page_cache` and `watch`.
+//!
+//! `HTTP_CACHE_MAX_RESPONSES` (default 100, least recently used out first; 0 turns it off)
+//! bounds the cache, which is kept in the worker snapshot. Bodies over 1 MiB aren't kept.
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use crate::config;
+use crate::fetch::{self, Conditional, Validators};
+use crate::http_util;
+
+const DEFAULT_MAX_RESPONSES: usize = 100;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct CachedResponse {
+    /// Method-less request key: the URL with its query.
+    key: String,
+    validators: Validators,
+    body: String,
+    used_at: u64,
+}
+
+/// Written from `&self` search paths, hence the `RefCell`.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct ResponseCache {
+    responses: RefCell<Vec<CachedResponse>>,
+}
+
+impl ResponseCache {
+    /// Sends the GET built by `request`, identified by `key`, through `http_util` with the
+    /// validators of the kept response for `key`. Returns the status and body; a 304 comes back
+    /// as 200 with the kept body.
+    pub fn send(
+        &self,
+        key: &str,
+        request: impl Fn() -> golem_wasi_http::RequestBuilder,
+        now: u64,
+    ) -> Result<(u16, String), String> {
+        let kept = self.get(key);
+        let response = http_util::send(|| {
+            let mut request = request();
+            if let Some(kept) = &kept {
+                if let Some(etag) = &kept.validators.etag {
+                    request = request.header("If-None-Match", etag.as_str());
+                }
+                if let Some(last_modified) = &kept.validators.last_modified {
+                    request = request.header("If-Modified-Since", last_modified.as_str());
+                }
+            }
+            request
+        })?;
+        let status = response.status();
+        if status == 304 {
+            if let Some(kept) = kept {
+                log::info!("Not modified, using the kept response for {key}");
+                self.touch(key, now);
+                return Ok((200, kept.body));
+            }
+        }
+
+        let validators = Validators {
+            etag: response.header("etag").map(str::to_string),
+            last_modified: response.header("last-modified").map(str::to_string),
+        };
+        let body = response.text();
+        if (200..300).contains(&status) && (validators.etag.is_some() || validators.last_modified.is_some()) {
+            self.store(CachedResponse {
+                key: key.to_string(),
+                validators,
+                body: body.clone(),
+                used_at: now,
+            });
+        }
+        Ok((status, body))
+    }
+
+    /// `fetch::fetch_text` as a conditional GET with the validators kept for `url`; returns
+    /// the body.
+    pub fn fetch_text(&self, url: &str, timeout: Duration, now: u64) -> Result<String, String> {
+        let kept = self.get(url);
+        let validators = kept.as_ref().map(|r| r.validators.clone()).unwrap_or_default();
+        match fetch::fetch_if_modified(url, timeout, &validators)? {
+            Conditional::NotModified => match kept {
+                Some(kept) => {
+                    self.touch(url, now);
+                    Ok(kept.body)
+                }
+                None => Err(format!("Could not fetch {url}: HTTP 304 for a response not in the cache")),
+            },
+            Conditional::Modified { body, validators, .. } => {
+                let body = String::from_utf8_lossy(&body).into_owned();
+                if validators.etag.is_some() || validators.last_modified.is_some() {
+                    self.store(CachedResponse {
+                        key: url.to_string(),
+                        validators,
+                        body: body.clone(),
+                        used_at: now,
+                    });
+                }
+                Ok(body)
+            }
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.responses.borrow().iter().find(|r| r.key == key).cloned()
+    }
+
+    fn touch(&self, key: &str, now: u64) {
+        if let Some(response) = self.responses.borrow_mut().iter_mut().find(|r| r.key == key) {
+            response.used_at = now;
+        }
+    }
+
+    fn store(&self, response: CachedResponse) {
+        let max_responses = max_responses();
+        if max_responses == 0 || response.body.len() > MAX_BODY_BYTES {
+            return;
+        }
+        let mut responses = self.responses.borrow_mut();
+        responses.retain(|r| r.key != response.key);
+        responses.push(response);
+        if responses.len() > max_responses {
+            responses.sort_by_key(|r| std::cmp::Reverse(r.used_at));
+            responses.truncate(max_responses);
+        }
+    }
+    /// Empties the cache and returns how many responses it had.
+    pub fn clear(&self) -> u32 {
+        self.responses.take().len() as u32
+    }
+}
+
+fn max_responses() -> usize {
+    config::env_parse::<usize>("HTTP_CACHE_MAX_RESPONSES").unwrap_or(DEFAULT_MAX_RESPONSES)
+}
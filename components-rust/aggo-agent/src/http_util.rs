@@ -0,0 +1,204 @@
+//! Shared plumbing for the clients that call providers directly: web search (`search_http`),
+//! provider GETs (`http_cache`: Wikipedia, arXiv, Semantic Scholar, sitemaps), page fetches
+//! (`fetch`), embeddings (`embed`) and archive.org lookups (`archive`).
+//!
+//! Failed calls are retried with exponential backoff and jitter: transport errors of the
+//! `retryable-transient` class (see `error_class`), 429 and 5xx answers other than 501, up to
+//! `HTTP_MAX_RETRIES` more times (default 2). Retry n waits a random time between half and all
+//! of `HTTP_RETRY_BASE_MS` (default 500) × 2ⁿ⁻¹, or what the answer's `Retry-After` asks for.
+//! A wait over `HTTP_RETRY_MAX_WAIT_SECONDS` (default 10) isn't taken and the answer is
+//! returned as it is, so a provider that is out of quota fails over to the next one at once.
+//! `HTTP_TIMEOUT_SECONDS` overrides the built-in timeouts of web search, Wikipedia and academic
+//! API calls.
+//!
+//! Requests ask for `gzip` or `br` compressed responses, and bodies are decoded according to
+//! their `Content-Encoding` (`gzip`, `deflate` or `br`, also stacked) before a client reads
+//! them. That includes responses a proxy compressed on its own accord. Decoded bodies are cut
+//! at 32 MiB.
+
+use std::io::Read;
+use std::time::Duration;
+
+use crate::clock;
+use crate::config;
+use crate::error_class::ErrorClass;
+use crate::sanitize;
+
+const DEFAULT_MAX_RETRIES: u32 = 2;
+const DEFAULT_RETRY_BASE_MS: u64 = 500;
+const DEFAULT_RETRY_MAX_WAIT_SECONDS: u64 = 10;
+const ACCEPT_ENCODING: &str = "gzip, br";
+const MAX_DECODED_BYTES: u64 = 32 * 1024 * 1024;
+/// Buffer size of the brotli decoder.
+const BROTLI_BUFFER: usize = 4096;
+
+/// A response with its body read and decoded.
+pub struct Response {
+    status: u16,
+    /// Lowercased names; values that aren't text are left out.
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The first value of header `name`, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The body as text (lossy UTF-8).
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(&self.body)
+    }
+
+    pub fn into_body(self) -> Vec<u8> {
+        self.body
+    }
+}
+
+/// Sends the request built by `request`, building it again for each retry, accepting a
+/// compressed response. Reads and decodes the body of the final answer.
+pub fn send(request: impl Fn() -> golem_wasi_http::RequestBuilder) -> Result<Response, String> {
+    let max_retries = config::env_parse::<u64>("HTTP_MAX_RETRIES").map_or(DEFAULT_MAX_RETRIES, |n| n as u32);
+    let mut retry = 0;
+    let response = loop {
+        let result = request().header("Accept-Encoding", ACCEPT_ENCODING).send();
+        // Whether the call may be repeated, and the wait the server asked for.
+        let (retryable, asked) = match &result {
+            Ok(response) => (
+                retried_status(response.status().as_u16()),
+                response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(retry_after),
+            ),
+            Err(err) => (ErrorClass::of_message(&err.to_string()).retryable(), None),
+        };
+        let wait = asked.unwrap_or_else(|| backoff(retry));
+        if !retryable || retry >= max_retries || wait > max_wait() {
+            // Transport errors name the URL, query string and all.
+            break result.map_err(|err| sanitize::outward("provider HTTP call", &err.to_string()))?;
+        }
+        retry += 1;
+        log::info!("Retrying a provider call in {} ms (retry {retry} of {max_retries})", wait.as_millis());
+        std::thread::sleep(wait);
+    };
+    let status = response.status().as_u16();
+    let headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.as_str().to_ascii_lowercase(), value.to_str().ok()?.to_string())))
+        .collect();
+    let body = response
+        .bytes()
+        .map_err(|err| format!("could not read the response: {err}"))?;
+    let mut response = Response { status, headers, body };
+    if let Some(encoding) = response.header("content-encoding").map(str::to_string) {
+        response.body = decode(&encoding, std::mem::take(&mut response.body))?;
+    }
+    Ok(response)
+}
+
+/// The timeout for a provider call: `HTTP_TIMEOUT_SECONDS`, else the caller's `default`.
+pub fn timeout(default: Duration) -> Duration {
+    config::env_parse::<u64>("HTTP_TIMEOUT_SECONDS").map_or(default, Duration::from_secs)
+}
+
+fn retried_status(status: u16) -> bool {
+    status == 429 || (status >= 500 && status != 501)
+}
+
+/// The wait before retry `retry` (0-based): a random time between half and all of the
+/// exponential step.
+fn backoff(retry: u32) -> Duration {
+    let base = config::env_parse::<u64>("HTTP_RETRY_BASE_MS").unwrap_or(DEFAULT_RETRY_BASE_MS);
+    let step = base.saturating_mul(1 << retry.min(16));
+    Duration::from_millis(step / 2 + jitter(step / 2))
+}
+
+/// A pseudo-random number in `0..=max`, from the clock's sub-second nanoseconds.
+fn jitter(max: u64) -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| u64::from(d.subsec_nanos()))
+        .unwrap_or(0);
+    nanos % (max + 1)
+}
+
+fn max_wait() -> Duration {
+    Duration::from_secs(config::env_parse::<u64>("HTTP_RETRY_MAX_WAIT_SECONDS").unwrap_or(DEFAULT_RETRY_MAX_WAIT_SECONDS))
+}
+
+/// A `Retry-After` value, in seconds or as an HTTP date (`Sun, 06 Nov 1994 08:49:37 GMT`).
+fn retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, ..] = parts.as_slice() else {
+        return None;
+    };
+    let month = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"]
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(month))? as u32
+        + 1;
+    let mut clock_parts = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (Some(Some(hours)), Some(Some(minutes)), Some(Some(seconds))) =
+        (clock_parts.next(), clock_parts.next(), clock_parts.next())
+    else {
+        return None;
+    };
+    let days = clock::days_from_civil(year.parse().ok()?, month, day.parse().ok()?);
+    let at = days * 86_400 + hours * 3600 + minutes * 60 + seconds;
+    Some(Duration::from_secs(at.saturating_sub(clock::now_secs())))
+}
+
+/// `body` with the codings of a `Content-Encoding` header undone, last applied first.
+fn decode(encoding: &str, mut body: Vec<u8>) -> Result<Vec<u8>, String> {
+    if body.is_empty() {
+        return Ok(body);
+    }
+    for coding in encoding.rsplit(',').map(|c| c.trim().to_ascii_lowercase()) {
+        let mut decoded = Vec::new();
+        let read = match coding.as_str() {
+            "" | "identity" => continue,
+            "gzip" | "x-gzip" => flate2::read::MultiGzDecoder::new(&body[..])
+                .take(MAX_DECODED_BYTES)
+                .read_to_end(&mut decoded),
+            // Servers disagree on whether `deflate` is zlib-wrapped; raw deflate is the fallback.
+            "deflate" => flate2::read::ZlibDecoder::new(&body[..])
+                .take(MAX_DECODED_BYTES)
+                .read_to_end(&mut decoded)
+                .or_else(|_| {
+                    decoded.clear();
+                    flate2::read::DeflateDecoder::new(&body[..])
+                        .take(MAX_DECODED_BYTES)
+                        .read_to_end(&mut decoded)
+                }),
+            "br" => brotli_decompressor::Decompressor::new(&body[..], BROTLI_BUFFER)
+                .take(MAX_DECODED_BYTES)
+                .read_to_end(&mut decoded),
+            other => return Err(format!("unsupported content encoding {other:?}")),
+        };
+        read.map_err(|err| format!("could not decode the {coding} response: {err}"))?;
+        body = decoded;
+    }
+    Ok(body)
+}
@@ -0,0 +1,150 @@
+//! Bulk import of monitored topics from an OPML feed list or a JSON bookmark export.
+//!
+//! Each feed or bookmark becomes one topic: its title, scoped to the source's site with a
+//! `site:` operator so the monitor follows what that source publishes.
+
+use golem_rust::Schema;
+
+use crate::citations;
+use crate::fetch::attribute;
+use crate::schedule::ScheduleInfo;
+
+/// Upper bound on topics created by one `import_topics` call.
+pub const MAX_IMPORTED_TOPICS: usize = 100;
+
+/// Cron used when the caller does not give one: Mondays at 07:00 UTC.
+pub const DEFAULT_IMPORT_CRON: &str = "0 7 * * 1";
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct SkippedTopic {
+    pub topic: String,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct TopicImport {
+    pub created: Vec<ScheduleInfo>,
+    pub skipped: Vec<SkippedTopic>,
+}
+
+/// One feed or bookmark found in the document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedEntry {
+    pub title: String,
+    pub url: Option<String>,
+}
+
+impl ImportedEntry {
+    /// The research topic for this entry: the title (or host) plus a `site:` scope.
+    pub fn topic(&self) -> Option<String> {
+        let host = self.url.as_deref().and_then(citations::host);
+        let title = self.title.trim();
+        match (title.is_empty(), host) {
+            (false, Some(host)) => Some(format!("{title} site:{host}")),
+            (false, None) => Some(title.to_string()),
+            (true, Some(host)) => Some(format!("{host} site:{host}")),
+            (true, None) => None,
+        }
+    }
+}
+
+/// Detects the format (OPML when the document starts with `<`, JSON otherwise) and lists
+/// its entries in document order.
+pub fn parse(document: &str) -> Result<Vec<ImportedEntry>, String> {
+    let document = document.trim_start_matches('\u{feff}').trim();
+    if document.is_empty() {
+        return Err("Nothing to import: the document is empty".to_string());
+    }
+
+    let entries = if document.starts_with('<') {
+        parse_opml(document)
+    } else {
+        let value: serde_json::Value = serde_json::from_str(document)
+            .map_err(|err| format!("Expected an OPML document or JSON bookmarks: {err}"))?;
+        let mut entries = Vec::new();
+        collect_bookmarks(&value, &mut entries);
+        entries
+    };
+
+    if entries.is_empty() {
+        return Err("No feeds or bookmarks found in the document".to_string());
+    }
+    Ok(entries)
+}
+
+/// Reads every `<outline>` with a feed or page URL; grouping outlines without one are skipped.
+fn parse_opml(document: &str) -> Vec<ImportedEntry> {
+    let mut entries = Vec::new();
+    let mut rest = document;
+    while let Some(start) = find_tag(rest, "<outline") {
+        let tag = &rest[start + "<outline".len()..];
+        let end = tag.find('>').unwrap_or(tag.len());
+        let attrs = &tag[..end];
+        rest = &tag[end..];
+
+        let url = attribute(attrs, "htmlUrl")
+            .or_else(|| attribute(attrs, "xmlUrl"))
+            .or_else(|| attribute(attrs, "url"));
+        let Some(url) = url else {
+            continue;
+        };
+        let title = attribute(attrs, "title")
+            .or_else(|| attribute(attrs, "text"))
+            .unwrap_or_default();
+        entries.push(ImportedEntry { title, url: Some(url) });
+    }
+    entries
+}
+
+fn find_tag(text: &str, tag: &str) -> Option<usize> {
+    let mut from = 0;
+    while let Some(i) = text[from..].find(tag) {
+        let at = from + i;
+        let next = text[at + tag.len()..].chars().next();
+        if next.is_some_and(|c| c.is_whitespace() || c == '/' || c == '>') {
+            return Some(at);
+        }
+        from = at + tag.len();
+    }
+    None
+}
+
+/// Walks any JSON bookmark shape: flat `[{title, url}]` lists as well as nested Chrome
+/// (`roots`/`children`, `name`/`url`) and Firefox (`children`, `title`/`uri`) exports.
+fn collect_bookmarks(value: &serde_json::Value, entries: &mut Vec<ImportedEntry>) {
+    match value {
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_bookmarks(item, entries);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            let url = ["url", "uri", "href", "link"]
+                .iter()
+                .find_map(|key| map.get(*key).and_then(|v| v.as_str()))
+                .map(str::trim)
+                .filter(|u| u.starts_with("http://") || u.starts_with("https://"));
+            if let Some(url) = url {
+                let title = ["title", "name", "text"]
+                    .iter()
+                    .find_map(|key| map.get(*key).and_then(|v| v.as_str()))
+                    .unwrap_or("")
+                    .trim()
+                    .to_string();
+                entries.push(ImportedEntry {
+                    title,
+                    url: Some(url.to_string()),
+                });
+            }
+            for (key, child) in map {
+                if child.is_array() || child.is_object() {
+                    if key == "meta_info" {
+                        continue;
+                    }
+                    collect_bookmarks(child, entries);
+                }
+            }
+        }
+        _ => {}
+    }
+}
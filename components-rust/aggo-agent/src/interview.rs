@@ -0,0 +1,82 @@
+//! Interview-question generation grounded in search results.
+//!
+//! Questions are written from the same retrieval as `research`; each one carries the search
+//! result that prompted it, so journalists and user researchers can see why it was asked.
+
+use golem_rust::Schema;
+
+use crate::prompt;
+use crate::truncate;
+use crate::SearchResult;
+
+const MAX_QUESTIONS: usize = 15;
+/// Length of the source excerpt attached to each question.
+const CONTEXT_CHARS: usize = 300;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct InterviewQuestion {
+    pub question: String,
+    /// Why the question is worth asking this audience.
+    pub rationale: String,
+    pub source_url: Option<String>,
+    pub source_title: Option<String>,
+    /// Excerpt of the search result that motivated the question.
+    pub source_context: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ExtractedQuestions {
+    questions: Vec<ExtractedQuestion>,
+}
+
+#[derive(serde::Deserialize)]
+struct ExtractedQuestion {
+    question: String,
+    #[serde(default)]
+    rationale: String,
+    #[serde(default)]
+    source: Option<usize>,
+}
+
+pub fn questions_prompt(topic: &str, audience: &str, sources: &[SearchResult]) -> String {
+    let sources = sources
+        .iter()
+        .enumerate()
+        .map(|(i, s)| format!("{}. {} ({})\n{}", i + 1, s.title, s.url, s.snippet))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    format!(
+        "Prepare interview questions about the topic \"{topic}\" for this audience: {audience}.\n\
+         Write 8 to 12 open-ended questions grounded in the numbered search results below: each should follow up on a specific claim, tension or gap in one result.\n\
+         For each give a one-sentence rationale and the number of the result that motivated it.\n\
+         Reply with JSON only, in this shape: {{\"questions\": [{{\"question\": \"...\", \"rationale\": \"...\", \"source\": 1}}]}}\n\n\
+         Search results:\n{sources}"
+    )
+}
+
+pub fn parse_questions(reply: &str, sources: &[SearchResult]) -> Result<Vec<InterviewQuestion>, String> {
+    let extracted: ExtractedQuestions = serde_json::from_str(prompt::json_object(reply))
+        .map_err(|err| format!("The model did not return valid question JSON: {err}"))?;
+
+    Ok(extracted
+        .questions
+        .into_iter()
+        .filter(|q| !q.question.trim().is_empty())
+        .take(MAX_QUESTIONS)
+        .map(|q| {
+            let source = q
+                .source
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| sources.get(i))
+                .filter(|s| !s.url.is_empty());
+            InterviewQuestion {
+                question: q.question.trim().to_string(),
+                rationale: q.rationale.trim().to_string(),
+                source_url: source.map(|s| s.url.clone()),
+                source_title: source.map(|s| s.title.clone()),
+                source_context: source.map(|s| truncate::chars(&s.snippet, CONTEXT_CHARS)),
+            }
+        })
+        .collect())
+}
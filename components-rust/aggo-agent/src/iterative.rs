@@ -0,0 +1,146 @@
+//! Iterative deep research for `deep_research(topic)`: search the topic, then in each round
+//! ask the model which sub-questions the sources found so far leave open, search each of them
+//! (with page fetching as configured) and add the new sources to the pool. The rounds stop at
+//! `DEEP_RESEARCH_MAX_ITERATIONS` (default 3), when the model has no new questions, or when a
+//! round finds no new sources. A final pass writes a long-form report from the whole pool.
+//!
+//! Unlike `start_deep_research`, which writes a job's outline one section per background
+//! invocation, this runs in a single invocation and returns the finished report.
+
+use crate::citations;
+use crate::config;
+use crate::prompt;
+use crate::truncate;
+use crate::SearchResult;
+
+const DEFAULT_MAX_ITERATIONS: u32 = 3;
+const MAX_ITERATIONS: u32 = 10;
+const DEFAULT_QUESTIONS_PER_ROUND: usize = 3;
+const MAX_QUESTIONS_PER_ROUND: usize = 8;
+/// Sources kept across rounds; later rounds' finds beyond this are dropped.
+const MAX_POOL: usize = 80;
+/// Snippet text per source shown to the model when it picks the next questions.
+const SOURCE_CHARS: usize = 300;
+const MAX_QUESTION_CHARS: usize = 200;
+
+pub struct IterationBudget {
+    pub max_iterations: u32,
+    pub questions_per_round: usize,
+}
+
+impl IterationBudget {
+    pub fn from_env() -> Self {
+        Self {
+            max_iterations: config::env_parse::<u64>("DEEP_RESEARCH_MAX_ITERATIONS")
+                .map_or(DEFAULT_MAX_ITERATIONS, |n| n as u32)
+                .clamp(1, MAX_ITERATIONS),
+            questions_per_round: config::env_parse::<u64>("DEEP_RESEARCH_QUESTIONS_PER_ROUND")
+                .map_or(DEFAULT_QUESTIONS_PER_ROUND, |n| n as usize)
+                .clamp(1, MAX_QUESTIONS_PER_ROUND),
+        }
+    }
+}
+
+/// What one round asked and found.
+pub struct Round {
+    pub questions: Vec<String>,
+    pub new_sources: usize,
+}
+
+impl Round {
+    pub fn note(&self, index: usize) -> String {
+        format!(
+            "Deep research round {index}: {} ({} new sources).",
+            self.questions.join("; "),
+            self.new_sources
+        )
+    }
+}
+
+/// Sources found across rounds, in the order found, without duplicate URLs.
+#[derive(Default)]
+pub struct SourcePool {
+    pub results: Vec<SearchResult>,
+}
+
+impl SourcePool {
+    /// Adds the `results` the pool doesn't have yet and returns how many were new.
+    pub fn add(&mut self, results: Vec<SearchResult>) -> usize {
+        let mut added = 0;
+        for result in results {
+            if self.results.len() >= MAX_POOL {
+                break;
+            }
+            let key = citations::normalize_url(&result.url);
+            if !self.results.iter().any(|r| citations::normalize_url(&r.url) == key) {
+                self.results.push(result);
+                added += 1;
+            }
+        }
+        added
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct NextQuestions {
+    #[serde(default)]
+    questions: Vec<String>,
+}
+
+pub fn questions_prompt(topic: &str, asked: &[String], sources: &[SearchResult], count: usize) -> String {
+    let sources = sources
+        .iter()
+        .enumerate()
+        .map(|(i, s)| format!("{}. {}: {}", i + 1, s.title, truncate::chars(&s.snippet, SOURCE_CHARS)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let asked = if asked.is_empty() {
+        "none yet".to_string()
+    } else {
+        asked.iter().map(|q| format!("- {q}")).collect::<Vec<_>>().join("\n")
+    };
+
+    format!(
+        "You are planning research for an in-depth report on the topic \"{topic}\". Below are the sources found so far and \
+         the sub-questions already searched.\n\
+         Name up to {count} new sub-questions that the report needs answered and the sources don't yet cover well. Each one is sent \
+         to a web search engine on its own, so make it specific and self-contained, naming the subject rather than saying \"it\". \
+         Don't repeat or rephrase a question already searched.\n\
+         Reply with JSON only, in this shape: {{\"questions\": [\"...\"]}}. Reply with {{\"questions\": []}} if the sources cover \
+         the topic well enough.\n\n\
+         Already searched:\n{asked}\n\n\
+         Sources:\n{sources}"
+    )
+}
+
+/// The new questions in the model's reply: trimmed, at most `count`, none already `asked`.
+pub fn parse_questions(reply: &str, asked: &[String], count: usize) -> Result<Vec<String>, String> {
+    let next: NextQuestions = serde_json::from_str(prompt::json_object(reply))
+        .map_err(|err| format!("The model did not return valid sub-question JSON: {err}"))?;
+    let mut questions: Vec<String> = Vec::new();
+    for question in next.questions {
+        let question = truncate::chars(&question.replace('\n', " "), MAX_QUESTION_CHARS);
+        let seen = |q: &String| q.eq_ignore_ascii_case(&question);
+        if !question.is_empty() && !asked.iter().any(seen) && !questions.iter().any(seen) {
+            questions.push(question);
+        }
+    }
+    questions.truncate(count);
+    Ok(questions)
+}
+
+pub fn report_prompt(topic: &str, questions: &[String], format_instructions: &str, sources: &[SearchResult]) -> String {
+    let sources_json = serde_json::to_string(sources).unwrap_or_else(|_| "[]".to_string());
+    let questions = questions.iter().map(|q| format!("- {q}")).collect::<Vec<_>>().join("\n");
+
+    format!(
+        "Write an in-depth, long-form research report on the topic \"{topic}\".\n\
+         The search results at the bottom, in json format, were gathered over several rounds of research, searching the topic and \
+         then each of the sub-questions below. Organise the report into sections by theme, answer every sub-question the sources \
+         allow, and say where they disagree or leave a question open. Cite the sources you rely on as inline markdown links, and \
+         end with a \"Further reading\" section of the most useful links.\n\
+         {format_instructions}\n\
+         Sub-questions researched:\n{questions}\n\n\
+         Search results: {sources_json}"
+    )
+}
@@ -0,0 +1,180 @@
+//! Outline-first deep research jobs.
+//!
+//! `start_deep_research` drafts an outline, then each section is researched and written in its
+//! own `advance_job` invocation, which the agent triggers on itself. Between invocations the
+//! finished sections can be read with `get_partial_report`, so UIs can render progressively.
+
+use golem_rust::Schema;
+
+use crate::schema::SCHEMA_VERSION;
+use crate::ResearchOptions;
+
+/// Finished jobs kept in state; the assembled reports also live in the report history.
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// Used when the caller gave no sections and the model's outline was unusable.
+pub const DEFAULT_OUTLINE: &[&str] = &[
+    "Overview",
+    "Key developments",
+    "Challenges and open questions",
+    "Outlook",
+    "Further reading",
+];
+
+#[derive(Clone, Debug, PartialEq, Schema, serde::Serialize, serde::Deserialize)]
+pub enum JobStatus {
+    Researching,
+    Completed,
+    Failed(String),
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct JobSection {
+    pub heading: String,
+    /// Section body once written.
+    pub content: Option<String>,
+    pub completed_at: Option<u64>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ResearchJob {
+    /// See `schema`; version 0 is a record from before versioning.
+    #[serde(default)]
+    pub schema_version: u32,
+    pub id: String,
+    pub workspace: String,
+    pub topic: String,
+    pub status: JobStatus,
+    pub sections: Vec<JobSection>,
+    pub created_at: u64,
+    pub updated_at: u64,
+    /// History ID of the assembled report, once completed.
+    pub report_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct PartialReport {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub completed_sections: u32,
+    pub total_sections: u32,
+    /// Markdown of the sections finished so far, in outline order.
+    pub report: String,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Job {
+    pub info: ResearchJob,
+    pub options: ResearchOptions,
+    pub model: String,
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct JobBook {
+    jobs: Vec<Job>,
+    next_id: u64,
+}
+
+impl JobBook {
+    pub fn create(
+        &mut self,
+        workspace: &str,
+        topic: &str,
+        outline: Vec<String>,
+        options: ResearchOptions,
+        model: String,
+        now: u64,
+    ) -> ResearchJob {
+        self.next_id += 1;
+        let info = ResearchJob {
+            schema_version: SCHEMA_VERSION,
+            id: format!("job-{}", self.next_id),
+            workspace: workspace.to_string(),
+            topic: topic.to_string(),
+            status: JobStatus::Researching,
+            sections: outline
+                .into_iter()
+                .map(|heading| JobSection {
+                    heading,
+                    content: None,
+                    completed_at: None,
+                })
+                .collect(),
+            created_at: now,
+            updated_at: now,
+            report_id: None,
+        };
+        self.jobs.push(Job {
+            info: info.clone(),
+            options,
+            model,
+        });
+        self.enforce_limit();
+        info
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Job> {
+        self.jobs.iter().find(|j| j.info.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|j| j.info.id == id)
+    }
+
+    /// Jobs still researching.
+    pub fn in_progress(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|j| j.info.status == JobStatus::Researching)
+            .count()
+    }
+
+    pub fn partial(&self, id: &str) -> Option<PartialReport> {
+        let job = &self.get(id)?.info;
+        Some(PartialReport {
+            job_id: job.id.clone(),
+            status: job.status.clone(),
+            completed_sections: job.sections.iter().filter(|s| s.content.is_some()).count() as u32,
+            total_sections: job.sections.len() as u32,
+            report: job.render(),
+        })
+    }
+
+    fn enforce_limit(&mut self) {
+        let finished = self
+            .jobs
+            .iter()
+            .filter(|j| j.info.status != JobStatus::Researching)
+            .count();
+        let mut excess = finished.saturating_sub(MAX_FINISHED_JOBS);
+        self.jobs.retain(|j| {
+            if excess > 0 && j.info.status != JobStatus::Researching {
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+impl ResearchJob {
+    pub fn next_pending(&self) -> Option<usize> {
+        self.sections.iter().position(|s| s.content.is_none())
+    }
+
+    pub fn outline(&self) -> Vec<String> {
+        self.sections.iter().map(|s| s.heading.clone()).collect()
+    }
+
+    /// The finished sections as a markdown document.
+    pub fn render(&self) -> String {
+        let mut report = format!("# {}\n", self.topic);
+        for section in &self.sections {
+            if let Some(content) = &section.content {
+                report.push_str(&format!("\n## {}\n\n{}\n", section.heading, content.trim()));
+            }
+        }
+        report
+    }
+}
@@ -0,0 +1,27 @@
+//! Interactive vs background execution lanes.
+//!
+//! An agent runs one invocation at a time, so a `run_due_schedules` call that works through
+//! every due schedule would hold interactive `research*` calls in the queue behind it. The
+//! background lane therefore runs at most `BACKGROUND_LANE_CONCURRENCY` scheduled jobs per
+//! invocation (default 1) and re-arms itself `BACKGROUND_LANE_YIELD_SECONDS` later (default 5)
+//! for the rest, letting queued interactive requests run in between. Interactive calls are
+//! never deferred.
+
+use crate::config;
+
+#[derive(Clone, Copy, Debug)]
+pub struct LaneBudgets {
+    /// Scheduled jobs run per `run_due_schedules` invocation.
+    pub background_concurrency: usize,
+    /// Delay before the background lane resumes a backlog.
+    pub background_yield_seconds: u64,
+}
+
+impl LaneBudgets {
+    pub fn from_env() -> Self {
+        Self {
+            background_concurrency: config::env_parse::<u64>("BACKGROUND_LANE_CONCURRENCY").unwrap_or(1).max(1) as usize,
+            background_yield_seconds: config::env_parse::<u64>("BACKGROUND_LANE_YIELD_SECONDS").unwrap_or(5),
+        }
+    }
+}
@@ -2,234 +2,3791 @@ use golem_rust::golem_ai::golem::llm::llm;
 use golem_rust::golem_ai::golem::llm::llm::{Config, ContentPart, Message, Role};
 use golem_rust::golem_ai::golem::web_search::types;
 use golem_rust::golem_ai::golem::web_search::web_search;
+use golem_rust::golem_wasm::golem_rpc_0_2_x::types::Datetime;
 use golem_rust::{agent_definition, agent_implementation, description, Schema};
 
+mod academic;
+mod actions;
+mod ai_content;
+mod archive;
+mod budget;
+mod cache;
+mod charts;
+mod citations;
+mod clock;
+mod compress;
+mod config;
+mod connectors;
+mod content_fetcher;
+mod context;
+mod conversation;
+mod corpus;
+mod credentials;
+mod data_export;
+mod differential;
+mod embed;
+mod error;
+mod error_class;
+mod explain;
+mod expansion;
+mod export;
+mod fallback;
+mod fetch;
+mod findings;
+mod health;
+mod history;
+mod http_cache;
+mod http_util;
+mod import;
+mod interview;
+mod iterative;
+mod jobs;
+mod lanes;
+mod llm_cache;
+mod locale;
+mod multilingual;
+mod notify;
+mod ollama;
+mod options;
+mod outline;
+mod page_cache;
+mod payload;
+mod pdf;
+mod prompt;
+mod query;
+mod quick;
+mod quota;
+mod rank;
+mod redact;
+mod refusal;
+mod relevance;
+mod report;
+mod report_chunks;
+mod results;
+mod rules;
+mod safety;
+mod sanitize;
+mod schedule;
+mod schema;
+mod search_cache;
+mod search_http;
+mod sessions;
+mod shedding;
+mod site;
+mod snapshot;
+mod stream;
+mod telemetry;
+mod templates;
+mod truncate;
+mod usage;
+mod warmup;
+mod watch;
+mod wikipedia;
+mod workspace;
+
+pub use actions::{ActionItem, ActionPriority};
+pub use ai_content::AiContentFlag;
+pub use archive::ArchivedSnapshot;
+pub use budget::{BudgetExceeded, BudgetStatus, SpendLimits};
+pub use cache::{ClearedCaches, ReportFreshness};
+pub use charts::ChartFormat;
+pub use config::{ConfigIssue, ConfigProblem, ConfigReport};
+pub use connectors::ConnectorInfo;
+pub use corpus::{CorpusDocument, CorpusStats, DocumentFilter, IngestDocument, MetadataEntry, TagCount};
+pub use data_export::{DataAttachment, DataExport, DataKind};
+pub use explain::{CorpusHit, IssuedQuery, ProviderCount, RetrievalExplanation};
+pub use error::AgentError;
+pub use import::{SkippedTopic, TopicImport};
+pub use interview::InterviewQuestion;
+pub use jobs::{JobSection, JobStatus, PartialReport, ResearchJob};
+pub use export::{
+    NarrationScript, NewsletterSection, ReadMoreLink, ShowNote, Slide, SlideDeck, SocialPlatform, SocialPost,
+    SocialThread,
+};
+pub use findings::{
+    CitationGraph, EvidenceEdge, EvidenceRelation, FindingNode, FindingsSubject, KeyFinding, SourceNode,
+};
+pub use health::{DependencyState, DependencyStatus, Heartbeat};
+pub use history::{ReportMetadata, ReportRecord, ReportSummary};
+pub use ollama::ModelUnavailable;
+pub use notify::{Delivery, EmailTarget, NotificationPrefs, QuietHours, SlackTarget, WebhookTarget};
+pub use options::{RequestPriority, ResearchOptions, TimeRange};
+pub use outline::{ExtractMode, ExtractedPage, FigureInfo, Heading, PageLink, PageOutline, TableInfo};
+pub use quick::QuickSummary;
+pub use refusal::Refusal;
+pub use relevance::RelevanceScore;
+pub use report::{ReportField, ReportSource, ResearchReport};
+pub use report_chunks::{ChunkInfo, ReportChunk, ReportManifest};
+pub use safety::FlaggedTopic;
+pub use sessions::{SessionId, SessionReport, SessionSource, SessionTurn};
+pub use shedding::Overloaded;
+pub use stream::{ResearchStream, StreamChunk, StreamStatus, StreamUpdate};
+pub use schedule::{ResearchRecipe, ScheduleInfo, ScheduleRun, TemplateRecipe, TopicRecipe};
+pub use templates::{ResearchTemplate, TemplateParam};
+pub use usage::UsageReport;
+pub use warmup::LlmWarmup;
+pub use watch::{PageChange, WatchInfo};
+
+use clock::now_secs;
+use error_class::{ErrorClass, ProviderError};
+
+/// Corpus candidates listed by `explain_retrieval`, beyond the ones actually used.
+const MAX_EXPLAINED_CORPUS_HITS: usize = 20;
+/// Repeats of an LLM call that failed with a transient error (see `error_class`).
+const LLM_TRANSIENT_RETRIES: u32 = 1;
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum WebSearchProvider {
+    Bing,
     Brave,
+    DuckDuckGo,
+    Exa,
     Google,
+    Kagi,
+    SearxNG,
     Serper,
     Tavily,
 }
 
 impl WebSearchProvider {
-    fn from_env() -> Self {
-        match std::env::var("WEB_SEARCH_PROVIDER")
-            .unwrap_or_else(|_| "brave".to_string())
-            .trim()
-            .to_ascii_lowercase()
-            .as_str()
-        {
-            "brave" => Self::Brave,
-            "google" => Self::Google,
-            "serper" => Self::Serper,
-            "tavily" => Self::Tavily,
-            other => panic!(
-                "Unsupported WEB_SEARCH_PROVIDER={other}. Supported: brave|google|serper|tavily"
-            ),
+    /// The provider linked through golem-ai. Bing, DuckDuckGo, Exa, Kagi and SearXNG have no golem-ai component;
+    /// choosing one calls it over HTTP and leaves the linked web search component unused.
+    /// An unsupported name is a configuration error; DuckDuckGo stands in for it.
+    fn from_env(config: &mut config::ConfigReport) -> Self {
+        let name = std::env::var("WEB_SEARCH_PROVIDER").unwrap_or_else(|_| "brave".to_string());
+        Self::parse(&name).unwrap_or_else(|err| {
+            config.error(
+                "WEB_SEARCH_PROVIDER",
+                config::ConfigProblem::Invalid,
+                format!("Unsupported WEB_SEARCH_PROVIDER: {err}."),
+            );
+            Self::DuckDuckGo
+        })
+    }
+
+    /// `WEB_SEARCH_PROVIDERS`: providers tried in order when one fails or finds nothing.
+    /// Defaults to the linked provider alone. Entries that can't be used are configuration
+    /// errors and left out.
+    fn chain_from_env(linked: Self, config: &mut config::ConfigReport) -> Vec<Self> {
+        let raw = std::env::var("WEB_SEARCH_PROVIDERS").unwrap_or_default();
+        let mut chain: Vec<Self> = Vec::new();
+        for name in raw.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let provider = match Self::parse(name) {
+                Ok(provider) => provider,
+                Err(err) => {
+                    config.error(
+                        "WEB_SEARCH_PROVIDERS",
+                        config::ConfigProblem::Invalid,
+                        format!("Unsupported WEB_SEARCH_PROVIDERS entry: {err}."),
+                    );
+                    continue;
+                }
+            };
+            if provider != linked && !provider.has_http_client() {
+                config.error(
+                    "WEB_SEARCH_PROVIDERS",
+                    config::ConfigProblem::Invalid,
+                    format!(
+                        "WEB_SEARCH_PROVIDERS: {} can only be used as the golem-ai linked WEB_SEARCH_PROVIDER.",
+                        provider.display_name()
+                    ),
+                );
+                continue;
+            }
+            if !chain.contains(&provider) {
+                chain.push(provider);
+            }
+        }
+        if chain.is_empty() {
+            chain.push(linked);
+        }
+        chain
+    }
+
+    /// `SEARCH_PROVIDER_ALLOWLIST`: providers outside the chain that a request may pick with
+    /// `ResearchOptions::search_provider`. Entries the agent can't call, or whose keys are
+    /// missing, are left out with a warning.
+    fn allowlist_from_env(linked: Self, chain: &[Self], config: &mut config::ConfigReport) -> Vec<Self> {
+        let raw = std::env::var("SEARCH_PROVIDER_ALLOWLIST").unwrap_or_default();
+        let mut allowlist: Vec<Self> = Vec::new();
+        for name in raw.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let provider = match Self::parse(name) {
+                Ok(provider) => provider,
+                Err(err) => {
+                    config.warn(
+                        "SEARCH_PROVIDER_ALLOWLIST",
+                        config::ConfigProblem::Invalid,
+                        format!("Unsupported SEARCH_PROVIDER_ALLOWLIST entry: {err}; ignoring it."),
+                    );
+                    continue;
+                }
+            };
+            if provider != linked && !provider.has_http_client() {
+                config.warn(
+                    "SEARCH_PROVIDER_ALLOWLIST",
+                    config::ConfigProblem::Invalid,
+                    format!(
+                        "SEARCH_PROVIDER_ALLOWLIST: {} can only be used as the golem-ai linked WEB_SEARCH_PROVIDER; ignoring it.",
+                        provider.display_name()
+                    ),
+                );
+                continue;
+            }
+            if let Some(key) = provider.missing_env_var() {
+                config.warn(
+                    key,
+                    config::ConfigProblem::Missing,
+                    format!(
+                        "{key} env var not configured (required for {} web search); leaving it out of SEARCH_PROVIDER_ALLOWLIST.",
+                        provider.display_name()
+                    ),
+                );
+                continue;
+            }
+            if !chain.contains(&provider) && !allowlist.contains(&provider) {
+                allowlist.push(provider);
+            }
+        }
+        allowlist
+    }
+
+    fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "bing" => Ok(Self::Bing),
+            "brave" => Ok(Self::Brave),
+            "duckduckgo" => Ok(Self::DuckDuckGo),
+            "exa" => Ok(Self::Exa),
+            "google" => Ok(Self::Google),
+            "kagi" => Ok(Self::Kagi),
+            "searxng" => Ok(Self::SearxNG),
+            "serper" => Ok(Self::Serper),
+            "tavily" => Ok(Self::Tavily),
+            other => Err(format!("{other:?}. Supported: bing|brave|duckduckgo|exa|google|kagi|searxng|serper|tavily")),
         }
     }
 
+    /// Whether the agent can call the provider directly, without its golem-ai component.
+    fn has_http_client(&self) -> bool {
+        matches!(
+            self,
+            Self::Bing | Self::Brave | Self::DuckDuckGo | Self::Exa | Self::Kagi | Self::SearxNG | Self::Serper
+        )
+    }
+
+    /// Whether golem-ai has a web search component for the provider.
+    fn has_golem_ai_component(&self) -> bool {
+        !matches!(self, Self::Bing | Self::DuckDuckGo | Self::Exa | Self::Kagi | Self::SearxNG)
+    }
+
     fn required_env_vars(&self) -> &'static [&'static str] {
         match self {
+            Self::Bing => &["BING_API_KEY"],
             Self::Brave => &["BRAVE_API_KEY"],
+            // Scrapes the public HTML page; no API key.
+            Self::DuckDuckGo => &[],
+            Self::Exa => &["EXA_API_KEY"],
             Self::Google => &["GOOGLE_API_KEY", "GOOGLE_SEARCH_ENGINE_ID"],
+            Self::Kagi => &["KAGI_API_KEY"],
+            // Self-hosted; no API key.
+            Self::SearxNG => &["SEARXNG_BASE_URL"],
             Self::Serper => &["SERPER_API_KEY"],
             Self::Tavily => &["TAVILY_API_KEY"],
         }
     }
 
+    /// The first of `required_env_vars` that isn't configured.
+    fn missing_env_var(&self) -> Option<&'static str> {
+        self.required_env_vars()
+            .iter()
+            .copied()
+            .find(|key| std::env::var(key).map_or(true, |value| config::is_placeholder(&value)))
+    }
+
+    /// Whether the provider honours `site:`, `OR` and `-term` operators in the query string.
+    /// SearXNG passes the query on to its engines, most of which do.
+    /// See the `query` module for how operators are mapped when it doesn't.
+    fn supports_query_operators(&self) -> bool {
+        match self {
+            Self::Bing
+            | Self::Brave
+            | Self::DuckDuckGo
+            | Self::Google
+            | Self::Kagi
+            | Self::SearxNG
+            | Self::Serper => true,
+            Self::Exa | Self::Tavily => false,
+        }
+    }
+
     fn display_name(&self) -> &'static str {
         match self {
+            Self::Bing => "Bing",
             Self::Brave => "Brave",
+            Self::DuckDuckGo => "DuckDuckGo",
+            Self::Exa => "Exa",
             Self::Google => "Google",
+            Self::Kagi => "Kagi",
+            Self::SearxNG => "SearXNG",
             Self::Serper => "Serper",
             Self::Tavily => "Tavily",
         }
     }
 }
 
-#[derive(Clone, Schema, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
 pub struct SearchResult {
-    url: String,
-    title: String,
-    snippet: String,
+    pub url: String,
+    pub title: String,
+    /// Snippet, or page text in full-text mode.
+    pub snippet: String,
 }
 
 #[agent_definition]
 pub trait ResearchAgent {
     fn new() -> Self;
 
+    #[description("Check that the LLM backend is ready to serve research (for Ollama: server reachable and model pulled)")]
+    fn self_test(&mut self) -> Result<String, AgentError>;
+
+    #[description("List env variables that are missing or invalid: errors block research, warnings are worked around")]
+    fn validate_config(&self) -> ConfigReport;
+
+    #[description("Liveness for orchestrators: uptime, in-flight jobs and streams, queue depth, and the state and last success of each search provider and LLM model called")]
+    fn heartbeat(&self) -> Heartbeat;
+
+    #[description("Load an LLM model ahead of traffic by sending it a tiny completion; defaults to the configured model")]
+    fn warmup_llm(&mut self, model: Option<String>) -> Result<LlmWarmup, AgentError>;
+
     #[description("Research and summarize a topic")]
-    fn research(&self, topic: String) -> String;
+    fn research(&mut self, topic: String) -> String;
+
+    #[description("Research a topic with per-call options (results, language, time range, style, ...) and return the report with its summary, key findings and cited sources as structured data")]
+    fn research_with_options(&mut self, topic: String, options: ResearchOptions) -> Result<ResearchReport, AgentError>;
+
+    #[description("Answer a follow-up question about the last research or research_with_options run from its sources and answer, without searching again")]
+    fn ask_followup(&mut self, question: String) -> Result<String, AgentError>;
+
+    #[description("Start a research session for a chat UI: research the topic and return the session handle for continue_session")]
+    fn start_session(&mut self, topic: String) -> Result<SessionId, AgentError>;
+
+    #[description("Continue a research session: search for the instruction, add what is new to the session's sources, and answer it with the conversation so far")]
+    fn continue_session(&mut self, session_id: SessionId, instruction: String) -> Result<SessionTurn, AgentError>;
+
+    #[description("A research session's report so far: the overview and each turn's answer, with the accumulated sources")]
+    fn get_session_report(&self, session_id: SessionId) -> Option<SessionReport>;
+
+    #[description("Research current events: news results from the last days_back days (Brave and Serper news search, a time range elsewhere), written as a dated news report")]
+    fn research_news(&mut self, topic: String, days_back: u32) -> Result<ResearchReport, AgentError>;
+
+    #[description("Re-research a cached report that went stale and replace it in the report cache; invoked automatically")]
+    fn refresh_cached_report(&mut self, key: String) -> Result<ResearchReport, AgentError>;
+
+    #[description("Empty the search result, LLM response, report, page and HTTP response caches, returning how many entries each had")]
+    fn clear_cache(&mut self) -> ClearedCaches;
+
+    #[description("Search only: the web, connector and corpus results research would draw on, with exclusions applied; no LLM call except translating a non-English topic")]
+    fn search(&mut self, topic: String, options: ResearchOptions) -> Result<Vec<SearchResult>, AgentError>;
+
+    #[description("Research what one site says about a topic: picks the best-matching pages from its sitemap, reads and synthesizes them")]
+    fn research_site(&mut self, domain: String, topic: String, options: ResearchOptions) -> Result<String, AgentError>;
+
+    #[description("Review the academic literature on a topic: papers from arXiv and Semantic Scholar, merged with their abstracts and summarized as a literature review citing DOIs")]
+    fn research_academic(&mut self, topic: String) -> Result<String, AgentError>;
+
+    #[description("Run a named research template in a workspace, filling its topic placeholders from params")]
+    fn research_with_template(
+        &mut self,
+        workspace: String,
+        name: String,
+        params: Vec<TemplateParam>,
+    ) -> Result<String, AgentError>;
+
+    #[description("Start an outline-first deep research job; sections are researched one at a time in the background")]
+    fn start_deep_research(&mut self, topic: String, options: ResearchOptions) -> Result<ResearchJob, AgentError>;
+
+    #[description("Research a topic in rounds: the model names the sub-questions the sources leave open, each is searched, and a long-form report is written from everything found; runs in one invocation")]
+    fn deep_research(&mut self, topic: String) -> Result<ResearchReport, AgentError>;
+
+    #[description("Research and write the next pending section of a deep research job; invoked automatically")]
+    fn advance_job(&mut self, job_id: String) -> Result<ResearchJob, AgentError>;
+
+    #[description("Get a deep research job's status and outline")]
+    fn get_job(&self, job_id: String) -> Option<ResearchJob>;
+
+    #[description("Get the sections of a deep research job finished so far, while later ones are still being researched")]
+    fn get_partial_report(&self, job_id: String) -> Option<PartialReport>;
+
+    #[description("Size, digest and chunk list of a deep research job's report, for fetching a large report with get_report_chunk")]
+    fn get_report_manifest(&self, job_id: String) -> Option<ReportManifest>;
+
+    #[description("One chunk of a deep research job's report, by index from 0; the chunks concatenated in order are the report")]
+    fn get_report_chunk(&self, job_id: String, index: u32) -> Result<ReportChunk, AgentError>;
+
+    #[description("Start researching a topic with the report streamed from the LLM; returns a stream-N at once, poll get_stream_chunks for the text as it arrives")]
+    fn research_stream(&mut self, topic: String, options: ResearchOptions) -> Result<ResearchStream, AgentError>;
+
+    #[description("Read the next batch of a research stream's LLM events; invoked automatically")]
+    fn advance_stream(&mut self, stream_id: String) -> Result<ResearchStream, AgentError>;
+
+    #[description("Text chunks of a research stream from from_index on, with its status and the index to poll from next")]
+    fn get_stream_chunks(&self, stream_id: String, from_index: u32) -> Option<StreamUpdate>;
+
+    #[description("List stored reports in a workspace, newest first")]
+    fn list_reports(&self, workspace: String) -> Vec<ReportSummary>;
+
+    #[description("List topics in a workspace that the safety check let run but flagged for review, newest first")]
+    fn list_flagged_topics(&self, workspace: String) -> Vec<FlaggedTopic>;
+
+    #[description("Get a stored report by ID from a workspace")]
+    fn get_report(&self, workspace: String, id: String) -> Option<ReportRecord>;
+
+    #[description("A workspace's stored reports as versioned JSON, oldest first, for backups and moving history between components; gzip+base64 with a prefix when over PAYLOAD_COMPRESS_THRESHOLD_BYTES")]
+    fn export_history(&self, workspace: String) -> Result<String, String>;
+
+    #[description("Add reports from export_history JSON (any schema version) to a workspace's history; returns the number imported")]
+    fn import_history(&mut self, workspace: String, data: String) -> Result<u32, String>;
+
+    #[description("Combine several stored reports in a workspace into one new report with a deduplicated source list")]
+    fn merge_reports(
+        &mut self,
+        workspace: String,
+        ids: Vec<String>,
+        instructions: String,
+    ) -> Result<ReportRecord, AgentError>;
+
+    #[description("Findings of a stored report as a graph, with edges to the cited sources that support or contradict each one")]
+    fn citation_graph(&mut self, workspace: String, report_id: String) -> Result<CitationGraph, AgentError>;
+
+    #[description("5-10 atomic, individually cited key findings from a deep research job, a stored report, or a fresh research run on a topic")]
+    fn key_findings(&mut self, workspace: String, subject: FindingsSubject) -> Result<Vec<KeyFinding>, AgentError>;
+
+    #[description("Recommended next steps for a persona or goal (e.g. 'a product manager evaluating X'), based on a job, stored report or fresh topic research")]
+    fn action_items(
+        &mut self,
+        workspace: String,
+        subject: FindingsSubject,
+        persona: String,
+    ) -> Result<Vec<ActionItem>, AgentError>;
+
+    #[description("Interview questions on a topic for an audience, each grounded in the search result that motivated it")]
+    fn interview_questions(
+        &mut self,
+        workspace: String,
+        topic: String,
+        audience: String,
+    ) -> Result<Vec<InterviewQuestion>, AgentError>;
+
+    #[description("Convert a deep research job's report into a slide outline with speaker notes, plus Marp/reveal.js markdown")]
+    fn export_slides(&mut self, job_id: String) -> Result<SlideDeck, AgentError>;
+
+    #[description("Rewrite a deep research job's report as a spoken-word script of about the given minutes, with citations moved to show notes")]
+    fn export_script(&mut self, job_id: String, minutes: u32) -> Result<NarrationScript, AgentError>;
+
+    #[description("Summarize a deep research job's report as a numbered social-media thread within the platform's length limit, ending with a link list")]
+    fn export_thread(&mut self, job_id: String, platform: SocialPlatform) -> Result<SocialThread, AgentError>;
+
+    #[description("Format a job, stored report (e.g. a monitor run) or topic as a newsletter HTML section from a template with heading, teaser, bullets and read-more links")]
+    fn export_newsletter_section(
+        &mut self,
+        workspace: String,
+        subject: FindingsSubject,
+        template: Option<String>,
+    ) -> Result<NewsletterSection, AgentError>;
+
+    #[description("Export a deep research job's tables, key findings or cited sources as CSV and JSONL attachments for spreadsheets and data pipelines; large attachments come gzip+base64 encoded")]
+    fn export_data(&mut self, job_id: String, kind: DataKind) -> Result<DataExport, AgentError>;
+
+    #[description("List the configured search connectors that research can query alongside web search")]
+    fn list_search_connectors(&self) -> Vec<ConnectorInfo>;
+
+    #[description("Fetch a page and return its readable text, its structural outline (headings, links, tables, figures), or both")]
+    fn extract_url(&self, url: String, mode: ExtractMode) -> Result<ExtractedPage, AgentError>;
+
+    #[description("Fetch one page and summarize it with a single fast LLM call (no web search); for save-and-summarize clients")]
+    fn quick_summarize(&mut self, url: String) -> Result<QuickSummary, AgentError>;
+
+    #[description("Add a document (text plus metadata) to a workspace's private corpus, or replace it by external_id; research in the workspace retrieves from it")]
+    fn ingest_document(&mut self, workspace: String, doc: IngestDocument) -> Result<CorpusDocument, String>;
+
+    #[description("List a workspace's corpus documents, newest first, filtered by tag, metadata, title or update time")]
+    fn list_documents(&self, workspace: String, filter: DocumentFilter) -> Result<Vec<CorpusDocument>, String>;
+
+    #[description("Add and remove tags on a corpus document")]
+    fn tag_document(
+        &mut self,
+        workspace: String,
+        id: String,
+        add: Vec<String>,
+        remove: Vec<String>,
+    ) -> Result<CorpusDocument, String>;
+
+    #[description("Rebuild the search index of one corpus document, or of the whole workspace when id is empty; returns the number reindexed")]
+    fn reindex_documents(&mut self, workspace: String, id: Option<String>) -> Result<u32, String>;
+
+    #[description("Delete a corpus document; returns whether it existed")]
+    fn delete_document(&mut self, workspace: String, id: String) -> bool;
+
+    #[description("Document count, size, tags and ingest times of a workspace's corpus")]
+    fn corpus_stats(&self, workspace: String) -> Result<CorpusStats, String>;
+
+    #[description("Show what a research run would retrieve for a topic without calling the LLM: queries issued, per-provider result counts, corpus scores and the context selected for the prompt")]
+    fn explain_retrieval(&mut self, topic: String, options: ResearchOptions) -> Result<RetrievalExplanation, AgentError>;
+
+    #[description("Research count, tokens, provider calls and estimated cost for a workspace in a YYYY-MM period (empty = current month)")]
+    fn usage_report(&self, workspace: String, period: String) -> Result<UsageReport, String>;
+
+    #[description("Set a workspace's monthly soft (warn) and hard (block) spend limits in USD; unset limits fall back to the defaults")]
+    fn set_spend_limits(&mut self, workspace: String, limits: SpendLimits) -> Result<BudgetStatus, String>;
+
+    #[description("Current month's spend and limits for a workspace")]
+    fn budget_status(&self, workspace: String) -> Result<BudgetStatus, String>;
+
+    #[description("Lift (or restore) a workspace's hard spend limit for the rest of the current month")]
+    fn override_spend_limit(&mut self, workspace: String, active: bool) -> Result<BudgetStatus, String>;
+
+    #[description("Create or replace a named research template")]
+    fn define_template(&mut self, template: ResearchTemplate) -> Result<ResearchTemplate, String>;
+
+    #[description("List the research templates known to this agent")]
+    fn list_templates(&self) -> Vec<ResearchTemplate>;
+
+    #[description("Delete a research template by name; returns whether it existed")]
+    fn delete_template(&mut self, name: String) -> bool;
+
+    #[description("Schedule a topic or template to run in a workspace on a cron expression (5 fields, UTC)")]
+    fn schedule(&mut self, workspace: String, recipe: ResearchRecipe, cron: String) -> Result<ScheduleInfo, String>;
+
+    #[description("Create topic schedules in bulk from an OPML feed list or JSON bookmark export; cron defaults to weekly")]
+    fn import_topics(
+        &mut self,
+        workspace: String,
+        document: String,
+        cron: Option<String>,
+    ) -> Result<TopicImport, String>;
+
+    #[description("List a workspace's schedules with their next run times")]
+    fn list_schedules(&self, workspace: String) -> Vec<ScheduleInfo>;
+
+    #[description("Set how a schedule's reports are delivered (webhook, email, Slack, or store-only) and its quiet hours")]
+    fn set_schedule_notifications(
+        &mut self,
+        workspace: String,
+        id: String,
+        prefs: NotificationPrefs,
+    ) -> Result<ScheduleInfo, String>;
+
+    #[description("Pause a schedule")]
+    fn pause_schedule(&mut self, workspace: String, id: String) -> Result<ScheduleInfo, String>;
+
+    #[description("Resume a paused schedule from the next matching time")]
+    fn resume_schedule(&mut self, workspace: String, id: String) -> Result<ScheduleInfo, String>;
+
+    #[description("Delete a schedule; returns whether it existed")]
+    fn delete_schedule(&mut self, workspace: String, id: String) -> bool;
+
+    #[description("Watch a web page: re-fetch it every interval_minutes and summarize what changed since the last check")]
+    fn watch_url(&mut self, workspace: String, url: String, interval_minutes: u32) -> Result<WatchInfo, String>;
+
+    #[description("List a workspace's watched pages with their recent change summaries")]
+    fn list_watches(&self, workspace: String) -> Vec<WatchInfo>;
+
+    #[description("Stop watching a page; returns whether the watch existed")]
+    fn unwatch_url(&mut self, workspace: String, id: String) -> bool;
+
+    #[description("Run every schedule that is due now; invoked automatically at each next-run time")]
+    fn run_due_schedules(&mut self) -> Vec<ScheduleRun>;
 }
 
 struct ResearchAgentImpl {
     model: String,
+    /// The provider linked through golem-ai.
     web_search_provider: WebSearchProvider,
+    /// Providers tried in order; the linked one goes through golem-ai, the others over HTTP.
+    web_search_chain: Vec<WebSearchProvider>,
+    connectors: Vec<connectors::ExternalSearchConnector>,
+    templates: templates::TemplateRegistry,
+    schedules: schedule::ScheduleBook,
+    outbox: notify::Outbox,
+    history: history::ReportHistory,
+    jobs: jobs::JobBook,
+    streams: stream::StreamBook,
+    watches: watch::WatchBook,
+    corpus: corpus::Corpus,
+    /// Embeds corpus chunks and queries; `None` without `EMBEDDING_MODEL` (keyword search only).
+    embedder: Option<embed::Embedder>,
+    usage: usage::UsageLedger,
+    budgets: budget::BudgetBook,
+    lanes: lanes::LaneBudgets,
+    prompt_budget: prompt::PromptBudget,
+    content_fetch: content_fetcher::FetchSettings,
+    page_cache: page_cache::PageCache,
+    page_cache_policy: page_cache::CachePolicy,
+    http_cache: http_cache::ResponseCache,
+    search_cache: search_cache::SearchCache,
+    search_cache_policy: search_cache::SearchCachePolicy,
+    iteration_budget: iterative::IterationBudget,
+    llm_cache: llm_cache::LlmCache,
+    llm_cache_policy: llm_cache::LlmCachePolicy,
+    relevance: relevance::RelevancePolicy,
+    exemplars: Vec<prompt::Exemplar>,
+    /// Models that have passed the Ollama readiness check.
+    ready_models: Vec<String>,
+    /// Models warmed since the worker started; not snapshotted, so a restored worker warms again.
+    warm_models: Vec<String>,
+    /// Context window in tokens per model, resolved on first use.
+    context_windows: Vec<(String, u32)>,
+    /// Models callers may select with `ResearchOptions::model` (`LLM_MODEL_ALLOWLIST`).
+    model_allowlist: Vec<String>,
+    /// Providers outside the chain that callers may select with `ResearchOptions::search_provider`
+    /// (`SEARCH_PROVIDER_ALLOWLIST`).
+    search_provider_allowlist: Vec<WebSearchProvider>,
+    fallback: fallback::FallbackPolicy,
+    footer: report::ReportFooter,
+    /// Kagi summarizer engine for pre-summarizing web results; `None` when off.
+    kagi_summarizer: Option<String>,
+    telemetry: telemetry::Telemetry,
+    health: health::DependencyHealth,
+    quotas: quota::QuotaBook,
+    load_limits: shedding::LoadLimits,
+    report_cache: cache::ReportCache,
+    cache_policy: cache::CachePolicy,
+    /// Problems found in the env when the worker started; research is refused while it has errors.
+    config: config::ConfigReport,
+    topic_screen: safety::TopicScreen,
+    moderation: safety::ModerationQueue,
+    /// The last research run, for `ask_followup`.
+    conversation: Option<conversation::Conversation>,
+    sessions: sessions::SessionBook,
+    /// When this instance was created or restored, for `heartbeat`.
+    started_at: u64,
 }
 
 #[agent_implementation]
 impl ResearchAgent for ResearchAgentImpl {
     fn new() -> Self {
+        // Configuration problems are reported by `validate_config` and block research rather
+        // than trapping here, which callers would only see as an opaque failure.
+        let mut config = config::ConfigReport::default();
         // Prefer a component-specific variable to avoid collisions with provider/runtime env.
-        let model = std::env::var("AGGO_LLM_MODEL")
-            .or_else(|_| std::env::var("LLM_MODEL"))
-            .unwrap_or_else(|_| "gpt-4".to_string());
-        if model.trim().is_empty() || model.trim() == "changeme" {
-            panic!(
-                "LLM_MODEL env var not configured. Check the component env in golem.yaml."
+        let model_var = if std::env::var("AGGO_LLM_MODEL").is_ok() { "AGGO_LLM_MODEL" } else { "LLM_MODEL" };
+        let model = std::env::var(model_var).unwrap_or_else(|_| "gpt-4".to_string());
+        if config::is_placeholder(&model) {
+            config.error(
+                model_var,
+                config::ConfigProblem::Missing,
+                format!("{model_var} env var not configured."),
             );
         }
 
-        let web_search_provider = WebSearchProvider::from_env();
+        let web_search_provider = WebSearchProvider::from_env(&mut config);
+        let mut web_search_chain = WebSearchProvider::chain_from_env(web_search_provider, &mut config);
 
-        for key in web_search_provider.required_env_vars() {
-            match std::env::var(key) {
-                Ok(value) if !value.trim().is_empty() && value.trim() != "changeme" => {}
-                _ => panic!(
-                    "{key} env var not configured (required for {} web search). Check the component env in golem.yaml.",
-                    web_search_provider.display_name()
-                ),
+        // Providers without valid keys are dropped; with none left, fall back to keyless
+        // DuckDuckGo scraping so the agent still runs in local development.
+        web_search_chain.retain(|provider| {
+            let missing = provider.missing_env_var();
+            if let Some(key) = missing {
+                config.warn(
+                    key,
+                    config::ConfigProblem::Missing,
+                    format!(
+                        "{key} env var not configured (required for {} web search); skipping it.",
+                        provider.display_name()
+                    ),
+                );
             }
+            missing.is_none()
+        });
+        if web_search_chain.is_empty() {
+            config.warn(
+                "WEB_SEARCH_PROVIDERS",
+                config::ConfigProblem::Missing,
+                "No configured web search provider has valid keys; falling back to DuckDuckGo HTML scraping, which is meant for local development only.",
+            );
+            web_search_chain.push(WebSearchProvider::DuckDuckGo);
+        }
+        let search_provider_allowlist =
+            WebSearchProvider::allowlist_from_env(web_search_provider, &web_search_chain, &mut config);
+        let kagi_summarizer = search_http::kagi_summarizer_engine();
+        if kagi_summarizer.is_some() && std::env::var("KAGI_API_KEY").map_or(true, |key| key.trim().is_empty()) {
+            config.error(
+                "KAGI_API_KEY",
+                config::ConfigProblem::Missing,
+                "KAGI_API_KEY env var not configured (required by KAGI_SUMMARIZER).",
+            );
         }
 
         Self {
             model,
             web_search_provider,
+            web_search_chain,
+            connectors: connectors::from_env(),
+            templates: templates::TemplateRegistry::from_env(),
+            schedules: schedule::ScheduleBook::default(),
+            outbox: notify::Outbox::default(),
+            history: history::ReportHistory::default(),
+            jobs: jobs::JobBook::default(),
+            streams: stream::StreamBook::default(),
+            watches: watch::WatchBook::default(),
+            corpus: corpus::Corpus::default(),
+            embedder: embed::Embedder::from_env(),
+            usage: usage::UsageLedger::default(),
+            budgets: budget::BudgetBook::default(),
+            lanes: lanes::LaneBudgets::from_env(),
+            prompt_budget: prompt::PromptBudget::from_env(),
+            content_fetch: content_fetcher::FetchSettings::from_env(),
+            page_cache: page_cache::PageCache::default(),
+            page_cache_policy: page_cache::CachePolicy::from_env(),
+            http_cache: http_cache::ResponseCache::default(),
+            search_cache: search_cache::SearchCache::default(),
+            search_cache_policy: search_cache::SearchCachePolicy::from_env(),
+            iteration_budget: iterative::IterationBudget::from_env(),
+            llm_cache: llm_cache::LlmCache::default(),
+            llm_cache_policy: llm_cache::LlmCachePolicy::from_env(),
+            relevance: relevance::RelevancePolicy::from_env(),
+            exemplars: prompt::exemplars_from_env(),
+            ready_models: Vec::new(),
+            warm_models: Vec::new(),
+            context_windows: Vec::new(),
+            model_allowlist: model_allowlist_from_env(),
+            search_provider_allowlist,
+            fallback: fallback::FallbackPolicy::from_env(),
+            footer: report::ReportFooter::from_env(),
+            kagi_summarizer,
+            telemetry: telemetry::Telemetry::from_env(),
+            health: health::DependencyHealth::default(),
+            quotas: quota::QuotaBook::default(),
+            load_limits: shedding::LoadLimits::from_env(),
+            report_cache: cache::ReportCache::default(),
+            cache_policy: cache::CachePolicy::from_env(),
+            config,
+            topic_screen: safety::TopicScreen::from_env(),
+            moderation: safety::ModerationQueue::default(),
+            conversation: None,
+            sessions: sessions::SessionBook::default(),
+            started_at: now_secs(),
         }
     }
 
-    fn research(&self, topic: String) -> String {
-        let search_results = match search_web_for_topic(self.web_search_provider, &topic) {
-            Ok(sr) => sr,
-            Err(err) => {
-                // If the web search provider fails (rate limit, invalid key, backend error),
-                // proceed with an empty/annotated result set so the LLM can still respond.
-                // This avoids returning a 400/500 to HTTP callers when third-party search fails.
-                vec![SearchResult {
-                    url: "".to_string(),
-                    title: "search-failed".to_string(),
-                    snippet: format!("Web search failed: {}", err),
-                }]
-            }
-        };
-
-        let search_results_json = serde_json::to_string(&search_results).unwrap_or_else(|_| "[]".to_string());
-
-        let prompt = format!(
-            "I'm writing a report on the topic \"{}\",\n\
-             Your job is to be a research-assistant and provide me an initial overview on the topic so I can dive into it in more detail.\n\
-             At the bottom are top search results from a search engine in json format. Use your own knowledge and the snippets from the search results to create the overview.\n\
-             Also include the best links to look into to learn more about the topic. Prioritize objective and reliable sources.\n\
-             \n\
-             Search results: {}",
-            topic, search_results_json
-        );
+    fn validate_config(&self) -> ConfigReport {
+        self.config.clone()
+    }
 
-        let config = Config {
-            model: self.model.clone(),
-            temperature: None,
-            max_tokens: None,
-            stop_sequences: None,
-            tools: None,
-            tool_choice: None,
-            provider_options: None,
-        };
+    fn self_test(&mut self) -> Result<String, AgentError> {
+        if !ollama::is_configured() {
+            return Ok(format!(
+                "LLM model {:?} configured; no readiness checks for this provider",
+                self.model
+            ));
+        }
 
-        let events = vec![llm::Event::Message(Message {
-            role: Role::Assistant,
-            name: Some("research-agent".to_string()),
-            content: vec![ContentPart::Text(prompt)],
-        })];
+        let model = self.model.clone();
+        self.ready_models.retain(|m| *m != model);
+        self.context_windows.retain(|(m, _)| *m != model);
+        self.ensure_llm_ready(&model)?;
+        let window = self.context_window(&model);
+        Ok(format!(
+            "Ollama at {} is serving model {:?} with a {window}-token context window",
+            ollama::base_url(),
+            self.model
+        ))
+    }
 
-        let response = match llm::send(&events, &config) {
-            Ok(r) => r,
-            Err(e) => {
-                let env_aggo_llm_model = std::env::var("AGGO_LLM_MODEL").ok();
-                let env_llm_model = std::env::var("LLM_MODEL").ok();
-                let ollama_base_url = std::env::var("GOLEM_OLLAMA_BASE_URL").ok();
-                return format!(
-                    "LLM call failed (model: {:?}, env AGGO_LLM_MODEL: {:?}, env LLM_MODEL: {:?}, GOLEM_OLLAMA_BASE_URL: {:?}). Display: {}. Debug: {:?}\n\nHint: for Ollama, ensure the model exists locally (e.g. `ollama pull {}`) and that GOLEM_OLLAMA_BASE_URL points to the right server.",
-                    self.model,
-                    env_aggo_llm_model,
-                    env_llm_model,
-                    ollama_base_url,
-                    e,
-                    e,
-                    self.model
-                );
+    fn heartbeat(&self) -> Heartbeat {
+        let now = now_secs();
+        let (due_schedules, due_watches, pending_notifications) = self.queued(now);
+        Heartbeat {
+            now,
+            started_at: self.started_at,
+            uptime_seconds: now.saturating_sub(self.started_at),
+            in_flight_jobs: self.jobs.in_progress() as u32,
+            in_flight_streams: self.streams.active() as u32,
+            queue_depth: due_schedules + due_watches + pending_notifications,
+            due_schedules,
+            due_watches,
+            pending_notifications,
+            dependencies: self.health.statuses(),
+            quotas: self.quotas.statuses(),
+        }
+    }
+
+    fn warmup_llm(&mut self, model: Option<String>) -> Result<LlmWarmup, AgentError> {
+        let model = self.model_for(&ResearchOptions {
+            model,
+            ..ResearchOptions::default()
+        })?;
+        if ollama::is_configured() && !self.ready_models.contains(&model) {
+            ollama::check_model(&model).map_err(AgentError::ModelUnavailable)?;
+            self.ready_models.push(model.clone());
+        }
+        let duration_ms = self.warm_up(&model)?;
+        Ok(LlmWarmup { model, duration_ms })
+    }
+
+    fn research(&mut self, topic: String) -> String {
+        self.research_and_record(workspace::DEFAULT_WORKSPACE, &topic, &ResearchOptions::default(), None)
+            .unwrap_or_else(|err| err.to_string())
+    }
+
+    fn research_with_options(&mut self, topic: String, options: ResearchOptions) -> Result<ResearchReport, AgentError> {
+        let workspace = workspace::normalize(options.workspace.as_deref().unwrap_or_default())
+            .map_err(AgentError::InvalidRequest)?;
+        let topic = topic.trim();
+        if topic.is_empty() {
+            return Err(AgentError::InvalidRequest("Topic must not be empty".to_string()));
+        }
+        options.validate().map_err(AgentError::InvalidRequest)?;
+        self.check_config()?;
+        self.check_load(&options)?;
+
+        let key = cache::key(&workspace, topic, &options);
+        let lookup = if options.bypass_cache() {
+            cache::Lookup::Miss
+        } else {
+            self.report_cache.lookup(&key, now_secs(), &self.cache_policy)
+        };
+        let cached = match lookup {
+            cache::Lookup::Fresh(report) => Some(report),
+            cache::Lookup::Stale(mut report) => {
+                if self.report_cache.begin_refresh(&key) {
+                    ResearchAgentClient::get().trigger_refresh_cached_report(key.clone());
+                    report.freshness.refreshing = true;
+                }
+                Some(report)
             }
+            cache::Lookup::Miss => None,
         };
+        if let Some(mut report) = cached {
+            report.budget_warning = self.budget_warning(&workspace);
+            let sources = report
+                .sources
+                .iter()
+                .map(|source| SearchResult {
+                    url: source.url.clone(),
+                    title: source.title.clone().unwrap_or_default(),
+                    snippet: String::new(),
+                })
+                .collect();
+            self.conversation = Some(conversation::Conversation::new(
+                &workspace,
+                topic,
+                &report.model,
+                sources,
+                &report.report,
+                now_secs(),
+            ));
+            return Ok(report.select(options.fields.as_deref()));
+        }
 
-        let text_result = response
-            .content
+        let (report, sources) = self.run_research(&workspace, topic, &options)?;
+        self.cache_report(key, &workspace, topic, &options, &report);
+        self.conversation = Some(conversation::Conversation::new(
+            &workspace,
+            topic,
+            &report.model,
+            sources,
+            &report.report,
+            now_secs(),
+        ));
+        Ok(report.select(options.fields.as_deref()))
+    }
+
+    fn ask_followup(&mut self, question: String) -> Result<String, AgentError> {
+        let question = question.trim();
+        if question.is_empty() {
+            return Err(AgentError::InvalidRequest("Question must not be empty".to_string()));
+        }
+        let Some(conversation) = self.conversation.clone() else {
+            return Err(AgentError::InvalidRequest(
+                "There is no research to follow up on yet; run research or research_with_options first".to_string(),
+            ));
+        };
+        let workspace = conversation.workspace.as_str();
+        let options = ResearchOptions::default();
+        self.check_config()?;
+        self.check_load(&options)?;
+        self.check_budget(workspace)?;
+        self.screen_topic(workspace, question)?;
+        let model = conversation.model.clone();
+        self.ensure_llm_ready(&model)?;
+        let context_window = self.context_window(&model);
+
+        let fixed_tokens = context::output_reserve(context_window) as usize
+            + prompt::estimate_tokens(conversation.prompt(question, &[]).len());
+        let available_tokens = (context_window as usize).saturating_sub(fixed_tokens);
+        let sources = self
+            .prompt_budget
+            .apply(conversation.sources.clone(), true, available_tokens);
+        let events = [user_message(conversation.prompt(question, &sources))];
+
+        let mut run_usage = usage::RunUsage::default();
+        let result = self.complete_with_fallback(&model, &options, &events, &mut run_usage);
+        let now = now_secs();
+        self.usage.record(workspace, now, &run_usage);
+        let (answer, _) = result?;
+        if let Some(conversation) = self.conversation.as_mut() {
+            conversation.record(question, &answer, now);
+        }
+        Ok(answer)
+    }
+
+    fn start_session(&mut self, topic: String) -> Result<SessionId, AgentError> {
+        let workspace = workspace::DEFAULT_WORKSPACE;
+        let topic = topic.trim();
+        if topic.is_empty() {
+            return Err(AgentError::InvalidRequest("Topic must not be empty".to_string()));
+        }
+        let options = ResearchOptions::default();
+        self.check_config()?;
+        self.check_load(&options)?;
+        self.check_budget(workspace)?;
+        self.screen_topic(workspace, topic)?;
+        let model = self.model.clone();
+        self.ensure_llm_ready(&model)?;
+
+        let mut run_usage = usage::RunUsage::default();
+        let context_window = self.context_window(&model);
+        let mut trace = explain::RetrievalExplanation::default();
+        let result = self.synthesize(workspace, topic, &options, &model, context_window, &mut run_usage, &mut trace);
+        let now = now_secs();
+        self.usage.record(workspace, now, &run_usage);
+        let (answer, model) = result?;
+        let answer = self.footer.append(answer, &model, &options.language(), now);
+        let conversation = conversation::Conversation::new(workspace, topic, &model, trace.selected, &answer, now);
+        Ok(self.sessions.create(conversation, now))
+    }
+
+    fn continue_session(&mut self, session_id: SessionId, instruction: String) -> Result<SessionTurn, AgentError> {
+        let instruction = instruction.trim();
+        if instruction.is_empty() {
+            return Err(AgentError::InvalidRequest("Instruction must not be empty".to_string()));
+        }
+        let Some(session) = self.sessions.get(session_id.trim()) else {
+            return Err(AgentError::InvalidRequest(format!("Unknown session {session_id:?}")));
+        };
+        let conversation = session.conversation.clone();
+        let turn = session.next_turn();
+        let workspace = conversation.workspace.as_str();
+        let options = ResearchOptions::default();
+        self.check_config()?;
+        self.check_load(&options)?;
+        self.check_budget(workspace)?;
+        self.screen_topic(workspace, instruction)?;
+        let model = conversation.model.clone();
+        self.ensure_llm_ready(&model)?;
+        let context_window = self.context_window(&model);
+
+        // One page of results on the instruction; a failed search leaves the sources the
+        // session already has.
+        let mut run_usage = usage::RunUsage::default();
+        let search_topic = format!("{} {instruction}", query::plain_topic(&conversation.topic));
+        let found = self
+            .search_sources(workspace, &search_topic, &options, 1, &mut run_usage)
+            .unwrap_or_else(|err| {
+                log::warn!("Search for session {session_id:?} failed, answering from its sources: {err}");
+                Vec::new()
+            });
+        // This turn's results go first, so they are the last to be cut to fit the window.
+        let found_urls: Vec<String> = found.iter().map(|r| citations::normalize_url(&r.url)).collect();
+        let earlier = conversation
+            .sources
             .iter()
-            .filter_map(|content_part| match content_part {
-                ContentPart::Text(txt) => Some(txt.clone()),
-                _ => None,
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+            .filter(|r| !found_urls.contains(&citations::normalize_url(&r.url)))
+            .cloned();
+        let candidates: Vec<SearchResult> = found.iter().cloned().chain(earlier).collect();
+        let fixed_tokens = context::output_reserve(context_window) as usize
+            + prompt::estimate_tokens(conversation.prompt(instruction, &[]).len());
+        let available_tokens = (context_window as usize).saturating_sub(fixed_tokens);
+        let sources = self.prompt_budget.apply(candidates, true, available_tokens);
+        let events = [user_message(conversation.prompt(instruction, &sources))];
 
-        format!("Finished research for topic {}:\n{}", topic, text_result)
+        let result = self.complete_with_fallback(&model, &options, &events, &mut run_usage);
+        let now = now_secs();
+        self.usage.record(workspace, now, &run_usage);
+        let (reply, _) = result?;
+        let Some(session) = self.sessions.get_mut(session_id.trim()) else {
+            return Err(AgentError::InvalidRequest(format!("Unknown session {session_id:?}")));
+        };
+        let new_sources = session.add_sources(found, turn);
+        Ok(session.record(instruction, &reply, new_sources, now))
     }
-}
 
-fn search_web_for_topic(provider: WebSearchProvider, topic: &str) -> Result<Vec<SearchResult>, String> {
-    let pages_to_retrieve = 3;
+    fn get_session_report(&self, session_id: SessionId) -> Option<SessionReport> {
+        self.sessions.get(session_id.trim()).map(sessions::Session::report)
+    }
 
-    let session = match web_search::start_search(&web_search::SearchParams {
-        query: topic.to_string(),
-        language: Some("lang_en".to_string()),
-        safe_search: Some(types::SafeSearchLevel::Off),
-        max_results: Some(10),
-        time_range: None,
-        include_domains: None,
-        exclude_domains: None,
-        include_images: None,
-        include_html: None,
-        advanced_answer: Some(true),
-        region: None,
-    }) {
-        Ok(s) => s,
-        Err(e) => {
-            return Err(format!(
-                "Failed to start web search (provider: {}, query: {:?}). Display: {}. Debug: {:?}",
-                provider.display_name(),
-                topic,
-                e,
-                e
-            ))
+    fn refresh_cached_report(&mut self, key: String) -> Result<ResearchReport, AgentError> {
+        let Some(entry) = self.report_cache.get(&key).cloned() else {
+            return Err(AgentError::InvalidRequest("No cached report to refresh".to_string()));
+        };
+        match self.run_research(&entry.workspace, &entry.topic, &entry.options) {
+            Ok((report, _)) => {
+                self.cache_report(key, &entry.workspace, &entry.topic, &entry.options, &report);
+                Ok(report)
+            }
+            Err(err) => {
+                // The stale report keeps being served until it expires; the next request
+                // after that researches the topic itself.
+                log::warn!("Refreshing the cached report on {:?} failed: {err}", entry.topic);
+                self.report_cache.end_refresh(&key);
+                Err(err)
+            }
         }
-    };
+    }
 
-    let mut content: Vec<SearchResult> = Vec::new();
+    fn clear_cache(&mut self) -> ClearedCaches {
+        let cleared = ClearedCaches {
+            search_results: self.search_cache.clear(),
+            llm_responses: self.llm_cache.clear(),
+            reports: self.report_cache.clear(),
+            pages: self.page_cache.clear(),
+            http_responses: self.http_cache.clear(),
+        };
+        log::info!("Cleared the caches: {cleared:?}");
+        cleared
+    }
 
-    for page_index in 0..pages_to_retrieve {
-        match session.next_page() {
-            Ok(page) => {
-                for item in page {
-                    content.push(SearchResult {
-                        url: item.url.clone(),
-                        title: item.title.clone(),
-                        snippet: item.snippet.clone(),
-                    });
+    fn research_news(&mut self, topic: String, days_back: u32) -> Result<ResearchReport, AgentError> {
+        self.research_with_options(
+            topic,
+            ResearchOptions {
+                news_days: Some(days_back),
+                ..ResearchOptions::default()
+            },
+        )
+    }
+
+    fn search(&mut self, topic: String, options: ResearchOptions) -> Result<Vec<SearchResult>, AgentError> {
+        let workspace = workspace::normalize(options.workspace.as_deref().unwrap_or_default())
+            .map_err(AgentError::InvalidRequest)?;
+        let topic = topic.trim();
+        if topic.is_empty() {
+            return Err(AgentError::InvalidRequest("Topic must not be empty".to_string()));
+        }
+        options.validate().map_err(AgentError::InvalidRequest)?;
+        self.check_config()?;
+        self.check_load(&options)?;
+        self.check_budget(&workspace)?;
+        connectors::select(&self.connectors, options.connectors.as_deref()).map_err(AgentError::InvalidRequest)?;
+        self.search_chain(&options)?;
+        self.screen_topic(&workspace, topic)?;
+
+        let mut run_usage = usage::RunUsage::default();
+        let results = self.search_sources(&workspace, topic, &options, options.depth(), &mut run_usage);
+        self.usage.add(&workspace, now_secs(), &run_usage);
+        results
+    }
+
+    fn research_site(&mut self, domain: String, topic: String, options: ResearchOptions) -> Result<String, AgentError> {
+        let workspace = workspace::normalize(options.workspace.as_deref().unwrap_or_default())
+            .map_err(AgentError::InvalidRequest)?;
+        let domain = site::normalize_domain(&domain).map_err(AgentError::InvalidRequest)?;
+        let topic = topic.trim();
+        if topic.is_empty() {
+            return Err(AgentError::InvalidRequest("Topic must not be empty".to_string()));
+        }
+        options.validate().map_err(AgentError::InvalidRequest)?;
+
+        self.check_config()?;
+        self.check_load(&options)?;
+        self.check_budget(&workspace)?;
+        self.screen_topic(&workspace, topic)?;
+        let model = self.model_for(&options)?;
+        self.ensure_llm_ready(&model)?;
+        let context_window = self.context_window(&model);
+
+        let entries = site::sitemap_entries(&domain, &self.http_cache, now_secs()).map_err(AgentError::InvalidRequest)?;
+        let selected = site::select(entries, topic, site::max_pages());
+        if selected.is_empty() {
+            return Err(AgentError::InvalidRequest(format!(
+                "No pages in the {domain} sitemap match {topic:?}"
+            )));
+        }
+
+        let compress_pages = options.compress.unwrap_or(true);
+        let mut pages = Vec::new();
+        let mut errors = Vec::new();
+        for entry in selected {
+            let page = match archive::fetch_page_or_snapshot(&entry.url, site::FETCH_TIMEOUT) {
+                Ok(page) if !page.text.is_empty() => page,
+                Ok(_) => continue,
+                Err(err) => {
+                    log::warn!("Skipping page: {err}");
+                    errors.push(AgentError::FetchError(sanitize::outward("fetch", &err)));
+                    continue;
                 }
+            };
+            let mut title = page.title.or(entry.title).unwrap_or_else(|| page.url.clone());
+            let mut url = page.url;
+            if let Some(snapshot) = page.snapshot {
+                title = format!("{title} (Wayback Machine snapshot of {})", snapshot.captured_on);
+                url = snapshot.url;
             }
-            Err(e) => {
-                return Err(format!(
-                    "Failed to retrieve web search page {}/{} (provider: {}, query: {:?}). Display: {}. Debug: {:?}",
-                    page_index + 1,
-                    pages_to_retrieve,
-                    provider.display_name(),
-                    topic,
-                    e,
-                    e
-                ));
+            pages.push(SearchResult {
+                title,
+                snippet: if compress_pages {
+                    compress::compress(&page.text, topic, compress::compression_ratio())
+                } else {
+                    page.text
+                },
+                url,
+            });
+        }
+        if pages.is_empty() {
+            return Err(AgentError::InvalidRequest(format!(
+                "None of the matching pages on {domain} could be read"
+            )));
+        }
+
+        let format_instructions = options.format_instructions();
+        let fixed_tokens = context::output_reserve(context_window) as usize
+            + prompt::estimate_tokens(site::site_prompt(&domain, topic, &format_instructions, &[]).len());
+        let available_tokens = (context_window as usize).saturating_sub(fixed_tokens);
+        let pages = self.prompt_budget.apply(pages, true, available_tokens);
+
+        let mut run_usage = usage::RunUsage::default();
+        let events = [user_message(site::site_prompt(&domain, topic, &format_instructions, &pages))];
+        let report = match self.complete(&model, &options, &events, &mut run_usage) {
+            Ok(report) => report,
+            Err(err) => {
+                self.usage.add(&workspace, now_secs(), &run_usage);
+                return Err(err);
+            }
+        };
+        let report = self.footer.append(report, &model, &options.language(), now_secs());
+        let metadata = history::ReportMetadata {
+            model,
+            seed: options.seed,
+            sources: Vec::new(),
+            errors,
+        };
+        let label = format!("{topic} site:{domain}");
+        Ok(self.record_report(&workspace, &label, None, metadata, report, &run_usage))
+    }
+
+    fn research_academic(&mut self, topic: String) -> Result<String, AgentError> {
+        let workspace = workspace::DEFAULT_WORKSPACE;
+        let topic = topic.trim();
+        if topic.is_empty() {
+            return Err(AgentError::InvalidRequest("Topic must not be empty".to_string()));
+        }
+
+        let options = ResearchOptions::default();
+        self.check_config()?;
+        self.check_load(&options)?;
+        self.check_budget(workspace)?;
+        self.screen_topic(workspace, topic)?;
+        let model = self.model.clone();
+        self.ensure_llm_ready(&model)?;
+        let context_window = self.context_window(&model);
+
+        let mut run_usage = usage::RunUsage::default();
+        let mut lists = Vec::new();
+        let mut failures = Vec::new();
+        for source in academic::AcademicSource::ALL {
+            let result = source.search(topic, &self.http_cache, now_secs(), &mut run_usage);
+            let class = result.as_ref().err().map(|err| ErrorClass::of_message(err));
+            self.health.record(&format!("search:{}", source.display_name().to_lowercase()), class, now_secs());
+            match result {
+                Ok(papers) => lists.push(papers),
+                Err(err) => {
+                    log::warn!("{err}");
+                    failures.push(sanitize::outward("research_academic", &err));
+                }
+            }
+        }
+        let papers: Vec<SearchResult> = academic::merge(lists).iter().map(academic::to_result).collect();
+        if papers.is_empty() {
+            self.usage.add(workspace, now_secs(), &run_usage);
+            return Err(AgentError::SearchFailed(if failures.is_empty() {
+                format!("No papers with abstracts found for {topic:?}")
+            } else {
+                failures.join("; ")
+            }));
+        }
+
+        let format_instructions = options.format_instructions();
+        let fixed_tokens = context::output_reserve(context_window) as usize
+            + prompt::estimate_tokens(academic::academic_prompt(topic, &format_instructions, &[]).len());
+        let available_tokens = (context_window as usize).saturating_sub(fixed_tokens);
+        let papers = self.prompt_budget.apply(papers, true, available_tokens);
+
+        let events = [user_message(academic::academic_prompt(topic, &format_instructions, &papers))];
+        let mut report = match self.complete(&model, &options, &events, &mut run_usage) {
+            Ok(report) => report,
+            Err(err) => {
+                self.usage.add(workspace, now_secs(), &run_usage);
+                return Err(err);
             }
+        };
+        if !failures.is_empty() {
+            report.push_str(&format!("\n\nNote: {}; papers are from the other source only.", failures.join("; ")));
         }
+        let report = self.footer.append(report, &model, &options.language(), now_secs());
+        let metadata = history::ReportMetadata {
+            model,
+            seed: None,
+            sources: Vec::new(),
+            errors: failures.into_iter().map(AgentError::SearchFailed).collect(),
+        };
+        let label = format!("{topic} (academic)");
+        Ok(self.record_report(workspace, &label, None, metadata, report, &run_usage))
     }
 
-    Ok(content)
+    fn research_with_template(
+        &mut self,
+        workspace: String,
+        name: String,
+        params: Vec<TemplateParam>,
+    ) -> Result<String, AgentError> {
+        let workspace = workspace::normalize(&workspace).map_err(AgentError::InvalidRequest)?;
+        let (topic, options) = self
+            .resolve_recipe(&ResearchRecipe::Template(TemplateRecipe { name, params }))
+            .map_err(AgentError::InvalidRequest)?;
+        self.research_and_record(&workspace, &topic, &options, None)
+    }
+
+    fn start_deep_research(&mut self, topic: String, options: ResearchOptions) -> Result<ResearchJob, AgentError> {
+        let workspace = workspace::normalize(options.workspace.as_deref().unwrap_or_default())
+            .map_err(AgentError::InvalidRequest)?;
+        let topic = topic.trim().to_string();
+        if topic.is_empty() {
+            return Err(AgentError::InvalidRequest("Topic must not be empty".to_string()));
+        }
+        options.validate().map_err(AgentError::InvalidRequest)?;
+        self.check_config()?;
+        self.check_load(&options)?;
+        self.check_budget(&workspace)?;
+        connectors::select(&self.connectors, options.connectors.as_deref()).map_err(AgentError::InvalidRequest)?;
+        self.search_chain(&options)?;
+        self.screen_topic(&workspace, &topic)?;
+        let model = self.model_for(&options)?;
+        self.ensure_llm_ready(&model)?;
+
+        let mut outline = options.sections();
+        if outline.is_empty() {
+            let mut run_usage = usage::RunUsage::default();
+            let events = [user_message(prompt::outline_prompt(
+                &query::plain_topic(&topic),
+                options.style(),
+            ))];
+            let reply = self.complete(&model, &options, &events, &mut run_usage);
+            self.usage.add(&workspace, now_secs(), &run_usage);
+            outline = match reply {
+                Ok(reply) => prompt::parse_outline(&reply),
+                Err(AgentError::LlmError(message)) => {
+                    log::warn!("Outline generation failed, using the default outline: {message}");
+                    Vec::new()
+                }
+                Err(err) => return Err(err),
+            };
+        }
+        if outline.is_empty() {
+            outline = jobs::DEFAULT_OUTLINE.iter().map(|h| h.to_string()).collect();
+        }
+
+        let job = self
+            .jobs
+            .create(&workspace, &topic, outline, options, model, now_secs());
+        ResearchAgentClient::get().trigger_advance_job(job.id.clone());
+        Ok(job)
+    }
+
+    fn deep_research(&mut self, topic: String) -> Result<ResearchReport, AgentError> {
+        let workspace = workspace::DEFAULT_WORKSPACE;
+        let topic = topic.trim();
+        if topic.is_empty() {
+            return Err(AgentError::InvalidRequest("Topic must not be empty".to_string()));
+        }
+        let options = ResearchOptions::default();
+        self.check_config()?;
+        self.check_load(&options)?;
+        self.check_budget(workspace)?;
+        self.search_chain(&options)?;
+        self.screen_topic(workspace, topic)?;
+        let model = self.model_for(&options)?;
+        self.ensure_llm_ready(&model)?;
+
+        let mut run_usage = usage::RunUsage::default();
+        let context_window = self.context_window(&model);
+        let mut trace = explain::RetrievalExplanation::default();
+        self.telemetry.start_run("deep_research");
+        let result =
+            self.research_in_rounds(workspace, topic, &options, &model, context_window, &mut run_usage, &mut trace);
+        self.telemetry.finish_run(result.as_ref().err().map(AgentError::kind));
+        let (answer, model) = result?;
+        let answer = report::append_notes(answer, &trace.notes);
+        Ok(self.finish_report(workspace, topic, &options, answer, model, &mut run_usage, trace))
+    }
+
+    fn advance_job(&mut self, job_id: String) -> Result<ResearchJob, AgentError> {
+        let Some(job) = self.jobs.get(&job_id) else {
+            return Err(AgentError::InvalidRequest(format!("Unknown job {job_id:?}")));
+        };
+        if job.info.status != JobStatus::Researching {
+            return Ok(job.info.clone());
+        }
+        let workspace = job.info.workspace.clone();
+        let topic = job.info.topic.clone();
+        let outline = job.info.outline();
+        let options = job.options.clone();
+        let model = job.model.clone();
+
+        if let Some(index) = job.info.next_pending() {
+            let written = self
+                .check_budget(&workspace)
+                .and_then(|_| self.ensure_llm_ready(&model))
+                .and_then(|_| {
+                    let context_window = self.context_window(&model);
+                    let mut run_usage = usage::RunUsage::default();
+                    self.telemetry.start_run("deep_research_section");
+                    let section = self.write_section(
+                        &workspace,
+                        &topic,
+                        &outline,
+                        &outline[index],
+                        &options,
+                        &model,
+                        context_window,
+                        &mut run_usage,
+                    );
+                    self.telemetry.finish_run(section.as_ref().err().map(AgentError::kind));
+                    self.usage.add(&workspace, now_secs(), &run_usage);
+                    section
+                });
+
+            let job = self.jobs.get_mut(&job_id).expect("job exists");
+            job.info.updated_at = now_secs();
+            match written {
+                Ok(content) => {
+                    job.info.sections[index].content = Some(content);
+                    job.info.sections[index].completed_at = Some(job.info.updated_at);
+                }
+                Err(err) => {
+                    job.info.status = JobStatus::Failed(err.to_string());
+                    return Err(err);
+                }
+            }
+        }
+
+        let job = self.jobs.get(&job_id).expect("job exists");
+        if job.info.next_pending().is_some() {
+            ResearchAgentClient::get().trigger_advance_job(job_id.clone());
+            return Ok(job.info.clone());
+        }
+
+        let now = now_secs();
+        let report = self.footer.append(job.info.render(), &model, &options.language(), now);
+        let metadata = history::ReportMetadata {
+            model,
+            seed: options.seed,
+            sources: Vec::new(),
+            errors: Vec::new(),
+        };
+        self.usage.count_research(&workspace, now);
+        let report_id = self
+            .history
+            .record(&workspace, &topic, None, now, report, metadata);
+
+        let job = self.jobs.get_mut(&job_id).expect("job exists");
+        job.info.status = JobStatus::Completed;
+        job.info.report_id = Some(report_id);
+        job.info.updated_at = now;
+        Ok(job.info.clone())
+    }
+
+    fn get_job(&self, job_id: String) -> Option<ResearchJob> {
+        self.jobs.get(&job_id).map(|job| job.info.clone())
+    }
+
+    fn get_partial_report(&self, job_id: String) -> Option<PartialReport> {
+        self.jobs.partial(&job_id)
+    }
+
+    fn get_report_manifest(&self, job_id: String) -> Option<ReportManifest> {
+        let (status, report) = self.job_text(&job_id)?;
+        Some(report_chunks::manifest(job_id.trim(), status, &report))
+    }
+
+    fn get_report_chunk(&self, job_id: String, index: u32) -> Result<ReportChunk, AgentError> {
+        let Some((_, report)) = self.job_text(&job_id) else {
+            return Err(AgentError::InvalidRequest(format!("Unknown job {job_id:?}")));
+        };
+        report_chunks::chunk(job_id.trim(), &report, index).map_err(AgentError::InvalidRequest)
+    }
+
+    fn research_stream(&mut self, topic: String, options: ResearchOptions) -> Result<ResearchStream, AgentError> {
+        let workspace = workspace::normalize(options.workspace.as_deref().unwrap_or_default())
+            .map_err(AgentError::InvalidRequest)?;
+        let topic = topic.trim();
+        if topic.is_empty() {
+            return Err(AgentError::InvalidRequest("Topic must not be empty".to_string()));
+        }
+        options.validate().map_err(AgentError::InvalidRequest)?;
+        self.check_config()?;
+        self.check_load(&options)?;
+        self.check_budget(&workspace)?;
+        connectors::select(&self.connectors, options.connectors.as_deref()).map_err(AgentError::InvalidRequest)?;
+        self.search_chain(&options)?;
+        self.screen_topic(&workspace, topic)?;
+        let model = self.model_for(&options)?;
+        self.ensure_llm_ready(&model)?;
+
+        let mut run_usage = usage::RunUsage::default();
+        let context_window = self.context_window(&model);
+        let mut trace = explain::RetrievalExplanation::default();
+        let events = self.research_events(&workspace, topic, &options, context_window, &mut run_usage, &mut trace);
+        self.usage.add(&workspace, now_secs(), &run_usage);
+        let chat = llm::stream(&events?, &llm_config(&model, &options));
+
+        let stream = self
+            .streams
+            .create(
+                &workspace,
+                topic,
+                &model,
+                &options.language(),
+                options.seed,
+                chat,
+                trace.notes,
+                now_secs(),
+            );
+        ResearchAgentClient::get().trigger_advance_stream(stream.id.clone());
+        Ok(stream)
+    }
+
+    fn advance_stream(&mut self, stream_id: String) -> Result<ResearchStream, AgentError> {
+        let Some(stream) = self.streams.get_mut(&stream_id) else {
+            return Err(AgentError::InvalidRequest(format!("Unknown stream {stream_id:?}")));
+        };
+        if stream.info.status != StreamStatus::Streaming {
+            return Ok(stream.info.clone());
+        }
+        let now = now_secs();
+        let end = match &stream.chat {
+            Some(chat) => {
+                let events = chat.blocking_get_next();
+                stream.push_events(events, now)
+            }
+            None => Some(stream::StreamEnd::Failed("The LLM stream was lost".to_string())),
+        };
+
+        match end {
+            None => {
+                ResearchAgentClient::get().trigger_advance_stream(stream_id);
+                Ok(stream.info.clone())
+            }
+            Some(stream::StreamEnd::Failed(message)) => {
+                stream.info.status = StreamStatus::Failed(message.clone());
+                let (workspace, run_usage) = (stream.info.workspace.clone(), stream.usage);
+                self.usage.add(&workspace, now, &run_usage);
+                Err(AgentError::LlmError(message))
+            }
+            Some(stream::StreamEnd::Finished) => {
+                let workspace = stream.info.workspace.clone();
+                let topic = stream.info.topic.clone();
+                let answer = report::append_notes(stream.text(), &stream.notes);
+                let answer = self.footer.append(answer, &stream.info.model, &stream.language, now);
+                let report = format!("Finished research for topic {topic}:\n{answer}");
+                let metadata = history::ReportMetadata {
+                    model: stream.info.model.clone(),
+                    seed: stream.seed,
+                    sources: Vec::new(),
+                    errors: Vec::new(),
+                };
+                let run_usage = stream.usage;
+                self.usage.record(&workspace, now, &run_usage);
+                let report_id = self.history.record(&workspace, &topic, None, now, report, metadata);
+
+                let stream = self.streams.get_mut(&stream_id).expect("stream exists");
+                stream.info.report_id = Some(report_id);
+                Ok(stream.info.clone())
+            }
+        }
+    }
+
+    fn get_stream_chunks(&self, stream_id: String, from_index: u32) -> Option<StreamUpdate> {
+        self.streams.update(stream_id.trim(), from_index)
+    }
+
+    fn list_reports(&self, workspace: String) -> Vec<ReportSummary> {
+        match workspace::normalize(&workspace) {
+            Ok(workspace) => self.history.list(&workspace),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn list_flagged_topics(&self, workspace: String) -> Vec<FlaggedTopic> {
+        match workspace::normalize(&workspace) {
+            Ok(workspace) => self.moderation.list(&workspace),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn get_report(&self, workspace: String, id: String) -> Option<ReportRecord> {
+        let workspace = workspace::normalize(&workspace).ok()?;
+        self.history.get(&workspace, &id).cloned()
+    }
+
+    fn export_history(&self, workspace: String) -> Result<String, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        Ok(payload::compress_marked(self.history.export(&workspace)))
+    }
+
+    fn import_history(&mut self, workspace: String, data: String) -> Result<u32, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        let records = history::parse_export(&payload::decompress_marked(&data)?)?;
+        Ok(self.history.import(&workspace, records))
+    }
+
+    fn merge_reports(
+        &mut self,
+        workspace: String,
+        ids: Vec<String>,
+        instructions: String,
+    ) -> Result<ReportRecord, AgentError> {
+        let workspace = workspace::normalize(&workspace).map_err(AgentError::InvalidRequest)?;
+        let mut reports: Vec<ReportRecord> = Vec::new();
+        for id in ids.iter().map(|id| id.trim()) {
+            if reports.iter().any(|r| r.id == id) {
+                continue;
+            }
+            match self.history.get(&workspace, id) {
+                Some(record) => reports.push(record.clone()),
+                None => {
+                    return Err(AgentError::InvalidRequest(format!(
+                        "Unknown report {id:?} in workspace {workspace:?}"
+                    )))
+                }
+            }
+        }
+        if reports.len() < 2 {
+            return Err(AgentError::InvalidRequest(
+                "merge_reports needs at least two distinct report IDs".to_string(),
+            ));
+        }
+
+        self.check_config()?;
+        self.check_load(&ResearchOptions::default())?;
+        self.check_budget(&workspace)?;
+        let model = self.model.clone();
+        self.ensure_llm_ready(&model)?;
+        let context_window = self.context_window(&model);
+
+        let all_text = reports.iter().map(|r| r.report.as_str()).collect::<Vec<_>>().join("\n");
+        let sources = citations::extract(&all_text);
+        let fixed_tokens = context::output_reserve(context_window) as usize
+            + prompt::estimate_tokens(prompt::merge_prompt(&instructions, &[], 0, &sources).len());
+        let available_chars = prompt::chars_for_tokens((context_window as usize).saturating_sub(fixed_tokens));
+        let max_report_chars = available_chars / reports.len();
+
+        let events = [user_message(prompt::merge_prompt(
+            &instructions,
+            &reports,
+            max_report_chars,
+            &sources,
+        ))];
+        let mut run_usage = usage::RunUsage::default();
+        let merged = self.complete(&model, &ResearchOptions::default(), &events, &mut run_usage);
+        let now = now_secs();
+        self.usage.record(&workspace, now, &run_usage);
+
+        let mut report = merged?.trim().to_string();
+        if !sources.is_empty() {
+            report = format!("{report}\n\n{}", citations::render_sources(&sources));
+        }
+        let topic = format!(
+            "Merged: {}",
+            reports.iter().map(|r| r.topic.as_str()).collect::<Vec<_>>().join("; ")
+        );
+        let report = self.footer.append(report, &model, options::DEFAULT_LANGUAGE, now);
+        let metadata = history::ReportMetadata {
+            model,
+            seed: None,
+            sources: Vec::new(),
+            errors: Vec::new(),
+        };
+        let id = self.history.record(&workspace, &topic, None, now, report, metadata);
+        self.history
+            .get(&workspace, &id)
+            .cloned()
+            .ok_or_else(|| AgentError::InvalidRequest(format!("Report {id:?} was not stored")))
+    }
+
+    fn citation_graph(&mut self, workspace: String, report_id: String) -> Result<CitationGraph, AgentError> {
+        let workspace = workspace::normalize(&workspace).map_err(AgentError::InvalidRequest)?;
+        let report = self.subject_report(&workspace, FindingsSubject::Report(report_id.clone()))?;
+
+        let sources = citations::extract(&report);
+        let reply = self.run_pass(&workspace, findings::extraction_prompt(&report, &sources))?;
+        findings::parse_graph(report_id.trim(), &reply, &sources).map_err(AgentError::ParseError)
+    }
+
+    fn key_findings(&mut self, workspace: String, subject: FindingsSubject) -> Result<Vec<KeyFinding>, AgentError> {
+        let workspace = workspace::normalize(&workspace).map_err(AgentError::InvalidRequest)?;
+        let report = self.subject_report(&workspace, subject)?;
+
+        let sources = citations::extract(&report);
+        let reply = self.run_pass(&workspace, findings::key_findings_prompt(&report, &sources))?;
+        findings::parse_key_findings(&reply, &sources).map_err(AgentError::ParseError)
+    }
+
+    fn action_items(
+        &mut self,
+        workspace: String,
+        subject: FindingsSubject,
+        persona: String,
+    ) -> Result<Vec<ActionItem>, AgentError> {
+        let workspace = workspace::normalize(&workspace).map_err(AgentError::InvalidRequest)?;
+        if persona.trim().is_empty() {
+            return Err(AgentError::InvalidRequest(
+                "Describe the persona or goal the recommendations are for".to_string(),
+            ));
+        }
+        let report = self.subject_report(&workspace, subject)?;
+
+        let sources = citations::extract(&report);
+        let reply = self.run_pass(&workspace, actions::actions_prompt(persona.trim(), &report, &sources))?;
+        actions::parse_actions(&reply, &sources).map_err(AgentError::ParseError)
+    }
+
+    fn interview_questions(
+        &mut self,
+        workspace: String,
+        topic: String,
+        audience: String,
+    ) -> Result<Vec<InterviewQuestion>, AgentError> {
+        let workspace = workspace::normalize(&workspace).map_err(AgentError::InvalidRequest)?;
+        let topic = topic.trim();
+        if topic.is_empty() {
+            return Err(AgentError::InvalidRequest("Topic must not be empty".to_string()));
+        }
+        let audience = match audience.trim() {
+            "" => "a general audience",
+            audience => audience,
+        };
+
+        let options = ResearchOptions::default();
+        self.check_config()?;
+        self.check_load(&options)?;
+        self.check_budget(&workspace)?;
+        let model = self.model.clone();
+        self.ensure_llm_ready(&model)?;
+        let context_window = self.context_window(&model);
+
+        let mut run_usage = usage::RunUsage::default();
+        let search_results = self.search_sources(&workspace, topic, &options, options.depth(), &mut run_usage)?;
+
+        let plain_topic = query::plain_topic(topic);
+        let fixed_tokens = context::output_reserve(context_window) as usize
+            + prompt::estimate_tokens(interview::questions_prompt(&plain_topic, audience, &[]).len());
+        let available_tokens = (context_window as usize).saturating_sub(fixed_tokens);
+        let sources = self.prompt_budget.apply(search_results, false, available_tokens);
+
+        let events = [user_message(interview::questions_prompt(&plain_topic, audience, &sources))];
+        let reply = self.complete(&model, &options, &events, &mut run_usage);
+        self.usage.record(&workspace, now_secs(), &run_usage);
+
+        interview::parse_questions(&reply?, &sources).map_err(AgentError::ParseError)
+    }
+
+    fn export_slides(&mut self, job_id: String) -> Result<SlideDeck, AgentError> {
+        let (workspace, topic, report) = self.job_report(&job_id)?;
+        let reply = self.run_pass(&workspace, export::slides_prompt(&report))?;
+        export::parse_slides(job_id.trim(), &topic, &reply).map_err(AgentError::ParseError)
+    }
+
+    fn export_script(&mut self, job_id: String, minutes: u32) -> Result<NarrationScript, AgentError> {
+        if minutes == 0 || minutes > export::MAX_SCRIPT_MINUTES {
+            return Err(AgentError::InvalidRequest(format!(
+                "Script length must be 1-{} minutes",
+                export::MAX_SCRIPT_MINUTES
+            )));
+        }
+        let (workspace, topic, report) = self.job_report(&job_id)?;
+        let reply = self.run_pass(&workspace, export::script_prompt(&report, minutes))?;
+        let sources = citations::extract(&report);
+        Ok(export::build_script(job_id.trim(), &topic, minutes, &reply, &sources))
+    }
+
+    fn export_thread(&mut self, job_id: String, platform: SocialPlatform) -> Result<SocialThread, AgentError> {
+        let (workspace, _, report) = self.job_report(&job_id)?;
+        let reply = self.run_pass(&workspace, export::thread_prompt(&report, platform))?;
+        let sources = citations::extract(&report);
+        export::build_thread(job_id.trim(), platform, &reply, &sources).map_err(AgentError::ParseError)
+    }
+
+    fn export_newsletter_section(
+        &mut self,
+        workspace: String,
+        subject: FindingsSubject,
+        template: Option<String>,
+    ) -> Result<NewsletterSection, AgentError> {
+        let workspace = workspace::normalize(&workspace).map_err(AgentError::InvalidRequest)?;
+        let report = self.subject_report(&workspace, subject)?;
+        let reply = self.run_pass(&workspace, export::newsletter_prompt(&report))?;
+        let sources = citations::extract(&report);
+        export::build_newsletter(&export::newsletter_template(template), &reply, &sources)
+            .map_err(AgentError::ParseError)
+    }
+
+    fn export_data(&mut self, job_id: String, kind: DataKind) -> Result<DataExport, AgentError> {
+        let (workspace, _, report) = self.job_report(&job_id)?;
+        let attachments = match kind {
+            DataKind::Tables => data_export::tables(&report),
+            DataKind::Sources => data_export::sources(&report),
+            DataKind::Findings => {
+                let sources = citations::extract(&report);
+                let reply = self.run_pass(&workspace, findings::key_findings_prompt(&report, &sources))?;
+                let findings = findings::parse_key_findings(&reply, &sources).map_err(AgentError::ParseError)?;
+                data_export::findings(&findings)
+            }
+        };
+        Ok(DataExport {
+            job_id: job_id.trim().to_string(),
+            kind,
+            attachments,
+        })
+    }
+
+    fn list_search_connectors(&self) -> Vec<ConnectorInfo> {
+        self.connectors.iter().map(|c| c.info()).collect()
+    }
+
+    fn extract_url(&self, url: String, mode: ExtractMode) -> Result<ExtractedPage, AgentError> {
+        let page = archive::fetch_page_or_snapshot(&url, fetch::DEFAULT_TIMEOUT)
+            .map_err(|err| AgentError::FetchError(sanitize::outward("fetch", &err)))?;
+        Ok(ExtractedPage::from_page(page, mode))
+    }
+
+    fn quick_summarize(&mut self, url: String) -> Result<QuickSummary, AgentError> {
+        let workspace = workspace::DEFAULT_WORKSPACE;
+        self.check_config()?;
+        self.check_load(&ResearchOptions::default())?;
+        self.check_budget(workspace)?;
+        let model = quick::model(&self.model);
+        self.ensure_llm_ready(&model)?;
+
+        let page = archive::fetch_page_or_snapshot(&url, quick::fetch_timeout())
+            .map_err(|err| AgentError::FetchError(sanitize::outward("fetch", &err)))?;
+        if page.text.trim().is_empty() {
+            return Err(AgentError::InvalidRequest(format!(
+                "{} has no readable text to summarize",
+                page.url
+            )));
+        }
+        let max_chars = quick::max_chars(self.context_window(&model));
+        let truncated = page.text.chars().count() > max_chars;
+        let text = truncate::chars(&page.text, max_chars);
+
+        let mut run_usage = usage::RunUsage::default();
+        let summary = self.complete(
+            &model,
+            &ResearchOptions::default(),
+            &[user_message(quick::summary_prompt(page.title.as_deref(), &text))],
+            &mut run_usage,
+        );
+        self.usage.add(workspace, now_secs(), &run_usage);
+
+        Ok(QuickSummary {
+            url: page.url,
+            title: page.title,
+            summary: summary?.trim().to_string(),
+            model,
+            truncated,
+            snapshot: page.snapshot,
+        })
+    }
+
+    fn ingest_document(&mut self, workspace: String, doc: IngestDocument) -> Result<CorpusDocument, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        let document = self.corpus.ingest(&workspace, doc, now_secs())?;
+        // Embedding failures leave the document searchable by keyword; reindex_documents retries.
+        self.embed_documents(&workspace, Some(&document.id));
+        Ok(self.corpus.get(&workspace, &document.id).cloned().unwrap_or(document))
+    }
+
+    fn list_documents(&self, workspace: String, filter: DocumentFilter) -> Result<Vec<CorpusDocument>, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        Ok(self.corpus.list(&workspace, &filter))
+    }
+
+    fn tag_document(
+        &mut self,
+        workspace: String,
+        id: String,
+        add: Vec<String>,
+        remove: Vec<String>,
+    ) -> Result<CorpusDocument, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        self.corpus.tag(&workspace, id.trim(), &add, &remove)
+    }
+
+    fn reindex_documents(&mut self, workspace: String, id: Option<String>) -> Result<u32, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        let id = id.as_deref().map(str::trim).filter(|id| !id.is_empty());
+        let count = self.corpus.reindex(&workspace, id)?;
+        self.embed_documents(&workspace, id);
+        Ok(count)
+    }
+
+    fn delete_document(&mut self, workspace: String, id: String) -> bool {
+        match workspace::normalize(&workspace) {
+            Ok(workspace) => self.corpus.delete(&workspace, id.trim()),
+            Err(_) => false,
+        }
+    }
+
+    fn corpus_stats(&self, workspace: String) -> Result<CorpusStats, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        Ok(self.corpus.stats(&workspace))
+    }
+
+    fn explain_retrieval(&mut self, topic: String, options: ResearchOptions) -> Result<RetrievalExplanation, AgentError> {
+        let workspace = workspace::normalize(options.workspace.as_deref().unwrap_or_default())
+            .map_err(AgentError::InvalidRequest)?;
+        let topic = topic.trim();
+        if topic.is_empty() {
+            return Err(AgentError::InvalidRequest("Topic must not be empty".to_string()));
+        }
+        options.validate().map_err(AgentError::InvalidRequest)?;
+        // Searches still cost provider calls, so the budget applies even without the LLM.
+        self.check_config()?;
+        self.check_load(&options)?;
+        self.check_budget(&workspace)?;
+        connectors::select(&self.connectors, options.connectors.as_deref()).map_err(AgentError::InvalidRequest)?;
+        self.search_chain(&options)?;
+        self.screen_topic(&workspace, topic)?;
+        let model = self.model_for(&options)?;
+        let context_window = self.context_window(&model);
+
+        let mut run_usage = usage::RunUsage::default();
+        let mut explanation = RetrievalExplanation {
+            topic: topic.to_string(),
+            ..Default::default()
+        };
+        let sources = self
+            .research_sources(&workspace, topic, &options, context_window, &mut run_usage, &mut explanation)
+            .map(|(sources, _)| sources);
+        // Searches are billed even when they leave nothing to research with.
+        self.usage.add(&workspace, now_secs(), &run_usage);
+        explanation.selected = sources?;
+        Ok(explanation)
+    }
+
+    fn usage_report(&self, workspace: String, period: String) -> Result<UsageReport, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        let period = usage::normalize_period(&period, now_secs())?;
+        Ok(self.usage.report(&workspace, &period))
+    }
+
+    fn set_spend_limits(&mut self, workspace: String, limits: SpendLimits) -> Result<BudgetStatus, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        self.budgets.set_limits(&workspace, limits)?;
+        Ok(self.budget_status_for(&workspace))
+    }
+
+    fn budget_status(&self, workspace: String) -> Result<BudgetStatus, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        Ok(self.budget_status_for(&workspace))
+    }
+
+    fn override_spend_limit(&mut self, workspace: String, active: bool) -> Result<BudgetStatus, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        self.budgets.set_override(&workspace, &clock::month_key(now_secs()), active);
+        Ok(self.budget_status_for(&workspace))
+    }
+
+    fn define_template(&mut self, template: ResearchTemplate) -> Result<ResearchTemplate, String> {
+        self.templates.define(template)
+    }
+
+    fn list_templates(&self) -> Vec<ResearchTemplate> {
+        self.templates.list()
+    }
+
+    fn delete_template(&mut self, name: String) -> bool {
+        self.templates.remove(&name)
+    }
+
+    fn schedule(&mut self, workspace: String, recipe: ResearchRecipe, cron: String) -> Result<ScheduleInfo, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        let notifications = match &recipe {
+            ResearchRecipe::Template(recipe) => match self.templates.get(&recipe.name) {
+                Some(template) => template.delivery.clone().unwrap_or_default(),
+                None => return Err(format!("Unknown research template {:?}", recipe.name)),
+            },
+            ResearchRecipe::Topic(_) => NotificationPrefs::default(),
+        };
+
+        let info = self
+            .schedules
+            .add(&workspace, recipe, &cron, notifications, now_secs())?;
+        self.arm_schedule_wakeup();
+        Ok(info)
+    }
+
+    fn import_topics(
+        &mut self,
+        workspace: String,
+        document: String,
+        cron: Option<String>,
+    ) -> Result<TopicImport, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        let cron = cron
+            .filter(|c| !c.trim().is_empty())
+            .unwrap_or_else(|| import::DEFAULT_IMPORT_CRON.to_string());
+        schedule::CronExpr::parse(&cron)?;
+        let entries = import::parse(&document)?;
+
+        let mut seen: Vec<String> = self
+            .schedules
+            .list(&workspace)
+            .into_iter()
+            .filter_map(|s| match s.recipe {
+                ResearchRecipe::Topic(recipe) => Some(recipe.topic.to_lowercase()),
+                ResearchRecipe::Template(_) => None,
+            })
+            .collect();
+        let mut created = Vec::new();
+        let mut skipped = Vec::new();
+        let now = now_secs();
+        for entry in entries {
+            let Some(topic) = entry.topic() else {
+                continue;
+            };
+            let reason = if seen.contains(&topic.to_lowercase()) {
+                Some("already monitored".to_string())
+            } else if created.len() >= import::MAX_IMPORTED_TOPICS {
+                Some(format!("import limit of {} topics reached", import::MAX_IMPORTED_TOPICS))
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                skipped.push(SkippedTopic { topic, reason });
+                continue;
+            }
+
+            let recipe = ResearchRecipe::Topic(TopicRecipe {
+                topic: topic.clone(),
+                options: ResearchOptions::default(),
+            });
+            match self
+                .schedules
+                .add(&workspace, recipe, &cron, NotificationPrefs::default(), now)
+            {
+                Ok(info) => {
+                    seen.push(topic.to_lowercase());
+                    created.push(info);
+                }
+                Err(reason) => skipped.push(SkippedTopic { topic, reason }),
+            }
+        }
+
+        if !created.is_empty() {
+            self.arm_schedule_wakeup();
+        }
+        Ok(TopicImport { created, skipped })
+    }
+
+    fn list_schedules(&self, workspace: String) -> Vec<ScheduleInfo> {
+        match workspace::normalize(&workspace) {
+            Ok(workspace) => self.schedules.list(&workspace),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn set_schedule_notifications(
+        &mut self,
+        workspace: String,
+        id: String,
+        prefs: NotificationPrefs,
+    ) -> Result<ScheduleInfo, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        self.schedules.set_notifications(&workspace, &id, prefs)
+    }
+
+    fn pause_schedule(&mut self, workspace: String, id: String) -> Result<ScheduleInfo, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        self.schedules.pause(&workspace, &id)
+    }
+
+    fn resume_schedule(&mut self, workspace: String, id: String) -> Result<ScheduleInfo, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        let info = self.schedules.resume(&workspace, &id, now_secs())?;
+        self.arm_schedule_wakeup();
+        Ok(info)
+    }
+
+    fn delete_schedule(&mut self, workspace: String, id: String) -> bool {
+        match workspace::normalize(&workspace) {
+            Ok(workspace) => self.schedules.remove(&workspace, &id),
+            Err(_) => false,
+        }
+    }
+
+    fn watch_url(&mut self, workspace: String, url: String, interval_minutes: u32) -> Result<WatchInfo, String> {
+        let workspace = workspace::normalize(&workspace)?;
+        let interval_minutes = watch::validate_interval(interval_minutes)?;
+        let (page, validators) = fetch::fetch_page_if_modified(&url, fetch::DEFAULT_TIMEOUT, &fetch::Validators::default())
+            .map_err(|err| sanitize::outward("fetch", &err))?
+            .ok_or_else(|| format!("Could not fetch {}: HTTP 304 without validators", url.trim()))?;
+
+        let info = self
+            .watches
+            .add(&workspace, &page.url, interval_minutes, &page.text, validators, now_secs());
+        self.arm_schedule_wakeup();
+        Ok(info)
+    }
+
+    fn list_watches(&self, workspace: String) -> Vec<WatchInfo> {
+        match workspace::normalize(&workspace) {
+            Ok(workspace) => self.watches.list(&workspace),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn unwatch_url(&mut self, workspace: String, id: String) -> bool {
+        match workspace::normalize(&workspace) {
+            Ok(workspace) => self.watches.remove(&workspace, id.trim()),
+            Err(_) => false,
+        }
+    }
+
+    fn run_due_schedules(&mut self) -> Vec<ScheduleRun> {
+        let mut runs = Vec::new();
+
+        for (prefs, notification) in self.outbox.take_ready(now_secs()) {
+            self.send_notification(&prefs, &notification);
+        }
+
+        // Background lane: a bounded slice per invocation; the rest runs after a yield.
+        let due_now = self.schedules.due(now_secs());
+        for due in due_now.into_iter().take(self.lanes.background_concurrency) {
+            let report = match self.resolve_recipe(&due.recipe) {
+                Ok((topic, options)) => self
+                    .research_and_record(&due.workspace, &topic, &options, Some(due.id.clone()))
+                    .unwrap_or_else(|err| err.to_string()),
+                Err(err) => err,
+            };
+            let ran_at = now_secs();
+            self.schedules.record_run(&due.id, ran_at, report.clone());
+
+            let notification = notify::Notification {
+                schedule_id: due.id.clone(),
+                title: format!("Scheduled research: {}", due.recipe.label()),
+                ran_at,
+                report: report.clone(),
+            };
+            match due.notifications.deferred_until(ran_at) {
+                Some(at) => {
+                    self.schedules
+                        .set_last_delivery(&due.id, format!("deferred by quiet hours until {at}"));
+                    self.outbox.defer(at, due.notifications.clone(), notification);
+                }
+                None => self.send_notification(&due.notifications, &notification),
+            }
+
+            runs.push(ScheduleRun {
+                schedule_id: due.id,
+                ran_at,
+                report,
+            });
+        }
+
+        let due_watches = self.watches.due(now_secs());
+        for due in due_watches.into_iter().take(self.lanes.background_concurrency) {
+            let outcome = self.check_watch(&due);
+            self.watches.record_check(&due.id, now_secs(), outcome);
+        }
+
+        self.arm_schedule_wakeup();
+        runs
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        snapshot::AgentSnapshot {
+            schema_version: schema::SCHEMA_VERSION,
+            templates: self.templates.clone(),
+            schedules: self.schedules.clone(),
+            outbox: self.outbox.clone(),
+            history: self.history.clone(),
+            jobs: self.jobs.clone(),
+            streams: self.streams.snapshot(),
+            watches: self.watches.clone(),
+            corpus: self.corpus.clone(),
+            usage: self.usage.clone(),
+            budgets: self.budgets.clone(),
+            ready_models: self.ready_models.clone(),
+            context_windows: self.context_windows.clone(),
+            report_cache: self.report_cache.clone(),
+            page_cache: self.page_cache.clone(),
+            http_cache: self.http_cache.clone(),
+            search_cache: self.search_cache.clone(),
+            llm_cache: self.llm_cache.clone(),
+            quotas: self.quotas.clone(),
+            moderation: self.moderation.clone(),
+            conversation: self.conversation.clone(),
+            sessions: self.sessions.clone(),
+        }
+        .encode()
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let snapshot = snapshot::AgentSnapshot::decode(&bytes)?;
+        self.templates = snapshot.templates;
+        self.schedules = snapshot.schedules;
+        self.outbox = snapshot.outbox;
+        self.history = snapshot.history;
+        self.jobs = snapshot.jobs;
+        self.streams = snapshot.streams;
+        self.watches = snapshot.watches;
+        self.corpus = snapshot.corpus;
+        self.usage = snapshot.usage;
+        self.budgets = snapshot.budgets;
+        self.ready_models = snapshot.ready_models;
+        self.context_windows = snapshot.context_windows;
+        self.report_cache = snapshot.report_cache;
+        self.page_cache = snapshot.page_cache;
+        self.http_cache = snapshot.http_cache;
+        self.search_cache = snapshot.search_cache;
+        self.llm_cache = snapshot.llm_cache;
+        self.quotas = snapshot.quotas;
+        self.moderation = snapshot.moderation;
+        self.conversation = snapshot.conversation;
+        self.sessions = snapshot.sessions;
+        Ok(())
+    }
+}
+
+impl ResearchAgentImpl {
+    /// Runs the research pipeline and stores the report in the workspace's history.
+    ///
+    /// Refuses to run once the workspace is over its hard spend limit; over the soft limit the
+    /// returned report is prefixed with a budget warning (the stored report is not).
+    fn research_and_record(
+        &mut self,
+        workspace: &str,
+        topic: &str,
+        options: &ResearchOptions,
+        schedule_id: Option<String>,
+    ) -> Result<String, AgentError> {
+        options.validate().map_err(AgentError::InvalidRequest)?;
+        self.check_config()?;
+        self.check_load(options)?;
+        self.check_budget(workspace)?;
+        connectors::select(&self.connectors, options.connectors.as_deref()).map_err(AgentError::InvalidRequest)?;
+        self.search_chain(options)?;
+        self.screen_topic(workspace, topic)?;
+
+        let model = self.model_for(options)?;
+        self.ensure_llm_ready(&model)?;
+
+        let mut run_usage = usage::RunUsage::default();
+        let context_window = self.context_window(&model);
+        let mut trace = explain::RetrievalExplanation::default();
+        let result = match schedule_id.as_deref() {
+            Some(id) if differential::enabled() => {
+                let previous = self.history.latest_for_schedule(workspace, id);
+                self.update_report(
+                    workspace,
+                    topic,
+                    options,
+                    &model,
+                    context_window,
+                    previous,
+                    &mut run_usage,
+                    &mut trace,
+                )
+            }
+            _ => self
+                .synthesize(workspace, topic, options, &model, context_window, &mut run_usage, &mut trace)
+                .map(|(answer, used_model)| (answer, used_model, Vec::new())),
+        };
+        let mut errors = trace.errors;
+        let (report, model, sources) = match result {
+            Ok((answer, used_model, sources)) => {
+                let answer = self.footer.append(answer, &used_model, &options.language(), now_secs());
+                // Scheduled runs leave the caller's conversation alone.
+                if schedule_id.is_none() {
+                    self.conversation = Some(conversation::Conversation::new(
+                        workspace,
+                        topic,
+                        &used_model,
+                        trace.selected.clone(),
+                        &answer,
+                        now_secs(),
+                    ));
+                }
+                (format!("Finished research for topic {topic}:\n{answer}"), used_model, sources)
+            }
+            // Provider failures are reported in the text rather than as an error, as before, and
+            // typed in the metadata. The record keeps no sources, so the next run updates the last
+            // good report instead.
+            Err(AgentError::LlmError(message)) => {
+                errors.push(AgentError::LlmError(message.clone()));
+                (message, model, Vec::new())
+            }
+            Err(err) => return Err(err),
+        };
+        let metadata = history::ReportMetadata {
+            model,
+            seed: options.seed,
+            sources,
+            errors,
+        };
+        Ok(self.record_report(workspace, topic, schedule_id, metadata, report, &run_usage))
+    }
+
+    /// The research pipeline behind `research_with_options`, without the report cache. Also
+    /// returns the sources the report was written from.
+    fn run_research(
+        &mut self,
+        workspace: &str,
+        topic: &str,
+        options: &ResearchOptions,
+    ) -> Result<(ResearchReport, Vec<SearchResult>), AgentError> {
+        self.check_budget(workspace)?;
+        connectors::select(&self.connectors, options.connectors.as_deref()).map_err(AgentError::InvalidRequest)?;
+        self.search_chain(options)?;
+        self.screen_topic(workspace, topic)?;
+
+        let model = self.model_for(options)?;
+        self.ensure_llm_ready(&model)?;
+
+        let mut run_usage = usage::RunUsage::default();
+        let context_window = self.context_window(&model);
+        let mut trace = explain::RetrievalExplanation::default();
+        let synthesized = self.synthesize(workspace, topic, options, &model, context_window, &mut run_usage, &mut trace);
+        let (answer, model) = match synthesized {
+            Ok(synthesized) => synthesized,
+            Err(err) => {
+                // What the run spent before failing still counts toward the budget.
+                self.usage.record(workspace, now_secs(), &run_usage);
+                return Err(err);
+            }
+        };
+        let selected = trace.selected.clone();
+        let report = self.finish_report(workspace, topic, options, answer, model, &mut run_usage, trace);
+        Ok((report, selected))
+    }
+
+    /// Extracts the key findings of a research run's answer, records the run's usage and
+    /// report, and returns the `ResearchReport`.
+    #[allow(clippy::too_many_arguments)]
+    fn finish_report(
+        &mut self,
+        workspace: &str,
+        topic: &str,
+        options: &ResearchOptions,
+        answer: String,
+        model: String,
+        run_usage: &mut usage::RunUsage,
+        trace: explain::RetrievalExplanation,
+    ) -> ResearchReport {
+        let sources = citations::extract(&answer);
+        let events = [user_message(findings::key_findings_prompt(&answer, &sources))];
+        let mut errors = trace.errors.clone();
+        let key_findings = self
+            .complete(&model, options, &events, run_usage)
+            .and_then(|reply| findings::parse_key_findings(&reply, &sources).map_err(AgentError::ParseError))
+            .unwrap_or_else(|err| {
+                log::warn!("Extracting key findings failed, returning the report without them: {err}");
+                errors.push(err);
+                Vec::new()
+            });
+
+        let now = now_secs();
+        self.usage.record(workspace, now, run_usage);
+        let metadata = history::ReportMetadata {
+            model: model.clone(),
+            seed: options.seed,
+            sources: Vec::new(),
+            errors: errors.clone(),
+        };
+        let answer = self.footer.append(answer, &model, &options.language(), now);
+        let report = format!("Finished research for topic {topic}:\n{answer}");
+        let report_id = self.history.record(workspace, topic, None, now, report, metadata);
+
+        ResearchReport {
+            schema_version: schema::SCHEMA_VERSION,
+            report_id,
+            topic: topic.to_string(),
+            summary: report::summary(&answer),
+            key_findings,
+            sources: sources
+                .into_iter()
+                .map(|citation| {
+                    let flagged = trace.ai_content.iter().any(|flag| flag.url == citation.url);
+                    let key = citations::normalize_url(&citation.url);
+                    let relevance = trace
+                        .relevance
+                        .iter()
+                        .find(|score| citations::normalize_url(&score.url) == key)
+                        .map(|score| score.score);
+                    ReportSource {
+                        likely_ai_generated: flagged,
+                        relevance,
+                        ..ReportSource::from(citation)
+                    }
+                })
+                .collect(),
+            report: answer,
+            model,
+            budget_warning: self.budget_warning(workspace),
+            freshness: ReportFreshness {
+                generated_at: now,
+                ..ReportFreshness::default()
+            },
+            errors,
+        }
+    }
+
+    /// The rounds of `deep_research` and its final report: searches the topic, then the
+    /// sub-questions the model names for each round, and writes the report from all sources
+    /// found. Returns the answer and the model that wrote it.
+    #[allow(clippy::too_many_arguments)]
+    fn research_in_rounds(
+        &self,
+        workspace: &str,
+        topic: &str,
+        options: &ResearchOptions,
+        model: &str,
+        context_window: u32,
+        run_usage: &mut usage::RunUsage,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> Result<(String, String), AgentError> {
+        let mut pool = iterative::SourcePool::default();
+        pool.add(self.search_sources_traced(workspace, topic, options, options.depth(), run_usage, trace)?);
+        let mut asked: Vec<String> = Vec::new();
+        for index in 1..=self.iteration_budget.max_iterations as usize {
+            let events = [user_message(iterative::questions_prompt(
+                &query::plain_topic(topic),
+                &asked,
+                &pool.results,
+                self.iteration_budget.questions_per_round,
+            ))];
+            let questions = self.complete(model, options, &events, run_usage).and_then(|reply| {
+                iterative::parse_questions(&reply, &asked, self.iteration_budget.questions_per_round)
+                    .map_err(AgentError::ParseError)
+            });
+            let questions = match questions {
+                Ok(questions) if questions.is_empty() => break,
+                Ok(questions) => questions,
+                Err(err) => {
+                    log::warn!("Picking deep research sub-questions failed, writing the report from what was found: {err}");
+                    trace.errors.push(err);
+                    break;
+                }
+            };
+            let mut new_sources = 0;
+            for question in &questions {
+                match self.search_sources_traced(workspace, question, options, 1, run_usage, trace) {
+                    Ok(results) => new_sources += pool.add(results),
+                    Err(err) => {
+                        log::warn!("Searching the sub-question {question:?} failed: {err}");
+                        trace.errors.push(err);
+                    }
+                }
+            }
+            asked.extend(questions.iter().cloned());
+            let round = iterative::Round { questions, new_sources };
+            trace.notes.push(round.note(index));
+            if new_sources == 0 {
+                break;
+            }
+        }
+
+        let format_instructions = options.format_instructions();
+        let plain_topic = query::plain_topic(topic);
+        let fixed_tokens = context::output_reserve(context_window) as usize
+            + prompt::estimate_tokens(iterative::report_prompt(&plain_topic, &asked, &format_instructions, &[]).len());
+        let available_tokens = (context_window as usize).saturating_sub(fixed_tokens);
+        let sources = self
+            .prompt_budget
+            .apply(pool.results, self.page_text(options), available_tokens);
+        trace.available_tokens = available_tokens as u32;
+        trace.selected = sources.clone();
+        let events = [user_message(iterative::report_prompt(&plain_topic, &asked, &format_instructions, &sources))];
+        self.complete_with_fallback(model, options, &events, run_usage)
+    }
+
+    /// Keeps a freshly researched report for repeat requests; a no-op with the cache off.
+    fn cache_report(
+        &mut self,
+        key: String,
+        workspace: &str,
+        topic: &str,
+        options: &ResearchOptions,
+        report: &ResearchReport,
+    ) {
+        let entry = cache::CacheEntry {
+            key,
+            workspace: workspace.to_string(),
+            topic: topic.to_string(),
+            options: options.clone(),
+            report: report.clone(),
+            generated_at: report.freshness.generated_at,
+            refreshing: false,
+        };
+        self.report_cache.store(entry, &self.cache_policy);
+    }
+
+    /// Records a finished research run's usage and report, and returns the report with the
+    /// soft-limit warning prepended when the workspace has crossed it.
+    fn record_report(
+        &mut self,
+        workspace: &str,
+        topic: &str,
+        schedule_id: Option<String>,
+        metadata: history::ReportMetadata,
+        report: String,
+        run_usage: &usage::RunUsage,
+    ) -> String {
+        let now = now_secs();
+        self.usage.record(workspace, now, run_usage);
+        self.history
+            .record(workspace, topic, schedule_id, now, report.clone(), metadata);
+
+        // Checked after recording so the run that crosses the soft limit already warns.
+        match self.budget_warning(workspace) {
+            Some(warning) => format!("{warning}\n\n{report}"),
+            None => report,
+        }
+    }
+
+    /// The report text a follow-up pass works on: a job's finished sections, a stored report,
+    /// or a fresh research run on a topic.
+    fn subject_report(&mut self, workspace: &str, subject: FindingsSubject) -> Result<String, AgentError> {
+        match subject {
+            FindingsSubject::Job(job_id) => match self.jobs.get(job_id.trim()) {
+                Some(job) if job.info.workspace == workspace => {
+                    if job.info.sections.iter().all(|s| s.content.is_none()) {
+                        return Err(AgentError::InvalidRequest(format!(
+                            "Job {job_id:?} has no finished sections yet"
+                        )));
+                    }
+                    Ok(job.info.render())
+                }
+                _ => Err(AgentError::InvalidRequest(format!(
+                    "Unknown job {job_id:?} in workspace {workspace:?}"
+                ))),
+            },
+            FindingsSubject::Report(report_id) => match self.history.get(workspace, report_id.trim()) {
+                Some(record) => Ok(record.report.clone()),
+                None => Err(AgentError::InvalidRequest(format!(
+                    "Unknown report {report_id:?} in workspace {workspace:?}"
+                ))),
+            },
+            FindingsSubject::Topic(topic) => {
+                self.research_and_record(workspace, &topic, &ResearchOptions::default(), None)
+            }
+        }
+    }
+
+    /// Workspace, topic and current report text of a deep research job, for exports.
+    fn job_report(&mut self, job_id: &str) -> Result<(String, String, String), AgentError> {
+        let Some(job) = self.jobs.get(job_id.trim()) else {
+            return Err(AgentError::InvalidRequest(format!("Unknown job {job_id:?}")));
+        };
+        let workspace = job.info.workspace.clone();
+        let topic = job.info.topic.clone();
+        let report = self.subject_report(&workspace, FindingsSubject::Job(job_id.to_string()))?;
+        Ok((workspace, topic, report))
+    }
+
+    /// Status and report of a deep research job: the assembled report in the history once it
+    /// is completed, the sections finished so far before that.
+    fn job_text(&self, job_id: &str) -> Option<(JobStatus, String)> {
+        let job = self.jobs.get(job_id.trim())?;
+        let stored = job
+            .info
+            .report_id
+            .as_ref()
+            .and_then(|id| self.history.get(&job.info.workspace, id))
+            .map(|record| record.report.clone());
+        Some((job.info.status.clone(), stored.unwrap_or_else(|| job.info.render())))
+    }
+
+    /// One LLM call on the default model over material the agent already has, charged to the
+    /// workspace's usage and budget.
+    fn run_pass(&mut self, workspace: &str, prompt: String) -> Result<String, AgentError> {
+        self.check_budget(workspace)?;
+        let model = self.model.clone();
+        self.ensure_llm_ready(&model)?;
+
+        let mut run_usage = usage::RunUsage::default();
+        let reply = self.complete(
+            &model,
+            &ResearchOptions::default(),
+            &[user_message(prompt)],
+            &mut run_usage,
+        );
+        self.usage.add(workspace, now_secs(), &run_usage);
+        reply
+    }
+
+    /// Due schedules, due watch checks and deferred notifications.
+    fn queued(&self, now: u64) -> (u32, u32, u32) {
+        (
+            self.schedules.due(now).len() as u32,
+            self.watches.due(now).len() as u32,
+            self.outbox.pending() as u32,
+        )
+    }
+
+    /// Fails with `ConfigError` while the env has configuration errors.
+    fn check_config(&self) -> Result<(), AgentError> {
+        if self.config.ok {
+            Ok(())
+        } else {
+            Err(AgentError::ConfigError(self.config.clone()))
+        }
+    }
+
+    /// Fails with `Overloaded` for a low-priority request while the worker is over its
+    /// load-shedding thresholds.
+    fn check_load(&self, options: &ResearchOptions) -> Result<(), AgentError> {
+        let (due_schedules, due_watches, pending_notifications) = self.queued(now_secs());
+        let in_flight = (self.jobs.in_progress() + self.streams.active()) as u32;
+        self.load_limits
+            .check(options.priority(), due_schedules + due_watches + pending_notifications, in_flight)
+            .map_err(AgentError::Overloaded)
+    }
+
+    /// Fails with `BudgetExceeded` once the workspace is at its hard limit for the month.
+    fn check_budget(&self, workspace: &str) -> Result<(), AgentError> {
+        let period = clock::month_key(now_secs());
+        let spent = self.usage.report(workspace, &period).estimated_cost_usd;
+        self.budgets
+            .check(workspace, &period, spent)
+            .map(|_| ())
+            .map_err(AgentError::BudgetExceeded)
+    }
+
+    /// Screens `topic` before anything is spent on it (see `safety`): fails with `Refused` for a
+    /// blocked topic and flags a sensitive one for review. The classifier model's usage is
+    /// charged to the workspace; when it fails, the topic is let through on the phrase lists.
+    fn screen_topic(&mut self, workspace: &str, topic: &str) -> Result<(), AgentError> {
+        if !self.topic_screen.enabled {
+            return Ok(());
+        }
+        let mut verdict = self.topic_screen.rules(topic);
+        if let (safety::Verdict::Allowed, Some(model)) = (&verdict, self.topic_screen.model.clone()) {
+            let mut run_usage = usage::RunUsage::default();
+            let events = [user_message(safety::classification_prompt(topic))];
+            verdict = match self.complete(&model, &ResearchOptions::default(), &events, &mut run_usage) {
+                Ok(reply) => safety::parse_verdict(&reply),
+                // The classifier declining to judge the topic is itself a reason to look at it.
+                Err(AgentError::Refused(refusal)) => safety::Verdict::Review(refusal.reason),
+                Err(err) => {
+                    log::warn!("Topic safety classifier {model:?} failed, going by the phrase lists: {err}");
+                    safety::Verdict::Allowed
+                }
+            };
+            self.usage.add(workspace, now_secs(), &run_usage);
+        }
+        match verdict {
+            safety::Verdict::Allowed => Ok(()),
+            safety::Verdict::Review(reason) => {
+                self.moderation.flag(workspace, topic, &reason);
+                Ok(())
+            }
+            safety::Verdict::Blocked(reason) => {
+                log::warn!("Topic {topic:?} in workspace {workspace:?} rejected by the safety check: {reason}");
+                Err(AgentError::Refused(Refusal {
+                    reason: format!("Topic not allowed: {reason}"),
+                }))
+            }
+        }
+    }
+
+    fn budget_warning(&self, workspace: &str) -> Option<String> {
+        let period = clock::month_key(now_secs());
+        let spent = self.usage.report(workspace, &period).estimated_cost_usd;
+        self.budgets.check(workspace, &period, spent).ok().flatten()
+    }
+
+    /// The model a request runs on: the deployment default unless the caller picked an
+    /// allowlisted one.
+    fn model_for(&self, options: &ResearchOptions) -> Result<String, AgentError> {
+        match options.model.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+            None => Ok(self.model.clone()),
+            Some(model) if model == self.model || self.model_allowlist.iter().any(|m| m == model) => {
+                Ok(model.to_string())
+            }
+            Some(model) => Err(AgentError::InvalidRequest(format!(
+                "Model {model:?} is not allowed. Allowed models: {}",
+                std::iter::once(&self.model)
+                    .chain(&self.model_allowlist)
+                    .map(|m| format!("{m:?}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// The web search providers to try for a request: `ResearchOptions::search_provider` alone
+    /// when set, which must be in the configured chain or in `SEARCH_PROVIDER_ALLOWLIST`;
+    /// otherwise the configured chain.
+    fn search_chain(&self, options: &ResearchOptions) -> Result<Vec<WebSearchProvider>, AgentError> {
+        let Some(name) = options.search_provider.as_deref().map(str::trim).filter(|p| !p.is_empty()) else {
+            return Ok(self.web_search_chain.clone());
+        };
+        let provider = WebSearchProvider::parse(name)
+            .map_err(|err| AgentError::InvalidRequest(format!("Unsupported search provider {err}")))?;
+        if self.web_search_chain.contains(&provider) || self.search_provider_allowlist.contains(&provider) {
+            return Ok(vec![provider]);
+        }
+        Err(AgentError::InvalidRequest(format!(
+            "Search provider {:?} is not allowed. Allowed providers: {}",
+            provider.display_name().to_lowercase(),
+            self.web_search_chain
+                .iter()
+                .chain(&self.search_provider_allowlist)
+                .map(|p| format!("{:?}", p.display_name().to_lowercase()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )))
+    }
+
+    /// Runs the Ollama readiness check once per model, then warms the model once if warmup is
+    /// on; later model problems are caught when the LLM call fails.
+    fn ensure_llm_ready(&mut self, model: &str) -> Result<(), AgentError> {
+        if ollama::is_configured() && !self.ready_models.iter().any(|m| m == model) {
+            ollama::check_model(model).map_err(AgentError::ModelUnavailable)?;
+            self.ready_models.push(model.to_string());
+        }
+        if warmup::enabled() && !self.warm_models.iter().any(|m| m == model) {
+            // A failed warmup doesn't fail the request: the real call reports the problem, and
+            // the next request tries warming again.
+            if let Err(err) = self.warm_up(model) {
+                log::warn!("Warming up LLM model {model:?} failed: {err}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the warmup completion and marks the model warm; returns how long it took in ms.
+    /// Not counted in workspace usage.
+    fn warm_up(&mut self, model: &str) -> Result<u64, AgentError> {
+        let started = telemetry::now_nanos();
+        let mut run_usage = usage::RunUsage::default();
+        // A cached reply would skip loading the model on the host, which is the point.
+        let options = ResearchOptions {
+            bypass_cache: Some(true),
+            ..ResearchOptions::default()
+        };
+        self.complete(
+            model,
+            &options,
+            &[user_message(warmup::PROMPT.to_string())],
+            &mut run_usage,
+        )?;
+        if !self.warm_models.iter().any(|m| m == model) {
+            self.warm_models.push(model.to_string());
+        }
+        Ok(telemetry::now_nanos().saturating_sub(started) / 1_000_000)
+    }
+
+    fn context_window(&mut self, model: &str) -> u32 {
+        if let Some((_, window)) = self.context_windows.iter().find(|(m, _)| m == model) {
+            return *window;
+        }
+        let window = context::resolve(model);
+        self.context_windows.push((model.to_string(), window));
+        window
+    }
+
+    fn budget_status_for(&self, workspace: &str) -> BudgetStatus {
+        let period = clock::month_key(now_secs());
+        let spent = self.usage.report(workspace, &period).estimated_cost_usd;
+        self.budgets.status(workspace, &period, spent)
+    }
+
+    /// The topic and options a recipe runs with.
+    fn resolve_recipe(&self, recipe: &ResearchRecipe) -> Result<(String, ResearchOptions), String> {
+        match recipe {
+            ResearchRecipe::Topic(recipe) => Ok((recipe.topic.clone(), recipe.options.clone())),
+            ResearchRecipe::Template(recipe) => {
+                let Some(template) = self.templates.get(&recipe.name) else {
+                    return Err(format!(
+                        "Unknown research template {:?}. Use list_templates to see available templates.",
+                        recipe.name
+                    ));
+                };
+
+                let topic = template.render_topic(&recipe.params)?;
+                Ok((topic, template.options.clone()))
+            }
+        }
+    }
+
+    /// Embeds the pending corpus chunks of one document, or of the workspace; failures are
+    /// logged and the chunks stay keyword-searchable.
+    fn embed_documents(&mut self, workspace: &str, id: Option<&str>) {
+        let Some(embedder) = &self.embedder else {
+            return;
+        };
+        if let Err(err) = self.corpus.embed(workspace, id, embedder) {
+            log::warn!("Embedding corpus documents in {workspace:?} failed: {err}");
+        }
+    }
+
+    /// Fetches a watched page and summarizes its changes; the snapshot only moves forward
+    /// once the change has been summarized, so a failed summary is retried next time.
+    fn check_watch(&mut self, due: &WatchInfo) -> Result<Option<PageChange>, String> {
+        let validators = self.watches.validators(&due.id);
+        let Some((page, validators)) = fetch::fetch_page_if_modified(&due.url, fetch::DEFAULT_TIMEOUT, &validators)?
+        else {
+            return Ok(None);
+        };
+        let diff = self.watches.diff(&due.id, &page.text);
+        if diff.is_empty() {
+            self.watches.set_snapshot(&due.id, &page.text, validators);
+            return Ok(None);
+        }
+
+        let summary = self
+            .run_pass(&due.workspace, watch::change_prompt(&due.url, &diff))
+            .map_err(|err| err.to_string())?;
+        self.watches.set_snapshot(&due.id, &page.text, validators);
+        Ok(Some(PageChange {
+            detected_at: now_secs(),
+            added_lines: diff.added.len() as u32,
+            removed_lines: diff.removed.len() as u32,
+            summary: summary.trim().to_string(),
+        }))
+    }
+
+    fn send_notification(&mut self, prefs: &NotificationPrefs, notification: &notify::Notification) {
+        let status = notify::deliver(prefs, notification).unwrap_or_else(|err| err);
+        self.schedules.set_last_delivery(&notification.schedule_id, status);
+    }
+
+    /// Schedules an invocation of `run_due_schedules` on this agent for the earliest next run
+    /// or deferred delivery, no sooner than the background lane's yield delay.
+    fn arm_schedule_wakeup(&mut self) {
+        let now = now_secs();
+        let also = self
+            .outbox
+            .next_delivery_at()
+            .into_iter()
+            .chain(self.watches.next_check_at())
+            .min();
+        let not_before = now + self.lanes.background_yield_seconds;
+        if let Some(at) = self.schedules.wakeup_to_arm(now, also, not_before) {
+            ResearchAgentClient::get().schedule_run_due_schedules(Datetime {
+                seconds: at,
+                nanoseconds: 0,
+            });
+        }
+    }
+
+    /// Searches and writes the report. Returns the model's answer with the retrieval notes and
+    /// the model that wrote it, which is a synthesis fallback model when the requested one
+    /// failed; `trace` records what was retrieved.
+    #[allow(clippy::too_many_arguments)]
+    fn synthesize(
+        &self,
+        workspace: &str,
+        topic: &str,
+        options: &ResearchOptions,
+        model: &str,
+        context_window: u32,
+        run_usage: &mut usage::RunUsage,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> Result<(String, String), AgentError> {
+        self.telemetry.start_run("research");
+        let result = self
+            .research_events(workspace, topic, options, context_window, run_usage, trace)
+            .and_then(|events| self.complete_with_fallback(model, options, &events, run_usage));
+        self.telemetry.finish_run(result.as_ref().err().map(AgentError::kind));
+        let (answer, model) = result?;
+        let answer = match options.charts {
+            Some(format) => self.add_charts(answer, format, &model, options, run_usage, trace),
+            None => answer,
+        };
+        Ok((report::append_notes(answer, &trace.notes), model))
+    }
+
+    /// `answer` with a "Charts" section drawn from the numeric series in it and its sources
+    /// (see `charts`). Without series, or when the pass fails, the answer is returned as it is.
+    fn add_charts(
+        &self,
+        answer: String,
+        format: ChartFormat,
+        model: &str,
+        options: &ResearchOptions,
+        run_usage: &mut usage::RunUsage,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> String {
+        let events = [user_message(charts::extraction_prompt(&answer, &trace.selected))];
+        let series = self
+            .complete(model, options, &events, run_usage)
+            .and_then(|reply| charts::parse_series(&reply, &answer, &trace.selected).map_err(AgentError::ParseError));
+        match series {
+            Ok(series) if series.is_empty() => answer,
+            Ok(series) => format!(
+                "{}\n\n{}",
+                answer.trim_end(),
+                charts::render(&series, format, &trace.selected, &options.language())
+            ),
+            Err(err) => {
+                log::warn!("Drawing charts failed, returning the report without them: {err}");
+                trace.errors.push(err);
+                answer
+            }
+        }
+    }
+
+    /// A scheduled run's report, written as an update of `previous` (the schedule's last good
+    /// report) from only the sources that are new or changed since; with nothing new the previous
+    /// report is kept without an LLM call. Without `previous` the report is written in full.
+    /// Also returns the source fingerprints to store with the report.
+    #[allow(clippy::too_many_arguments)]
+    fn update_report(
+        &self,
+        workspace: &str,
+        topic: &str,
+        options: &ResearchOptions,
+        model: &str,
+        context_window: u32,
+        previous: Option<&history::ReportRecord>,
+        run_usage: &mut usage::RunUsage,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> Result<(String, String, Vec<differential::SourceFingerprint>), AgentError> {
+        self.telemetry.start_run("research");
+        let result = self
+            .search_sources_traced(workspace, topic, options, options.depth(), run_usage, trace)
+            .and_then(|results| match previous {
+                Some(previous) => self.write_update(topic, options, model, context_window, previous, results, run_usage),
+                None => {
+                    let (sources, exemplars) = self.fit_sources(topic, options, context_window, results.clone(), trace);
+                    let events = research_request(topic, options, &sources, exemplars);
+                    let (answer, model) = self.complete_with_fallback(model, options, &events, run_usage)?;
+                    Ok((answer, model, differential::fingerprint(&results[..sources.len()])))
+                }
+            });
+        self.telemetry.finish_run(result.as_ref().err().map(AgentError::kind));
+        let (answer, model, fingerprints) = result?;
+        Ok((report::append_notes(answer, &trace.notes), model, fingerprints))
+    }
+
+    /// The update half of `update_report`.
+    #[allow(clippy::too_many_arguments)]
+    fn write_update(
+        &self,
+        topic: &str,
+        options: &ResearchOptions,
+        model: &str,
+        context_window: u32,
+        previous: &history::ReportRecord,
+        results: Vec<SearchResult>,
+        run_usage: &mut usage::RunUsage,
+    ) -> Result<(String, String, Vec<differential::SourceFingerprint>), AgentError> {
+        let previous_report = differential::report_body(&previous.report);
+        let previous_date = clock::date_key(previous.created_at);
+        let changed = differential::changed(results, &previous.metadata.sources);
+        if changed.is_empty() {
+            log::info!("No new or changed sources for {topic:?} since {previous_date}; keeping its report");
+            let note = format!("No new or changed sources since {previous_date}; the report is unchanged.");
+            let report = report::append_notes(previous_report.to_string(), &[note]);
+            return Ok((report, previous.metadata.model.clone(), previous.metadata.sources.clone()));
+        }
+
+        let format_instructions = options.format_instructions();
+        let plain_topic = query::plain_topic(topic);
+        let fixed_tokens = context::output_reserve(context_window) as usize
+            + prompt::estimate_tokens(
+                differential::update_prompt(&plain_topic, previous_report, &previous_date, &format_instructions, &[])
+                    .len(),
+            );
+        let available_tokens = (context_window as usize).saturating_sub(fixed_tokens);
+        let sources = self
+            .prompt_budget
+            .apply(changed.clone(), self.page_text(options), available_tokens);
+        log::info!(
+            "Updating the {previous_date} report on {topic:?} from {} new or changed sources",
+            sources.len()
+        );
+
+        let events = [user_message(differential::update_prompt(
+            &plain_topic,
+            previous_report,
+            &previous_date,
+            &format_instructions,
+            &sources,
+        ))];
+        let (answer, model) = self.complete_with_fallback(model, options, &events, run_usage)?;
+        let fingerprints = differential::merge(
+            &previous.metadata.sources,
+            differential::fingerprint(&changed[..sources.len()]),
+        );
+        Ok((answer, model, fingerprints))
+    }
+
+    /// Searches and builds the research request: few-shot exemplars, then the prompt with the
+    /// sources that fit.
+    fn research_events(
+        &self,
+        workspace: &str,
+        topic: &str,
+        options: &ResearchOptions,
+        context_window: u32,
+        run_usage: &mut usage::RunUsage,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> Result<Vec<llm::Event>, AgentError> {
+        let (sources, exemplars) = self.research_sources(workspace, topic, options, context_window, run_usage, trace)?;
+        Ok(research_request(topic, options, &sources, exemplars))
+    }
+
+    /// Searches for a research run and fits the results into what's left of the context
+    /// window after the instructions, few-shot exemplars and the space reserved for the answer.
+    /// Returns the sources and the exemplars that still fit.
+    fn research_sources(
+        &self,
+        workspace: &str,
+        topic: &str,
+        options: &ResearchOptions,
+        context_window: u32,
+        run_usage: &mut usage::RunUsage,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> Result<(Vec<SearchResult>, &[prompt::Exemplar]), AgentError> {
+        let search_results = self.search_sources_traced(workspace, topic, options, options.depth(), run_usage, trace)?;
+        Ok(self.fit_sources(topic, options, context_window, search_results, trace))
+    }
+
+    /// The context-window half of `research_sources`, for results already searched.
+    fn fit_sources(
+        &self,
+        topic: &str,
+        options: &ResearchOptions,
+        context_window: u32,
+        search_results: Vec<SearchResult>,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> (Vec<SearchResult>, &[prompt::Exemplar]) {
+        let format_instructions = options.format_instructions();
+        let plain_topic = query::plain_topic(topic);
+        let window = context_window as usize;
+        let fixed_tokens = context::output_reserve(context_window) as usize
+            + prompt::estimate_tokens(prompt::research_prompt(&plain_topic, &format_instructions, &[]).len());
+        let mut exemplars = self.exemplars.as_slice();
+        let mut exemplar_tokens = prompt::estimate_tokens(prompt::exemplar_chars(exemplars, &format_instructions));
+        if !exemplars.is_empty() && window.saturating_sub(fixed_tokens + exemplar_tokens) < window / 4 {
+            log::warn!("Skipping few-shot exemplars: they don't fit the {window}-token context window");
+            exemplars = &[];
+            exemplar_tokens = 0;
+        }
+        let available_tokens = window.saturating_sub(fixed_tokens + exemplar_tokens);
+
+        let candidate_urls: Vec<String> = search_results.iter().map(|r| r.url.clone()).collect();
+        let sources = self
+            .prompt_budget
+            .apply(search_results, self.page_text(options), available_tokens);
+        trace.available_tokens = available_tokens as u32;
+        trace.dropped = candidate_urls.into_iter().skip(sources.len()).collect();
+        trace.selected = sources.clone();
+        (sources, exemplars)
+    }
+
+    /// Researches one section of a deep research job and returns its body.
+    #[allow(clippy::too_many_arguments)]
+    fn write_section(
+        &self,
+        workspace: &str,
+        topic: &str,
+        outline: &[String],
+        heading: &str,
+        options: &ResearchOptions,
+        model: &str,
+        context_window: u32,
+        run_usage: &mut usage::RunUsage,
+    ) -> Result<String, AgentError> {
+        // One page of results per section unless the caller asked for a specific depth.
+        let pages = options.depth.map_or(1, |_| options.depth());
+        let plain_topic = query::plain_topic(topic);
+        let query = format!("{plain_topic} {heading}");
+        let search_results = self.search_sources(workspace, &query, options, pages, run_usage)?;
+
+        let fixed_tokens = context::output_reserve(context_window) as usize
+            + prompt::estimate_tokens(
+                prompt::section_prompt(&plain_topic, outline, heading, options.style(), &[]).len(),
+            );
+        let available_tokens = (context_window as usize).saturating_sub(fixed_tokens);
+        let sources = self
+            .prompt_budget
+            .apply(search_results, self.page_text(options), available_tokens);
+
+        let events = [user_message(prompt::section_prompt(
+            &plain_topic,
+            outline,
+            heading,
+            options.style(),
+            &sources,
+        ))];
+        self.complete_with_fallback(model, options, &events, run_usage)
+            .map(|(section, _)| section)
+    }
+
+    /// Whether sources may carry page text (full-text mode or fetched pages), which the prompt
+    /// budget allows `PROMPT_MAX_PAGE_CHARS` rather than the snippet limit.
+    fn page_text(&self, options: &ResearchOptions) -> bool {
+        options.full_text() || self.content_fetch.pages(options.fetch_pages) > 0
+    }
+
+    /// Replaces the snippets of the web results most likely to reach the prompt with Kagi's
+    /// summary of the page. A result keeps its snippet when summarizing it fails.
+    fn presummarize(
+        &self,
+        results: &mut [SearchResult],
+        ai_flags: &[ai_content::AiContentFlag],
+        engine: &str,
+        options: &ResearchOptions,
+        run_usage: &mut usage::RunUsage,
+    ) {
+        let language = options.language();
+        for result in results
+            .iter_mut()
+            .filter(|result| !ai_flags.iter().any(|flag| flag.url == result.url))
+            .take(self.prompt_budget.max_sources)
+        {
+            match search_http::kagi_summarize(&result.url, engine, &language, &self.quotas, run_usage) {
+                Ok(summary) => result.snippet = summary,
+                Err(err) => log::warn!("Keeping the snippet of {}: {err}", result.url),
+            }
+        }
+    }
+
+    /// Searches for `search_topic` and returns the filtered (and, in full-text mode, compressed)
+    /// results. A failed web search is handled by the search fallback policy.
+    fn search_sources(
+        &self,
+        workspace: &str,
+        search_topic: &str,
+        options: &ResearchOptions,
+        pages: u32,
+        run_usage: &mut usage::RunUsage,
+    ) -> Result<Vec<SearchResult>, AgentError> {
+        let mut trace = explain::RetrievalExplanation::default();
+        self.search_sources_traced(workspace, search_topic, options, pages, run_usage, &mut trace)
+    }
+
+    /// `search_sources`, recording the queries, provider counts and corpus scores in `trace`.
+    fn search_sources_traced(
+        &self,
+        workspace: &str,
+        search_topic: &str,
+        options: &ResearchOptions,
+        pages: u32,
+        run_usage: &mut usage::RunUsage,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> Result<Vec<SearchResult>, AgentError> {
+        let mut exclude_terms = query::inline_exclusions(search_topic);
+        exclude_terms.extend(options.exclude_terms());
+        let grounding = if options.use_wikipedia() {
+            self.wikipedia_summary(search_topic, options, run_usage, trace)
+        } else {
+            None
+        };
+
+        let search_results = match self.search_web_expanded(search_topic, options, pages, &exclude_terms, run_usage, trace) {
+            Ok(results) => Ok(results),
+            Err(err) if self.fallback.search == fallback::SearchFallback::Fail => {
+                return Err(AgentError::SearchFailed(err))
+            }
+            Err(err) => {
+                log::warn!("{err}; continuing with corpus and connector results only");
+                Err(err)
+            }
+        };
+
+        let plain = query::plain_topic(search_topic);
+        let selected = connectors::select(&self.connectors, options.connectors.as_deref()).unwrap_or_default();
+        let mut internal_results = Vec::new();
+        for connector in selected {
+            run_usage.search_calls += 1;
+            trace.queries.push(explain::IssuedQuery {
+                provider: connector.name.clone(),
+                query: plain.clone(),
+                include_domains: Vec::new(),
+                exclude_domains: Vec::new(),
+            });
+            match connector.search(&plain) {
+                Ok(results) => {
+                    let raw = results.len();
+                    let kept = query::filter_excluded(results, &exclude_terms);
+                    trace.provider(&connector.name, raw, kept.len(), None);
+                    internal_results.extend(kept);
+                }
+                Err(err) => {
+                    log::warn!("Search connector {:?} failed: {err}", connector.name);
+                    trace.provider(&connector.name, 0, 0, Some(err));
+                }
+            }
+        }
+        let corpus_results = self.search_corpus(workspace, &plain, &exclude_terms, trace);
+        internal_results.splice(0..0, corpus_results);
+        let search_results = match search_results {
+            Ok(results) => results,
+            Err(err) if internal_results.is_empty() && grounding.is_none() => {
+                return Err(AgentError::SearchFailed(err))
+            }
+            Err(_) => Vec::new(),
+        };
+        // Before page fetching, so a page isn't fetched once per copy.
+        let (search_results, duplicates) = results::dedup(search_results);
+        trace.duplicates.extend(duplicates);
+        let ai_flags = if ai_content::enabled() {
+            ai_content::flag(&search_results)
+        } else {
+            Vec::new()
+        };
+        let mut search_results = search_results;
+        if let Some(engine) = &self.kagi_summarizer {
+            self.presummarize(&mut search_results, &ai_flags, engine, options, run_usage);
+        }
+        let fetch_pages = self.content_fetch.pages(options.fetch_pages);
+        if fetch_pages > 0 {
+            // Results flagged as AI-generated are ranked last, so their pages aren't worth fetching.
+            let candidates = search_results
+                .iter_mut()
+                .filter(|result| !ai_flags.iter().any(|flag| flag.url == result.url));
+            let failures = content_fetcher::enrich(
+                candidates,
+                fetch_pages,
+                &self.content_fetch,
+                &self.page_cache,
+                &self.page_cache_policy,
+                now_secs(),
+            );
+            for err in failures {
+                log::warn!("Keeping the snippet: {err}");
+                trace.errors.push(AgentError::FetchError(sanitize::outward("fetch", &err)));
+            }
+        }
+        let search_results = connectors::interleave(search_results, internal_results);
+        let (mut search_results, scores) = self.relevance.apply(search_results, &plain);
+        trace.relevance.extend(scores);
+        ai_content::demote(&mut search_results, &ai_flags);
+        if !ai_flags.is_empty() {
+            let urls: Vec<&str> = ai_flags.iter().map(|flag| flag.url.as_str()).collect();
+            trace
+                .notes
+                .push(format!("Ranked last as likely AI-generated: {}.", urls.join(", ")));
+        }
+        trace.ai_content.extend(ai_flags);
+
+        if options.compress() {
+            let ratio = compress::compression_ratio();
+            for result in &mut search_results {
+                result.snippet = compress::compress(&result.snippet, &plain, ratio);
+            }
+        }
+        search_results.splice(0..0, grounding);
+
+        // Web results can repeat corpus, connector and Wikipedia results.
+        let (search_results, duplicates) = results::dedup(search_results);
+        trace.duplicates.extend(duplicates);
+        Ok(search_results)
+    }
+
+    /// The Wikipedia grounding source for `search_topic`, if there is an article; failures are
+    /// noted in `trace` and research continues without it.
+    fn wikipedia_summary(
+        &self,
+        search_topic: &str,
+        options: &ResearchOptions,
+        run_usage: &mut usage::RunUsage,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> Option<SearchResult> {
+        let plain = query::plain_topic(search_topic);
+        trace.queries.push(explain::IssuedQuery {
+            provider: "Wikipedia".to_string(),
+            query: plain.clone(),
+            include_domains: Vec::new(),
+            exclude_domains: Vec::new(),
+        });
+        let result = wikipedia::summary(&plain, &options.language(), &self.http_cache, now_secs(), run_usage);
+        let class = result.as_ref().err().map(|err| ErrorClass::of_message(err));
+        self.health.record("search:wikipedia", class, now_secs());
+        match result {
+            Ok(summary) => {
+                let found = usize::from(summary.is_some());
+                trace.provider("Wikipedia", found, found, None);
+                summary
+            }
+            Err(err) => {
+                log::warn!("{err}; continuing without Wikipedia grounding");
+                let err = sanitize::outward("search:wikipedia", &err);
+                trace.provider("Wikipedia", 0, 0, Some(err.clone()));
+                trace.errors.push(AgentError::SearchFailed(format!("Wikipedia: {err}")));
+                trace.notes.push(format!("Wikipedia grounding was unavailable: {err}."));
+                None
+            }
+        }
+    }
+
+    /// Web results from the first provider in the chain that returns any, exclusions applied.
+    /// Providers that failed or found nothing before it are noted in `trace.notes`.
+    /// With `expand_queries`, the web search for the 2-4 queries the model rewrites the topic
+    /// into (see `expansion`), sharing the topic's pages between them; otherwise, or when
+    /// planning fails, `search_web_in_languages`.
+    fn search_web_expanded(
+        &self,
+        search_topic: &str,
+        options: &ResearchOptions,
+        pages: u32,
+        exclude_terms: &[String],
+        run_usage: &mut usage::RunUsage,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> Result<Vec<SearchResult>, String> {
+        if !options.expand_queries() {
+            return self.search_web_in_languages(search_topic, options, pages, exclude_terms, run_usage, trace);
+        }
+        let plain = query::plain_topic(search_topic);
+        let language = multilingual::detect(&plain).filter(|_| options.multilingual());
+        let events = [user_message(expansion::expansion_prompt(&plain, language.as_deref()))];
+        let planned = self
+            .complete(&self.model, &ResearchOptions::default(), &events, run_usage)
+            .and_then(|reply| expansion::parse_queries(&reply, language.as_deref()).map_err(AgentError::ParseError));
+        let planned = match planned {
+            Ok(planned) => planned,
+            Err(err) => {
+                log::warn!("Query expansion failed, searching the topic as written: {err}");
+                trace.errors.push(err);
+                return self.search_web_in_languages(search_topic, options, pages, exclude_terms, run_usage, trace);
+            }
+        };
+
+        let pages_per_query = pages.div_ceil(planned.len() as u32).max(1);
+        let mut lists = Vec::new();
+        let mut last_error = String::new();
+        for planned_query in &planned {
+            let query_options = ResearchOptions {
+                language: planned_query.language.clone().or_else(|| options.language.clone()),
+                ..options.clone()
+            };
+            let search_query = query::with_operators(&planned_query.query, search_topic);
+            match self.search_web(&search_query, &query_options, pages_per_query, exclude_terms, run_usage, trace) {
+                Ok(results) => lists.push(results),
+                Err(err) => {
+                    log::warn!("Searching the expanded query {:?} failed: {err}", planned_query.query);
+                    last_error = err;
+                }
+            }
+        }
+        let queries: Vec<&str> = planned.iter().map(|p| p.query.as_str()).collect();
+        trace.notes.push(format!("Searched the topic as: {}.", queries.join("; ")));
+        if lists.is_empty() {
+            return Err(last_error);
+        }
+        Ok(expansion::merge(lists))
+    }
+
+    /// `search_web`, and for a topic written in another language (see `multilingual`) also a
+    /// search for its English translation, with the two result lists interleaved. The topic's
+    /// own search prefers results in its language.
+    fn search_web_in_languages(
+        &self,
+        search_topic: &str,
+        options: &ResearchOptions,
+        pages: u32,
+        exclude_terms: &[String],
+        run_usage: &mut usage::RunUsage,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> Result<Vec<SearchResult>, String> {
+        let plain = query::plain_topic(search_topic);
+        let language = multilingual::detect(&plain).filter(|_| options.multilingual());
+        let Some(language) = language else {
+            return self.search_web(search_topic, options, pages, exclude_terms, run_usage, trace);
+        };
+        let native_options = ResearchOptions {
+            language: Some(language.clone()),
+            ..options.clone()
+        };
+        let native = self.search_web(search_topic, &native_options, pages, exclude_terms, run_usage, trace);
+
+        let events = [user_message(multilingual::translation_prompt(&plain, &language))];
+        let english = match self.complete(&self.model, &ResearchOptions::default(), &events, run_usage) {
+            Ok(reply) => multilingual::parse_translation(&reply).filter(|query| !query.eq_ignore_ascii_case(&plain)),
+            Err(err) => {
+                log::warn!("Translating the topic into English failed, searching it as written only: {err}");
+                trace.errors.push(err);
+                None
+            }
+        };
+        let Some(english) = english else {
+            return native;
+        };
+        let english_options = ResearchOptions {
+            language: Some("en".to_string()),
+            ..options.clone()
+        };
+        let english_query = query::with_operators(&english, search_topic);
+        let translated = self.search_web(&english_query, &english_options, pages, exclude_terms, run_usage, trace);
+        trace.notes.push(format!(
+            "Searched in {} and in English ({english:?}).",
+            multilingual::language_name(&language)
+        ));
+        match (native, translated) {
+            (Ok(native), Ok(translated)) => Ok(connectors::interleave(native, translated)),
+            (Ok(results), Err(err)) | (Err(err), Ok(results)) => {
+                log::warn!("One of the two language searches failed, using the other: {err}");
+                Ok(results)
+            }
+            (Err(err), Err(_)) => Err(err),
+        }
+    }
+
+    fn search_web(
+        &self,
+        search_topic: &str,
+        options: &ResearchOptions,
+        pages: u32,
+        exclude_terms: &[String],
+        run_usage: &mut usage::RunUsage,
+        trace: &mut explain::RetrievalExplanation,
+    ) -> Result<Vec<SearchResult>, String> {
+        let option_exclusions = options.exclude_terms();
+        let mut failures = Vec::new();
+        let mut last_error = String::new();
+
+        let chain = self.search_chain(options).map_err(|err| err.to_string())?;
+        for (index, &provider) in chain.iter().enumerate() {
+            let name = provider.display_name();
+            let search_query = query::build_query(provider, search_topic, &option_exclusions);
+            let cache_key = search_cache::key(name, &search_query, pages, options);
+            let cached = if options.bypass_cache() {
+                None
+            } else {
+                self.search_cache.get(&cache_key, now_secs(), &self.search_cache_policy)
+            };
+            if let Some(cached) = cached {
+                log::info!("Using cached {name} results for {:?}", search_query.query);
+                let raw = cached.len();
+                let kept = query::filter_excluded(cached, exclude_terms);
+                trace.provider(name, raw, kept.len(), None);
+                trace.notes.push(format!("Web search: {name} results served from the search cache."));
+                if !failures.is_empty() {
+                    trace.notes.push(format!("Web search: {}; results from {name}.", failures.join("; ")));
+                }
+                return Ok(kept);
+            }
+            if let Some(reason) = self.quotas.check(&name.to_lowercase(), now_secs()) {
+                log::warn!("Skipping web search with {name}: {reason}");
+                trace.provider(name, 0, 0, Some(reason.clone()));
+                failures.push(format!("{name} skipped ({reason})"));
+                last_error = format!("{name} skipped: {reason}");
+                continue;
+            }
+            trace.queries.push(explain::IssuedQuery {
+                provider: name.to_string(),
+                query: search_query.query.clone(),
+                include_domains: search_query.include_domains.clone(),
+                exclude_domains: search_query.exclude_domains.clone(),
+            });
+
+            let started = telemetry::now_nanos();
+            let results = match (provider, options.news_days()) {
+                // golem-ai has no news search; the news endpoints are always called over HTTP.
+                (WebSearchProvider::Brave, Some(days)) => {
+                    search_http::brave_news_search(&search_query, pages, options, days, &self.quotas, run_usage).map_err(ProviderError::from)
+                }
+                (WebSearchProvider::Serper, Some(days)) => {
+                    search_http::serper_news_search(&search_query, pages, options, days, &self.quotas, run_usage).map_err(ProviderError::from)
+                }
+                _ if provider == self.web_search_provider && provider.has_golem_ai_component() => {
+                    search_web_for_topic(provider, &search_query, pages, options, self.fallback.fetch, &self.quotas, run_usage)
+                }
+                (WebSearchProvider::Bing, _) => search_http::bing_http_search(&search_query, pages, options, &self.quotas, run_usage).map_err(ProviderError::from),
+                (WebSearchProvider::Brave, _) => search_http::brave_http_search(&search_query, pages, options, &self.quotas, run_usage).map_err(ProviderError::from),
+                (WebSearchProvider::DuckDuckGo, _) => {
+                    search_http::duckduckgo_http_search(&search_query, pages, options, &self.quotas, run_usage).map_err(ProviderError::from)
+                }
+                (WebSearchProvider::Exa, _) => search_http::exa_http_search(&search_query, pages, options, &self.quotas, run_usage).map_err(ProviderError::from),
+                (WebSearchProvider::Kagi, _) => search_http::kagi_http_search(&search_query, pages, options, &self.quotas, run_usage).map_err(ProviderError::from),
+                (WebSearchProvider::SearxNG, _) => search_http::searxng_http_search(&search_query, pages, options, &self.quotas, run_usage).map_err(ProviderError::from),
+                (WebSearchProvider::Serper, _) => search_http::serper_http_search(&search_query, pages, options, &self.quotas, run_usage).map_err(ProviderError::from),
+                _ => Err(ProviderError::new(ErrorClass::Permanent, format!("{name} has no HTTP client"))),
+            };
+            let class = results.as_ref().err().map(|err| err.class);
+            self.health.record(&format!("search:{}", provider.display_name().to_lowercase()), class, now_secs());
+            self.telemetry.stage(
+                "search",
+                started,
+                class.map(|_| "search-failed"),
+                vec![
+                    ("aggo.provider", name.into()),
+                    ("aggo.results", results.as_ref().map_or(0, |r| r.len() as u64).into()),
+                ],
+            );
+            let last = index + 1 == chain.len();
+            match results {
+                Ok(results) if results.is_empty() && !last => {
+                    trace.provider(name, 0, 0, None);
+                    failures.push(format!("{name} found nothing"));
+                }
+                Ok(results) => {
+                    self.search_cache.store(cache_key, &results, now_secs(), &self.search_cache_policy);
+                    let raw = results.len();
+                    let kept = query::filter_excluded(results, exclude_terms);
+                    trace.provider(name, raw, kept.len(), None);
+                    if !failures.is_empty() {
+                        trace.notes.push(format!("Web search: {}; results from {name}.", failures.join("; ")));
+                    }
+                    return Ok(kept);
+                }
+                Err(err) => {
+                    log::warn!("Web search with {name} failed ({}): {err}", err.class.name());
+                    let message = sanitize::outward(&format!("search:{}", name.to_lowercase()), &err.message);
+                    trace.provider(name, 0, 0, Some(message.clone()));
+                    trace.errors.push(AgentError::SearchFailed(format!("{name}: {message}")));
+                    failures.push(format!("{name} failed ({})", truncate::chars(&message, 200)));
+                    last_error = message;
+                    if !err.class.falls_back() {
+                        // The query itself was rejected; other providers would reject it too.
+                        break;
+                    }
+                }
+            }
+        }
+
+        trace.notes.push(format!("Web search failed: {}.", failures.join("; ")));
+        if chain.len() > 1 {
+            last_error = format!("Every web search provider failed; the last error was: {last_error}");
+        }
+        Err(last_error)
+    }
+
+    /// The best-matching documents of `workspace`'s corpus (the workspace the run is for), exclusions
+    /// applied.
+    fn search_corpus(
+        &self,
+        workspace: &str,
+        plain_topic: &str,
+        exclude_terms: &[String],
+        trace: &mut explain::RetrievalExplanation,
+    ) -> Vec<SearchResult> {
+        let limit = corpus::results_per_query();
+        if self.corpus.is_empty(workspace) {
+            return Vec::new();
+        }
+        let query_vector = self.embedder.as_ref().and_then(|embedder| {
+            embedder
+                .embed(&[plain_topic])
+                .map_err(|err| log::warn!("Embedding the corpus query failed, using keyword search: {err}"))
+                .ok()
+                .and_then(|vectors| vectors.into_iter().next())
+        });
+        let mode = corpus::RetrievalMode::from_env();
+        trace.corpus_mode = format!(
+            "{} ({})",
+            mode.name(),
+            if query_vector.is_some() { "query embedded" } else { "no query embedding" }
+        );
+
+        let ranked = self.corpus.rank(workspace, plain_topic, query_vector.as_deref(), mode);
+        let mut results = Vec::new();
+        for score in ranked.iter().take(MAX_EXPLAINED_CORPUS_HITS.max(limit)) {
+            let result = self.corpus.result(score);
+            let used = results.len() < limit && !query::is_excluded(&result, exclude_terms);
+            if used {
+                results.push(result);
+            }
+            trace.corpus_hits.push(self.corpus.hit(score, used));
+        }
+        trace.provider("corpus", ranked.len(), results.len(), None);
+        results
+    }
+
+    /// `complete`, retrying with each synthesis fallback model in turn while the model fails.
+    /// Returns the reply and the model that wrote it.
+    fn complete_with_fallback(
+        &self,
+        model: &str,
+        options: &ResearchOptions,
+        events: &[llm::Event],
+        run_usage: &mut usage::RunUsage,
+    ) -> Result<(String, String), AgentError> {
+        let mut result = self.complete(model, options, events, run_usage);
+        let mut used = model;
+        for fallback_model in self.fallback.synthesis_models.iter().filter(|m| m.as_str() != model) {
+            match &result {
+                Err(err @ (AgentError::LlmError(_) | AgentError::ModelUnavailable(_)))
+                    if ErrorClass::of_error(err).falls_back() =>
+                {
+                    log::warn!("Model {used:?} failed, falling back to {fallback_model:?}: {err}");
+                }
+                _ => break,
+            }
+            result = self.complete(fallback_model, options, events, run_usage);
+            used = fallback_model;
+        }
+        result.map(|reply| (reply, used.to_string()))
+    }
+
+    /// Sends `events` to the model and returns the text of the reply.
+    fn complete(
+        &self,
+        model: &str,
+        options: &ResearchOptions,
+        events: &[llm::Event],
+        run_usage: &mut usage::RunUsage,
+    ) -> Result<String, AgentError> {
+        let started = telemetry::now_nanos();
+        let before = *run_usage;
+        let result = self.send_to_llm(model, options, events, run_usage);
+        let error = result.as_ref().err().map(AgentError::kind);
+        // A refusal is an answer; the model is working.
+        let class = match &result {
+            Err(AgentError::Refused(_)) | Ok(_) => None,
+            Err(err) => Some(ErrorClass::of_error(err)),
+        };
+        self.health.record(&format!("llm:{model}"), class, now_secs());
+        self.telemetry.stage(
+            "llm",
+            started,
+            error,
+            vec![
+                ("aggo.model", model.into()),
+                ("gen_ai.usage.input_tokens", (run_usage.input_tokens - before.input_tokens).into()),
+                ("gen_ai.usage.output_tokens", (run_usage.output_tokens - before.output_tokens).into()),
+            ],
+        );
+        result
+    }
+
+    fn send_to_llm(
+        &self,
+        model: &str,
+        options: &ResearchOptions,
+        events: &[llm::Event],
+        run_usage: &mut usage::RunUsage,
+    ) -> Result<String, AgentError> {
+        let config = llm_config(model, options);
+        let cache_key = llm_cache::key(events, &config);
+        if !options.bypass_cache() {
+            if let Some(reply) = self.llm_cache.get(&cache_key, now_secs(), &self.llm_cache_policy) {
+                log::info!("Using a cached reply from {model:?}");
+                return Ok(reply);
+            }
+        }
+        let mut retries = 0;
+        let response = loop {
+            match llm::send(events, &config) {
+                Ok(r) => {
+                    run_usage.add_llm_response(r.metadata.usage.as_ref());
+                    break r;
+                }
+                Err(e) if ErrorClass::of_llm(&e).retryable() && retries < LLM_TRANSIENT_RETRIES => {
+                    run_usage.add_llm_response(None);
+                    retries += 1;
+                    log::warn!("LLM call to {model:?} failed with a transient error, retrying: {e}");
+                }
+                Err(e) => {
+                    run_usage.add_llm_response(None);
+                    if ollama::is_configured() {
+                        ollama::check_model(model).map_err(AgentError::ModelUnavailable)?;
+                    }
+                    log::warn!(
+                        "LLM call failed (model: {:?}, env AGGO_LLM_MODEL: {:?}, env LLM_MODEL: {:?}). Display: {}. Debug: {:?}",
+                        model,
+                        std::env::var("AGGO_LLM_MODEL").ok(),
+                        std::env::var("LLM_MODEL").ok(),
+                        e,
+                        e
+                    );
+                    // The code name keeps the error classifiable (see `ErrorClass::of_message`).
+                    let message = sanitize::outward(&format!("llm:{model}"), &e.message);
+                    return Err(AgentError::LlmError(format!(
+                        "LLM call to {model:?} failed ({:?}): {message}",
+                        e.code
+                    )));
+                }
+            }
+        };
+
+        let reply = response
+            .content
+            .iter()
+            .filter_map(|content_part| match content_part {
+                ContentPart::Text(txt) => Some(txt.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if let Some(refusal) = refusal::detect(&reply, response.metadata.finish_reason) {
+            log::warn!("Model {model:?} declined the request: {}", refusal.reason);
+            return Err(AgentError::Refused(refusal));
+        }
+        self.llm_cache.store(cache_key, &reply, now_secs(), &self.llm_cache_policy);
+        Ok(reply)
+    }
+}
+
+fn search_web_for_topic(
+    provider: WebSearchProvider,
+    search_query: &query::ProviderQuery,
+    pages_to_retrieve: u32,
+    options: &ResearchOptions,
+    fetch: fallback::FetchFallback,
+    quotas: &quota::QuotaBook,
+    run_usage: &mut usage::RunUsage,
+) -> Result<Vec<SearchResult>, ProviderError> {
+    let topic = &search_query.query;
+    let full_text = options.full_text();
+
+    let session = match web_search::start_search(&web_search::SearchParams {
+        query: topic.to_string(),
+        language: Some(format!("lang_{}", options.language())),
+        safe_search: Some(types::SafeSearchLevel::Off),
+        max_results: Some(options.max_results()),
+        time_range: options.time_range().map(|range| match range {
+            TimeRange::Day => types::TimeRange::Day,
+            TimeRange::Week => types::TimeRange::Week,
+            TimeRange::Month => types::TimeRange::Month,
+            TimeRange::Year => types::TimeRange::Year,
+        }),
+        include_domains: non_empty(&search_query.include_domains),
+        exclude_domains: non_empty(&search_query.exclude_domains),
+        include_images: None,
+        include_html: full_text.then_some(true),
+        advanced_answer: Some(true),
+        region: None,
+    }) {
+        Ok(s) => s,
+        Err(e) => {
+            return Err(ProviderError::new(
+                ErrorClass::of_search(&e),
+                format!(
+                    "Failed to start web search (provider: {}, query: {:?}). Display: {}. Debug: {:?}",
+                    provider.display_name(),
+                    topic,
+                    e,
+                    e
+                ),
+            ))
+        }
+    };
+
+    let mut content: Vec<SearchResult> = Vec::new();
+
+    for page_index in 0..pages_to_retrieve {
+        if let Err(reason) = quotas.acquire(&provider.display_name().to_lowercase()) {
+            if content.is_empty() {
+                return Err(ProviderError::new(
+                    ErrorClass::Quota,
+                    format!("{} search skipped: {reason}", provider.display_name()),
+                ));
+            }
+            log::warn!("Stopping after {page_index} pages of {} results: {reason}", provider.display_name());
+            break;
+        }
+        run_usage.search_calls += 1;
+        match session.next_page() {
+            Ok(page) => {
+                for item in page {
+                    // Providers that return page text put it in content chunks; others only
+                    // have the snippet, which is used as-is.
+                    let page_text = item
+                        .content_chunks
+                        .as_ref()
+                        .filter(|_| full_text)
+                        .map(|chunks| chunks.join("\n"))
+                        .filter(|text| !text.trim().is_empty());
+                    if full_text && page_text.is_none() && fetch == fallback::FetchFallback::Skip {
+                        continue;
+                    }
+                    content.push(SearchResult {
+                        url: item.url.clone(),
+                        title: item.title.clone(),
+                        snippet: page_text.unwrap_or_else(|| item.snippet.clone()),
+                    });
+                }
+            }
+            Err(e) => {
+                return Err(ProviderError::new(
+                    ErrorClass::of_search(&e),
+                    format!(
+                        "Failed to retrieve web search page {}/{} (provider: {}, query: {:?}). Display: {}. Debug: {:?}",
+                        page_index + 1,
+                        pages_to_retrieve,
+                        provider.display_name(),
+                        topic,
+                        e,
+                        e
+                    ),
+                ));
+            }
+        }
+    }
+
+    Ok(content)
+}
+
+fn llm_config(model: &str, options: &ResearchOptions) -> Config {
+    Config {
+        model: model.to_string(),
+        temperature: None,
+        max_tokens: context::max_output_tokens(),
+        stop_sequences: None,
+        tools: None,
+        tool_choice: None,
+        provider_options: options.seed.map(|seed| {
+            vec![llm::Kv {
+                key: "seed".to_string(),
+                value: seed.to_string(),
+            }]
+        }),
+    }
+}
+
+/// The research request for fitted sources: few-shot exemplars, then the prompt.
+fn research_request(
+    topic: &str,
+    options: &ResearchOptions,
+    sources: &[SearchResult],
+    exemplars: &[prompt::Exemplar],
+) -> Vec<llm::Event> {
+    let format_instructions = options.format_instructions();
+    let prompt = prompt::research_prompt(&query::plain_topic(topic), &format_instructions, sources);
+
+    if exemplars.is_empty() {
+        return vec![llm::Event::Message(Message {
+            role: Role::Assistant,
+            name: Some("research-agent".to_string()),
+            content: vec![ContentPart::Text(prompt)],
+        })];
+    }
+    // After the exemplars the request must read as the next user turn, framed like theirs, or
+    // the model continues the last example instead of answering.
+    let mut events = prompt::exemplar_events(exemplars, &format_instructions);
+    events.push(user_message(prompt));
+    events
+}
+
+fn user_message(text: String) -> llm::Event {
+    llm::Event::Message(Message {
+        role: Role::User,
+        name: None,
+        content: vec![ContentPart::Text(text)],
+    })
+}
+
+fn model_allowlist_from_env() -> Vec<String> {
+    std::env::var("LLM_MODEL_ALLOWLIST")
+        .unwrap_or_default()
+        .split(',')
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .collect()
+}
+
+fn non_empty(values: &[String]) -> Option<Vec<String>> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.to_vec())
+    }
 }
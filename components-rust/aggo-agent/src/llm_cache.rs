@@ -0,0 +1,159 @@
+//! LLM replies kept per request, so an identical completion (same model, messages and config,
+//! including the seed) within `LLM_CACHE_TTL_SECONDS` (default 0, off) is answered at once,
+//! with no tokens spent. The key is a digest of the request; replies to repeated research runs
+//! match when their search results do, as with the search cache on.
+//!
+//! Refusals and failures aren't kept, and streamed completions bypass the cache.
+//! `LLM_CACHE_MAX_ENTRIES` (default 100, oldest dropped first) bounds the cache, which is part of
+//! the worker snapshot; `clear_cache` empties it and `ResearchOptions::bypass_cache` skips it.
+
+use std::cell::RefCell;
+
+use golem_rust::golem_ai::golem::llm::llm;
+
+use crate::config;
+use crate::differential;
+
+const DEFAULT_MAX_ENTRIES: usize = 100;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CachedReply {
+    pub key: String,
+    pub reply: String,
+    pub stored_at: u64,
+}
+
+pub struct LlmCachePolicy {
+    ttl_seconds: u64,
+    max_entries: usize,
+}
+
+impl LlmCachePolicy {
+    pub fn from_env() -> Self {
+        Self {
+            ttl_seconds: config::env_parse::<u64>("LLM_CACHE_TTL_SECONDS").unwrap_or(0),
+            max_entries: config::env_parse::<u64>("LLM_CACHE_MAX_ENTRIES").map_or(DEFAULT_MAX_ENTRIES, |n| n as usize),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.ttl_seconds > 0 && self.max_entries > 0
+    }
+}
+
+/// Written from the `&self` completion path, hence the `RefCell`.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct LlmCache {
+    entries: RefCell<Vec<CachedReply>>,
+}
+
+/// The cache key of sending `events` with `config`, which names the model.
+pub fn key(events: &[llm::Event], config: &llm::Config) -> String {
+    let request = format!("{config:?}\n{events:?}");
+    format!("{}-{}", differential::digest(&request), request.len())
+}
+
+impl LlmCache {
+    /// The reply kept under `key`, if it is younger than the TTL.
+    pub fn get(&self, key: &str, now: u64, policy: &LlmCachePolicy) -> Option<String> {
+        if !policy.enabled() {
+            return None;
+        }
+        self.entries
+            .borrow()
+            .iter()
+            .find(|e| e.key == key && now.saturating_sub(e.stored_at) < policy.ttl_seconds)
+            .map(|e| e.reply.clone())
+    }
+
+    pub fn store(&self, key: String, reply: &str, now: u64, policy: &LlmCachePolicy) {
+        if !policy.enabled() {
+            return;
+        }
+        let mut entries = self.entries.borrow_mut();
+        entries.retain(|e| e.key != key && now.saturating_sub(e.stored_at) < policy.ttl_seconds);
+        entries.push(CachedReply {
+            key,
+            reply: reply.to_string(),
+            stored_at: now,
+        });
+        let excess = entries.len().saturating_sub(policy.max_entries);
+        entries.drain(..excess);
+    }
+
+    /// Empties the cache and returns how many replies it had.
+    pub fn clear(&self) -> u32 {
+        self.entries.take().len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ResearchOptions;
+
+    fn policy(ttl_seconds: u64, max_entries: usize) -> LlmCachePolicy {
+        LlmCachePolicy {
+            ttl_seconds,
+            max_entries,
+        }
+    }
+
+    fn request_key(model: &str, prompt: &str, seed: Option<u64>) -> String {
+        let options = ResearchOptions {
+            seed,
+            ..ResearchOptions::default()
+        };
+        key(&[crate::user_message(prompt.to_string())], &crate::llm_config(model, &options))
+    }
+
+    #[test]
+    fn key_covers_model_messages_and_seed() {
+        let base = request_key("llama3", "prompt", None);
+        assert_eq!(base, request_key("llama3", "prompt", None));
+        assert_ne!(base, request_key("mistral", "prompt", None));
+        assert_ne!(base, request_key("llama3", "prompt!", None));
+        assert_ne!(base, request_key("llama3", "prompt", Some(7)));
+        assert_ne!(request_key("llama3", "prompt", Some(7)), request_key("llama3", "prompt", Some(8)));
+    }
+
+    #[test]
+    fn replies_expire_after_the_ttl() {
+        let policy = policy(60, 10);
+        let cache = LlmCache::default();
+        cache.store("k".to_string(), "reply", 1000, &policy);
+        assert_eq!(cache.get("k", 1059, &policy).as_deref(), Some("reply"));
+        assert_eq!(cache.get("k", 1060, &policy), None);
+        assert_eq!(cache.get("other", 1000, &policy), None);
+
+        // A newer reply replaces the old one.
+        cache.store("k".to_string(), "newer", 1050, &policy);
+        assert_eq!(cache.get("k", 1100, &policy).as_deref(), Some("newer"));
+    }
+
+    #[test]
+    fn a_disabled_cache_keeps_nothing() {
+        let cache = LlmCache::default();
+        cache.store("k".to_string(), "reply", 1000, &policy(0, 10));
+        assert_eq!(cache.get("k", 1000, &policy(60, 10)), None);
+
+        cache.store("k".to_string(), "reply", 1000, &policy(60, 10));
+        assert_eq!(cache.get("k", 1000, &policy(60, 0)), None);
+    }
+
+    #[test]
+    fn drops_expired_and_oldest_entries() {
+        let policy = policy(60, 2);
+        let cache = LlmCache::default();
+        cache.store("a".to_string(), "1", 1000, &policy);
+        cache.store("b".to_string(), "2", 1010, &policy);
+        cache.store("c".to_string(), "3", 1020, &policy);
+        assert_eq!(cache.get("a", 1020, &policy), None);
+        assert_eq!(cache.get("b", 1020, &policy).as_deref(), Some("2"));
+
+        // Storing after `b` expired drops it even below the limit.
+        cache.store("d".to_string(), "4", 1075, &policy);
+        assert_eq!(cache.clear(), 2);
+        assert_eq!(cache.get("d", 1075, &policy), None);
+    }
+}
@@ -0,0 +1,186 @@
+//! Text layout and formatting that depend on the script or the report language.
+//!
+//! Monospace layouts (the text bar charts) measure text in terminal columns rather than
+//! characters: CJK ideographs, kana, Hangul and fullwidth forms take two columns, combining
+//! marks and invisible format characters none. Labels in right-to-left scripts (Arabic,
+//! Hebrew, …) are wrapped in Unicode directional isolates so they can't reorder the bars and
+//! numbers around them.
+//!
+//! Numbers and dates shown in reports follow the report language (`ResearchOptions::language`):
+//! `1,234.5` in English, `1.234,5` in German, `1 234,5` in French, `2026年10月16日` in
+//! Japanese. English dates stay ISO (`2026-10-16`), as do dates in languages without a format
+//! here.
+
+use crate::clock;
+
+const ELLIPSIS: char = '…';
+/// FIRST STRONG ISOLATE and POP DIRECTIONAL ISOLATE.
+const FSI: char = '\u{2068}';
+const PDI: char = '\u{2069}';
+const NARROW_NO_BREAK_SPACE: char = '\u{202f}';
+const NO_BREAK_SPACE: char = '\u{a0}';
+
+/// Whether `text` contains a letter of a right-to-left script.
+pub fn has_rtl(text: &str) -> bool {
+    text.chars().any(|c| {
+        matches!(c as u32, 0x0590..=0x08ff | 0xfb1d..=0xfdff | 0xfe70..=0xfefc | 0x10800..=0x10fff | 0x1e800..=0x1efff)
+    })
+}
+
+/// `text` wrapped in directional isolates if it contains right-to-left letters, so that it
+/// reads correctly without reordering what is around it.
+pub fn isolate(text: &str) -> String {
+    if has_rtl(text) {
+        format!("{FSI}{text}{PDI}")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Columns `c` takes in a monospace font.
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x00..=0x1f | 0x7f..=0x9f => 0,
+        // Combining marks.
+        0x0300..=0x036f | 0x0483..=0x0489 | 0x0591..=0x05bd | 0x0610..=0x061a | 0x064b..=0x065f | 0x0670 => 0,
+        0x06d6..=0x06dc | 0x06df..=0x06e4 | 0x0e31 | 0x0e34..=0x0e3a | 0x0e47..=0x0e4e => 0,
+        0x1ab0..=0x1aff | 0x1dc0..=0x1dff | 0x20d0..=0x20ff | 0xfe20..=0xfe2f => 0,
+        // Zero-width, joiners, directional and variation selectors.
+        0x200b..=0x200f | 0x202a..=0x202e | 0x2060..=0x206f | 0xfe00..=0xfe0f | 0xfeff => 0,
+        // Hangul Jamo, CJK, kana, Hangul syllables, fullwidth forms and emoji.
+        0x1100..=0x115f | 0x2e80..=0x303e | 0x3041..=0x33ff | 0x3400..=0x4dbf | 0x4e00..=0x9fff => 2,
+        0xa000..=0xa4cf | 0xac00..=0xd7a3 | 0xf900..=0xfaff | 0xfe30..=0xfe4f | 0xff00..=0xff60 | 0xffe0..=0xffe6 => 2,
+        0x1f300..=0x1f64f | 0x1f900..=0x1f9ff | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+/// Columns `text` takes in a monospace font.
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// `text` cut to at most `max` columns, ending in `…` if cut. Cuts fall between characters,
+/// never between a letter and its combining marks.
+pub fn truncate_width(text: &str, max: usize) -> String {
+    if display_width(text) <= max {
+        return text.to_string();
+    }
+    let mut cut = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        let w = char_width(c);
+        if width + w + 1 > max {
+            break;
+        }
+        width += w;
+        cut.push(c);
+    }
+    cut.truncate(cut.trim_end().len());
+    cut.push(ELLIPSIS);
+    cut
+}
+
+/// `text` padded with spaces to `width` columns.
+pub fn pad_to_width(text: &str, width: usize) -> String {
+    format!("{text}{}", " ".repeat(width.saturating_sub(display_width(text))))
+}
+
+/// `value` written the way `language` writes numbers, without a trailing `.0`.
+pub fn format_number(value: f64, language: &str) -> String {
+    let plain = if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value.abs() as i64)
+    } else {
+        format!("{}", value.abs())
+    };
+    let (integer, fraction) = plain.split_once('.').map_or((plain.as_str(), None), |(i, f)| (i, Some(f)));
+
+    let (group, decimal) = separators(language);
+    let mut written = if language == "hi" {
+        group_indian(integer, group)
+    } else if language == "es" && integer.len() <= 4 {
+        // Spanish leaves four-digit numbers ungrouped.
+        integer.to_string()
+    } else {
+        group_thousands(integer, group)
+    };
+    if let Some(fraction) = fraction {
+        written.push(decimal);
+        written.push_str(fraction);
+    }
+    if value < 0.0 {
+        written.insert(0, '-');
+    }
+    written
+}
+
+/// The thousands separator and decimal mark of `language`.
+fn separators(language: &str) -> (char, char) {
+    match language {
+        "de" | "es" | "it" | "pt" | "nl" | "id" | "tr" | "da" | "el" => ('.', ','),
+        "fr" => (NARROW_NO_BREAK_SPACE, ','),
+        "ru" | "uk" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "hu" => (NO_BREAK_SPACE, ','),
+        _ => (',', '.'),
+    }
+}
+
+fn group_thousands(digits: &str, separator: char) -> String {
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// Indian grouping: the last three digits, then groups of two (`12,34,567`).
+fn group_indian(digits: &str, separator: char) -> String {
+    if digits.len() <= 3 {
+        return digits.to_string();
+    }
+    let (head, tail) = digits.split_at(digits.len() - 3);
+    let mut grouped = String::new();
+    for (i, c) in head.chars().enumerate() {
+        if i > 0 && (head.len() - i).is_multiple_of(2) {
+            grouped.push(separator);
+        }
+        grouped.push(c);
+    }
+    format!("{grouped}{separator}{tail}")
+}
+
+/// The date containing `secs` (UTC), written the way `language` writes dates.
+pub fn format_date(secs: u64, language: &str) -> String {
+    let (year, month, day) = clock::civil_from_days(secs / 86_400);
+    let Some(name) = month_name(language, month) else {
+        return match language {
+            "ja" | "zh" => format!("{year}年{month}月{day}日"),
+            "ko" => format!("{year}년 {month}월 {day}일"),
+            _ => clock::date_key(secs),
+        };
+    };
+    match language {
+        "de" => format!("{day}. {name} {year}"),
+        "es" | "pt" => format!("{day} de {name} de {year}"),
+        "fr" if day == 1 => format!("1er {name} {year}"),
+        "ru" => format!("{day} {name} {year} г."),
+        _ => format!("{day} {name} {year}"),
+    }
+}
+
+/// The month's name as used in a date (Russian uses the genitive).
+fn month_name(language: &str, month: u32) -> Option<&'static str> {
+    let names: [&str; 12] = match language {
+        "de" => ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober", "November", "Dezember"],
+        "fr" => ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre", "décembre"],
+        "es" => ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"],
+        "it" => ["gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto", "settembre", "ottobre", "novembre", "dicembre"],
+        "pt" => ["janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro", "novembro", "dezembro"],
+        "nl" => ["januari", "februari", "maart", "april", "mei", "juni", "juli", "augustus", "september", "oktober", "november", "december"],
+        "ru" => ["января", "февраля", "марта", "апреля", "мая", "июня", "июля", "августа", "сентября", "октября", "ноября", "декабря"],
+        _ => return None,
+    };
+    names.get(month as usize - 1).copied()
+}
@@ -0,0 +1,140 @@
+//! Searching non-English topics in their own language and in English. A topic written in
+//! another language is searched as written with that language's results preferred, and again
+//! as an English query that the model translates it into; the two result lists are interleaved
+//! and the report is written from both. The report language is still
+//! `ResearchOptions::language` (default English).
+//!
+//! The topic's language is guessed without a model call: from its script (CJK, Cyrillic,
+//! Arabic, …) or, for Latin-script topics, from common words and letters of German, French,
+//! Spanish, Italian, Portuguese and Dutch. Short topics without such clues count as English.
+//! `ResearchOptions::multilingual` set to false turns this off.
+
+use crate::truncate;
+
+const MAX_QUERY_CHARS: usize = 300;
+
+/// Common words per Latin-script language; English is included so that English topics
+/// mentioning a foreign word aren't misread.
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "of", "for", "with", "in", "on", "is", "how", "what", "why", "to", "a"]),
+    ("de", &["der", "die", "das", "und", "für", "mit", "über", "ist", "nicht", "ein", "eine", "von", "im", "den", "wie", "zu"]),
+    ("fr", &["le", "la", "les", "des", "et", "pour", "avec", "dans", "est", "une", "du", "sur", "au", "aux", "comment"]),
+    ("es", &["el", "la", "los", "las", "y", "para", "con", "en", "del", "una", "por", "que", "cómo", "sobre"]),
+    ("it", &["il", "lo", "gli", "le", "della", "per", "con", "che", "una", "di", "e", "nel", "sulla", "come"]),
+    ("pt", &["o", "os", "as", "para", "com", "não", "uma", "do", "da", "em", "no", "na", "sobre", "como"]),
+    ("nl", &["de", "het", "een", "en", "voor", "met", "van", "is", "niet", "op", "over", "hoe"]),
+];
+
+/// Letters that only some Latin-script languages use.
+const LETTERS: &[(&str, &[char])] = &[
+    ("de", &['ä', 'ö', 'ü', 'ß']),
+    ("fr", &['è', 'ê', 'ç', 'à', 'ù', 'œ']),
+    ("es", &['ñ', '¿', '¡', 'á', 'í', 'ó', 'ú']),
+    ("pt", &['ã', 'õ', 'ç', 'â', 'ô']),
+    ("it", &['ì', 'ò', 'à', 'è']),
+];
+
+/// The ISO 639-1 code of the language `topic` is written in, if it isn't English.
+pub fn detect(topic: &str) -> Option<String> {
+    if let Some(code) = by_script(topic) {
+        return Some(code.to_string());
+    }
+    let lower = topic.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let scores: Vec<(&str, usize)> = STOPWORDS
+        .iter()
+        .map(|(code, stopwords)| {
+            let word_hits = words.iter().filter(|w| stopwords.contains(w)).count();
+            let letter_hits = LETTERS
+                .iter()
+                .filter(|(letter_code, _)| letter_code == code)
+                .flat_map(|(_, letters)| letters.iter())
+                .filter(|letter| lower.contains(**letter))
+                .count();
+            (*code, word_hits * 2 + letter_hits)
+        })
+        .collect();
+    let best = scores.iter().map(|(_, score)| *score).max().unwrap_or(0);
+    let leaders: Vec<&str> = scores.iter().filter(|(_, s)| *s == best).map(|(code, _)| *code).collect();
+    match leaders.as_slice() {
+        [code] if best >= 2 && *code != "en" => Some(code.to_string()),
+        _ => None,
+    }
+}
+
+fn by_script(topic: &str) -> Option<&'static str> {
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    let mut latin = 0;
+    for c in topic.chars().filter(|c| c.is_alphabetic()) {
+        let code = match c as u32 {
+            0x3040..=0x30ff => "ja",
+            0xac00..=0xd7af | 0x1100..=0x11ff => "ko",
+            0x4e00..=0x9fff | 0x3400..=0x4dbf => "zh",
+            0x0400..=0x04ff => "ru",
+            0x0600..=0x06ff => "ar",
+            0x0370..=0x03ff => "el",
+            0x0590..=0x05ff => "he",
+            0x0900..=0x097f => "hi",
+            0x0e00..=0x0e7f => "th",
+            _ => {
+                latin += 1;
+                continue;
+            }
+        };
+        match counts.iter_mut().find(|(known, _)| *known == code) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((code, 1)),
+        }
+    }
+    let total: usize = counts.iter().map(|(_, count)| count).sum();
+    if total == 0 || total < latin {
+        return None;
+    }
+    // Japanese mixes kana with kanji; any kana makes the topic Japanese.
+    if counts.iter().any(|(code, _)| *code == "ja") {
+        return Some("ja");
+    }
+    counts.into_iter().max_by_key(|(_, count)| *count).map(|(code, _)| code)
+}
+
+pub fn language_name(code: &str) -> &str {
+    match code {
+        "de" => "German",
+        "fr" => "French",
+        "es" => "Spanish",
+        "it" => "Italian",
+        "pt" => "Portuguese",
+        "nl" => "Dutch",
+        "ja" => "Japanese",
+        "ko" => "Korean",
+        "zh" => "Chinese",
+        "ru" => "Russian",
+        "ar" => "Arabic",
+        "el" => "Greek",
+        "he" => "Hebrew",
+        "hi" => "Hindi",
+        "th" => "Thai",
+        other => other,
+    }
+}
+
+pub fn translation_prompt(topic: &str, language: &str) -> String {
+    format!(
+        "Translate this {} web search query into the English query a researcher would type to find the same information. \
+         Keep names, product names and technical terms as they are usually written in English. \
+         Reply with the English query only, on one line, without quotes or explanations.\n\n\
+         Query: {topic}",
+        language_name(language)
+    )
+}
+
+/// The English query in the model's reply, if it gave one.
+pub fn parse_translation(reply: &str) -> Option<String> {
+    let line = reply.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line.strip_prefix("Query:").unwrap_or(line).trim();
+    let query = line.trim_matches(|c| c == '"' || c == '\'' || c == '`').trim();
+    (!query.is_empty()).then(|| truncate::chars(query, MAX_QUERY_CHARS))
+}
@@ -0,0 +1,456 @@
+//! Delivery of scheduled research results.
+//!
+//! Each schedule carries `NotificationPrefs`. Reports finished inside the schedule's quiet
+//! hours are parked in the `Outbox` and delivered when the quiet window ends.
+
+use golem_rust::Schema;
+
+use crate::sanitize::REDACTED;
+use crate::truncate;
+
+/// Slack rejects very long messages; longer reports are cut and point at `list_schedules`.
+const SLACK_MAX_CHARS: usize = 3000;
+
+#[derive(Clone, Debug, Default, Schema, serde::Serialize, serde::Deserialize)]
+pub enum Delivery {
+    /// Keep the report in agent state only (visible through `list_schedules`).
+    #[default]
+    StoreOnly,
+    /// POST a JSON payload `{schedule_id, title, ran_at, report}` to the URL.
+    Webhook(WebhookTarget),
+    /// Send through the HTTP mail relay configured by `NOTIFY_EMAIL_ENDPOINT`.
+    Email(EmailTarget),
+    /// POST to a Slack incoming-webhook URL.
+    Slack(SlackTarget),
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct WebhookTarget {
+    pub url: String,
+    /// Optional `Authorization` header value sent with the request; shown redacted.
+    pub authorization: Option<String>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct EmailTarget {
+    pub to: Vec<String>,
+    pub subject_prefix: Option<String>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct SlackTarget {
+    /// A bearer secret in itself, so shown with only its host.
+    pub webhook_url: String,
+}
+
+/// A daily window (local to `utc_offset_minutes`) in which nothing is delivered.
+/// `start_hour` may be greater than `end_hour` for windows that wrap midnight, e.g. 22 → 7.
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct QuietHours {
+    pub start_hour: u32,
+    pub end_hour: u32,
+    pub utc_offset_minutes: i32,
+}
+
+#[derive(Clone, Debug, Default, Schema, serde::Serialize, serde::Deserialize)]
+pub struct NotificationPrefs {
+    pub delivery: Delivery,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// A finished report waiting for delivery.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Notification {
+    pub schedule_id: String,
+    pub title: String,
+    pub ran_at: u64,
+    pub report: String,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct Pending {
+    deliver_at: u64,
+    prefs: NotificationPrefs,
+    notification: Notification,
+}
+
+/// Notifications deferred by quiet hours.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Outbox {
+    pending: Vec<Pending>,
+}
+
+impl Outbox {
+    pub fn defer(&mut self, deliver_at: u64, prefs: NotificationPrefs, notification: Notification) {
+        self.pending.push(Pending {
+            deliver_at,
+            prefs,
+            notification,
+        });
+    }
+
+    /// Removes and returns notifications whose quiet window has ended.
+    pub fn take_ready(&mut self, now: u64) -> Vec<(NotificationPrefs, Notification)> {
+        let (ready, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|p| p.deliver_at <= now);
+        self.pending = waiting;
+        ready.into_iter().map(|p| (p.prefs, p.notification)).collect()
+    }
+
+    /// Notifications waiting for their quiet window to end.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn next_delivery_at(&self) -> Option<u64> {
+        self.pending.iter().map(|p| p.deliver_at).min()
+    }
+}
+
+impl QuietHours {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.start_hour > 23 || self.end_hour > 23 {
+            return Err("Quiet hours must use hours 0-23".to_string());
+        }
+        if self.utc_offset_minutes.abs() > 14 * 60 {
+            return Err("Quiet hours UTC offset must be within ±14h".to_string());
+        }
+        Ok(())
+    }
+
+    /// If `now` falls inside the window, returns the unix time at which it ends.
+    pub fn quiet_until(&self, now: u64) -> Option<u64> {
+        if self.start_hour == self.end_hour {
+            return None;
+        }
+
+        let offset = i64::from(self.utc_offset_minutes) * 60;
+        let local = now as i64 + offset;
+        let seconds_into_day = local.rem_euclid(86_400);
+        let hour = (seconds_into_day / 3600) as u32;
+
+        let inside = if self.start_hour < self.end_hour {
+            hour >= self.start_hour && hour < self.end_hour
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        };
+        if !inside {
+            return None;
+        }
+
+        let local_midnight = local - seconds_into_day;
+        let mut end = local_midnight + i64::from(self.end_hour) * 3600;
+        if end <= local {
+            end += 86_400;
+        }
+        Some((end - offset) as u64)
+    }
+}
+
+impl NotificationPrefs {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.delivery {
+            Delivery::StoreOnly => {}
+            Delivery::Webhook(target) => validate_url(&target.url)?,
+            Delivery::Slack(target) => validate_url(&target.webhook_url)?,
+            Delivery::Email(target) => {
+                if target.to.iter().all(|to| to.trim().is_empty()) {
+                    return Err("Email delivery needs at least one recipient".to_string());
+                }
+                if email_endpoint().is_none() {
+                    return Err("Email delivery requires NOTIFY_EMAIL_ENDPOINT to be configured".to_string());
+                }
+            }
+        }
+        match &self.quiet_hours {
+            Some(quiet) => quiet.validate(),
+            None => Ok(()),
+        }
+    }
+
+    /// These prefs as returned to callers, with their credentials redacted. Stored state
+    /// keeps the real values for delivery.
+    pub fn redacted(&self) -> Self {
+        let delivery = match &self.delivery {
+            Delivery::Webhook(target) => Delivery::Webhook(WebhookTarget {
+                url: target.url.clone(),
+                authorization: target.authorization.as_ref().map(|_| REDACTED.to_string()),
+            }),
+            Delivery::Slack(target) => Delivery::Slack(SlackTarget {
+                webhook_url: redact_path(&target.webhook_url),
+            }),
+            delivery => delivery.clone(),
+        };
+        Self {
+            delivery,
+            quiet_hours: self.quiet_hours.clone(),
+        }
+    }
+
+    /// These prefs with any credential still redacted (a round-tripped `redacted` value) put
+    /// back from `stored`, so callers can edit prefs they only ever saw redacted.
+    pub fn keep_credentials(mut self, stored: &NotificationPrefs) -> Self {
+        match (&mut self.delivery, &stored.delivery) {
+            (Delivery::Webhook(target), Delivery::Webhook(stored))
+                if target.authorization.as_deref() == Some(REDACTED) =>
+            {
+                target.authorization = stored.authorization.clone();
+            }
+            (Delivery::Slack(target), Delivery::Slack(stored))
+                if target.webhook_url.trim() == redact_path(&stored.webhook_url) =>
+            {
+                target.webhook_url = stored.webhook_url.clone();
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// When delivery must wait for quiet hours to end, the time it may happen.
+    pub fn deferred_until(&self, now: u64) -> Option<u64> {
+        if matches!(self.delivery, Delivery::StoreOnly) {
+            return None;
+        }
+        self.quiet_hours.as_ref().and_then(|q| q.quiet_until(now))
+    }
+}
+
+/// Sends the notification; returns a short status suitable for `ScheduleInfo::last_delivery`.
+pub fn deliver(prefs: &NotificationPrefs, notification: &Notification) -> Result<String, String> {
+    let client = golem_wasi_http::Client::new();
+
+    let (channel, request) = match &prefs.delivery {
+        Delivery::StoreOnly => return Ok("stored".to_string()),
+        Delivery::Webhook(target) => {
+            let mut request = client.post(&target.url).json(&serde_json::json!({
+                "schedule_id": notification.schedule_id,
+                "title": notification.title,
+                "ran_at": notification.ran_at,
+                "report": notification.report,
+            }));
+            if let Some(auth) = &target.authorization {
+                request = request.header("Authorization", auth);
+            }
+            ("webhook", request)
+        }
+        Delivery::Slack(target) => {
+            let mut text = format!("*{}*\n{}", notification.title, notification.report);
+            if text.chars().count() > SLACK_MAX_CHARS {
+                text = truncate::prefix(&text, SLACK_MAX_CHARS).to_string();
+                text.push_str(&format!(
+                    "…\n_(truncated; full report in schedule {})_",
+                    notification.schedule_id
+                ));
+            }
+            ("slack", client.post(&target.webhook_url).json(&serde_json::json!({ "text": text })))
+        }
+        Delivery::Email(target) => {
+            let endpoint = email_endpoint()
+                .ok_or_else(|| "NOTIFY_EMAIL_ENDPOINT is not configured".to_string())?;
+            let subject = match &target.subject_prefix {
+                Some(prefix) => format!("{} {}", prefix.trim(), notification.title),
+                None => notification.title.clone(),
+            };
+            let mut request = client.post(&endpoint).json(&serde_json::json!({
+                "to": target.to,
+                "subject": subject,
+                "text": notification.report,
+            }));
+            if let Ok(key) = std::env::var("NOTIFY_EMAIL_API_KEY") {
+                if !key.trim().is_empty() {
+                    request = request.header("Authorization", format!("Bearer {}", key.trim()));
+                }
+            }
+            ("email", request)
+        }
+    };
+
+    match request.send() {
+        Ok(response) if response.status().is_success() => Ok(format!("delivered via {channel}")),
+        Ok(response) => Err(format!("{channel} delivery failed with HTTP {}", response.status())),
+        Err(err) => Err(format!("{channel} delivery failed: {err}")),
+    }
+}
+
+fn email_endpoint() -> Option<String> {
+    std::env::var("NOTIFY_EMAIL_ENDPOINT")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// `url` with everything after its host replaced by `[redacted]`.
+fn redact_path(url: &str) -> String {
+    let url = url.trim();
+    let host_start = url.find("://").map_or(0, |i| i + 3);
+    let host_end = url[host_start..].find(['/', '?', '#']).map_or(url.len(), |i| host_start + i);
+    format!("{}/{REDACTED}", &url[..host_end])
+}
+
+fn validate_url(url: &str) -> Result<(), String> {
+    let url = url.trim();
+    if url.starts_with("https://") || url.starts_with("http://") {
+        Ok(())
+    } else {
+        Err(format!("Delivery URL must be http(s): {url:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 3600;
+    /// 2026-10-16 00:00 UTC.
+    const MIDNIGHT: u64 = 1_792_108_800;
+
+    fn quiet(start_hour: u32, end_hour: u32, utc_offset_minutes: i32) -> QuietHours {
+        QuietHours {
+            start_hour,
+            end_hour,
+            utc_offset_minutes,
+        }
+    }
+
+    fn webhook(authorization: Option<&str>) -> NotificationPrefs {
+        NotificationPrefs {
+            delivery: Delivery::Webhook(WebhookTarget {
+                url: "https://example.com/hook".to_string(),
+                authorization: authorization.map(str::to_string),
+            }),
+            quiet_hours: None,
+        }
+    }
+
+    fn slack(webhook_url: &str) -> NotificationPrefs {
+        NotificationPrefs {
+            delivery: Delivery::Slack(SlackTarget {
+                webhook_url: webhook_url.to_string(),
+            }),
+            quiet_hours: None,
+        }
+    }
+
+    #[test]
+    fn quiet_window_within_a_day() {
+        let window = quiet(9, 17, 0);
+        assert_eq!(window.quiet_until(MIDNIGHT + 8 * HOUR), None);
+        assert_eq!(window.quiet_until(MIDNIGHT + 9 * HOUR), Some(MIDNIGHT + 17 * HOUR));
+        assert_eq!(window.quiet_until(MIDNIGHT + 16 * HOUR + 59 * 60), Some(MIDNIGHT + 17 * HOUR));
+        assert_eq!(window.quiet_until(MIDNIGHT + 17 * HOUR), None);
+    }
+
+    #[test]
+    fn quiet_window_wrapping_midnight() {
+        let window = quiet(22, 7, 0);
+        assert_eq!(window.quiet_until(MIDNIGHT + 21 * HOUR), None);
+        // Late evening waits for the next morning.
+        assert_eq!(window.quiet_until(MIDNIGHT + 23 * HOUR), Some(MIDNIGHT + 31 * HOUR));
+        // Small hours wait for the same morning.
+        assert_eq!(window.quiet_until(MIDNIGHT + 3 * HOUR), Some(MIDNIGHT + 7 * HOUR));
+        assert_eq!(window.quiet_until(MIDNIGHT + 7 * HOUR), None);
+        assert_eq!(window.quiet_until(MIDNIGHT + 12 * HOUR), None);
+    }
+
+    #[test]
+    fn quiet_window_in_local_time() {
+        // 22:00-07:00 at UTC+2 is 20:00-05:00 UTC.
+        let east = quiet(22, 7, 120);
+        assert_eq!(east.quiet_until(MIDNIGHT + 19 * HOUR), None);
+        assert_eq!(east.quiet_until(MIDNIGHT + 21 * HOUR), Some(MIDNIGHT + 29 * HOUR));
+        assert_eq!(east.quiet_until(MIDNIGHT + 6 * HOUR), None);
+        // 22:00-07:00 at UTC-5 is 03:00-12:00 UTC.
+        let west = quiet(22, 7, -300);
+        assert_eq!(west.quiet_until(MIDNIGHT + 2 * HOUR), None);
+        assert_eq!(west.quiet_until(MIDNIGHT + 3 * HOUR), Some(MIDNIGHT + 12 * HOUR));
+        assert_eq!(west.quiet_until(MIDNIGHT + 11 * HOUR), Some(MIDNIGHT + 12 * HOUR));
+    }
+
+    #[test]
+    fn an_empty_quiet_window_never_defers() {
+        assert_eq!(quiet(5, 5, 0).quiet_until(MIDNIGHT + 5 * HOUR), None);
+    }
+
+    #[test]
+    fn validates_quiet_hours() {
+        assert!(quiet(22, 7, 840).validate().is_ok());
+        assert!(quiet(24, 7, 0).validate().is_err());
+        assert!(quiet(22, 7, -841).validate().is_err());
+    }
+
+    #[test]
+    fn store_only_is_never_deferred() {
+        let prefs = NotificationPrefs {
+            delivery: Delivery::StoreOnly,
+            quiet_hours: Some(quiet(0, 23, 0)),
+        };
+        assert_eq!(prefs.deferred_until(MIDNIGHT + HOUR), None);
+
+        let prefs = NotificationPrefs {
+            quiet_hours: Some(quiet(0, 23, 0)),
+            ..webhook(None)
+        };
+        assert_eq!(prefs.deferred_until(MIDNIGHT + HOUR), Some(MIDNIGHT + 23 * HOUR));
+    }
+
+    #[test]
+    fn outbox_releases_notifications_once_due() {
+        let notification = |id: &str| Notification {
+            schedule_id: id.to_string(),
+            title: "t".to_string(),
+            ran_at: MIDNIGHT,
+            report: "r".to_string(),
+        };
+        let mut outbox = Outbox::default();
+        outbox.defer(MIDNIGHT + 7 * HOUR, webhook(None), notification("a"));
+        outbox.defer(MIDNIGHT + 5 * HOUR, webhook(None), notification("b"));
+        assert_eq!(outbox.next_delivery_at(), Some(MIDNIGHT + 5 * HOUR));
+
+        assert!(outbox.take_ready(MIDNIGHT + 4 * HOUR).is_empty());
+        let ready = outbox.take_ready(MIDNIGHT + 5 * HOUR);
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].1.schedule_id, "b");
+        assert_eq!((outbox.pending(), outbox.next_delivery_at()), (1, Some(MIDNIGHT + 7 * HOUR)));
+
+        assert_eq!(outbox.take_ready(MIDNIGHT + 8 * HOUR).len(), 1);
+        assert_eq!((outbox.pending(), outbox.next_delivery_at()), (0, None));
+    }
+
+    #[test]
+    fn redacts_delivery_credentials() {
+        let Delivery::Webhook(target) = webhook(Some("Bearer secret")).redacted().delivery else {
+            panic!("not a webhook");
+        };
+        assert_eq!(target.authorization.as_deref(), Some(REDACTED));
+        assert_eq!(target.url, "https://example.com/hook");
+        let Delivery::Webhook(target) = webhook(None).redacted().delivery else {
+            panic!("not a webhook");
+        };
+        assert_eq!(target.authorization, None);
+
+        let Delivery::Slack(target) = slack("https://hooks.slack.com/services/T0/B0/XyZ").redacted().delivery else {
+            panic!("not slack");
+        };
+        assert_eq!(target.webhook_url, "https://hooks.slack.com/[redacted]");
+    }
+
+    #[test]
+    fn round_tripped_redactions_keep_the_stored_credentials() {
+        let stored = webhook(Some("Bearer secret"));
+        let Delivery::Webhook(target) = stored.redacted().keep_credentials(&stored).delivery else {
+            panic!("not a webhook");
+        };
+        assert_eq!(target.authorization.as_deref(), Some("Bearer secret"));
+        let Delivery::Webhook(target) = webhook(Some("Bearer new")).keep_credentials(&stored).delivery else {
+            panic!("not a webhook");
+        };
+        assert_eq!(target.authorization.as_deref(), Some("Bearer new"));
+
+        let stored = slack("https://hooks.slack.com/services/T0/B0/XyZ");
+        let Delivery::Slack(target) = stored.redacted().keep_credentials(&stored).delivery else {
+            panic!("not slack");
+        };
+        assert_eq!(target.webhook_url, "https://hooks.slack.com/services/T0/B0/XyZ");
+    }
+}
@@ -0,0 +1,147 @@
+//! Readiness checks for Ollama deployments.
+//!
+//! The Ollama LLM module fails opaquely when the configured model hasn't been pulled, so when
+//! `LLM_PROVIDER=ollama` (or `GOLEM_OLLAMA_BASE_URL` is set) the agent asks the server's
+//! `/api/tags` endpoint which models it has and reports a typed `ModelUnavailable` error.
+
+use golem_rust::Schema;
+
+use crate::config;
+use crate::sanitize;
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+/// The context size Ollama serves a model with when neither the request nor the Modelfile
+/// sets `num_ctx`.
+const DEFAULT_NUM_CTX: u32 = 4_096;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ModelUnavailable {
+    pub model: String,
+    /// The Ollama server that was queried.
+    pub endpoint: String,
+    /// Models the server reported; empty if it could not be reached.
+    pub available_models: Vec<String>,
+    /// What to do about it, e.g. the `ollama pull` command to run.
+    pub hint: String,
+}
+
+#[derive(serde::Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<TagsModel>,
+}
+
+#[derive(serde::Deserialize)]
+struct TagsModel {
+    name: String,
+}
+
+pub fn is_configured() -> bool {
+    let provider = std::env::var("LLM_PROVIDER").unwrap_or_default();
+    provider.trim().eq_ignore_ascii_case("ollama") || configured_base_url().is_some()
+}
+
+pub fn base_url() -> String {
+    configured_base_url().unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+}
+
+/// Verifies the Ollama server is reachable and has `model` pulled.
+pub fn check_model(model: &str) -> Result<(), ModelUnavailable> {
+    let base_url = base_url();
+    // Usually an internal host, which callers aren't told.
+    let endpoint = sanitize::sanitize(&base_url);
+    let unreachable = |detail: String| ModelUnavailable {
+        model: model.to_string(),
+        endpoint: endpoint.clone(),
+        available_models: Vec::new(),
+        hint: format!(
+            "Ollama is not reachable at {endpoint} ({}). Start it with `ollama serve` or point GOLEM_OLLAMA_BASE_URL at the right server.",
+            sanitize::outward("ollama", &detail)
+        ),
+    };
+
+    let response = golem_wasi_http::Client::new()
+        .get(format!("{base_url}/api/tags"))
+        .send()
+        .map_err(|err| unreachable(err.to_string()))?;
+    if !response.status().is_success() {
+        return Err(unreachable(format!("HTTP {}", response.status())));
+    }
+    let tags: TagsResponse = response
+        .json()
+        .map_err(|err| unreachable(format!("unexpected /api/tags response: {err}")))?;
+
+    let available_models: Vec<String> = tags.models.into_iter().map(|m| m.name).collect();
+    if available_models.iter().any(|name| same_model(name, model)) {
+        return Ok(());
+    }
+
+    Err(ModelUnavailable {
+        model: model.to_string(),
+        hint: format!("Model {model:?} is not pulled on {endpoint}. Run `ollama pull {model}` on that host, or set AGGO_LLM_MODEL to one of the available models."),
+        endpoint,
+        available_models,
+    })
+}
+
+/// The context size Ollama will run `model` with: an explicit `num_ctx` parameter in its
+/// Modelfile, otherwise the server's default (`OLLAMA_CONTEXT_LENGTH` if the agent is given the
+/// value the server runs with, else 4096). The architecture's `*.context_length` is only an
+/// upper bound: the agent doesn't send `num_ctx`, so Ollama never serves the full length on its
+/// own and would silently cut a prompt sized for it.
+pub fn context_window(model: &str) -> Option<u32> {
+    let response = golem_wasi_http::Client::new()
+        .post(format!("{}/api/show", base_url()))
+        .json(&serde_json::json!({ "model": model }))
+        .send()
+        .ok()
+        .filter(|response| response.status().is_success())?;
+    let show: serde_json::Value = response.json().ok()?;
+
+    let num_ctx = show["parameters"].as_str().and_then(|parameters| {
+        parameters.lines().find_map(|line| {
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (Some("num_ctx"), Some(value)) => value.parse::<u32>().ok(),
+                _ => None,
+            }
+        })
+    });
+
+    let context_length = show["model_info"].as_object().and_then(|info| {
+        info.iter().find_map(|(key, value)| {
+            if key.ends_with(".context_length") {
+                value.as_u64().and_then(|n| u32::try_from(n).ok())
+            } else {
+                None
+            }
+        })
+    });
+    let served = num_ctx.unwrap_or_else(default_num_ctx);
+    Some(context_length.map_or(served, |length| served.min(length)))
+}
+
+fn default_num_ctx() -> u32 {
+    config::env_parse::<u32>("OLLAMA_CONTEXT_LENGTH")
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_NUM_CTX)
+}
+
+/// Ollama lists models with their tag; an untagged name means `:latest`.
+fn same_model(listed: &str, wanted: &str) -> bool {
+    let with_tag = |name: &str| {
+        if name.contains(':') {
+            name.to_string()
+        } else {
+            format!("{name}:latest")
+        }
+    };
+    with_tag(listed.trim()) == with_tag(wanted.trim())
+}
+
+fn configured_base_url() -> Option<String> {
+    std::env::var("GOLEM_OLLAMA_BASE_URL")
+        .ok()
+        .map(|v| v.trim().trim_end_matches('/').to_string())
+        .filter(|v| !v.is_empty())
+}
@@ -0,0 +1,234 @@
+use golem_rust::Schema;
+
+use crate::charts::ChartFormat;
+use crate::content_fetcher;
+use crate::report::ReportField;
+
+const DEFAULT_DEPTH: u32 = 3;
+const MAX_DEPTH: u32 = 5;
+const DEFAULT_MAX_RESULTS: u32 = 10;
+const MAX_MAX_RESULTS: u32 = 20;
+pub const DEFAULT_LANGUAGE: &str = "en";
+const MAX_NEWS_DAYS: u32 = 365;
+
+/// Per-call knobs for `research_with_options`. Every field is optional so HTTP callers
+/// only need to send what they want to change; `research(topic)` uses the defaults.
+#[derive(Clone, Debug, Default, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ResearchOptions {
+    /// Terms that steer retrieval away from unrelated senses of the topic. They are sent to
+    /// the search provider as `-term` where supported and always applied as a post-filter.
+    pub exclude_terms: Option<Vec<String>>,
+    /// Number of search result pages to retrieve (1-5, default 3).
+    pub depth: Option<u32>,
+    /// Free-form writing style for the report, e.g. "executive brief" or "technical deep-dive".
+    pub style: Option<String>,
+    /// Section headings the report must contain, in order.
+    pub sections: Option<Vec<String>>,
+    /// Workspace the report is stored under (default: "default").
+    pub workspace: Option<String>,
+    /// Ask the provider for page text (where supported) and use it instead of short snippets.
+    pub full_text: Option<bool>,
+    /// Extractively compress page text to its most relevant sentences before prompting
+    /// (default: on in full-text mode).
+    pub compress: Option<bool>,
+    /// Sampling seed passed to the LLM provider for reproducible output (OpenAI, Ollama);
+    /// recorded in the report metadata.
+    pub seed: Option<u64>,
+    /// LLM model for this request; must be the default model or listed in `LLM_MODEL_ALLOWLIST`.
+    pub model: Option<String>,
+    /// Web search provider for this request (e.g. "serper"), used alone in place of the
+    /// `WEB_SEARCH_PROVIDERS` chain; must be in that chain or listed in `SEARCH_PROVIDER_ALLOWLIST`.
+    pub search_provider: Option<String>,
+    /// Search connectors (see `list_search_connectors`) to query alongside web search, by
+    /// name. Default: every connector enabled by default; an empty list turns them off.
+    pub connectors: Option<Vec<String>>,
+    /// Web search results per page (1-20, default 10).
+    pub max_results: Option<u32>,
+    /// ISO 639-1 code such as "de": results in that language are preferred and the report is
+    /// written in it (default "en").
+    pub language: Option<String>,
+    /// Only use web results published within this period.
+    pub time_range: Option<TimeRange>,
+    /// Research recent news: Brave and Serper search their news endpoints for results from the
+    /// last N days (1-365), and other providers get the smallest `time_range` covering them.
+    pub news_days: Option<u32>,
+    /// `low` requests are rejected with `overloaded` while the worker is over its load-shedding
+    /// thresholds (default `normal`, never shed).
+    pub priority: Option<RequestPriority>,
+    /// Ground the report in the topic's Wikipedia article: its summary is fetched before web
+    /// search and sent as the first source (default off).
+    pub use_wikipedia: Option<bool>,
+    /// Download this many of the top web results and add their readable page text to the
+    /// snippets (0-10, default `CONTENT_FETCH_PAGES` or 3; 0 turns it off).
+    pub fetch_pages: Option<u32>,
+    /// Draw the numeric series the research found (a metric over years, a comparison across
+    /// items) into a "Charts" section, as Mermaid or text charts. Costs one more LLM call
+    /// (default off).
+    pub charts: Option<ChartFormat>,
+    /// Parts of the `ResearchReport` to return, e.g. `[summary, sources]`; the others come back
+    /// empty, to keep payloads small. Doesn't change what is researched or stored (default: all).
+    pub fields: Option<Vec<ReportField>>,
+    /// Research afresh: skip the report, search result and LLM response caches. What this run
+    /// finds still replaces their entries (default off).
+    pub bypass_cache: Option<bool>,
+    /// Search a topic written in another language both as written, preferring results in its
+    /// language, and as an English translation made by the model (default on).
+    pub multilingual: Option<bool>,
+    /// Have the model rewrite the topic into 2-4 targeted search queries and search them all,
+    /// merging the results. Costs one more LLM call (default off).
+    pub expand_queries: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Schema, serde::Serialize, serde::Deserialize)]
+pub enum TimeRange {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Schema, serde::Serialize, serde::Deserialize)]
+pub enum RequestPriority {
+    Normal,
+    Low,
+}
+
+impl ResearchOptions {
+    pub fn exclude_terms(&self) -> Vec<String> {
+        self.exclude_terms
+            .iter()
+            .flatten()
+            .map(|term| term.trim().to_string())
+            .filter(|term| !term.is_empty())
+            .collect()
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth.unwrap_or(DEFAULT_DEPTH).clamp(1, MAX_DEPTH)
+    }
+
+    pub fn news_days(&self) -> Option<u32> {
+        self.news_days
+    }
+
+    /// `time_range`, or in news mode the smallest range covering `news_days`.
+    pub fn time_range(&self) -> Option<TimeRange> {
+        self.time_range.or_else(|| {
+            self.news_days.map(|days| match days {
+                0..=1 => TimeRange::Day,
+                2..=7 => TimeRange::Week,
+                8..=31 => TimeRange::Month,
+                _ => TimeRange::Year,
+            })
+        })
+    }
+
+    pub fn priority(&self) -> RequestPriority {
+        self.priority.unwrap_or(RequestPriority::Normal)
+    }
+
+    pub fn use_wikipedia(&self) -> bool {
+        self.use_wikipedia.unwrap_or(false)
+    }
+
+    pub fn full_text(&self) -> bool {
+        self.full_text.unwrap_or(false)
+    }
+
+    pub fn expand_queries(&self) -> bool {
+        self.expand_queries.unwrap_or(false)
+    }
+
+    pub fn multilingual(&self) -> bool {
+        self.multilingual.unwrap_or(true)
+    }
+
+    pub fn bypass_cache(&self) -> bool {
+        self.bypass_cache.unwrap_or(false)
+    }
+
+    pub fn compress(&self) -> bool {
+        self.full_text() && self.compress.unwrap_or(true)
+    }
+
+    pub fn max_results(&self) -> u32 {
+        self.max_results.unwrap_or(DEFAULT_MAX_RESULTS).clamp(1, MAX_MAX_RESULTS)
+    }
+
+    /// The lowercased language code, `en` when unset.
+    pub fn language(&self) -> String {
+        self.language
+            .as_deref()
+            .map(|l| l.trim().to_ascii_lowercase())
+            .filter(|l| !l.is_empty())
+            .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string())
+    }
+
+    /// Rejects values that would otherwise be passed to the provider as garbage.
+    pub fn validate(&self) -> Result<(), String> {
+        let language = self.language();
+        if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_lowercase()) {
+            return Err(format!("Language must be an ISO 639-1 code such as \"de\", got {language:?}"));
+        }
+        if let Some(pages) = self.fetch_pages {
+            if pages > content_fetcher::MAX_PAGES {
+                return Err(format!(
+                    "Fetch pages must be at most {}, got {pages}",
+                    content_fetcher::MAX_PAGES
+                ));
+            }
+        }
+        if let Some(days) = self.news_days {
+            if !(1..=MAX_NEWS_DAYS).contains(&days) {
+                return Err(format!("News days must be between 1 and {MAX_NEWS_DAYS}, got {days}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Extra prompt instructions derived from `style`, `sections` and a non-English `language`;
+    /// empty when none is set.
+    pub fn format_instructions(&self) -> String {
+        let mut instructions = String::new();
+
+        let language = self.language();
+        if language != DEFAULT_LANGUAGE {
+            instructions.push_str(&format!(
+                "Write the overview in the language with ISO 639-1 code \"{language}\".\n"
+            ));
+        }
+
+        if let Some(days) = self.news_days {
+            instructions.push_str(&format!(
+                "This is a news report on the last {days} day(s): lead with the most recent developments, give the date of each event where the sources state it, and don't present older background as news.\n"
+            ));
+        }
+
+        if let Some(style) = self.style() {
+            instructions.push_str(&format!("Write the overview in the style of: {style}.\n"));
+        }
+
+        let sections = self.sections();
+        if !sections.is_empty() {
+            instructions.push_str("Structure the overview with exactly these section headings, in this order:\n");
+            for section in sections {
+                instructions.push_str(&format!("- {section}\n"));
+            }
+        }
+
+        instructions
+    }
+
+    pub fn style(&self) -> Option<&str> {
+        self.style.as_deref().map(str::trim).filter(|s| !s.is_empty())
+    }
+
+    pub fn sections(&self) -> Vec<String> {
+        self.sections
+            .iter()
+            .flatten()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+}
@@ -0,0 +1,222 @@
+//! Structural outline of an HTML page (headings, links, tables and figures), so callers can
+//! navigate a long document without transferring its full text.
+
+use golem_rust::Schema;
+
+use crate::archive::ArchivedSnapshot;
+use crate::fetch::{self, FetchedPage};
+
+const MAX_HEADINGS: usize = 200;
+const MAX_LINKS: usize = 200;
+const MAX_TABLES: usize = 50;
+const MAX_FIGURES: usize = 50;
+const MAX_LABEL_CHARS: usize = 200;
+
+/// What `extract_url` returns besides the page's identity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Schema, serde::Serialize, serde::Deserialize)]
+pub enum ExtractMode {
+    /// Readable text only.
+    Text,
+    /// Structural outline only, without the text.
+    Outline,
+    /// Both.
+    TextAndOutline,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ExtractedPage {
+    pub url: String,
+    pub title: Option<String>,
+    /// Readable text; `None` in outline mode.
+    pub text: Option<String>,
+    /// `None` in text mode and for non-HTML pages.
+    pub outline: Option<PageOutline>,
+    /// The archived copy that was read because the live page could not be fetched.
+    pub snapshot: Option<ArchivedSnapshot>,
+}
+
+#[derive(Clone, Debug, Default, Schema, serde::Serialize, serde::Deserialize)]
+pub struct PageOutline {
+    pub headings: Vec<Heading>,
+    pub links: Vec<PageLink>,
+    pub tables: Vec<TableInfo>,
+    pub figures: Vec<FigureInfo>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct Heading {
+    /// 1-6, from `<h1>` to `<h6>`.
+    pub level: u8,
+    pub text: String,
+    /// The heading's `id`, usable as a `#fragment` link.
+    pub anchor: Option<String>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct PageLink {
+    pub text: String,
+    /// Absolute URL.
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct TableInfo {
+    pub caption: Option<String>,
+    /// Header cell labels, when the table has them.
+    pub headers: Vec<String>,
+    pub rows: u32,
+    pub columns: u32,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct FigureInfo {
+    pub caption: Option<String>,
+    /// Absolute image URL.
+    pub image_url: Option<String>,
+}
+
+/// A start tag found in the document.
+struct Tag<'a> {
+    name: String,
+    attrs: &'a str,
+    /// Byte offset just past the tag's `>`.
+    end: usize,
+}
+
+impl ExtractedPage {
+    pub fn from_page(page: FetchedPage, mode: ExtractMode) -> Self {
+        let outline = match (mode, page.html.as_deref()) {
+            (ExtractMode::Outline | ExtractMode::TextAndOutline, Some(html)) => Some(outline(html, &page.url)),
+            _ => None,
+        };
+        ExtractedPage {
+            text: (mode != ExtractMode::Outline).then_some(page.text),
+            url: page.url,
+            title: page.title,
+            outline,
+            snapshot: page.snapshot,
+        }
+    }
+}
+
+/// Outline of the page's readable region (`<article>`, `<main>` or `<body>`).
+pub fn outline(html: &str, base_url: &str) -> PageOutline {
+    let region = fetch::readable_region(html);
+    let tags = start_tags(region);
+    let mut outline = PageOutline::default();
+
+    for tag in &tags {
+        match tag.name.as_str() {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" if outline.headings.len() < MAX_HEADINGS => {
+                let text = label(inner(region, tag));
+                if !text.is_empty() {
+                    outline.headings.push(Heading {
+                        level: tag.name[1..].parse().unwrap_or(1),
+                        text,
+                        anchor: fetch::attribute(tag.attrs, "id"),
+                    });
+                }
+            }
+            "a" if outline.links.len() < MAX_LINKS => {
+                let Some(url) = fetch::attribute(tag.attrs, "href").and_then(|href| fetch::resolve_url(base_url, &href))
+                else {
+                    continue;
+                };
+                if outline.links.iter().any(|l| l.url == url) {
+                    continue;
+                }
+                let text = label(inner(region, tag));
+                let text = if text.is_empty() {
+                    fetch::attribute(tag.attrs, "title").unwrap_or_else(|| url.clone())
+                } else {
+                    text
+                };
+                outline.links.push(PageLink { text, url });
+            }
+            "table" if outline.tables.len() < MAX_TABLES => {
+                outline.tables.push(table_info(inner(region, tag)));
+            }
+            "figure" if outline.figures.len() < MAX_FIGURES => {
+                let body = inner(region, tag);
+                let caption = first_element(body, "figcaption").map(label).filter(|c| !c.is_empty());
+                let image_url = start_tags(body)
+                    .iter()
+                    .find(|t| t.name == "img")
+                    .and_then(|img| fetch::attribute(img.attrs, "src"))
+                    .and_then(|src| fetch::resolve_url(base_url, &src));
+                outline.figures.push(FigureInfo { caption, image_url });
+            }
+            _ => {}
+        }
+    }
+    outline
+}
+
+fn table_info(table: &str) -> TableInfo {
+    let rows: Vec<&str> = elements(table, "tr");
+    let cells = |row: &str| elements(row, "td").len() + elements(row, "th").len();
+    let headers = rows
+        .first()
+        .map(|row| elements(row, "th").into_iter().map(label).collect())
+        .unwrap_or_default();
+    TableInfo {
+        caption: first_element(table, "caption").map(label).filter(|c| !c.is_empty()),
+        headers,
+        rows: rows.len() as u32,
+        columns: rows.iter().map(|row| cells(row)).max().unwrap_or(0) as u32,
+    }
+}
+
+/// Every start tag, lowercased name with its raw attribute text.
+fn start_tags(html: &str) -> Vec<Tag<'_>> {
+    let mut tags = Vec::new();
+    let mut from = 0;
+    while let Some(i) = html[from..].find('<') {
+        let start = from + i + 1;
+        let Some(len) = html[start..].find('>') else {
+            break;
+        };
+        let body = &html[start..start + len];
+        from = start + len + 1;
+        if !body.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+        let name_len = body
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(body.len());
+        tags.push(Tag {
+            name: body[..name_len].to_ascii_lowercase(),
+            attrs: &body[name_len..],
+            end: from,
+        });
+    }
+    tags
+}
+
+/// Content between a start tag and its closing tag (or the end of the document).
+fn inner<'a>(html: &'a str, tag: &Tag) -> &'a str {
+    let rest = &html[tag.end..];
+    let end = fetch::find_close_tag(rest, &tag.name).unwrap_or(rest.len());
+    &rest[..end]
+}
+
+/// Inner content of every `<name>` element in `html`.
+fn elements<'a>(html: &'a str, name: &str) -> Vec<&'a str> {
+    start_tags(html)
+        .iter()
+        .filter(|t| t.name == name)
+        .map(|t| inner(html, t))
+        .collect()
+}
+
+fn first_element<'a>(html: &'a str, name: &str) -> Option<&'a str> {
+    start_tags(html)
+        .iter()
+        .find(|t| t.name == name)
+        .map(|t| inner(html, t))
+}
+
+fn label(html: &str) -> String {
+    let text = fetch::strip_tags(html).split_whitespace().collect::<Vec<_>>().join(" ");
+    crate::truncate::chars(&text, MAX_LABEL_CHARS)
+}
@@ -0,0 +1,91 @@
+//! Pages fetched by the content fetcher, kept with a hash of their body and the server's
+//! validators so a repeat fetch can be skipped or answered without re-extracting the page.
+//!
+//! A page checked within `PAGE_CACHE_FRESH_SECONDS` (default 3600) is served from the cache
+//! without a request. After that it is fetched again as a conditional GET (`If-None-Match` /
+//! `If-Modified-Since` where the server sent an ETag or Last-Modified); a 304, or a body whose
+//! hash matches the cached one, serves the cached text. `PAGE_CACHE_MAX_PAGES` (default 200,
+//! least recently checked out first; 0 turns the cache off) bounds it. The cache is part of the
+//! worker snapshot, so it survives restarts.
+
+use std::cell::RefCell;
+
+use crate::config;
+use crate::fetch::Validators;
+
+const DEFAULT_FRESH_SECONDS: u64 = 3600;
+const DEFAULT_MAX_PAGES: usize = 200;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CachedPage {
+    pub url: String,
+    /// `differential::digest` of the body as downloaded.
+    pub content_hash: String,
+    pub validators: Validators,
+    /// The page's readable text as the content fetcher extracted it.
+    pub text: String,
+    /// When the body last changed (unix seconds).
+    pub changed_at: u64,
+    /// When the page was last fetched or confirmed unchanged.
+    pub checked_at: u64,
+}
+
+pub struct CachePolicy {
+    pub fresh_seconds: u64,
+    max_pages: usize,
+}
+
+impl CachePolicy {
+    pub fn from_env() -> Self {
+        Self {
+            fresh_seconds: config::env_parse::<u64>("PAGE_CACHE_FRESH_SECONDS").unwrap_or(DEFAULT_FRESH_SECONDS),
+            max_pages: config::env_parse::<u64>("PAGE_CACHE_MAX_PAGES").map_or(DEFAULT_MAX_PAGES, |n| n as usize),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.max_pages > 0
+    }
+}
+
+/// Written from the `&self` research pipeline, hence the `RefCell`.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct PageCache {
+    pages: RefCell<Vec<CachedPage>>,
+}
+
+impl PageCache {
+    pub fn get(&self, url: &str) -> Option<CachedPage> {
+        self.pages.borrow().iter().find(|p| p.url == url).cloned()
+    }
+
+    /// Marks a cached page as confirmed unchanged at `now`, with the validators of the
+    /// response that confirmed it when it sent any.
+    pub fn confirm(&self, url: &str, validators: Option<Validators>, now: u64) {
+        if let Some(page) = self.pages.borrow_mut().iter_mut().find(|p| p.url == url) {
+            page.checked_at = now;
+            if let Some(validators) = validators {
+                page.validators = validators;
+            }
+        }
+    }
+
+    /// Keeps `page` in place of any earlier copy of its URL.
+    pub fn store(&self, page: CachedPage, policy: &CachePolicy) {
+        if !policy.enabled() {
+            return;
+        }
+        let mut pages = self.pages.borrow_mut();
+        pages.retain(|p| p.url != page.url);
+        pages.push(page);
+        if pages.len() > policy.max_pages {
+            pages.sort_by_key(|p| std::cmp::Reverse(p.checked_at));
+            pages.truncate(policy.max_pages);
+        }
+    }
+
+    /// Empties the cache and returns how many pages it had.
+    pub fn clear(&self) -> u32 {
+        self.pages.take().len() as u32
+    }
+}
@@ -0,0 +1,97 @@
+//! Compression of large export payloads. With `PAYLOAD_COMPRESS_THRESHOLD_BYTES` set, an
+//! `export_history` result or `export_data` attachment larger than that is gzipped and
+//! base64-encoded, so a big workspace history or table dump doesn't blow past invocation size
+//! limits. Smaller payloads, and all payloads when the variable is unset or 0, are returned as
+//! plain text.
+//!
+//! `export_history` marks a compressed result with the `gzip+base64:` prefix, which
+//! `import_history` recognises; attachments say so in their `encoding` field instead.
+
+use std::io::{Read, Write};
+
+use crate::config;
+
+/// The `encoding` of compressed attachments, and the prefix of compressed history exports.
+pub const GZIP_BASE64: &str = "gzip+base64";
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Size in bytes above which payloads are compressed; `None` when compression is off.
+pub fn threshold() -> Option<usize> {
+    config::env_parse::<usize>("PAYLOAD_COMPRESS_THRESHOLD_BYTES").filter(|bytes| *bytes > 0)
+}
+
+/// `text` gzipped and base64-encoded if it is over the threshold and that makes it smaller.
+pub fn compress(text: &str) -> Option<String> {
+    if text.len() <= threshold()? {
+        return None;
+    }
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let gzipped = encoder.write_all(text.as_bytes()).and_then(|_| encoder.finish());
+    match gzipped {
+        Ok(bytes) => {
+            let encoded = encode_base64(&bytes);
+            (encoded.len() < text.len()).then_some(encoded)
+        }
+        Err(err) => {
+            log::warn!("Could not compress a {} byte payload, returning it as is: {err}", text.len());
+            None
+        }
+    }
+}
+
+/// `text` with the `gzip+base64:` prefix if it was compressed, as returned by `export_history`.
+pub fn compress_marked(text: String) -> String {
+    match compress(&text) {
+        Some(compressed) => format!("{GZIP_BASE64}:{compressed}"),
+        None => text,
+    }
+}
+
+/// Undoes `compress_marked`; unmarked text is returned unchanged.
+pub fn decompress_marked(data: &str) -> Result<String, String> {
+    let Some(encoded) = data.trim().strip_prefix(GZIP_BASE64).and_then(|rest| rest.strip_prefix(':')) else {
+        return Ok(data.to_string());
+    };
+    let bytes = decode_base64(encoded).ok_or_else(|| "The compressed payload is not valid base64".to_string())?;
+    let mut text = String::new();
+    flate2::read::MultiGzDecoder::new(&bytes[..])
+        .read_to_string(&mut text)
+        .map_err(|err| format!("The compressed payload could not be decompressed: {err}"))?;
+    Ok(text)
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .take_while(|b| *b != b'=')
+        .map(|b| ALPHABET.iter().position(|a| *a == b).map(|p| p as u8))
+        .collect::<Option<_>>()?;
+    if digits.len() % 4 == 1 {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, d)| n | (*d as u32) << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(bytes)
+}
@@ -0,0 +1,488 @@
+//! Text extraction from PDF documents, so papers and reports linked from search results can be
+//! read and quoted like web pages.
+//!
+//! This is a small extractor, not a renderer: it inflates the document's Flate-compressed (or
+//! uncompressed) content streams and reads the strings shown by the text operators, in stream
+//! order. Strings in composite fonts are mapped through the document's `ToUnicode` CMaps; other
+//! strings are read as Latin-1. Lines are rejoined into paragraphs. Scanned PDFs (images only)
+//! and encrypted ones yield no text.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Inflated bytes kept per stream, so a malformed or hostile stream can't exhaust memory.
+const MAX_STREAM_BYTES: u64 = 4 * 1024 * 1024;
+/// A `TJ` adjustment this far left (thousandths of an em) separates words.
+const WORD_GAP: f64 = -200.0;
+/// Filters other than Flate, whose streams are skipped (images and rarely used encodings).
+const UNSUPPORTED_FILTERS: [&[u8]; 7] = [
+    b"/DCTDecode",
+    b"/JPXDecode",
+    b"/CCITTFaxDecode",
+    b"/JBIG2Decode",
+    b"/LZWDecode",
+    b"/ASCII85Decode",
+    b"/RunLengthDecode",
+];
+
+pub struct PdfText {
+    /// From the document information dictionary, when it isn't compressed away.
+    pub title: Option<String>,
+    pub text: String,
+}
+
+/// By content type, or by the `%PDF-` signature for servers that send a generic type.
+pub fn is_pdf(content_type: &str, body: &[u8]) -> bool {
+    content_type.starts_with("application/pdf") || body.starts_with(b"%PDF-")
+}
+
+pub fn extract(pdf: &[u8]) -> Result<PdfText, String> {
+    if find(pdf, b"/Encrypt", 0).is_some() {
+        return Err("the PDF is encrypted".to_string());
+    }
+
+    let mut cmap = CharMap::default();
+    let mut contents = Vec::new();
+    for (dict, data) in streams(pdf) {
+        let Some(data) = decode_stream(dict, data) else {
+            continue;
+        };
+        if find(&data, b"begincmap", 0).is_some() {
+            cmap.parse(&data);
+        } else if find(&data, b"BT", 0).is_some() {
+            contents.push(data);
+        }
+    }
+
+    let lines: Vec<String> = contents
+        .iter()
+        .flat_map(|content| show_text(content, &cmap))
+        .collect();
+    let text = paragraphs(&lines);
+    if text.is_empty() {
+        return Err("the PDF has no extractable text (it may be scanned)".to_string());
+    }
+    Ok(PdfText {
+        title: info_title(pdf),
+        text,
+    })
+}
+
+/// Each stream's dictionary (from its `obj` keyword) and raw data.
+fn streams(pdf: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut streams = Vec::new();
+    let mut from = 0;
+    while let Some(at) = find(pdf, b"stream", from) {
+        from = at + b"stream".len();
+        if pdf[..at].ends_with(b"end") {
+            continue;
+        }
+        let mut start = from;
+        if pdf.get(start) == Some(&b'\r') {
+            start += 1;
+        }
+        if pdf.get(start) == Some(&b'\n') {
+            start += 1;
+        }
+        let Some(end) = find(pdf, b"endstream", start) else {
+            break;
+        };
+        let dict_start = rfind(&pdf[..at], b"obj").unwrap_or(at.saturating_sub(512));
+        streams.push((&pdf[dict_start..at], &pdf[start..end]));
+        from = end + b"endstream".len();
+    }
+    streams
+}
+
+/// The stream's data, inflated when Flate-compressed; `None` for images, fonts, metadata and
+/// filters this module doesn't decode.
+fn decode_stream(dict: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    let skipped = [&b"/Image"[..], b"/FontFile", b"/Length1", b"/XRef", b"/Metadata"];
+    if skipped.iter().chain(UNSUPPORTED_FILTERS.iter()).any(|key| find(dict, key, 0).is_some()) {
+        return None;
+    }
+    if find(dict, b"/FlateDecode", 0).is_none() {
+        return Some(data.to_vec());
+    }
+    let mut inflated = Vec::new();
+    let result = flate2::read::ZlibDecoder::new(data)
+        .take(MAX_STREAM_BYTES)
+        .read_to_end(&mut inflated);
+    // A truncated stream (the body is capped) still yields the text before the cut.
+    match result {
+        Ok(_) => Some(inflated),
+        Err(_) if !inflated.is_empty() => Some(inflated),
+        Err(_) => None,
+    }
+}
+
+/// `ToUnicode` mappings from every CMap in the document, by code width.
+#[derive(Default)]
+struct CharMap {
+    codes: HashMap<(usize, u32), String>,
+    /// Width of most mapped codes: 2 for composite fonts.
+    width: usize,
+}
+
+impl CharMap {
+    fn parse(&mut self, data: &[u8]) {
+        let text = String::from_utf8_lossy(data);
+        for section in sections(&text, "beginbfchar", "endbfchar") {
+            let hex = hex_tokens(section);
+            for pair in hex.chunks_exact(2) {
+                self.insert(&pair[0], decode_utf16(&pair[1]));
+            }
+        }
+        for section in sections(&text, "beginbfrange", "endbfrange") {
+            for line in section.lines() {
+                let hex = hex_tokens(line);
+                let [low, high, first, rest @ ..] = hex.as_slice() else {
+                    continue;
+                };
+                let (Some(low_code), Some(high_code)) = (code(low), code(high)) else {
+                    continue;
+                };
+                if line.contains('[') {
+                    // `<low> <high> [<dst> <dst> ...]`: one destination per code.
+                    for (offset, dst) in std::iter::once(first).chain(rest).enumerate() {
+                        let code = low_code + offset as u32;
+                        if code > high_code {
+                            break;
+                        }
+                        self.insert_code(low.len(), code, decode_utf16(dst));
+                    }
+                } else {
+                    // `<low> <high> <dst>`: consecutive destinations from `dst`.
+                    let start = decode_utf16(first);
+                    let Some(base) = start.chars().last() else {
+                        continue;
+                    };
+                    let prefix: String = start.chars().take(start.chars().count() - 1).collect();
+                    for code in low_code..=high_code.min(low_code + 0xFFFF) {
+                        if let Some(c) = char::from_u32(base as u32 + (code - low_code)) {
+                            self.insert_code(low.len(), code, format!("{prefix}{c}"));
+                        }
+                    }
+                }
+            }
+        }
+        let two_byte = self.codes.keys().filter(|(width, _)| *width == 2).count();
+        self.width = if two_byte * 2 >= self.codes.len() { 2 } else { 1 };
+    }
+
+    fn insert(&mut self, src: &[u8], dst: String) {
+        if let Some(code) = code(src) {
+            self.insert_code(src.len(), code, dst);
+        }
+    }
+
+    fn insert_code(&mut self, width: usize, code: u32, dst: String) {
+        self.codes.entry((width, code)).or_insert(dst);
+    }
+
+    /// A shown string as text: through the CMaps for hex strings and strings of control bytes
+    /// (glyph IDs of composite fonts), else as Latin-1.
+    fn decode(&self, bytes: &[u8], hex: bool) -> String {
+        let glyph_ids = hex || bytes.iter().any(|b| *b < 0x20 && !b.is_ascii_whitespace());
+        if glyph_ids && !self.codes.is_empty() {
+            let mapped: String = bytes
+                .chunks(self.width)
+                .filter_map(|chunk| code(chunk).and_then(|c| self.codes.get(&(chunk.len(), c))))
+                .map(String::as_str)
+                .collect();
+            if !mapped.is_empty() {
+                return mapped;
+            }
+        }
+        bytes
+            .iter()
+            .map(|&b| char::from(b))
+            .filter(|c| !c.is_control() || *c == '\n')
+            .collect()
+    }
+}
+
+enum Token {
+    Str(Vec<u8>, bool),
+    Number(f64),
+    ArrayStart,
+    ArrayEnd,
+    Op(String),
+}
+
+/// Lines of text shown by a content stream's text operators.
+fn show_text(content: &[u8], cmap: &CharMap) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut operands: Vec<Token> = Vec::new();
+    let mut tokens = Tokenizer { data: content, at: 0 };
+    while let Some(token) = tokens.next() {
+        let Token::Op(op) = token else {
+            operands.push(token);
+            continue;
+        };
+        match op.as_str() {
+            "Tj" | "'" | "\"" => {
+                if op != "Tj" {
+                    end_line(&mut line, &mut lines);
+                }
+                if let Some(Token::Str(bytes, hex)) = operands.last() {
+                    line.push_str(&cmap.decode(bytes, *hex));
+                }
+            }
+            "TJ" => {
+                for operand in &operands {
+                    match operand {
+                        Token::Str(bytes, hex) => line.push_str(&cmap.decode(bytes, *hex)),
+                        Token::Number(n) if *n < WORD_GAP && !line.ends_with(' ') => line.push(' '),
+                        _ => {}
+                    }
+                }
+            }
+            "Td" | "TD" => {
+                let ty = match operands.as_slice() {
+                    [.., Token::Number(_), Token::Number(ty)] => *ty,
+                    _ => 0.0,
+                };
+                if ty.abs() > f64::EPSILON {
+                    end_line(&mut line, &mut lines);
+                } else if !line.ends_with(' ') && !line.is_empty() {
+                    line.push(' ');
+                }
+            }
+            "T*" | "Tm" | "ET" => end_line(&mut line, &mut lines),
+            // Inline image data is binary; skip to its end.
+            "ID" => tokens.skip_past(b"EI"),
+            _ => {}
+        }
+        operands.clear();
+    }
+    end_line(&mut line, &mut lines);
+    lines
+}
+
+fn end_line(line: &mut String, lines: &mut Vec<String>) {
+    let text = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !text.is_empty() {
+        lines.push(text);
+    }
+    line.clear();
+}
+
+/// Joins layout lines into paragraphs: a line continues the previous one unless that ended a
+/// sentence; a trailing hyphen joins the halves of a split word.
+fn paragraphs(lines: &[String]) -> String {
+    let mut text = String::new();
+    for line in lines {
+        if text.is_empty() {
+            text.push_str(line);
+        } else if text.ends_with(['.', '!', '?', ':']) {
+            text.push('\n');
+            text.push_str(line);
+        } else if text.ends_with('-') && !text.ends_with(" -") {
+            text.pop();
+            text.push_str(line);
+        } else {
+            text.push(' ');
+            text.push_str(line);
+        }
+    }
+    text
+}
+
+struct Tokenizer<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl Tokenizer<'_> {
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            let &byte = self.data.get(self.at)?;
+            match byte {
+                b if b.is_ascii_whitespace() => self.at += 1,
+                b'%' => {
+                    while self.data.get(self.at).is_some_and(|b| *b != b'\n' && *b != b'\r') {
+                        self.at += 1;
+                    }
+                }
+                b'(' => return Some(Token::Str(self.literal(), false)),
+                b'<' if self.data.get(self.at + 1) == Some(&b'<') => self.at += 2,
+                b'>' if self.data.get(self.at + 1) == Some(&b'>') => self.at += 2,
+                b'<' => return Some(Token::Str(self.hex(), true)),
+                b'[' => {
+                    self.at += 1;
+                    return Some(Token::ArrayStart);
+                }
+                b']' => {
+                    self.at += 1;
+                    return Some(Token::ArrayEnd);
+                }
+                b'/' => {
+                    self.at += 1;
+                    self.word();
+                }
+                _ => {
+                    let word = self.word();
+                    if word.is_empty() {
+                        self.at += 1;
+                        continue;
+                    }
+                    return Some(match word.parse::<f64>() {
+                        Ok(n) => Token::Number(n),
+                        Err(_) => Token::Op(word),
+                    });
+                }
+            }
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.at;
+        while self
+            .data
+            .get(self.at)
+            .is_some_and(|b| !b.is_ascii_whitespace() && !b"()<>[]{}/%".contains(b))
+        {
+            self.at += 1;
+        }
+        String::from_utf8_lossy(&self.data[start..self.at]).into_owned()
+    }
+
+    /// A `(...)` string with nested parentheses and backslash escapes.
+    fn literal(&mut self) -> Vec<u8> {
+        self.at += 1;
+        let mut bytes = Vec::new();
+        let mut depth = 0;
+        while let Some(&byte) = self.data.get(self.at) {
+            self.at += 1;
+            match byte {
+                b'\\' => {
+                    let Some(&next) = self.data.get(self.at) else {
+                        break;
+                    };
+                    self.at += 1;
+                    match next {
+                        b'n' => bytes.push(b'\n'),
+                        b'r' => bytes.push(b'\r'),
+                        b't' => bytes.push(b'\t'),
+                        b'b' | b'f' => {}
+                        b'0'..=b'7' => {
+                            let mut value = u32::from(next - b'0');
+                            for _ in 0..2 {
+                                match self.data.get(self.at) {
+                                    Some(&d @ b'0'..=b'7') => {
+                                        value = value * 8 + u32::from(d - b'0');
+                                        self.at += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            bytes.push(value as u8);
+                        }
+                        b'\r' | b'\n' => {}
+                        other => bytes.push(other),
+                    }
+                }
+                b'(' => {
+                    depth += 1;
+                    bytes.push(byte);
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    bytes.push(byte);
+                }
+                _ => bytes.push(byte),
+            }
+        }
+        bytes
+    }
+
+    fn hex(&mut self) -> Vec<u8> {
+        self.at += 1;
+        let start = self.at;
+        while self.data.get(self.at).is_some_and(|b| *b != b'>') {
+            self.at += 1;
+        }
+        let digits = String::from_utf8_lossy(&self.data[start..self.at]).into_owned();
+        self.at += 1;
+        hex_bytes(&digits)
+    }
+
+    fn skip_past(&mut self, marker: &[u8]) {
+        self.at = find(self.data, marker, self.at).map_or(self.data.len(), |i| i + marker.len());
+    }
+}
+
+/// The document information dictionary's `/Title`.
+fn info_title(pdf: &[u8]) -> Option<String> {
+    let at = find(pdf, b"/Title", 0)? + b"/Title".len();
+    let mut tokens = Tokenizer { data: pdf, at };
+    let Some(Token::Str(bytes, _)) = tokens.next() else {
+        return None;
+    };
+    let title = if bytes.starts_with(&[0xFE, 0xFF]) {
+        decode_utf16(&bytes[2..])
+    } else {
+        bytes.iter().map(|&b| char::from(b)).collect()
+    };
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+fn sections<'a>(text: &'a str, begin: &str, end: &str) -> Vec<&'a str> {
+    let mut sections = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(begin) {
+        let body = &rest[start + begin.len()..];
+        let stop = body.find(end).unwrap_or(body.len());
+        sections.push(&body[..stop]);
+        rest = &body[stop..];
+    }
+    sections
+}
+
+/// The `<...>` hex strings in `text`, as bytes.
+fn hex_tokens(text: &str) -> Vec<Vec<u8>> {
+    text.split('<')
+        .skip(1)
+        .filter_map(|part| part.split_once('>').map(|(digits, _)| hex_bytes(digits)))
+        .collect()
+}
+
+fn hex_bytes(digits: &str) -> Vec<u8> {
+    let mut digits: Vec<u8> = digits
+        .bytes()
+        .filter(u8::is_ascii_hexdigit)
+        .map(|d| char::from(d).to_digit(16).unwrap_or(0) as u8)
+        .collect();
+    if digits.len() % 2 == 1 {
+        digits.push(0);
+    }
+    digits.chunks_exact(2).map(|pair| pair[0] << 4 | pair[1]).collect()
+}
+
+fn code(bytes: &[u8]) -> Option<u32> {
+    (!bytes.is_empty() && bytes.len() <= 4).then(|| bytes.iter().fold(0, |code, b| code << 8 | u32::from(*b)))
+}
+
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| from + i)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|window| window == needle)
+}
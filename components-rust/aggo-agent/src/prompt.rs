@@ -0,0 +1,274 @@
+//! Prompt assembly for the research call.
+//!
+//! Search results are capped before they reach the prompt so context usage is predictable:
+//! at most `PROMPT_MAX_SOURCES` sources (default 15), and each snippet cut to
+//! `PROMPT_MAX_SNIPPET_CHARS` characters (default 600) or `PROMPT_MAX_SNIPPET_TOKENS` tokens,
+//! whichever is smaller. Tokens are estimated at four characters each. In full-text mode the
+//! per-source cap is `PROMPT_MAX_PAGE_CHARS` instead (default 4000).
+//!
+//! These caps are then scaled down to what fits the model's context window (see `context`):
+//! snippets are shortened first, down to `MIN_SOURCE_CHARS`, and only then are sources dropped.
+//!
+//! `PROMPT_EXEMPLARS` (a JSON array of `{"topic", "report"}`) adds few-shot example exchanges
+//! ahead of the real request, which helps smaller local models stick to the expected format.
+
+use golem_rust::golem_ai::golem::llm::llm::{ContentPart, Event, Message, Role};
+
+use crate::citations::Citation;
+use crate::config;
+use crate::history::ReportRecord;
+use crate::truncate;
+use crate::SearchResult;
+
+const CHARS_PER_TOKEN: usize = 4;
+/// Shortest useful per-source excerpt when shrinking to fit the context window.
+const MIN_SOURCE_CHARS: usize = 200;
+/// JSON keys and punctuation around each source in the prompt.
+const SOURCE_JSON_OVERHEAD: usize = 40;
+const MAX_OUTLINE_SECTIONS: usize = 8;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PromptBudget {
+    pub max_sources: usize,
+    pub max_snippet_chars: usize,
+    pub max_page_chars: usize,
+}
+
+impl PromptBudget {
+    pub fn from_env() -> Self {
+        let max_snippet_chars = config::env_parse::<usize>("PROMPT_MAX_SNIPPET_CHARS").unwrap_or(600);
+        let max_snippet_tokens = config::env_parse::<usize>("PROMPT_MAX_SNIPPET_TOKENS");
+        Self {
+            max_sources: config::env_parse::<usize>("PROMPT_MAX_SOURCES").unwrap_or(15).max(1),
+            max_page_chars: config::env_parse::<usize>("PROMPT_MAX_PAGE_CHARS").unwrap_or(4000),
+            max_snippet_chars: match max_snippet_tokens {
+                Some(tokens) => max_snippet_chars.min(tokens * CHARS_PER_TOKEN),
+                None => max_snippet_chars,
+            },
+        }
+    }
+
+    /// Keeps the first `max_sources` results (provider relevance order) and trims their snippets,
+    /// shrinking both further if they would not fit in `available_tokens`.
+    pub fn apply(&self, results: Vec<SearchResult>, full_text: bool, available_tokens: usize) -> Vec<SearchResult> {
+        let configured_chars = if full_text {
+            self.max_page_chars
+        } else {
+            self.max_snippet_chars
+        };
+        let available_chars = available_tokens * CHARS_PER_TOKEN;
+
+        let mut count = results.len().min(self.max_sources);
+        let mut max_chars = configured_chars;
+        while count > 0 {
+            let fixed: usize = results[..count]
+                .iter()
+                .map(|r| r.url.len() + r.title.len() + SOURCE_JSON_OVERHEAD)
+                .sum();
+            let per_source = available_chars.saturating_sub(fixed) / count;
+            if per_source >= configured_chars.min(MIN_SOURCE_CHARS) || count == 1 {
+                max_chars = configured_chars.min(per_source);
+                break;
+            }
+            count -= 1;
+        }
+        if count < results.len().min(self.max_sources) || max_chars < configured_chars {
+            log::info!(
+                "Prompt budget scaled to the context window: {count} sources, {max_chars} chars each"
+            );
+        }
+
+        results
+            .into_iter()
+            .take(count)
+            .map(|mut result| {
+                result.snippet = truncate::chars(&result.snippet, max_chars);
+                result
+            })
+            .collect()
+    }
+}
+
+/// An example topic and the report it should produce.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Exemplar {
+    pub topic: String,
+    pub report: String,
+}
+
+pub fn exemplars_from_env() -> Vec<Exemplar> {
+    let Ok(raw) = std::env::var("PROMPT_EXEMPLARS") else {
+        return Vec::new();
+    };
+    if raw.trim().is_empty() {
+        return Vec::new();
+    }
+
+    match serde_json::from_str::<Vec<Exemplar>>(&raw) {
+        Ok(exemplars) => exemplars
+            .into_iter()
+            .filter(|e| !e.topic.trim().is_empty() && !e.report.trim().is_empty())
+            .collect(),
+        Err(err) => {
+            log::warn!("PROMPT_EXEMPLARS is not a valid JSON exemplar list: {err}");
+            Vec::new()
+        }
+    }
+}
+
+/// Request/response message pairs for the exemplars, framed like the real request so the
+/// model sees the same instructions followed by a well-formed report.
+pub fn exemplar_events(exemplars: &[Exemplar], format_instructions: &str) -> Vec<Event> {
+    exemplars
+        .iter()
+        .flat_map(|exemplar| {
+            [
+                Event::Message(Message {
+                    role: Role::User,
+                    name: None,
+                    content: vec![ContentPart::Text(research_prompt(
+                        exemplar.topic.trim(),
+                        format_instructions,
+                        &[],
+                    ))],
+                }),
+                Event::Message(Message {
+                    role: Role::Assistant,
+                    name: Some("research-agent".to_string()),
+                    content: vec![ContentPart::Text(exemplar.report.trim().to_string())],
+                }),
+            ]
+        })
+        .collect()
+}
+
+/// Estimated prompt tokens taken by everything except the sources.
+pub fn estimate_tokens(text_chars: usize) -> usize {
+    text_chars.div_ceil(CHARS_PER_TOKEN)
+}
+
+pub fn chars_for_tokens(tokens: usize) -> usize {
+    tokens * CHARS_PER_TOKEN
+}
+
+pub fn exemplar_chars(exemplars: &[Exemplar], format_instructions: &str) -> usize {
+    exemplars
+        .iter()
+        .map(|e| research_prompt(e.topic.trim(), format_instructions, &[]).len() + e.report.len())
+        .sum()
+}
+
+pub fn research_prompt(topic: &str, format_instructions: &str, sources: &[SearchResult]) -> String {
+    let search_results_json = serde_json::to_string(sources).unwrap_or_else(|_| "[]".to_string());
+
+    format!(
+        "I'm writing a report on the topic \"{}\",\n\
+         Your job is to be a research-assistant and provide me an initial overview on the topic so I can dive into it in more detail.\n\
+         At the bottom are top search results from a search engine in json format. Use your own knowledge and the snippets from the search results to create the overview.\n\
+         Also include the best links to look into to learn more about the topic. Prioritize objective and reliable sources.\n\
+         {}\n\
+         Search results: {}",
+        topic, format_instructions, search_results_json
+    )
+}
+
+/// The outermost `{...}` of a reply, tolerating code fences and chatter around the JSON.
+pub fn json_object(reply: &str) -> &str {
+    match (reply.find('{'), reply.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => reply,
+    }
+}
+
+pub fn outline_prompt(topic: &str, style: Option<&str>) -> String {
+    let style = style
+        .map(|style| format!(" The report will be written in the style of: {style}."))
+        .unwrap_or_default();
+    format!(
+        "Propose an outline for a research report on the topic \"{topic}\".{style}\n\
+         Reply with 4 to 6 section headings, one per line, with no numbering, bullets or commentary."
+    )
+}
+
+/// Section headings from an outline reply, with any list markers the model added stripped.
+pub fn parse_outline(reply: &str) -> Vec<String> {
+    reply
+        .lines()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches(|c: char| c == '#' || c == '-' || c == '*' || c.is_ascii_digit() || c == '.' || c == ')')
+                .trim()
+                .trim_matches('*')
+                .trim()
+                .to_string()
+        })
+        .filter(|line| !line.is_empty() && line.chars().count() <= 120)
+        .take(MAX_OUTLINE_SECTIONS)
+        .collect()
+}
+
+pub fn section_prompt(
+    topic: &str,
+    outline: &[String],
+    heading: &str,
+    style: Option<&str>,
+    sources: &[SearchResult],
+) -> String {
+    let search_results_json = serde_json::to_string(sources).unwrap_or_else(|_| "[]".to_string());
+    let outline = outline
+        .iter()
+        .map(|h| format!("- {h}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let style = style
+        .map(|style| format!("Write in the style of: {style}.\n"))
+        .unwrap_or_default();
+
+    format!(
+        "I'm writing a report on the topic \"{topic}\" with this outline:\n{outline}\n\
+         Write only the body of the section \"{heading}\" (no heading), using your own knowledge and the search results below.\n\
+         Cite the sources you rely on as inline markdown links. Prioritize objective and reliable sources.\n\
+         {style}\
+         Search results: {search_results_json}"
+    )
+}
+
+/// Prompt for combining stored reports; each report is cut to `max_report_chars`.
+pub fn merge_prompt(
+    instructions: &str,
+    reports: &[ReportRecord],
+    max_report_chars: usize,
+    sources: &[Citation],
+) -> String {
+    let instructions = match instructions.trim() {
+        "" => String::new(),
+        instructions => format!("Instructions for the combined document: {instructions}\n"),
+    };
+    let sources = sources
+        .iter()
+        .map(|c| format!("- {}", c.url))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let reports = reports
+        .iter()
+        .map(|r| {
+            format!(
+                "### Report {} — {} (created {})\n{}",
+                r.id,
+                r.topic,
+                r.created_at,
+                truncate::chars(&r.report, max_report_chars)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    format!(
+        "Combine the research reports below into a single coherent document.\n\
+         Merge overlapping findings instead of repeating them, keep notable differences between reports, and prefer newer reports where they disagree.\n\
+         Cite sources as inline markdown links using only the URLs listed under Sources; a numbered source list is appended separately, so don't add one.\n\
+         {instructions}\
+         Sources:\n{sources}\n\n\
+         {reports}"
+    )
+}
@@ -0,0 +1,279 @@
+//! Turns a user topic into a provider query.
+//!
+//! Topics may carry search-engine operators. They are parsed into tokens and re-rendered per
+//! provider instead of being sent through as opaque text:
+//!
+//! | operator            | Bing / Brave / Google / Serper | Exa / Tavily               |
+//! |---------------------|--------------------------------|----------------------------|
+//! | `"exact phrase"`    | kept                           | kept                       |
+//! | `site:example.com`  | kept                           | moved to `include_domains` |
+//! | `-site:example.com` | kept                           | moved to `exclude_domains` |
+//! | `a OR b` / `a \| b` | `OR`                           | dropped (terms kept)       |
+//! | `-term`             | kept                           | dropped (post-filtered)    |
+//!
+//! DuckDuckGo, Kagi and SearXNG are handled like the first column. Lower-case `or` is treated as a plain
+//! word; `|` is normalised to `OR`.
+
+use crate::{SearchResult, WebSearchProvider};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum QueryToken {
+    Word(String),
+    Phrase(String),
+    Site(String),
+    ExcludeSite(String),
+    Exclude(String),
+    Or,
+}
+
+/// The query and domain filters sent to a provider for one search.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProviderQuery {
+    pub query: String,
+    pub include_domains: Vec<String>,
+    pub exclude_domains: Vec<String>,
+}
+
+/// Builds the query sent to the provider from the topic and the caller's exclusions.
+pub fn build_query(
+    provider: WebSearchProvider,
+    topic: &str,
+    exclude_terms: &[String],
+) -> ProviderQuery {
+    let mut tokens = tokenize(topic);
+    tokens.extend(exclude_terms.iter().map(|term| QueryToken::Exclude(term.clone())));
+    render(provider, &tokens)
+}
+
+/// The topic with operators stripped, for use in prompts and report headings.
+/// Plain-text topics are returned unchanged.
+pub fn plain_topic(topic: &str) -> String {
+    let tokens = tokenize(topic);
+    if tokens.iter().all(|token| matches!(token, QueryToken::Word(_))) {
+        return topic.trim().to_string();
+    }
+
+    tokens
+        .into_iter()
+        .filter_map(|token| match token {
+            QueryToken::Word(word) | QueryToken::Phrase(word) => Some(word),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `query` with the site filters and exclusions written in `topic`, for a rewritten topic
+/// (such as a translation) that should search the same sites.
+pub fn with_operators(query: &str, topic: &str) -> String {
+    let operators: Vec<String> = tokenize(topic)
+        .into_iter()
+        .filter_map(|token| match token {
+            QueryToken::Site(domain) => Some(format!("site:{domain}")),
+            QueryToken::ExcludeSite(domain) => Some(format!("-site:{domain}")),
+            QueryToken::Exclude(term) => Some(format!("-{}", quote_if_needed(&term))),
+            _ => None,
+        })
+        .collect();
+    if operators.is_empty() {
+        return query.to_string();
+    }
+    format!("{query} {}", operators.join(" "))
+}
+
+/// Drops results whose title or snippet mentions any excluded term (case-insensitive).
+/// Applied regardless of provider support, since `-term` is only a hint for most engines.
+pub fn filter_excluded(results: Vec<SearchResult>, exclude_terms: &[String]) -> Vec<SearchResult> {
+    if exclude_terms.is_empty() {
+        return results;
+    }
+
+    results
+        .into_iter()
+        .filter(|result| !is_excluded(result, exclude_terms))
+        .collect()
+}
+
+/// Whether the result's title or snippet contains any of the terms (case-insensitive).
+pub fn is_excluded(result: &SearchResult, exclude_terms: &[String]) -> bool {
+    let title = result.title.to_lowercase();
+    let snippet = result.snippet.to_lowercase();
+    exclude_terms.iter().any(|term| {
+        let needle = term.to_lowercase();
+        title.contains(&needle) || snippet.contains(&needle)
+    })
+}
+
+/// Exclusion terms written inline in the topic (`-term`), so they can be post-filtered
+/// alongside `ResearchOptions::exclude_terms`.
+pub fn inline_exclusions(topic: &str) -> Vec<String> {
+    tokenize(topic)
+        .into_iter()
+        .filter_map(|token| match token {
+            QueryToken::Exclude(term) => Some(term),
+            _ => None,
+        })
+        .collect()
+}
+
+fn tokenize(topic: &str) -> Vec<QueryToken> {
+    let mut tokens = Vec::new();
+    let mut chars = topic.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let negated = c == '-';
+        if negated {
+            chars.next();
+        }
+
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            let phrase = phrase.trim().to_string();
+            if !phrase.is_empty() {
+                tokens.push(if negated {
+                    QueryToken::Exclude(phrase)
+                } else {
+                    QueryToken::Phrase(phrase)
+                });
+            }
+            continue;
+        }
+
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            word.push(c);
+            chars.next();
+        }
+
+        if word.is_empty() {
+            // A lone '-' is just punctuation.
+            continue;
+        }
+
+        let site = word
+            .strip_prefix("site:")
+            .filter(|domain| !domain.is_empty())
+            .map(|domain| domain.to_ascii_lowercase());
+
+        tokens.push(match (negated, site) {
+            (false, Some(domain)) => QueryToken::Site(domain),
+            (true, Some(domain)) => QueryToken::ExcludeSite(domain),
+            (true, None) => QueryToken::Exclude(word),
+            (false, None) if word == "OR" || word == "|" => QueryToken::Or,
+            (false, None) => QueryToken::Word(word),
+        });
+    }
+
+    tokens
+}
+
+fn render(provider: WebSearchProvider, tokens: &[QueryToken]) -> ProviderQuery {
+    let operators = provider.supports_query_operators();
+    let mut parts: Vec<String> = Vec::new();
+    let mut out = ProviderQuery::default();
+
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            QueryToken::Word(word) => parts.push(word.clone()),
+            QueryToken::Phrase(phrase) => parts.push(format!("\"{phrase}\"")),
+            QueryToken::Site(domain) if operators => parts.push(format!("site:{domain}")),
+            QueryToken::Site(domain) => out.include_domains.push(domain.clone()),
+            QueryToken::ExcludeSite(domain) if operators => parts.push(format!("-site:{domain}")),
+            QueryToken::ExcludeSite(domain) => out.exclude_domains.push(domain.clone()),
+            QueryToken::Exclude(term) if operators => parts.push(format!("-{}", quote_if_needed(term))),
+            QueryToken::Exclude(_) => {}
+            QueryToken::Or => {
+                // Only meaningful between two operands.
+                let has_left = index > 0 && !parts.is_empty();
+                let has_right = index + 1 < tokens.len();
+                if operators && has_left && has_right && parts.last().map(String::as_str) != Some("OR") {
+                    parts.push("OR".to_string());
+                }
+            }
+        }
+    }
+
+    out.query = parts.join(" ");
+    out
+}
+
+fn quote_if_needed(term: &str) -> String {
+    if term.contains(char::is_whitespace) {
+        format!("\"{}\"", term.replace('"', ""))
+    } else {
+        term.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(w: &str) -> QueryToken {
+        QueryToken::Word(w.to_string())
+    }
+
+    #[test]
+    fn tokenize_reads_operators() {
+        assert_eq!(
+            tokenize(r#"  rust "async await" site:Docs.RS -site:reddit.com -tokio -"old news" a OR b | c "#),
+            [
+                word("rust"),
+                QueryToken::Phrase("async await".to_string()),
+                QueryToken::Site("docs.rs".to_string()),
+                QueryToken::ExcludeSite("reddit.com".to_string()),
+                QueryToken::Exclude("tokio".to_string()),
+                QueryToken::Exclude("old news".to_string()),
+                word("a"),
+                QueryToken::Or,
+                word("b"),
+                QueryToken::Or,
+                word("c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_treats_stray_punctuation_as_words() {
+        assert_eq!(tokenize("a - b or c site:"), [word("a"), word("b"), word("or"), word("c"), word("site:")]);
+        assert_eq!(tokenize(r#""" " " "unclosed phrase"#), [QueryToken::Phrase("unclosed phrase".to_string())]);
+        assert!(tokenize("   ").is_empty());
+    }
+
+    #[test]
+    fn renders_operators_per_provider() {
+        let topic = r#"rust OR "async await" site:docs.rs -site:reddit.com -tokio"#;
+        let exclude = ["old news".to_string()];
+
+        let brave = build_query(WebSearchProvider::Brave, topic, &exclude);
+        assert_eq!(
+            brave.query,
+            r#"rust OR "async await" site:docs.rs -site:reddit.com -tokio -"old news""#
+        );
+        assert!(brave.include_domains.is_empty() && brave.exclude_domains.is_empty());
+
+        let tavily = build_query(WebSearchProvider::Tavily, topic, &exclude);
+        assert_eq!(tavily.query, r#"rust "async await""#);
+        assert_eq!(tavily.include_domains, ["docs.rs"]);
+        assert_eq!(tavily.exclude_domains, ["reddit.com"]);
+
+        // `OR` without an operand on each side is dropped.
+        assert_eq!(build_query(WebSearchProvider::Brave, "OR a OR OR b OR", &[]).query, "a OR b");
+    }
+
+    #[test]
+    fn plain_topic_strips_operators() {
+        assert_eq!(plain_topic("  plain  topic "), "plain  topic");
+        assert_eq!(plain_topic(r#"rust "async await" site:docs.rs -tokio"#), "rust async await");
+        assert_eq!(inline_exclusions(r#"rust -tokio -"old news""#), ["tokio", "old news"]);
+    }
+}
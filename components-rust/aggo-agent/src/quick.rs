@@ -0,0 +1,63 @@
+//! Low-latency single-page summaries for save-and-summarize clients such as a browser
+//! extension: one fetch and one LLM call, no web search.
+//!
+//! `QUICK_SUMMARY_MODEL` picks a cheaper model (default: the agent's model),
+//! `QUICK_SUMMARY_TIMEOUT_SECONDS` bounds the page fetch (default 8) and
+//! `QUICK_SUMMARY_MAX_CHARS` caps the page text sent to the model (default 12000).
+
+use std::time::Duration;
+
+use golem_rust::Schema;
+
+use crate::archive::ArchivedSnapshot;
+use crate::config;
+use crate::prompt;
+
+const DEFAULT_TIMEOUT_SECONDS: u64 = 8;
+const DEFAULT_MAX_CHARS: usize = 12_000;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct QuickSummary {
+    pub url: String,
+    pub title: Option<String>,
+    pub summary: String,
+    pub model: String,
+    /// Whether the page text was cut to fit the summary budget.
+    pub truncated: bool,
+    /// The archived copy that was summarized because the live page could not be fetched.
+    pub snapshot: Option<ArchivedSnapshot>,
+}
+
+pub fn model(default_model: &str) -> String {
+    std::env::var("QUICK_SUMMARY_MODEL")
+        .ok()
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| default_model.to_string())
+}
+
+pub fn fetch_timeout() -> Duration {
+    let seconds = config::env_parse::<u64>("QUICK_SUMMARY_TIMEOUT_SECONDS")
+        .filter(|s| *s > 0)
+        .unwrap_or(DEFAULT_TIMEOUT_SECONDS);
+    Duration::from_secs(seconds)
+}
+
+/// Page characters sent to the model: `QUICK_SUMMARY_MAX_CHARS`, further limited to what
+/// the model's context window leaves room for.
+pub fn max_chars(context_window: u32) -> usize {
+    let configured = config::env_parse::<usize>("QUICK_SUMMARY_MAX_CHARS")
+        .filter(|c| *c > 0)
+        .unwrap_or(DEFAULT_MAX_CHARS);
+    let available = context_window.saturating_sub(crate::context::output_reserve(context_window) + 200);
+    configured.min(prompt::chars_for_tokens(available as usize))
+}
+
+pub fn summary_prompt(title: Option<&str>, text: &str) -> String {
+    format!(
+        "Summarize this web page for someone deciding whether to read it.\n\
+         Start with one sentence on what the page is, then 3 to 5 short bullets with its key points. Stick to what the page says.\n\n\
+         Title: {}\n\nPage text:\n{text}",
+        title.unwrap_or("(none)")
+    )
+}
@@ -0,0 +1,276 @@
+//! Provider quota tracking: web search calls are counted per provider against its quota windows,
+//! so the agent slows down or moves on to the next provider in the chain before the provider
+//! starts answering 429.
+//!
+//! Windows come from `SEARCH_QUOTAS` (JSON; calls per `second`, `minute`, `hour`, `day` or
+//! `month` for each provider, lowercase), e.g. `{"brave": {"second": 1, "month": 2000},
+//! "serper": {"month": 2500}}`. A month is counted as 30 days from the window's first call.
+//! Providers that report their limits in `X-RateLimit-Policy` / `-Remaining` / `-Reset` headers,
+//! as Brave does for its per-second and per-month windows, are tracked from those without
+//! configuration, and the reported numbers replace the agent's own count.
+//!
+//! Before each call a used-up window that resets within `QUOTA_MAX_WAIT_SECONDS` (default 2) is
+//! waited out; a longer one skips the provider, so the fallback chain moves on to the next. The
+//! counts are part of the worker snapshot, and `heartbeat` lists them.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use golem_rust::Schema;
+
+use crate::config;
+use crate::http_util;
+
+const DEFAULT_MAX_WAIT_SECONDS: u64 = 2;
+/// Reset values past this are unix timestamps rather than seconds from now.
+const TIMESTAMP_RESET: u64 = 1_000_000_000;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct QuotaWindow {
+    /// Window length in seconds.
+    pub seconds: u64,
+    pub limit: u64,
+    /// Calls made in the current window.
+    pub used: u64,
+    /// When the current window ends (unix seconds); 0 until the window's first call.
+    pub resets_at: u64,
+    /// Whether the provider reported this window in its rate-limit headers.
+    pub reported: bool,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct QuotaStatus {
+    /// Provider name as in `SEARCH_QUOTAS`, e.g. `brave`.
+    pub provider: String,
+    pub windows: Vec<QuotaWindow>,
+    /// Calls that waited for a window to reset.
+    pub throttled_calls: u32,
+    /// Searches that skipped the provider because a window was used up.
+    pub skipped_searches: u32,
+}
+
+/// Written from the `&self` search paths, hence the `RefCell`.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct QuotaBook {
+    providers: RefCell<Vec<QuotaStatus>>,
+}
+
+impl QuotaBook {
+    /// Why `provider` should be skipped for now: one of its windows is used up and doesn't
+    /// reset within `QUOTA_MAX_WAIT_SECONDS`.
+    pub fn check(&self, provider: &str, now: u64) -> Option<String> {
+        let mut providers = self.providers.borrow_mut();
+        let status = entry(&mut providers, provider, now);
+        let reason = blocked(status, now).filter(|(wait, _)| *wait > max_wait()).map(|(_, reason)| reason);
+        if reason.is_some() {
+            status.skipped_searches += 1;
+        }
+        reason
+    }
+
+    /// Counts a call to `provider`, first waiting for a used-up window that resets within
+    /// `QUOTA_MAX_WAIT_SECONDS`. Fails when a window stays used up for longer.
+    pub fn acquire(&self, provider: &str) -> Result<(), String> {
+        loop {
+            let now = now_millis();
+            let wait = {
+                let mut providers = self.providers.borrow_mut();
+                let status = entry(&mut providers, provider, now / 1000);
+                match blocked(status, now / 1000) {
+                    None => {
+                        for window in &mut status.windows {
+                            if window.resets_at == 0 {
+                                window.resets_at = now / 1000 + window.seconds;
+                            }
+                            window.used += 1;
+                        }
+                        return Ok(());
+                    }
+                    Some((wait, reason)) if wait > max_wait() => return Err(reason),
+                    Some((wait, _)) => {
+                        status.throttled_calls += 1;
+                        // Windows end on whole seconds; wait for the next one to start.
+                        Duration::from_millis((now / 1000 + wait) * 1000 - now)
+                    }
+                }
+            };
+            log::info!("Waiting {} ms for the {provider} quota window to reset", wait.as_millis());
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Takes `provider`'s own account of its windows from the rate-limit headers of `response`.
+    pub fn observe(&self, provider: &str, response: &http_util::Response) {
+        let Some(policy) = response.header("x-ratelimit-policy") else {
+            return;
+        };
+        let remaining = numbers(response.header("x-ratelimit-remaining").unwrap_or_default());
+        let reset = numbers(response.header("x-ratelimit-reset").unwrap_or_default());
+        let now = now_millis() / 1000;
+
+        let mut providers = self.providers.borrow_mut();
+        let status = entry(&mut providers, provider, now);
+        // `<limit>;w=<seconds>` per window, in the same order as the other headers.
+        for (index, item) in policy.split(',').enumerate() {
+            let mut parts = item.split(';').map(str::trim);
+            let Some(limit) = parts.next().and_then(|limit| limit.parse::<u64>().ok()) else {
+                continue;
+            };
+            let Some(seconds) = parts
+                .find_map(|part| part.strip_prefix("w="))
+                .and_then(|w| w.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            let (Some(remaining), Some(reset)) = (remaining.get(index).copied().flatten(), reset.get(index).copied().flatten())
+            else {
+                continue;
+            };
+            let window = QuotaWindow {
+                seconds,
+                limit,
+                used: limit.saturating_sub(remaining),
+                resets_at: if reset > TIMESTAMP_RESET { reset } else { now + reset.max(1) },
+                reported: true,
+            };
+            match status.windows.iter_mut().find(|w| w.seconds == seconds) {
+                Some(existing) => *existing = window,
+                None => status.windows.push(window),
+            }
+        }
+    }
+
+    /// Providers with quota windows, by name.
+    pub fn statuses(&self) -> Vec<QuotaStatus> {
+        let mut statuses: Vec<QuotaStatus> = self
+            .providers
+            .borrow()
+            .iter()
+            .filter(|status| !status.windows.is_empty())
+            .cloned()
+            .collect();
+        statuses.sort_by(|a, b| a.provider.cmp(&b.provider));
+        statuses
+    }
+}
+
+/// `provider`'s entry, with the configured windows applied and windows that have ended reset.
+fn entry<'a>(providers: &'a mut Vec<QuotaStatus>, provider: &str, now: u64) -> &'a mut QuotaStatus {
+    let index = match providers.iter().position(|p| p.provider == provider) {
+        Some(index) => index,
+        None => {
+            providers.push(QuotaStatus {
+                provider: provider.to_string(),
+                windows: Vec::new(),
+                throttled_calls: 0,
+                skipped_searches: 0,
+            });
+            providers.len() - 1
+        }
+    };
+    let status = &mut providers[index];
+    for (seconds, limit) in configured_windows(provider) {
+        match status.windows.iter_mut().find(|w| w.seconds == seconds) {
+            Some(window) if !window.reported => window.limit = limit,
+            Some(_) => {}
+            None => status.windows.push(QuotaWindow {
+                seconds,
+                limit,
+                used: 0,
+                resets_at: 0,
+                reported: false,
+            }),
+        }
+    }
+    for window in &mut status.windows {
+        if window.resets_at != 0 && now >= window.resets_at {
+            window.used = 0;
+            window.resets_at = 0;
+        }
+    }
+    status
+}
+
+/// Seconds until every used-up window of `status` has reset, and why the provider is blocked;
+/// `None` when a call can go out now.
+fn blocked(status: &QuotaStatus, now: u64) -> Option<(u64, String)> {
+    status
+        .windows
+        .iter()
+        .filter(|window| window.used >= window.limit)
+        .map(|window| {
+            let wait = window.resets_at.saturating_sub(now).max(1);
+            let reason = format!(
+                "{} quota of {} calls per {} is used up for another {wait} s",
+                status.provider,
+                window.limit,
+                window_name(window.seconds)
+            );
+            (wait, reason)
+        })
+        .max_by_key(|(wait, _)| *wait)
+}
+
+fn window_name(seconds: u64) -> String {
+    match seconds {
+        1 => "second".to_string(),
+        60 => "minute".to_string(),
+        3600 => "hour".to_string(),
+        86_400 => "day".to_string(),
+        2_592_000 => "month".to_string(),
+        _ => format!("{seconds} s"),
+    }
+}
+
+/// `SEARCH_QUOTAS` windows for `provider` as (seconds, limit).
+fn configured_windows(provider: &str) -> Vec<(u64, u64)> {
+    let Ok(raw) = std::env::var("SEARCH_QUOTAS") else {
+        return Vec::new();
+    };
+    if raw.trim().is_empty() {
+        return Vec::new();
+    }
+    let quotas: BTreeMap<String, BTreeMap<String, u64>> = match serde_json::from_str(&raw) {
+        Ok(quotas) => quotas,
+        Err(err) => {
+            log::warn!("SEARCH_QUOTAS is not a valid quota map, ignoring it: {err}");
+            return Vec::new();
+        }
+    };
+    quotas
+        .get(provider)
+        .into_iter()
+        .flatten()
+        .filter_map(|(window, &limit)| {
+            let seconds = match window.as_str() {
+                "second" => 1,
+                "minute" => 60,
+                "hour" => 3600,
+                "day" => 86_400,
+                "month" => 2_592_000,
+                other => {
+                    log::warn!("Unknown SEARCH_QUOTAS window {other:?} for {provider}");
+                    return None;
+                }
+            };
+            Some((seconds, limit))
+        })
+        .collect()
+}
+
+/// A comma-separated header list of numbers, by position.
+fn numbers(header: &str) -> Vec<Option<u64>> {
+    header.split(',').map(|n| n.trim().parse::<u64>().ok()).collect()
+}
+
+fn max_wait() -> u64 {
+    config::env_parse::<u64>("QUOTA_MAX_WAIT_SECONDS").unwrap_or(DEFAULT_MAX_WAIT_SECONDS)
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
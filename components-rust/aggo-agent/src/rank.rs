@@ -0,0 +1,68 @@
+//! Lexical relevance scoring shared by features that pick documents without a search
+//! provider.
+
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+/// Reciprocal rank fusion constant; 60 is the value from the original paper and damps the
+/// influence of top ranks in any single list.
+const RRF_K: f64 = 60.0;
+
+/// Okapi BM25 score of each tokenized document against the query terms. Terms match as
+/// prefixes, so "price" also matches "pricing".
+pub fn bm25<D: AsRef<[String]>>(documents: &[D], query: &[String]) -> Vec<f64> {
+    if documents.is_empty() || query.is_empty() {
+        return vec![0.0; documents.len()];
+    }
+
+    let count = documents.len() as f64;
+    let average_len = documents.iter().map(|doc| doc.as_ref().len()).sum::<usize>() as f64 / count;
+    let idf: Vec<f64> = query
+        .iter()
+        .map(|term| {
+            let with_term = documents
+                .iter()
+                .filter(|doc| doc.as_ref().iter().any(|w| w.starts_with(term.as_str())))
+                .count() as f64;
+            ((count - with_term + 0.5) / (with_term + 0.5) + 1.0).ln()
+        })
+        .collect();
+
+    documents
+        .iter()
+        .map(|doc| {
+            let doc = doc.as_ref();
+            let len_norm = 1.0 - B + B * doc.len() as f64 / average_len.max(1.0);
+            query
+                .iter()
+                .zip(&idf)
+                .map(|(term, idf)| {
+                    let tf = doc.iter().filter(|w| w.starts_with(term.as_str())).count() as f64;
+                    idf * tf * (K1 + 1.0) / (tf + K1 * len_norm)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Reciprocal rank fusion of several rankings (item indices, best first) over `len` items:
+/// each ranking adds `1 / (RRF_K + rank)` to the items it contains. Items no ranking contains
+/// score 0.
+pub fn reciprocal_rank_fusion(rankings: &[Vec<usize>], len: usize) -> Vec<f64> {
+    let mut scores = vec![0.0; len];
+    for ranking in rankings {
+        for (rank, &item) in ranking.iter().enumerate() {
+            if let Some(score) = scores.get_mut(item) {
+                *score += 1.0 / (RRF_K + rank as f64 + 1.0);
+            }
+        }
+    }
+    scores
+}
+
+/// Indices of the `limit` highest positive scores, best first.
+pub fn top_indices(scores: &[f64], limit: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..scores.len()).filter(|&i| scores[i] > 0.0).collect();
+    indices.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+    indices.truncate(limit);
+    indices
+}
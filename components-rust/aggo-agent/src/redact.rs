@@ -0,0 +1,103 @@
+//! PII redaction for text from internal sources (chat messages, mail) before it reaches a
+//! prompt or a stored report: email addresses, phone-like numbers and user mentions are
+//! replaced with placeholders.
+
+/// Digit runs at least this long (phone, account and card numbers) are redacted; shorter
+/// ones such as dates, years and versions are kept.
+const MIN_REDACTED_DIGITS: usize = 9;
+
+pub fn redact(text: &str) -> String {
+    let text = redact_mentions(text);
+    let text = text
+        .split_inclusive(char::is_whitespace)
+        .map(|token| {
+            let word = token.trim_matches(|c: char| c.is_whitespace() || "<>()[],;:.\"'".contains(c));
+            if is_email(word) {
+                token.replacen(word, "[email]", 1)
+            } else {
+                token.to_string()
+            }
+        })
+        .collect::<String>();
+    redact_numbers(&text)
+}
+
+/// Slack-style `<@U123>` / `<@U123|name>` user mentions.
+fn redact_mentions(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<@") {
+        out.push_str(&rest[..start]);
+        match rest[start..].find('>') {
+            Some(len) => {
+                out.push_str("[user]");
+                rest = &rest[start + len + 1..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_email(word: &str) -> bool {
+    let Some((local, domain)) = word.split_once('@') else {
+        return false;
+    };
+    let local_ok = !local.is_empty()
+        && local.chars().all(|c| c.is_alphanumeric() || "._%+-".contains(c));
+    let domain = domain.trim_end_matches('.');
+    let domain_ok = domain.contains('.')
+        && !domain.starts_with('.')
+        && domain.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '-');
+    local_ok && domain_ok
+}
+
+/// Replaces runs of digits joined by single separators (`+`, space, `-`, `.`, parentheses)
+/// that hold at least `MIN_REDACTED_DIGITS` digits.
+fn redact_numbers(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let starts_number = chars[i].is_ascii_digit()
+            || ((chars[i] == '+' || chars[i] == '(') && chars.get(i + 1).is_some_and(char::is_ascii_digit));
+        if !starts_number {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        // Extend over digits and single separators between them.
+        let start = i;
+        let mut end = i;
+        let mut digits = 0;
+        let mut j = i;
+        while j < chars.len() {
+            let c = chars[j];
+            if c.is_ascii_digit() {
+                digits += 1;
+                j += 1;
+                end = j;
+            } else if (j == start && (c == '+' || c == '('))
+                || (" -.()".contains(c) && chars.get(j + 1).is_some_and(|n| n.is_ascii_digit() || *n == '('))
+            {
+                j += 1;
+            } else if c == ')' {
+                j += 1;
+                end = j;
+            } else {
+                break;
+            }
+        }
+        if digits >= MIN_REDACTED_DIGITS {
+            out.push_str("[number]");
+        } else {
+            out.extend(&chars[start..end]);
+        }
+        i = end.max(start + 1);
+    }
+    out
+}
@@ -0,0 +1,65 @@
+//! Model refusals: a reply the provider's content filter stopped, or a short reply that opens
+//! by declining the request ("I'm sorry, but I can't help with that"), is returned as a typed
+//! `Refusal` instead of becoming a report whose body is the apology.
+//!
+//! Text is only taken for a refusal when the declining sentence opens the reply and the reply
+//! is short, so a report that mentions what it couldn't find is not mistaken for one.
+
+use golem_rust::golem_ai::golem::llm::llm;
+use golem_rust::Schema;
+
+/// Replies longer than this are reports, whatever their first sentence says.
+const MAX_REFUSAL_CHARS: usize = 800;
+/// How much of the reply's opening is searched for a declining phrase.
+const OPENING_CHARS: usize = 160;
+const MAX_REASON_CHARS: usize = 300;
+const DECLINING_PHRASES: [&str; 16] = [
+    "i'm sorry, but i can't",
+    "i'm sorry, but i cannot",
+    "i am sorry, but i cannot",
+    "sorry, i can't help",
+    "i can't help with",
+    "i cannot help with",
+    "i can't assist with",
+    "i cannot assist with",
+    "i can't provide",
+    "i cannot provide",
+    "i won't be able to help",
+    "i'm unable to help",
+    "i am unable to help",
+    "i'm not able to help",
+    "i must decline",
+    "as an ai language model, i cannot",
+];
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct Refusal {
+    /// The model's own explanation (the opening of its reply), or why the provider stopped it.
+    pub reason: String,
+}
+
+/// The refusal in a model reply, if it is one.
+pub fn detect(reply: &str, finish_reason: Option<llm::FinishReason>) -> Option<Refusal> {
+    if finish_reason == Some(llm::FinishReason::ContentFilter) {
+        return Some(Refusal {
+            reason: "The provider's content filter stopped the reply".to_string(),
+        });
+    }
+    let reply = reply.trim();
+    if reply.is_empty() || reply.chars().count() > MAX_REFUSAL_CHARS {
+        return None;
+    }
+    let opening: String = reply
+        .chars()
+        .take(OPENING_CHARS)
+        .collect::<String>()
+        .to_lowercase()
+        .replace('\u{2019}', "'");
+    if !DECLINING_PHRASES.iter().any(|phrase| opening.contains(phrase)) {
+        return None;
+    }
+    let first_paragraph = reply.split("\n\n").next().unwrap_or(reply);
+    Some(Refusal {
+        reason: crate::truncate::chars(first_paragraph, MAX_REASON_CHARS),
+    })
+}
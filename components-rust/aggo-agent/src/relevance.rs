@@ -0,0 +1,119 @@
+//! Relevance scoring of search results before the prompt is built. Each web, connector and
+//! corpus result is scored against the topic's terms from its title, URL path and snippet (or
+//! page text): half BM25 relative to the best result, half the share of the topic's terms it
+//! mentions, so scores run from 0 to 1. No LLM call.
+//!
+//! Scores are always recorded (`explain_retrieval`, `ResearchReport.sources`). Results are
+//! only reordered and cut when `RELEVANCE_TOP_K` or `RELEVANCE_MIN_SCORE` is set: then the
+//! results are sorted best first, those below the minimum score are dropped and at most the
+//! top k are kept. A Wikipedia grounding source is not scored and keeps its place.
+
+use golem_rust::Schema;
+
+use crate::{compress, config, rank, SearchResult};
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct RelevanceScore {
+    pub url: String,
+    /// 0 to 1; see the module docs.
+    pub score: f64,
+    /// False if the result was cut by `RELEVANCE_MIN_SCORE` or `RELEVANCE_TOP_K`.
+    pub kept: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RelevancePolicy {
+    /// Results kept at most; 0 keeps all.
+    pub top_k: usize,
+    /// Results scoring lower are dropped; 0 keeps all.
+    pub min_score: f64,
+}
+
+impl RelevancePolicy {
+    pub fn from_env() -> Self {
+        Self {
+            top_k: config::env_parse::<usize>("RELEVANCE_TOP_K").unwrap_or(0),
+            min_score: config::env_parse::<f64>("RELEVANCE_MIN_SCORE")
+                .filter(|s| s.is_finite())
+                .unwrap_or(0.0)
+                .clamp(0.0, 1.0),
+        }
+    }
+
+    fn filters(&self) -> bool {
+        self.top_k > 0 || self.min_score > 0.0
+    }
+
+    /// `results` scored against `topic` and, if this policy filters, sorted best first and cut.
+    /// Returns the results kept and the score of every result, kept ones first.
+    pub fn apply(&self, results: Vec<SearchResult>, topic: &str) -> (Vec<SearchResult>, Vec<RelevanceScore>) {
+        let scores = score(&results, topic);
+        if scores.is_empty() {
+            return (results, Vec::new());
+        }
+        let mut scored: Vec<(f64, SearchResult)> = scores.into_iter().zip(results).collect();
+        if self.filters() {
+            scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        }
+
+        let mut kept = Vec::new();
+        let mut cut = Vec::new();
+        for (score, result) in scored {
+            let keep = score >= self.min_score && (self.top_k == 0 || kept.len() < self.top_k);
+            if keep {
+                kept.push((score, result));
+            } else {
+                cut.push(RelevanceScore {
+                    url: result.url,
+                    score,
+                    kept: false,
+                });
+            }
+        }
+        let mut record: Vec<RelevanceScore> = kept
+            .iter()
+            .map(|(score, result)| RelevanceScore {
+                url: result.url.clone(),
+                score: *score,
+                kept: true,
+            })
+            .collect();
+        record.extend(cut);
+        (kept.into_iter().map(|(_, result)| result).collect(), record)
+    }
+}
+
+/// Scores of `results` against `topic`, rounded to three decimals; empty if the topic has no
+/// content words to score by.
+fn score(results: &[SearchResult], topic: &str) -> Vec<f64> {
+    let terms = compress::terms(topic);
+    if terms.is_empty() {
+        return Vec::new();
+    }
+    let documents: Vec<Vec<String>> = results
+        .iter()
+        .map(|result| {
+            let path = result.url.split_once("://").map_or(result.url.as_str(), |(_, rest)| rest);
+            let mut words: Vec<String> = compress::words(&result.title).collect();
+            words.extend(compress::words(path));
+            words.extend(compress::words(&result.snippet));
+            words
+        })
+        .collect();
+    let bm25 = rank::bm25(&documents, &terms);
+    let best = bm25.iter().copied().fold(0.0, f64::max);
+
+    documents
+        .iter()
+        .zip(bm25)
+        .map(|(words, bm25)| {
+            let matched = terms
+                .iter()
+                .filter(|term| words.iter().any(|w| w.starts_with(term.as_str())))
+                .count();
+            let relative = if best > 0.0 { bm25 / best } else { 0.0 };
+            let coverage = matched as f64 / terms.len() as f64;
+            ((relative + coverage) / 2.0 * 1000.0).round() / 1000.0
+        })
+        .collect()
+}
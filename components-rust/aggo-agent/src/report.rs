@@ -0,0 +1,185 @@
+//! The typed result of `research_with_options`: the report plus a summary, key findings and
+//! cited sources pulled out of it, so HTTP callers don't have to parse prose. Also the footers
+//! appended to generated reports.
+
+use golem_rust::Schema;
+
+use crate::cache::ReportFreshness;
+use crate::citations::Citation;
+use crate::error::AgentError;
+use crate::findings::KeyFinding;
+use crate::{locale, truncate};
+
+const MAX_SUMMARY_CHARS: usize = 1200;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ResearchReport {
+    /// See `schema`; version 0 is a record from before versioning.
+    #[serde(default)]
+    pub schema_version: u32,
+    /// Id of the stored report in the workspace history.
+    pub report_id: String,
+    pub topic: String,
+    /// The report's opening paragraph.
+    pub summary: String,
+    /// Empty when the findings pass fails; the report itself is still returned.
+    pub key_findings: Vec<KeyFinding>,
+    /// Links cited in the report, in order of first citation.
+    pub sources: Vec<ReportSource>,
+    /// The full markdown report.
+    pub report: String,
+    pub model: String,
+    /// Set once the workspace is past its soft spend limit.
+    pub budget_warning: Option<String>,
+    /// When the report was researched and whether it came from the report cache.
+    #[serde(default)]
+    pub freshness: ReportFreshness,
+    /// Failures the run got past, by kind: search providers, pages, and a key findings pass
+    /// that failed (`llm-error`) or couldn't be parsed (`parse-error`).
+    #[serde(default)]
+    pub errors: Vec<AgentError>,
+}
+
+/// A part of a `ResearchReport` callers can ask for with `ResearchOptions::fields`. The
+/// identifying fields (`schema_version`, `report_id`, `topic`, `model`, `budget_warning`,
+/// `freshness`) are always returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Schema, serde::Serialize, serde::Deserialize)]
+pub enum ReportField {
+    Summary,
+    KeyFindings,
+    Sources,
+    Report,
+    Errors,
+}
+
+impl ResearchReport {
+    /// The report with the parts not in `fields` emptied; all of it without a selection.
+    pub fn select(mut self, fields: Option<&[ReportField]>) -> Self {
+        let Some(fields) = fields else {
+            return self;
+        };
+        if !fields.contains(&ReportField::Summary) {
+            self.summary = String::new();
+        }
+        if !fields.contains(&ReportField::KeyFindings) {
+            self.key_findings = Vec::new();
+        }
+        if !fields.contains(&ReportField::Sources) {
+            self.sources = Vec::new();
+        }
+        if !fields.contains(&ReportField::Report) {
+            self.report = String::new();
+        }
+        if !fields.contains(&ReportField::Errors) {
+            self.errors = Vec::new();
+        }
+        self
+    }
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ReportSource {
+    pub url: String,
+    pub title: Option<String>,
+    /// The source looked like AI-generated content and was ranked last; see `ai_content`.
+    #[serde(default)]
+    pub likely_ai_generated: bool,
+    /// The source's relevance score (0 to 1) if it was a scored search result; see `relevance`.
+    #[serde(default)]
+    pub relevance: Option<f64>,
+}
+
+impl From<Citation> for ReportSource {
+    fn from(citation: Citation) -> Self {
+        ReportSource {
+            url: citation.url,
+            title: citation.title,
+            likely_ai_generated: false,
+            relevance: None,
+        }
+    }
+}
+
+/// The report with retrieval notes, such as failed search providers, added as a footnote.
+pub fn append_notes(report: String, notes: &[String]) -> String {
+    if notes.is_empty() {
+        return report;
+    }
+    let notes: Vec<String> = notes.iter().map(|note| format!("_{note}_")).collect();
+    format!("{}\n\n---\n{}", report.trim_end(), notes.join("\n\n"))
+}
+
+/// Attribution footer appended to every generated report, for rules on labelling
+/// AI-generated content. Set with `REPORT_FOOTER` (JSON), e.g.
+/// `{"generated_by": "Generated by the AGGO research agent (AI)", "model": true, "date": true, "disclaimer": "Verify before relying on it."}`.
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct ReportFooter {
+    pub generated_by: Option<String>,
+    /// Name the model that wrote the report.
+    pub model: bool,
+    /// Add the generation date (UTC), written the way the report language writes dates.
+    pub date: bool,
+    pub disclaimer: Option<String>,
+}
+
+impl ReportFooter {
+    pub fn from_env() -> Self {
+        let Ok(raw) = std::env::var("REPORT_FOOTER") else {
+            return Self::default();
+        };
+        if raw.trim().is_empty() {
+            return Self::default();
+        }
+
+        match serde_json::from_str::<ReportFooter>(&raw) {
+            Ok(footer) => footer,
+            Err(err) => {
+                log::warn!("REPORT_FOOTER is not a valid footer, reports get none: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    /// The report with the footer after everything else, including retrieval notes; unchanged
+    /// when no footer is configured.
+    pub fn append(&self, report: String, model: &str, language: &str, now: u64) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        if let Some(notice) = non_blank(&self.generated_by) {
+            lines.push(notice.to_string());
+        }
+        let mut details = Vec::new();
+        if self.model {
+            details.push(format!("Model: {model}"));
+        }
+        if self.date {
+            details.push(format!("Generated: {}", locale::format_date(now, language)));
+        }
+        if !details.is_empty() {
+            lines.push(details.join(" · "));
+        }
+        if let Some(disclaimer) = non_blank(&self.disclaimer) {
+            lines.push(disclaimer.to_string());
+        }
+        if lines.is_empty() {
+            return report;
+        }
+        let lines: Vec<String> = lines.iter().map(|line| format!("<sub>{line}</sub>")).collect();
+        format!("{}\n\n---\n{}", report.trim_end(), lines.join("<br>\n"))
+    }
+}
+
+fn non_blank(text: &Option<String>) -> Option<&str> {
+    text.as_deref().map(str::trim).filter(|t| !t.is_empty())
+}
+
+/// The first paragraph of prose, skipping headings, lists and tables.
+pub fn summary(report: &str) -> String {
+    let paragraph = report
+        .split("\n\n")
+        .map(str::trim)
+        .find(|p| !p.is_empty() && !p.starts_with(['#', '-', '*', '|', '>']) && !p.starts_with("1."))
+        .unwrap_or_default();
+    let paragraph = paragraph.split_whitespace().collect::<Vec<_>>().join(" ");
+    truncate::chars(&paragraph, MAX_SUMMARY_CHARS)
+}
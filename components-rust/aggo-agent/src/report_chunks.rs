@@ -0,0 +1,143 @@
+//! Chunked retrieval of deep research reports too large for one invocation payload. A client
+//! reads `get_report_manifest(job_id)` and then each `get_report_chunk(job_id, index)`;
+//! concatenating the chunks in order gives the report exactly.
+//!
+//! Chunks are at most `REPORT_CHUNK_BYTES` (default 256 KiB) and end at a line break where one
+//! falls in their second half, otherwise at a character boundary, so the same report always
+//! splits the same way. Every chunk carries the digest of the whole report: a client that sees
+//! it change (a job still being researched got a new section) starts over from the manifest.
+
+use golem_rust::Schema;
+
+use crate::config;
+use crate::differential;
+use crate::jobs::JobStatus;
+use crate::truncate;
+
+const DEFAULT_CHUNK_BYTES: usize = 256 * 1024;
+const MIN_CHUNK_BYTES: usize = 1024;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ChunkInfo {
+    pub index: u32,
+    pub bytes: u32,
+    pub digest: String,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ReportManifest {
+    pub job_id: String,
+    /// A researching job's report grows as sections are finished.
+    pub status: JobStatus,
+    /// UTF-8 bytes of the whole report.
+    pub total_bytes: u64,
+    /// 64-bit FNV-1a of the whole report, in hex.
+    pub digest: String,
+    pub chunk_count: u32,
+    pub chunks: Vec<ChunkInfo>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ReportChunk {
+    pub job_id: String,
+    pub index: u32,
+    pub chunk_count: u32,
+    /// The manifest's `digest`; compare it to detect a report that changed between calls.
+    pub report_digest: String,
+    /// Digest of `content`.
+    pub digest: String,
+    pub content: String,
+}
+
+pub fn chunk_bytes() -> usize {
+    config::env_parse::<usize>("REPORT_CHUNK_BYTES")
+        .unwrap_or(DEFAULT_CHUNK_BYTES)
+        .max(MIN_CHUNK_BYTES)
+}
+
+/// `report` split into chunks of at most `max_bytes`.
+pub fn split(report: &str, max_bytes: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = report;
+    while rest.len() > max_bytes {
+        let mut end = truncate::prefix_bytes(rest, max_bytes).len();
+        if let Some(newline) = rest[..end].rfind('\n').filter(|n| *n >= max_bytes / 2) {
+            end = newline + 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    if !rest.is_empty() || chunks.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+pub fn manifest(job_id: &str, status: JobStatus, report: &str) -> ReportManifest {
+    let chunks: Vec<ChunkInfo> = split(report, chunk_bytes())
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| ChunkInfo {
+            index: index as u32,
+            bytes: chunk.len() as u32,
+            digest: differential::digest(chunk),
+        })
+        .collect();
+    ReportManifest {
+        job_id: job_id.to_string(),
+        status,
+        total_bytes: report.len() as u64,
+        digest: differential::digest(report),
+        chunk_count: chunks.len() as u32,
+        chunks,
+    }
+}
+
+pub fn chunk(job_id: &str, report: &str, index: u32) -> Result<ReportChunk, String> {
+    let chunks = split(report, chunk_bytes());
+    let Some(content) = chunks.get(index as usize) else {
+        return Err(format!(
+            "Chunk {index} is out of range; the report of job {job_id:?} has {} chunks",
+            chunks.len()
+        ));
+    };
+    Ok(ReportChunk {
+        job_id: job_id.to_string(),
+        index,
+        chunk_count: chunks.len() as u32,
+        report_digest: differential::digest(report),
+        digest: differential::digest(content),
+        content: content.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_empty_or_small_report_is_one_chunk() {
+        assert_eq!(split("", 8), [""]);
+        assert_eq!(split("abc", 8), ["abc"]);
+        assert_eq!(split("abcdefgh", 8), ["abcdefgh"]);
+    }
+
+    #[test]
+    fn prefers_a_line_break_in_the_second_half() {
+        assert_eq!(split("aaaa\nbbbbbbbbbb", 8), ["aaaa\n", "bbbbbbbb", "bb"]);
+        // A break in the first half would make too small a chunk.
+        assert_eq!(split("a\nbbbbbbbbbb", 8), ["a\nbbbbbb", "bbbb"]);
+    }
+
+    #[test]
+    fn cuts_between_characters_and_rejoins_exactly() {
+        let report = "héllo wörld\n日本語のレポート\ne\u{301}e\u{301}e\u{301} 👍🏽 🇩🇪🇫🇷\n".repeat(20);
+        for max_bytes in [5, 7, 16, 64, 1024] {
+            let chunks = split(&report, max_bytes);
+            assert!(chunks.iter().all(|c| !c.is_empty() && c.len() <= max_bytes));
+            assert_eq!(chunks.concat(), report);
+            assert_eq!(split(&report, max_bytes), chunks);
+        }
+    }
+}
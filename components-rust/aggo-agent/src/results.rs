@@ -0,0 +1,41 @@
+//! Deduplication of search results before the prompt is built. Results merged from several
+//! pages, providers, expanded queries, connectors and the corpus often contain the same page
+//! under slightly different URLs (http and https, `www.`, tracking parameters, a trailing
+//! slash); `citations::normalize_url` maps those to one key, and only the first result per key
+//! is kept, in its place.
+//!
+//! The kept result takes the better parts of its duplicates: the longer snippet (page text a
+//! later copy was enriched with), the https URL and a title where it had none.
+
+use std::collections::HashMap;
+
+use crate::citations;
+use crate::SearchResult;
+
+/// `results` without duplicates, and the URLs of the duplicates that were dropped.
+pub fn dedup(results: Vec<SearchResult>) -> (Vec<SearchResult>, Vec<String>) {
+    let mut kept: Vec<SearchResult> = Vec::with_capacity(results.len());
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut dropped = Vec::new();
+    for result in results {
+        let key = citations::normalize_url(&result.url);
+        let Some(&i) = index.get(&key) else {
+            index.insert(key, kept.len());
+            kept.push(result);
+            continue;
+        };
+        let first = &mut kept[i];
+        if result.snippet.trim().len() > first.snippet.trim().len() {
+            first.snippet = result.snippet;
+        }
+        if first.title.trim().is_empty() {
+            first.title = result.title;
+        }
+        if first.url.starts_with("http://") && result.url.starts_with("https://") {
+            dropped.push(std::mem::replace(&mut first.url, result.url));
+        } else {
+            dropped.push(result.url);
+        }
+    }
+    (kept, dropped)
+}
@@ -0,0 +1,244 @@
+//! Per-domain extraction rules, for important sites whose pages the generic extractor reads
+//! badly.
+//!
+//! `EXTRACTION_RULES` holds a JSON array of rules. Selectors are a small CSS subset: `tag`,
+//! `#id`, `.class` and combinations such as `div.post-body` (no descendant or attribute
+//! selectors). Example:
+//!
+//! ```json
+//! [{"domain": "example.com", "include": ["div.post-body"], "exclude": [".related", "aside"],
+//!   "exclude_text": ["Subscribe to our newsletter"]}]
+//! ```
+
+use crate::fetch;
+
+/// Elements that never have a closing tag.
+const VOID_ELEMENTS: [&str; 8] = ["img", "br", "hr", "input", "meta", "link", "source", "wbr"];
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct ExtractionRule {
+    /// Applies to this host and its subdomains; the most specific matching rule wins.
+    pub domain: String,
+    /// Elements holding the content; when none match, the usual readable region is used.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Elements removed before extraction, e.g. comments, related links or cookie banners.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Text lines containing any of these (case-insensitive) are dropped.
+    #[serde(default)]
+    pub exclude_text: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Selector {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+/// The rule configured for `url`'s host, if any.
+pub fn rule_for(url: &str) -> Option<ExtractionRule> {
+    let host = crate::citations::host(url)?;
+    let raw = std::env::var("EXTRACTION_RULES").ok()?;
+    if raw.trim().is_empty() {
+        return None;
+    }
+    let rules: Vec<ExtractionRule> = match serde_json::from_str(&raw) {
+        Ok(rules) => rules,
+        Err(err) => {
+            log::warn!("EXTRACTION_RULES is not a valid JSON rule list: {err}");
+            return None;
+        }
+    };
+
+    rules
+        .into_iter()
+        .filter(|rule| {
+            let domain = rule.domain.trim().trim_start_matches("www.").to_ascii_lowercase();
+            !domain.is_empty() && (host == domain || host.ends_with(&format!(".{domain}")))
+        })
+        .max_by_key(|rule| rule.domain.trim().len())
+}
+
+impl ExtractionRule {
+    /// Readable text of `html` with this rule's include, exclude and text filters applied.
+    pub fn extract(&self, html: &str) -> String {
+        let included: Vec<&str> = selectors(&self.include)
+            .iter()
+            .flat_map(|selector| spans(html, selector))
+            .map(|(start, end)| &html[start..end])
+            .collect();
+        let region = if included.is_empty() {
+            fetch::readable_region(html).to_string()
+        } else {
+            included.join("\n")
+        };
+
+        let mut region = region;
+        for selector in selectors(&self.exclude) {
+            region = remove_spans(&region, &spans(&region, &selector));
+        }
+
+        let blocked: Vec<String> = self
+            .exclude_text
+            .iter()
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+        fetch::strip_tags(&region)
+            .lines()
+            .filter(|line| {
+                let line = line.to_lowercase();
+                !blocked.iter().any(|b| line.contains(b.as_str()))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn selectors(list: &[String]) -> Vec<Selector> {
+    list.iter()
+        .flat_map(|s| s.split(','))
+        .filter_map(|s| {
+            let selector = parse_selector(s);
+            if selector.is_none() && !s.trim().is_empty() {
+                log::warn!("Ignoring unsupported extraction selector {:?}", s.trim());
+            }
+            selector
+        })
+        .collect()
+}
+
+fn parse_selector(text: &str) -> Option<Selector> {
+    let text = text.trim();
+    if text.is_empty() || text.contains(|c: char| c.is_whitespace() || "[]>+~:*".contains(c)) {
+        return None;
+    }
+
+    let mut selector = Selector::default();
+    let mut rest = text;
+    let tag_end = rest.find(['.', '#']).unwrap_or(rest.len());
+    if tag_end > 0 {
+        selector.tag = Some(rest[..tag_end].to_ascii_lowercase());
+    }
+    rest = &rest[tag_end..];
+    while let Some(marker) = rest.chars().next() {
+        let name_end = rest[1..].find(['.', '#']).map_or(rest.len(), |i| i + 1);
+        let name = &rest[1..name_end];
+        if name.is_empty() {
+            return None;
+        }
+        match marker {
+            '#' => selector.id = Some(name.to_string()),
+            _ => selector.classes.push(name.to_string()),
+        }
+        rest = &rest[name_end..];
+    }
+    Some(selector)
+}
+
+fn matches(selector: &Selector, name: &str, attrs: &str) -> bool {
+    if selector.tag.as_deref().is_some_and(|tag| tag != name) {
+        return false;
+    }
+    if let Some(id) = &selector.id {
+        if fetch::attribute(attrs, "id").as_deref() != Some(id.as_str()) {
+            return false;
+        }
+    }
+    if !selector.classes.is_empty() {
+        let class = fetch::attribute(attrs, "class").unwrap_or_default();
+        let classes: Vec<&str> = class.split_whitespace().collect();
+        if !selector.classes.iter().all(|c| classes.contains(&c.as_str())) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Byte ranges (start tag through closing tag) of the outermost elements matching `selector`.
+fn spans(html: &str, selector: &Selector) -> Vec<(usize, usize)> {
+    let lower = html.to_ascii_lowercase();
+    let mut found: Vec<(usize, usize)> = Vec::new();
+    let mut from = 0;
+    while let Some(i) = html[from..].find('<') {
+        let start = from + i;
+        let Some(len) = html[start..].find('>') else {
+            break;
+        };
+        let open_end = start + len + 1;
+        from = open_end;
+
+        let body = &html[start + 1..open_end - 1];
+        if !body.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+        let name_len = body.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(body.len());
+        let name = body[..name_len].to_ascii_lowercase();
+        if !matches(selector, &name, &body[name_len..]) {
+            continue;
+        }
+
+        let end = if VOID_ELEMENTS.contains(&name.as_str()) || body.ends_with('/') {
+            open_end
+        } else {
+            closing_end(&lower, open_end, &name)
+        };
+        found.push((start, end));
+        // Matches nested inside this one are already covered.
+        from = end;
+    }
+    found
+}
+
+/// End of the closing tag that balances an element opened just before `from`.
+fn closing_end(lower: &str, from: usize, name: &str) -> usize {
+    let open = format!("<{name}");
+    let close = format!("</{name}");
+    let mut depth = 1;
+    let mut at = from;
+    while depth > 0 {
+        let next_open = find_boundary(lower, at, &open);
+        let Some(next_close) = find_boundary(lower, at, &close) else {
+            return lower.len();
+        };
+        match next_open {
+            Some(o) if o < next_close => {
+                depth += 1;
+                at = o + open.len();
+            }
+            _ => {
+                depth -= 1;
+                at = next_close + close.len();
+            }
+        }
+    }
+    lower[at..].find('>').map_or(lower.len(), |i| at + i + 1)
+}
+
+/// Next `pattern` at or after `from` that is followed by `>`, `/` or whitespace.
+fn find_boundary(lower: &str, from: usize, pattern: &str) -> Option<usize> {
+    let mut at = from;
+    while let Some(i) = lower[at..].find(pattern) {
+        let pos = at + i;
+        let next = lower.as_bytes().get(pos + pattern.len());
+        if next.is_none_or(|c| *c == b'>' || *c == b'/' || c.is_ascii_whitespace()) {
+            return Some(pos);
+        }
+        at = pos + pattern.len();
+    }
+    None
+}
+
+fn remove_spans(html: &str, spans: &[(usize, usize)]) -> String {
+    let mut kept = String::with_capacity(html.len());
+    let mut at = 0;
+    for &(start, end) in spans {
+        kept.push_str(&html[at..start]);
+        kept.push('\n');
+        at = end;
+    }
+    kept.push_str(&html[at..]);
+    kept
+}
@@ -0,0 +1,196 @@
+//! Topic screening before a run spends anything. A topic is matched against phrase lists
+//! first: a blocked phrase rejects it at once, a review phrase lets it run but flags it for the
+//! moderation queue (`list_flagged_topics`). Topics the lists don't decide are passed to
+//! `TOPIC_SAFETY_MODEL`, a small model asked for a one-line verdict, when one is set.
+//!
+//! The built-in lists are short and conservative; `TOPIC_BLOCKED_TERMS` and
+//! `TOPIC_REVIEW_TERMS` (comma-separated, case-insensitive, matched on word boundaries) extend
+//! them. `TOPIC_SAFETY=false` turns screening off.
+
+use golem_rust::Schema;
+
+use crate::clock;
+
+/// Flagged topics kept for review; oldest are dropped first.
+const MAX_FLAGGED: usize = 500;
+const MAX_REASON_CHARS: usize = 200;
+const BLOCKED_TERMS: [&str; 12] = [
+    "child sexual abuse material",
+    "csam",
+    "child pornography",
+    "synthesize sarin",
+    "synthesize vx",
+    "make ricin",
+    "weaponize anthrax",
+    "build a pipe bomb",
+    "make a pipe bomb",
+    "build a dirty bomb",
+    "synthesize methamphetamine",
+    "cook meth",
+];
+const REVIEW_TERMS: [&str; 14] = [
+    "explosive",
+    "explosives",
+    "nerve agent",
+    "bioweapon",
+    "ransomware",
+    "malware",
+    "exploit",
+    "zero-day",
+    "hacking",
+    "ghost gun",
+    "suicide method",
+    "self-harm",
+    "doxxing",
+    "stalkerware",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Allowed,
+    /// Runs, but goes to the moderation queue with this reason.
+    Review(String),
+    /// Rejected before any search or LLM call, with this reason.
+    Blocked(String),
+}
+
+/// Phrase lists and the optional classifier model, read from the env.
+#[derive(Clone, Debug, Default)]
+pub struct TopicScreen {
+    pub enabled: bool,
+    blocked: Vec<String>,
+    review: Vec<String>,
+    /// `TOPIC_SAFETY_MODEL`; `None` screens with the phrase lists only.
+    pub model: Option<String>,
+}
+
+impl TopicScreen {
+    pub fn from_env() -> Self {
+        let enabled = !std::env::var("TOPIC_SAFETY")
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "false" | "0" | "off"))
+            .unwrap_or(false);
+        let model = std::env::var("TOPIC_SAFETY_MODEL")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !crate::config::is_placeholder(v));
+        Self {
+            enabled,
+            blocked: terms(&BLOCKED_TERMS, "TOPIC_BLOCKED_TERMS"),
+            review: terms(&REVIEW_TERMS, "TOPIC_REVIEW_TERMS"),
+            model,
+        }
+    }
+
+    /// The verdict of the phrase lists; `Allowed` also when nothing matched.
+    pub fn rules(&self, topic: &str) -> Verdict {
+        let topic = topic.to_lowercase();
+        if let Some(term) = self.blocked.iter().find(|term| contains_phrase(&topic, term)) {
+            return Verdict::Blocked(format!("the topic mentions {term:?}"));
+        }
+        if let Some(term) = self.review.iter().find(|term| contains_phrase(&topic, term)) {
+            return Verdict::Review(format!("the topic mentions {term:?}"));
+        }
+        Verdict::Allowed
+    }
+}
+
+fn terms(built_in: &[&str], var: &str) -> Vec<String> {
+    let extra = std::env::var(var).unwrap_or_default();
+    built_in
+        .iter()
+        .map(|term| term.to_string())
+        .chain(extra.split(',').map(|term| term.trim().to_lowercase()))
+        .filter(|term| !term.is_empty())
+        .collect()
+}
+
+/// Whether `phrase` occurs in `text` with no letter or digit right before or after it.
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    let mut from = 0;
+    while let Some(found) = text[from..].find(phrase) {
+        let at = from + found;
+        let end = at + phrase.len();
+        let starts_word = text[..at].chars().next_back().is_none_or(|c| !c.is_alphanumeric());
+        let ends_word = text[end..].chars().next().is_none_or(|c| !c.is_alphanumeric());
+        if starts_word && ends_word {
+            return true;
+        }
+        from = at + text[at..].chars().next().map_or(1, char::len_utf8);
+    }
+    false
+}
+
+/// The classifier prompt for `topic`.
+pub fn classification_prompt(topic: &str) -> String {
+    format!(
+        "You screen research topics before a research assistant spends money on them. \
+         Answer with exactly one line: ALLOW, REVIEW: <reason> or BLOCK: <reason>.\n\
+         BLOCK topics that ask for operational help with serious harm: weapons capable of mass \
+         casualties, sexual content involving minors, or instructions for violent or criminal acts.\n\
+         REVIEW topics that are legitimate to research but sensitive: security vulnerabilities, \
+         weapons, self-harm, extremism, surveillance of individuals.\n\
+         ALLOW everything else, including news, history and policy on sensitive subjects.\n\n\
+         Topic: {topic}"
+    )
+}
+
+/// The verdict in a classifier reply. A reply that names no verdict is taken for `Review`, so
+/// an unexpected answer is looked at rather than passed.
+pub fn parse_verdict(reply: &str) -> Verdict {
+    let line = reply.trim().lines().next().unwrap_or_default().trim();
+    let (label, reason) = line.split_once(':').unwrap_or((line, ""));
+    let reason = crate::truncate::chars(reason.trim(), MAX_REASON_CHARS);
+    let reason = if reason.is_empty() {
+        "the safety classifier flagged the topic".to_string()
+    } else {
+        reason
+    };
+    match label.trim().trim_matches(['*', '`']).to_ascii_uppercase().as_str() {
+        "ALLOW" => Verdict::Allowed,
+        "BLOCK" => Verdict::Blocked(reason),
+        "REVIEW" => Verdict::Review(reason),
+        _ => Verdict::Review(format!(
+            "unexpected safety classifier reply: {}",
+            crate::truncate::chars(line, MAX_REASON_CHARS)
+        )),
+    }
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct FlaggedTopic {
+    pub workspace: String,
+    pub topic: String,
+    pub reason: String,
+    pub flagged_at: u64,
+}
+
+/// Topics that ran but were flagged for review, kept in agent state.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct ModerationQueue {
+    flagged: Vec<FlaggedTopic>,
+}
+
+impl ModerationQueue {
+    pub fn flag(&mut self, workspace: &str, topic: &str, reason: &str) {
+        log::warn!("Topic {topic:?} in workspace {workspace:?} flagged for review: {reason}");
+        self.flagged.push(FlaggedTopic {
+            workspace: workspace.to_string(),
+            topic: topic.to_string(),
+            reason: reason.to_string(),
+            flagged_at: clock::now_secs(),
+        });
+        if self.flagged.len() > MAX_FLAGGED {
+            self.flagged.remove(0);
+        }
+    }
+
+    /// The workspace's flagged topics, newest first.
+    pub fn list(&self, workspace: &str) -> Vec<FlaggedTopic> {
+        self.flagged
+            .iter()
+            .rev()
+            .filter(|flagged| flagged.workspace == workspace)
+            .cloned()
+            .collect()
+    }
+}
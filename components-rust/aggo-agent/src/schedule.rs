@@ -0,0 +1,460 @@
+//! Cron-scheduled recurring research.
+//!
+//! Schedules live in agent state, so they survive worker restarts through Golem's durable
+//! execution. The agent arms a scheduled invocation of `run_due_schedules` for the earliest
+//! `next_run_at`; each run re-arms for the next one.
+
+use golem_rust::Schema;
+
+use crate::clock;
+use crate::notify::NotificationPrefs;
+use crate::schema::SCHEMA_VERSION;
+use crate::{ResearchOptions, TemplateParam};
+
+/// What a schedule runs: either a plain topic with options, or a named template.
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+// Part of the agent's interface, where a boxed variant has no representation.
+#[allow(clippy::large_enum_variant)]
+pub enum ResearchRecipe {
+    Topic(TopicRecipe),
+    Template(TemplateRecipe),
+}
+
+impl ResearchRecipe {
+    /// Short human-readable label used in notification titles.
+    pub fn label(&self) -> String {
+        match self {
+            Self::Topic(recipe) => recipe.topic.clone(),
+            Self::Template(recipe) => format!("template {}", recipe.name),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct TopicRecipe {
+    pub topic: String,
+    pub options: ResearchOptions,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct TemplateRecipe {
+    pub name: String,
+    pub params: Vec<TemplateParam>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ScheduleInfo {
+    /// See `schema`; version 0 is a record from before versioning.
+    #[serde(default)]
+    pub schema_version: u32,
+    pub id: String,
+    pub workspace: String,
+    pub recipe: ResearchRecipe,
+    pub cron: String,
+    pub paused: bool,
+    /// Unix seconds (UTC) of the next run; `None` while paused.
+    pub next_run_at: Option<u64>,
+    pub last_run_at: Option<u64>,
+    pub last_report: Option<String>,
+    /// Returned with credentials redacted; see `NotificationPrefs::redacted`.
+    pub notifications: NotificationPrefs,
+    /// Outcome of the most recent delivery attempt, e.g. "delivered via slack".
+    pub last_delivery: Option<String>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ScheduleRun {
+    pub schedule_id: String,
+    pub ran_at: u64,
+    pub report: String,
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct ScheduleBook {
+    schedules: Vec<Schedule>,
+    next_id: u64,
+    /// Earliest time a `run_due_schedules` invocation is already scheduled for.
+    armed_at: Option<u64>,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct Schedule {
+    info: ScheduleInfo,
+    cron: CronExpr,
+}
+
+impl ScheduleInfo {
+    /// This info as returned to callers; `due` keeps the real delivery credentials.
+    fn redacted(&self) -> Self {
+        Self {
+            notifications: self.notifications.redacted(),
+            ..self.clone()
+        }
+    }
+}
+
+impl ScheduleBook {
+    pub fn add(
+        &mut self,
+        workspace: &str,
+        recipe: ResearchRecipe,
+        cron: &str,
+        notifications: NotificationPrefs,
+        now: u64,
+    ) -> Result<ScheduleInfo, String> {
+        notifications.validate()?;
+        let expr = CronExpr::parse(cron)?;
+        let next_run_at = expr.next_after(now);
+        if next_run_at.is_none() {
+            return Err(format!("Cron expression {cron:?} never fires"));
+        }
+
+        self.next_id += 1;
+        let info = ScheduleInfo {
+            schema_version: SCHEMA_VERSION,
+            id: format!("sched-{}", self.next_id),
+            workspace: workspace.to_string(),
+            recipe,
+            cron: cron.trim().to_string(),
+            paused: false,
+            next_run_at,
+            last_run_at: None,
+            last_report: None,
+            notifications,
+            last_delivery: None,
+        };
+        self.schedules.push(Schedule {
+            info: info.clone(),
+            cron: expr,
+        });
+        Ok(info.redacted())
+    }
+
+    pub fn list(&self, workspace: &str) -> Vec<ScheduleInfo> {
+        self.schedules
+            .iter()
+            .filter(|s| s.info.workspace == workspace)
+            .map(|s| s.info.redacted())
+            .collect()
+    }
+
+    pub fn pause(&mut self, workspace: &str, id: &str) -> Result<ScheduleInfo, String> {
+        let schedule = self.find_mut(workspace, id)?;
+        schedule.info.paused = true;
+        schedule.info.next_run_at = None;
+        Ok(schedule.info.redacted())
+    }
+
+    pub fn resume(&mut self, workspace: &str, id: &str, now: u64) -> Result<ScheduleInfo, String> {
+        let schedule = self.find_mut(workspace, id)?;
+        schedule.info.paused = false;
+        schedule.info.next_run_at = schedule.cron.next_after(now);
+        Ok(schedule.info.redacted())
+    }
+
+    pub fn set_notifications(
+        &mut self,
+        workspace: &str,
+        id: &str,
+        prefs: NotificationPrefs,
+    ) -> Result<ScheduleInfo, String> {
+        let schedule = self.find_mut(workspace, id)?;
+        let prefs = prefs.keep_credentials(&schedule.info.notifications);
+        prefs.validate()?;
+        schedule.info.notifications = prefs;
+        Ok(schedule.info.redacted())
+    }
+
+    pub fn set_last_delivery(&mut self, id: &str, status: String) {
+        if let Some(schedule) = self.find_by_id(id) {
+            schedule.info.last_delivery = Some(status);
+        }
+    }
+
+    pub fn remove(&mut self, workspace: &str, id: &str) -> bool {
+        let before = self.schedules.len();
+        self.schedules
+            .retain(|s| !(s.info.id == id && s.info.workspace == workspace));
+        self.schedules.len() != before
+    }
+
+    /// Due schedules, most overdue first.
+    pub fn due(&self, now: u64) -> Vec<ScheduleInfo> {
+        let mut due: Vec<ScheduleInfo> = self
+            .schedules
+            .iter()
+            .filter(|s| !s.info.paused && s.info.next_run_at.is_some_and(|at| at <= now))
+            .map(|s| s.info.clone())
+            .collect();
+        due.sort_by_key(|s| s.next_run_at);
+        due
+    }
+
+    pub fn record_run(&mut self, id: &str, now: u64, report: String) {
+        if let Some(schedule) = self.find_by_id(id) {
+            schedule.info.last_run_at = Some(now);
+            schedule.info.last_report = Some(report);
+            schedule.info.next_run_at = schedule.cron.next_after(now);
+        }
+    }
+
+    /// Returns the time to arm a wakeup for, if it is earlier than any wakeup already armed.
+    /// `also` lets callers fold in other timed work, such as deferred notifications; anything
+    /// earlier than `not_before` (e.g. a backlog the background lane yielded on) is pushed to it.
+    pub fn wakeup_to_arm(&mut self, now: u64, also: Option<u64>, not_before: u64) -> Option<u64> {
+        if self.armed_at.is_some_and(|at| at <= now) {
+            self.armed_at = None;
+        }
+
+        let next = self
+            .schedules
+            .iter()
+            .filter_map(|s| s.info.next_run_at)
+            .chain(also)
+            .min()?
+            .max(not_before);
+
+        match self.armed_at {
+            Some(armed) if armed <= next => None,
+            _ => {
+                self.armed_at = Some(next);
+                Some(next)
+            }
+        }
+    }
+
+    fn find_mut(&mut self, workspace: &str, id: &str) -> Result<&mut Schedule, String> {
+        self.schedules
+            .iter_mut()
+            .find(|s| s.info.id == id && s.info.workspace == workspace)
+            .ok_or_else(|| format!("Unknown schedule {id:?} in workspace {workspace:?}"))
+    }
+
+    /// Lookup for internal bookkeeping, where the caller already holds a valid schedule ID.
+    fn find_by_id(&mut self, id: &str) -> Option<&mut Schedule> {
+        self.schedules.iter_mut().find(|s| s.info.id == id)
+    }
+}
+
+/// A standard five-field cron expression (`minute hour day-of-month month day-of-week`),
+/// evaluated in UTC. Supports `*`, lists, ranges, steps, month/day names, and the
+/// `@hourly`/`@daily`/`@weekly`/`@monthly`/`@yearly` shorthands.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CronExpr {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far ahead `next_after` searches before giving up (covers leap-day schedules).
+const SEARCH_DAYS: u64 = 366 * 5;
+
+impl CronExpr {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = expr.trim();
+        let expanded = match expr.to_ascii_lowercase().as_str() {
+            "@hourly" => "0 * * * *".to_string(),
+            "@daily" | "@midnight" => "0 0 * * *".to_string(),
+            "@weekly" => "0 0 * * 0".to_string(),
+            "@monthly" => "0 0 1 * *".to_string(),
+            "@yearly" | "@annually" => "0 0 1 1 *".to_string(),
+            _ => expr.to_string(),
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "Invalid cron expression {expr:?}: expected 5 fields (minute hour day-of-month month day-of-week)"
+            ));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7, &DAY_NAMES, 0)
+            .map_err(|e| format!("Invalid day-of-week in {expr:?}: {e}"))?;
+        // Both 0 and 7 mean Sunday.
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59, &[], 0)
+                .map_err(|e| format!("Invalid minute in {expr:?}: {e}"))?,
+            hours: parse_field(fields[1], 0, 23, &[], 0)
+                .map_err(|e| format!("Invalid hour in {expr:?}: {e}"))?,
+            days_of_month: parse_field(fields[2], 1, 31, &[], 0)
+                .map_err(|e| format!("Invalid day-of-month in {expr:?}: {e}"))?,
+            months: parse_field(fields[3], 1, 12, &MONTH_NAMES, 1)
+                .map_err(|e| format!("Invalid month in {expr:?}: {e}"))?,
+            days_of_week,
+            // Like Vixie cron, a field starting with `*` (`*`, `*/2`) counts as unrestricted.
+            dom_restricted: !fields[2].starts_with('*'),
+            dow_restricted: !fields[4].starts_with('*'),
+        })
+    }
+
+    /// The first matching minute strictly after `after` (unix seconds, UTC).
+    pub fn next_after(&self, after: u64) -> Option<u64> {
+        let start_minute = after / 60 + 1;
+        let first_day = start_minute / 1440;
+
+        (first_day..first_day + SEARCH_DAYS)
+            .filter(|&day| self.matches_day(day))
+            .find_map(|day| {
+                let from = if day == first_day { start_minute % 1440 } else { 0 };
+                (from..1440)
+                    .find(|&m| self.hours[(m / 60) as usize] && self.minutes[(m % 60) as usize])
+                    .map(|m| (day * 1440 + m) * 60)
+            })
+    }
+
+    fn matches_day(&self, days_since_epoch: u64) -> bool {
+        let (_, month, day) = clock::civil_from_days(days_since_epoch);
+        if !self.months[month as usize] {
+            return false;
+        }
+
+        // 1970-01-01 was a Thursday.
+        let weekday = ((days_since_epoch + 4) % 7) as usize;
+        let dom = self.days_of_month[day as usize];
+        let dow = self.days_of_week[weekday];
+
+        // Vixie cron semantics: when both fields are restricted, either may match.
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            _ => dom && dow,
+        }
+    }
+}
+
+/// Parses one cron field into a lookup table indexed by value (`0..=max`).
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], name_base: u32) -> Result<Vec<bool>, String> {
+    let mut allowed = vec![false; max as usize + 1];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("bad step {step:?}"))?,
+            ),
+            None => (part, 1),
+        };
+
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((lo, hi)) = range.split_once('-') {
+            (
+                parse_value(lo, names, name_base)?,
+                parse_value(hi, names, name_base)?,
+            )
+        } else {
+            let value = parse_value(range, names, name_base)?;
+            // `5/15` means "from 5 to the end, every 15".
+            (value, if part.contains('/') { max } else { value })
+        };
+
+        if lo < min || hi > max || lo > hi {
+            return Err(format!("{part:?} is outside {min}-{max}"));
+        }
+
+        for value in (lo..=hi).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+
+    Ok(allowed)
+}
+
+fn parse_value(value: &str, names: &[&str], name_base: u32) -> Result<u32, String> {
+    if let Ok(n) = value.parse::<u32>() {
+        return Ok(n);
+    }
+    let lower = value.to_ascii_lowercase();
+    names
+        .iter()
+        .position(|name| *name == lower)
+        .map(|index| index as u32 + name_base)
+        .ok_or_else(|| format!("unknown value {value:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i64, month: u32, day: u32, hour: u64, minute: u64) -> u64 {
+        clock::days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60
+    }
+
+    fn next(expr: &str, after: u64) -> u64 {
+        CronExpr::parse(expr).unwrap().next_after(after).unwrap()
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        assert!(CronExpr::parse("0 0 * *").is_err());
+        assert!(CronExpr::parse("60 * * * *").is_err());
+        assert!(CronExpr::parse("0 0 0 * *").is_err());
+        assert!(CronExpr::parse("*/0 * * * *").is_err());
+        assert!(CronExpr::parse("0 0 * foo *").is_err());
+        assert!(CronExpr::parse("0 0 5-1 * *").is_err());
+    }
+
+    #[test]
+    fn expands_shorthands() {
+        assert_eq!(CronExpr::parse("@daily"), CronExpr::parse("0 0 * * *"));
+        assert_eq!(CronExpr::parse("@WEEKLY"), CronExpr::parse("0 0 * * sun"));
+        assert_eq!(CronExpr::parse("@yearly"), CronExpr::parse("0 0 1 jan *"));
+    }
+
+    #[test]
+    fn next_is_strictly_after() {
+        // 2026-10-16 is a Friday.
+        let friday_0930 = at(2026, 10, 16, 9, 30);
+        assert_eq!(next("30 9 * * *", friday_0930 - 60), friday_0930);
+        assert_eq!(next("30 9 * * *", friday_0930), at(2026, 10, 17, 9, 30));
+        assert_eq!(next("*/15 * * * *", friday_0930), at(2026, 10, 16, 9, 45));
+    }
+
+    #[test]
+    fn matches_names_ranges_and_sunday_as_seven() {
+        let friday = at(2026, 10, 16, 12, 0);
+        assert_eq!(next("0 0 * * 7", friday), at(2026, 10, 18, 0, 0));
+        assert_eq!(next("0 0 * * 0", friday), at(2026, 10, 18, 0, 0));
+        assert_eq!(next("0 8 * * mon-fri", friday), at(2026, 10, 19, 8, 0));
+        assert_eq!(next("0 0 1 jan,jul *", friday), at(2027, 1, 1, 0, 0));
+    }
+
+    #[test]
+    fn either_day_field_matches_when_both_are_restricted() {
+        // The 20th is a Tuesday, before the next Friday.
+        assert_eq!(next("0 0 20 * 5", at(2026, 10, 17, 0, 0)), at(2026, 10, 20, 0, 0));
+        assert_eq!(next("0 0 13 * 5", at(2026, 10, 16, 12, 0)), at(2026, 10, 23, 0, 0));
+    }
+
+    #[test]
+    fn wildcard_day_fields_are_unrestricted() {
+        // Only the day of week restricts.
+        assert_eq!(next("0 0 * * 1", at(2026, 10, 16, 12, 0)), at(2026, 10, 19, 0, 0));
+        // `*/2` is unrestricted, so both fields must match: odd days that are Mondays.
+        assert_eq!(next("0 0 */2 * 1", at(2026, 10, 16, 12, 0)), at(2026, 10, 19, 0, 0));
+        assert_eq!(next("0 0 */2 * 1", at(2026, 10, 19, 0, 0)), at(2026, 11, 9, 0, 0));
+        // Only the day of month restricts.
+        assert_eq!(next("0 0 1 * */2", at(2026, 10, 16, 12, 0)), at(2026, 11, 1, 0, 0));
+    }
+
+    #[test]
+    fn finds_leap_days() {
+        assert_eq!(next("0 0 29 2 *", at(2026, 10, 16, 0, 0)), at(2028, 2, 29, 0, 0));
+    }
+}
@@ -0,0 +1,9 @@
+//! Versioning of the records the agent keeps and returns.
+//!
+//! Records that outlive a single call (stored reports, jobs, streams, corpus documents,
+//! schedules and watches) and the structured research result carry `schema_version`, bumped
+//! whenever their shape changes incompatibly. Serialized records without the field predate
+//! versioning and read as version 0. Fields added since are `#[serde(default)]`, so JSON from
+//! an older component still deserializes, and unknown fields from a newer one are ignored.
+
+pub const SCHEMA_VERSION: u32 = 1;
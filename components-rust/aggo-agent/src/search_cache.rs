@@ -0,0 +1,105 @@
+//! Web search results kept per provider and query, so a topic researched again within
+//! `SEARCH_CACHE_TTL_SECONDS` (default 0, off) doesn't spend provider quota. The key is the
+//! provider with the query as sent (case-folded, whitespace collapsed) and the options that
+//! change what the provider returns: domains, pages, results per page, language, time range,
+//! news days and full-text mode. Exclusion filters are applied after the lookup, as for fresh
+//! results.
+//!
+//! Empty results and failures aren't kept. `SEARCH_CACHE_MAX_ENTRIES` (default 200, oldest
+//! dropped first) bounds the cache, which is part of the worker snapshot; `clear_cache`
+//! empties it and `ResearchOptions::bypass_cache` skips it.
+
+use std::cell::RefCell;
+
+use crate::config;
+use crate::options::ResearchOptions;
+use crate::query::ProviderQuery;
+use crate::SearchResult;
+
+const DEFAULT_MAX_ENTRIES: usize = 200;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CachedSearch {
+    pub key: String,
+    pub results: Vec<SearchResult>,
+    pub stored_at: u64,
+}
+
+pub struct SearchCachePolicy {
+    ttl_seconds: u64,
+    max_entries: usize,
+}
+
+impl SearchCachePolicy {
+    pub fn from_env() -> Self {
+        Self {
+            ttl_seconds: config::env_parse::<u64>("SEARCH_CACHE_TTL_SECONDS").unwrap_or(0),
+            max_entries: config::env_parse::<u64>("SEARCH_CACHE_MAX_ENTRIES").map_or(DEFAULT_MAX_ENTRIES, |n| n as usize),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.ttl_seconds > 0 && self.max_entries > 0
+    }
+}
+
+/// Written from the `&self` research pipeline, hence the `RefCell`.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct SearchCache {
+    entries: RefCell<Vec<CachedSearch>>,
+}
+
+/// The cache key of a search with `provider` (its display name).
+pub fn key(provider: &str, query: &ProviderQuery, pages: u32, options: &ResearchOptions) -> String {
+    let normalized = query.query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let domains = |domains: &[String]| {
+        let mut domains: Vec<String> = domains.iter().map(|d| d.to_lowercase()).collect();
+        domains.sort();
+        domains.join(",")
+    };
+    format!(
+        "{}\n{normalized}\n+{}\n-{}\n{pages}/{}/{}/{:?}/{:?}/{}",
+        provider.to_lowercase(),
+        domains(&query.include_domains),
+        domains(&query.exclude_domains),
+        options.max_results(),
+        options.language(),
+        options.time_range(),
+        options.news_days(),
+        options.full_text()
+    )
+}
+
+impl SearchCache {
+    /// The results kept under `key`, if they are younger than the TTL.
+    pub fn get(&self, key: &str, now: u64, policy: &SearchCachePolicy) -> Option<Vec<SearchResult>> {
+        if !policy.enabled() {
+            return None;
+        }
+        self.entries
+            .borrow()
+            .iter()
+            .find(|e| e.key == key && now.saturating_sub(e.stored_at) < policy.ttl_seconds)
+            .map(|e| e.results.clone())
+    }
+
+    pub fn store(&self, key: String, results: &[SearchResult], now: u64, policy: &SearchCachePolicy) {
+        if !policy.enabled() || results.is_empty() {
+            return;
+        }
+        let mut entries = self.entries.borrow_mut();
+        entries.retain(|e| e.key != key && now.saturating_sub(e.stored_at) < policy.ttl_seconds);
+        entries.push(CachedSearch {
+            key,
+            results: results.to_vec(),
+            stored_at: now,
+        });
+        let excess = entries.len().saturating_sub(policy.max_entries);
+        entries.drain(..excess);
+    }
+
+    /// Empties the cache and returns how many entries it had.
+    pub fn clear(&self) -> u32 {
+        self.entries.take().len() as u32
+    }
+}
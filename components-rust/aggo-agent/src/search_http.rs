@@ -0,0 +1,899 @@
+//! Direct HTTP clients for web search providers.
+//!
+//! Only one provider can be linked through golem-ai per build (`WEB_SEARCH_PROVIDER`). The
+//! other providers in a `WEB_SEARCH_PROVIDERS` fallback chain are called here, over
+//! `golem_wasi_http`, with the same API keys. Bing, DuckDuckGo, Exa, Kagi and SearXNG have no
+//! golem-ai component and are always called here. Kagi's summarizer, used to pre-summarize sources, lives here too.
+
+use std::time::Duration;
+
+use crate::options::TimeRange;
+use crate::query::ProviderQuery;
+use crate::quota::QuotaBook;
+use crate::{clock, fetch, http_util, usage, ResearchOptions, SearchResult};
+
+const BRAVE_API: &str = "https://api.search.brave.com/res/v1/web/search";
+/// Brave returns at most 20 results per page and 9 pages past the first.
+const BRAVE_MAX_COUNT: u32 = 20;
+const BRAVE_MAX_OFFSET: u32 = 9;
+const BRAVE_NEWS_API: &str = "https://api.search.brave.com/res/v1/news/search";
+/// Brave News returns at most 50 results per page.
+const BRAVE_NEWS_MAX_COUNT: u32 = 50;
+const BING_API: &str = "https://api.bing.microsoft.com/v7.0/search";
+/// Bing returns at most 50 results per request.
+const BING_MAX_COUNT: u32 = 50;
+const DUCKDUCKGO_LITE: &str = "https://lite.duckduckgo.com/lite/";
+/// DuckDuckGo pages fetched at most; the lite endpoint is meant for light use.
+const DUCKDUCKGO_MAX_PAGES: u32 = 3;
+/// The lite endpoint turns away requests without a browser-like user agent.
+const DUCKDUCKGO_USER_AGENT: &str = "Mozilla/5.0 (compatible; aggo-agent; +https://github.com/haymant/AGGO-AGENT)";
+const EXA_API: &str = "https://api.exa.ai/search";
+/// Exa returns at most 100 results per request.
+const EXA_MAX_RESULTS: u32 = 100;
+/// Highlight excerpts requested per result, and sentences per excerpt.
+const EXA_HIGHLIGHTS: u32 = 3;
+const EXA_HIGHLIGHT_SENTENCES: u32 = 3;
+/// Page text requested per result in full-text mode.
+const EXA_MAX_TEXT_CHARS: u32 = 20_000;
+const KAGI_SEARCH_API: &str = "https://kagi.com/api/v0/search";
+const KAGI_SUMMARIZE_API: &str = "https://kagi.com/api/v0/summarize";
+/// Summaries take a while for long pages.
+const KAGI_SUMMARIZE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Pages fetched from SearXNG at most; its page size is set by the instance.
+const SEARXNG_MAX_PAGES: u32 = 10;
+const SERPER_API: &str = "https://google.serper.dev/search";
+const SERPER_NEWS_API: &str = "https://google.serper.dev/news";
+/// Serper pages past this rarely return anything new.
+const SERPER_MAX_PAGES: u32 = 10;
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Brave Search web results; Brave has no page text, so results always carry the snippet.
+pub fn brave_http_search(
+    search_query: &ProviderQuery,
+    pages: u32,
+    options: &ResearchOptions,
+    quotas: &QuotaBook,
+    run_usage: &mut usage::RunUsage,
+) -> Result<Vec<SearchResult>, String> {
+    let key = std::env::var("BRAVE_API_KEY").map_err(|_| "BRAVE_API_KEY is not set".to_string())?;
+    let count = options.max_results().min(BRAVE_MAX_COUNT);
+    let mut results = Vec::new();
+
+    for offset in 0..pages.min(BRAVE_MAX_OFFSET + 1) {
+        let mut params = vec![
+            ("q", search_query.query.clone()),
+            ("count", count.to_string()),
+            ("offset", offset.to_string()),
+            ("search_lang", options.language()),
+        ];
+        if let Some(range) = options.time_range() {
+            let freshness = match range {
+                TimeRange::Day => "pd",
+                TimeRange::Week => "pw",
+                TimeRange::Month => "pm",
+                TimeRange::Year => "py",
+            };
+            params.push(("freshness", freshness.to_string()));
+        }
+
+        run_usage.search_calls += 1;
+        let request = || {
+            golem_wasi_http::Client::new()
+                .get(BRAVE_API)
+                .query(&params)
+                .header("Accept", "application/json")
+                .header("X-Subscription-Token", key.trim())
+                .timeout(http_util::timeout(TIMEOUT))
+        };
+        let response = send(quotas, "brave", "Brave search", request)?;
+        if !response.is_success() {
+            return Err(format!("Brave search failed: HTTP {}", response.status()));
+        }
+        let body: serde_json::Value = response
+            .json()
+            .map_err(|err| format!("Brave search returned invalid JSON: {err}"))?;
+
+        let page: Vec<SearchResult> = body["web"]["results"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                let url = item["url"].as_str()?.to_string();
+                Some(SearchResult {
+                    title: plain_text(item["title"].as_str().unwrap_or(&url)),
+                    snippet: plain_text(item["description"].as_str().unwrap_or_default()),
+                    url,
+                })
+            })
+            .collect();
+        let last_page = (page.len() as u32) < count;
+        results.extend(page);
+        if last_page {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+/// Brave News results from the last `days` days, newest first as Brave ranks them. Each snippet
+/// starts with the article's publication date so the report can date events.
+pub fn brave_news_search(
+    search_query: &ProviderQuery,
+    pages: u32,
+    options: &ResearchOptions,
+    days: u32,
+    quotas: &QuotaBook,
+    run_usage: &mut usage::RunUsage,
+) -> Result<Vec<SearchResult>, String> {
+    let key = std::env::var("BRAVE_API_KEY").map_err(|_| "BRAVE_API_KEY is not set".to_string())?;
+    let count = options.max_results().min(BRAVE_NEWS_MAX_COUNT);
+    let now = clock::now_secs();
+    let since = clock::date_key(now.saturating_sub(u64::from(days) * 86_400));
+    let mut results = Vec::new();
+
+    for offset in 0..pages.min(BRAVE_MAX_OFFSET + 1) {
+        let params = vec![
+            ("q", search_query.query.clone()),
+            ("count", count.to_string()),
+            ("offset", offset.to_string()),
+            ("search_lang", options.language()),
+            ("freshness", format!("{since}to{}", clock::date_key(now))),
+        ];
+
+        run_usage.search_calls += 1;
+        let request = || {
+            golem_wasi_http::Client::new()
+                .get(BRAVE_NEWS_API)
+                .query(&params)
+                .header("Accept", "application/json")
+                .header("X-Subscription-Token", key.trim())
+                .timeout(http_util::timeout(TIMEOUT))
+        };
+        let response = send(quotas, "brave", "Brave news search", request)?;
+        if !response.is_success() {
+            return Err(format!("Brave news search failed: HTTP {}", response.status()));
+        }
+        let body: serde_json::Value = response
+            .json()
+            .map_err(|err| format!("Brave news search returned invalid JSON: {err}"))?;
+
+        let items = body["results"].as_array().map(Vec::as_slice).unwrap_or_default();
+        let last_page = (items.len() as u32) < count;
+        for item in items {
+            let Some(url) = item["url"].as_str() else {
+                continue;
+            };
+            // `page_age` is an ISO timestamp; its date prefix compares as a string.
+            let published = item["page_age"].as_str().map(|age| age.get(..10).unwrap_or(age));
+            if published.is_some_and(|date| date < since.as_str()) {
+                continue;
+            }
+            let description = plain_text(item["description"].as_str().unwrap_or_default());
+            results.push(SearchResult {
+                url: url.to_string(),
+                title: plain_text(item["title"].as_str().unwrap_or(url)),
+                snippet: match published {
+                    Some(date) => format!("({date}) {description}"),
+                    None => description,
+                },
+            });
+        }
+        if last_page {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+/// Bing Web Search results from `webPages.value`. The market comes from `BING_MARKET` (e.g.
+/// `en-US`); without it Bing picks one from the request, and only the UI language is set.
+pub fn bing_http_search(
+    search_query: &ProviderQuery,
+    pages: u32,
+    options: &ResearchOptions,
+    quotas: &QuotaBook,
+    run_usage: &mut usage::RunUsage,
+) -> Result<Vec<SearchResult>, String> {
+    let key = std::env::var("BING_API_KEY").map_err(|_| "BING_API_KEY is not set".to_string())?;
+    let market = std::env::var("BING_MARKET")
+        .ok()
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty());
+    let count = options.max_results().min(BING_MAX_COUNT);
+    let mut results = Vec::new();
+
+    for page in 0..pages.max(1) {
+        let mut params = vec![
+            ("q", search_query.query.clone()),
+            ("count", count.to_string()),
+            ("offset", (page * count).to_string()),
+            ("setLang", options.language()),
+            ("responseFilter", "Webpages".to_string()),
+            ("textDecorations", "false".to_string()),
+        ];
+        if let Some(market) = &market {
+            params.push(("mkt", market.clone()));
+        }
+        if let Some(range) = options.time_range() {
+            let freshness = match range {
+                TimeRange::Day => "Day".to_string(),
+                TimeRange::Week => "Week".to_string(),
+                TimeRange::Month => "Month".to_string(),
+                // Bing has no year value; a date range does the same.
+                TimeRange::Year => {
+                    let now = clock::now_secs();
+                    format!("{}..{}", clock::date_key(now.saturating_sub(365 * 86_400)), clock::date_key(now))
+                }
+            };
+            params.push(("freshness", freshness));
+        }
+
+        run_usage.search_calls += 1;
+        let request = || {
+            golem_wasi_http::Client::new()
+                .get(BING_API)
+                .query(&params)
+                .header("Ocp-Apim-Subscription-Key", key.trim())
+                .timeout(http_util::timeout(TIMEOUT))
+        };
+        let response = send(quotas, "bing", "Bing search", request)?;
+        if !response.is_success() {
+            return Err(format!("Bing search failed: HTTP {}", response.status()));
+        }
+        let body: serde_json::Value = response
+            .json()
+            .map_err(|err| format!("Bing search returned invalid JSON: {err}"))?;
+
+        let page: Vec<SearchResult> = body["webPages"]["value"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                let url = item["url"].as_str()?.to_string();
+                Some(SearchResult {
+                    title: plain_text(item["name"].as_str().unwrap_or(&url)),
+                    snippet: plain_text(item["snippet"].as_str().unwrap_or_default()),
+                    url,
+                })
+            })
+            .collect();
+        let last_page = (page.len() as u32) < count;
+        results.extend(page);
+        if last_page {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+/// DuckDuckGo results scraped from the HTML lite endpoint. Needs no API key, which makes it the
+/// fallback when no other provider is configured; it is meant for local development, as
+/// DuckDuckGo may rate-limit or block automated use.
+pub fn duckduckgo_http_search(
+    search_query: &ProviderQuery,
+    pages: u32,
+    options: &ResearchOptions,
+    quotas: &QuotaBook,
+    run_usage: &mut usage::RunUsage,
+) -> Result<Vec<SearchResult>, String> {
+    let max_results = options.max_results() as usize;
+    let mut results: Vec<SearchResult> = Vec::new();
+
+    for _ in 0..pages.clamp(1, DUCKDUCKGO_MAX_PAGES) {
+        let mut params = vec![("q", search_query.query.clone())];
+        if !results.is_empty() {
+            params.push(("s", results.len().to_string()));
+            params.push(("dc", (results.len() + 1).to_string()));
+        }
+        if let Some(range) = options.time_range() {
+            let df = match range {
+                TimeRange::Day => "d",
+                TimeRange::Week => "w",
+                TimeRange::Month => "m",
+                TimeRange::Year => "y",
+            };
+            params.push(("df", df.to_string()));
+        }
+
+        run_usage.search_calls += 1;
+        let request = || {
+            golem_wasi_http::Client::new()
+                .get(DUCKDUCKGO_LITE)
+                .query(&params)
+                .header("User-Agent", DUCKDUCKGO_USER_AGENT)
+                .header("Accept-Language", options.language())
+                .timeout(http_util::timeout(TIMEOUT))
+        };
+        let response = send(quotas, "duckduckgo", "DuckDuckGo search", request)?;
+        if !response.is_success() {
+            return Err(format!("DuckDuckGo search failed: HTTP {}", response.status()));
+        }
+        let html = response.text();
+
+        let page = parse_duckduckgo_lite(&html);
+        let found = page.len();
+        for result in page {
+            if results.len() < max_results && !results.iter().any(|r| r.url == result.url) {
+                results.push(result);
+            }
+        }
+        if found == 0 || results.len() >= max_results {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+/// Result links (`class="result-link"`) and the `result-snippet` cell that follows each.
+fn parse_duckduckgo_lite(html: &str) -> Vec<SearchResult> {
+    let mut results = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find("<a ") {
+        let Some(tag_end) = rest[start..].find('>').map(|i| start + i) else {
+            break;
+        };
+        let attrs = &rest[start + 3..tag_end];
+        rest = &rest[tag_end + 1..];
+        if !fetch::attribute(attrs, "class").is_some_and(|class| class.contains("result-link")) {
+            continue;
+        }
+        let Some(url) = fetch::attribute(attrs, "href").and_then(|href| duckduckgo_target(&href)) else {
+            continue;
+        };
+
+        let title_end = fetch::find_close_tag(rest, "a").unwrap_or(rest.len());
+        let title = plain_text(&rest[..title_end]);
+        let next_link = rest.find("result-link").unwrap_or(rest.len());
+        let snippet = rest[..next_link]
+            .find("result-snippet")
+            .and_then(|at| {
+                let cell = &rest[at..next_link];
+                let body = &cell[cell.find('>')? + 1..];
+                let end = fetch::find_close_tag(body, "td").unwrap_or(body.len());
+                Some(plain_text(&body[..end]))
+            })
+            .unwrap_or_default();
+        results.push(SearchResult {
+            title: if title.is_empty() { url.clone() } else { title },
+            snippet,
+            url,
+        });
+    }
+    results
+}
+
+/// The result URL behind a lite-page link: `//duckduckgo.com/l/?uddg=<encoded URL>` redirects
+/// are unwrapped; ad links (`/y.js`) are dropped.
+fn duckduckgo_target(href: &str) -> Option<String> {
+    if href.contains("duckduckgo.com/y.js") {
+        return None;
+    }
+    if let Some(at) = href.find("uddg=") {
+        let encoded = href[at + 5..].split('&').next().unwrap_or_default();
+        return Some(percent_decode(encoded)).filter(|url| url.starts_with("http"));
+    }
+    Some(href.to_string()).filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Exa search results. `EXA_SEARCH_TYPE` picks `neural` (default, embedding-based), `keyword`
+/// or `auto`. Snippets are Exa's highlighted excerpts, the passages most relevant to the
+/// query; in full-text mode the page text is used. Exa has no paging, so one request asks for
+/// all pages' worth of results. Domain filters are sent as Exa's include/exclude domains.
+pub fn exa_http_search(
+    search_query: &ProviderQuery,
+    pages: u32,
+    options: &ResearchOptions,
+    quotas: &QuotaBook,
+    run_usage: &mut usage::RunUsage,
+) -> Result<Vec<SearchResult>, String> {
+    let key = std::env::var("EXA_API_KEY").map_err(|_| "EXA_API_KEY is not set".to_string())?;
+    let full_text = options.full_text();
+    let mut contents = serde_json::json!({
+        "highlights": {
+            "numSentences": EXA_HIGHLIGHT_SENTENCES,
+            "highlightsPerUrl": EXA_HIGHLIGHTS,
+            "query": search_query.query,
+        },
+    });
+    if full_text {
+        contents["text"] = serde_json::json!({ "maxCharacters": EXA_MAX_TEXT_CHARS });
+    }
+    let mut payload = serde_json::json!({
+        "query": search_query.query,
+        "type": exa_search_type(),
+        "numResults": (options.max_results() * pages.max(1)).min(EXA_MAX_RESULTS),
+        "contents": contents,
+    });
+    if !search_query.include_domains.is_empty() {
+        payload["includeDomains"] = serde_json::json!(search_query.include_domains);
+    }
+    if !search_query.exclude_domains.is_empty() {
+        payload["excludeDomains"] = serde_json::json!(search_query.exclude_domains);
+    }
+    if let Some(range) = options.time_range() {
+        let days = match range {
+            TimeRange::Day => 1,
+            TimeRange::Week => 7,
+            TimeRange::Month => 30,
+            TimeRange::Year => 365,
+        };
+        let since = clock::now_secs().saturating_sub(days * 86_400);
+        payload["startPublishedDate"] = format!("{}T00:00:00.000Z", clock::date_key(since)).into();
+    }
+
+    run_usage.search_calls += 1;
+    let request = || {
+        golem_wasi_http::Client::new()
+            .post(EXA_API)
+            .header("x-api-key", key.trim())
+            .json(&payload)
+            .timeout(http_util::timeout(TIMEOUT))
+    };
+    let response = send(quotas, "exa", "Exa search", request)?;
+    if !response.is_success() {
+        return Err(format!("Exa search failed: HTTP {}", response.status()));
+    }
+    let body: serde_json::Value = response
+        .json()
+        .map_err(|err| format!("Exa search returned invalid JSON: {err}"))?;
+
+    Ok(body["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let url = item["url"].as_str()?.to_string();
+            let highlights: Vec<&str> = item["highlights"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|h| h.as_str())
+                .map(str::trim)
+                .filter(|h| !h.is_empty())
+                .collect();
+            let text = item["text"].as_str().map(str::trim).filter(|t| full_text && !t.is_empty());
+            let snippet = match text {
+                Some(text) => text.to_string(),
+                None => highlights.join(" … "),
+            };
+            Some(SearchResult {
+                title: item["title"]
+                    .as_str()
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .unwrap_or(&url)
+                    .to_string(),
+                snippet,
+                url,
+            })
+        })
+        .collect())
+}
+
+fn exa_search_type() -> &'static str {
+    match std::env::var("EXA_SEARCH_TYPE")
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "keyword" => "keyword",
+        "auto" => "auto",
+        _ => "neural",
+    }
+}
+
+/// Kagi Search results. Kagi has no paging or freshness parameters, so one request asks for
+/// all pages' worth of results and the time range is not applied.
+pub fn kagi_http_search(
+    search_query: &ProviderQuery,
+    pages: u32,
+    options: &ResearchOptions,
+    quotas: &QuotaBook,
+    run_usage: &mut usage::RunUsage,
+) -> Result<Vec<SearchResult>, String> {
+    let key = kagi_key()?;
+    let limit = options.max_results() * pages.max(1);
+
+    run_usage.search_calls += 1;
+    let request = || {
+        golem_wasi_http::Client::new()
+            .get(KAGI_SEARCH_API)
+            .query(&[("q", search_query.query.clone()), ("limit", limit.to_string())])
+            .header("Authorization", format!("Bot {key}"))
+            .timeout(http_util::timeout(TIMEOUT))
+    };
+    let response = send(quotas, "kagi", "Kagi search", request)?;
+    if !response.is_success() {
+        return Err(format!("Kagi search failed: HTTP {}", response.status()));
+    }
+    let body: serde_json::Value = response
+        .json()
+        .map_err(|err| format!("Kagi search returned invalid JSON: {err}"))?;
+
+    // `t` 0 is a search result; 1 is a list of related searches.
+    Ok(body["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|item| item["t"].as_u64() == Some(0))
+        .filter_map(|item| {
+            let url = item["url"].as_str()?.to_string();
+            Some(SearchResult {
+                title: plain_text(item["title"].as_str().unwrap_or(&url)),
+                snippet: plain_text(item["snippet"].as_str().unwrap_or_default()),
+                url,
+            })
+        })
+        .collect())
+}
+
+/// Kagi's summary of the page at `url`, written in `language` (ISO 639-1).
+pub fn kagi_summarize(
+    url: &str,
+    engine: &str,
+    language: &str,
+    quotas: &QuotaBook,
+    run_usage: &mut usage::RunUsage,
+) -> Result<String, String> {
+    let key = kagi_key()?;
+
+    run_usage.search_calls += 1;
+    let request = || {
+        golem_wasi_http::Client::new()
+            .post(KAGI_SUMMARIZE_API)
+            .header("Authorization", format!("Bot {key}"))
+            .json(&serde_json::json!({
+                "url": url,
+                "engine": engine,
+                "summary_type": "summary",
+                "target_language": language.to_uppercase(),
+            }))
+            .timeout(KAGI_SUMMARIZE_TIMEOUT)
+    };
+    let response = send(quotas, "kagi", "Kagi summarizer", request)?;
+    if !response.is_success() {
+        return Err(format!("Kagi summarizer failed: HTTP {}", response.status()));
+    }
+    let body: serde_json::Value = response
+        .json()
+        .map_err(|err| format!("Kagi summarizer returned invalid JSON: {err}"))?;
+    body["data"]["output"]
+        .as_str()
+        .map(|summary| summary.trim().to_string())
+        .filter(|summary| !summary.is_empty())
+        .ok_or_else(|| "Kagi summarizer returned no summary".to_string())
+}
+
+/// `KAGI_SUMMARIZER`: the Kagi summarizer engine used to pre-summarize web results before the
+/// LLM call (`cecil`, `agnes`, `daphne` or `muriel`); unset turns pre-summarization off.
+pub fn kagi_summarizer_engine() -> Option<String> {
+    let engine = std::env::var("KAGI_SUMMARIZER").ok()?.trim().to_ascii_lowercase();
+    match engine.as_str() {
+        "" | "false" | "off" => None,
+        "cecil" | "agnes" | "daphne" | "muriel" => Some(engine),
+        other => {
+            log::warn!("Unknown KAGI_SUMMARIZER engine {other:?}, using cecil");
+            Some("cecil".to_string())
+        }
+    }
+}
+
+fn kagi_key() -> Result<String, String> {
+    std::env::var("KAGI_API_KEY")
+        .map(|key| key.trim().to_string())
+        .map_err(|_| "KAGI_API_KEY is not set".to_string())
+}
+
+/// Results from a self-hosted SearXNG instance (`SEARXNG_BASE_URL`) through its JSON API, which
+/// has to be enabled in the instance's `search.formats`. `SEARXNG_ENGINES` (comma-separated)
+/// limits the engines queried; otherwise the instance's defaults are used.
+pub fn searxng_http_search(
+    search_query: &ProviderQuery,
+    pages: u32,
+    options: &ResearchOptions,
+    quotas: &QuotaBook,
+    run_usage: &mut usage::RunUsage,
+) -> Result<Vec<SearchResult>, String> {
+    let base_url = std::env::var("SEARXNG_BASE_URL")
+        .ok()
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty())
+        .ok_or_else(|| "SEARXNG_BASE_URL is not set".to_string())?;
+    let engines: Vec<String> = std::env::var("SEARXNG_ENGINES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|engine| engine.trim().to_string())
+        .filter(|engine| !engine.is_empty())
+        .collect();
+    let max_results = options.max_results() as usize;
+    let mut results: Vec<SearchResult> = Vec::new();
+
+    for page in 1..=pages.clamp(1, SEARXNG_MAX_PAGES) {
+        let mut params = vec![
+            ("q", search_query.query.clone()),
+            ("format", "json".to_string()),
+            ("pageno", page.to_string()),
+            ("language", options.language()),
+            ("safesearch", "0".to_string()),
+        ];
+        if !engines.is_empty() {
+            params.push(("engines", engines.join(",")));
+        }
+        if let Some(range) = options.time_range() {
+            let time_range = match range {
+                TimeRange::Day => "day",
+                TimeRange::Week => "week",
+                TimeRange::Month => "month",
+                TimeRange::Year => "year",
+            };
+            params.push(("time_range", time_range.to_string()));
+        }
+
+        run_usage.search_calls += 1;
+        let request = || {
+            golem_wasi_http::Client::new()
+                .get(format!("{base_url}/search"))
+                .query(&params)
+                .header("Accept", "application/json")
+                .timeout(http_util::timeout(TIMEOUT))
+        };
+        let response = send(quotas, "searxng", "SearXNG search", request)?;
+        if !response.is_success() {
+            // 403 is what an instance without the JSON format enabled answers.
+            return Err(format!("SearXNG search failed: HTTP {}", response.status()));
+        }
+        let body: serde_json::Value = response
+            .json()
+            .map_err(|err| format!("SearXNG search returned invalid JSON: {err}"))?;
+
+        let mut found = 0;
+        for item in body["results"].as_array().into_iter().flatten() {
+            let Some(url) = item["url"].as_str() else {
+                continue;
+            };
+            found += 1;
+            // Meta-search merges engines, but the same page can still come back on a later page.
+            if results.len() < max_results && !results.iter().any(|r| r.url == url) {
+                results.push(SearchResult {
+                    url: url.to_string(),
+                    title: plain_text(item["title"].as_str().unwrap_or(url)),
+                    snippet: plain_text(item["content"].as_str().unwrap_or_default()),
+                });
+            }
+        }
+        if found == 0 || results.len() >= max_results {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+/// Serper (Google) results: the knowledge graph entry first, then organic results, then
+/// "people also ask" answers, with duplicate URLs dropped. Results always carry the snippet.
+pub fn serper_http_search(
+    search_query: &ProviderQuery,
+    pages: u32,
+    options: &ResearchOptions,
+    quotas: &QuotaBook,
+    run_usage: &mut usage::RunUsage,
+) -> Result<Vec<SearchResult>, String> {
+    let key = std::env::var("SERPER_API_KEY").map_err(|_| "SERPER_API_KEY is not set".to_string())?;
+    let num = options.max_results();
+    let mut results: Vec<SearchResult> = Vec::new();
+
+    for page in 1..=pages.clamp(1, SERPER_MAX_PAGES) {
+        let mut payload = serde_json::json!({
+            "q": search_query.query,
+            "num": num,
+            "page": page,
+            "hl": options.language(),
+        });
+        if let Some(range) = options.time_range() {
+            let tbs = match range {
+                TimeRange::Day => "qdr:d",
+                TimeRange::Week => "qdr:w",
+                TimeRange::Month => "qdr:m",
+                TimeRange::Year => "qdr:y",
+            };
+            payload["tbs"] = tbs.into();
+        }
+
+        run_usage.search_calls += 1;
+        let request = || {
+            golem_wasi_http::Client::new()
+                .post(SERPER_API)
+                .header("X-API-KEY", key.trim())
+                .json(&payload)
+                .timeout(http_util::timeout(TIMEOUT))
+        };
+        let response = send(quotas, "serper", "Serper search", request)?;
+        if !response.is_success() {
+            return Err(format!("Serper search failed: HTTP {}", response.status()));
+        }
+        let body: serde_json::Value = response
+            .json()
+            .map_err(|err| format!("Serper search returned invalid JSON: {err}"))?;
+
+        let organic = body["organic"].as_array().map(Vec::len).unwrap_or(0);
+        let mut found = Vec::new();
+        // The knowledge graph and related questions repeat on every page; take them once.
+        if page == 1 {
+            found.extend(serper_knowledge_graph(&body["knowledgeGraph"]));
+        }
+        found.extend(
+            body["organic"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|item| serper_result(item, "link", "title", "snippet")),
+        );
+        if page == 1 {
+            found.extend(
+                body["peopleAlsoAsk"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|item| serper_result(item, "link", "question", "snippet")),
+            );
+        }
+        for result in found {
+            if !results.iter().any(|r| r.url == result.url) {
+                results.push(result);
+            }
+        }
+        if (organic as u32) < num {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+/// Google News results via Serper from the last `days` days (a custom date range, which Google
+/// applies to publication dates). Snippets start with Serper's date label, e.g. "2 hours ago".
+pub fn serper_news_search(
+    search_query: &ProviderQuery,
+    pages: u32,
+    options: &ResearchOptions,
+    days: u32,
+    quotas: &QuotaBook,
+    run_usage: &mut usage::RunUsage,
+) -> Result<Vec<SearchResult>, String> {
+    let key = std::env::var("SERPER_API_KEY").map_err(|_| "SERPER_API_KEY is not set".to_string())?;
+    let num = options.max_results();
+    let now = clock::now_secs();
+    let tbs = format!(
+        "cdr:1,cd_min:{},cd_max:{}",
+        us_date(now.saturating_sub(u64::from(days) * 86_400)),
+        us_date(now)
+    );
+    let mut results: Vec<SearchResult> = Vec::new();
+
+    for page in 1..=pages.clamp(1, SERPER_MAX_PAGES) {
+        let payload = serde_json::json!({
+            "q": search_query.query,
+            "num": num,
+            "page": page,
+            "hl": options.language(),
+            "tbs": tbs,
+        });
+
+        run_usage.search_calls += 1;
+        let request = || {
+            golem_wasi_http::Client::new()
+                .post(SERPER_NEWS_API)
+                .header("X-API-KEY", key.trim())
+                .json(&payload)
+                .timeout(http_util::timeout(TIMEOUT))
+        };
+        let response = send(quotas, "serper", "Serper news search", request)?;
+        if !response.is_success() {
+            return Err(format!("Serper news search failed: HTTP {}", response.status()));
+        }
+        let body: serde_json::Value = response
+            .json()
+            .map_err(|err| format!("Serper news search returned invalid JSON: {err}"))?;
+
+        let items = body["news"].as_array().map(Vec::as_slice).unwrap_or_default();
+        for item in items {
+            let Some(mut result) = serper_result(item, "link", "title", "snippet") else {
+                continue;
+            };
+            if let Some(date) = item["date"].as_str() {
+                result.snippet = format!("({date}) {}", result.snippet);
+            }
+            if !results.iter().any(|r| r.url == result.url) {
+                results.push(result);
+            }
+        }
+        if (items.len() as u32) < num {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+/// `MM/DD/YYYY`, the date format of Google's custom date range.
+fn us_date(secs: u64) -> String {
+    let (year, month, day) = clock::civil_from_days(secs / 86_400);
+    format!("{month:02}/{day:02}/{year:04}")
+}
+
+fn serper_result(item: &serde_json::Value, url: &str, title: &str, snippet: &str) -> Option<SearchResult> {
+    let url = item[url].as_str()?.to_string();
+    Some(SearchResult {
+        title: plain_text(item[title].as_str().unwrap_or(&url)),
+        snippet: plain_text(item[snippet].as_str().unwrap_or_default()),
+        url,
+    })
+}
+
+/// The knowledge graph panel, as a result linking to its source (or the entity's website).
+fn serper_knowledge_graph(graph: &serde_json::Value) -> Option<SearchResult> {
+    let url = graph["descriptionLink"].as_str().or_else(|| graph["website"].as_str())?;
+    let mut snippet = graph["description"].as_str().unwrap_or_default().to_string();
+    if let Some(attributes) = graph["attributes"].as_object() {
+        for (name, value) in attributes {
+            if let Some(value) = value.as_str() {
+                snippet.push_str(&format!(" {name}: {value}."));
+            }
+        }
+    }
+    Some(SearchResult {
+        url: url.to_string(),
+        title: plain_text(graph["title"].as_str().unwrap_or(url)),
+        snippet: plain_text(&snippet),
+    })
+}
+
+/// Sends a `provider` request within its quota (see `quota`) through `http_util`, and takes in
+/// the rate-limit headers of the answer. `label` names the call in errors.
+fn send(
+    quotas: &QuotaBook,
+    provider: &str,
+    label: &str,
+    request: impl Fn() -> golem_wasi_http::RequestBuilder,
+) -> Result<http_util::Response, String> {
+    quotas.acquire(provider).map_err(|reason| format!("{label} skipped: {reason}"))?;
+    let response = http_util::send(request).map_err(|err| format!("{label} failed: {err}"))?;
+    quotas.observe(provider, &response);
+    Ok(response)
+}
+
+/// Brave marks matches with `<strong>` and escapes entities in titles and descriptions; Serper
+/// text can carry entities too.
+fn plain_text(html: &str) -> String {
+    fetch::decode_entities(&fetch::strip_tags(html)).trim().to_string()
+}
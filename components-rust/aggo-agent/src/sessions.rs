@@ -0,0 +1,181 @@
+//! Research sessions for chat UIs: `start_session` researches a topic and returns a handle,
+//! each `continue_session` searches for the instruction, adds what it finds to the session's
+//! sources and answers from all of them with the conversation so far, and
+//! `get_session_report` returns the overview with every answer as a section.
+//!
+//! Sessions live in agent state next to the single `ask_followup` conversation and don't
+//! replace it. The least recently used sessions are dropped beyond `MAX_SESSIONS`, and a
+//! session keeps its last 100 turns and 60 sources.
+
+use golem_rust::Schema;
+
+use crate::citations;
+use crate::conversation::Conversation;
+use crate::schema::SCHEMA_VERSION;
+use crate::truncate;
+use crate::SearchResult;
+
+const MAX_SESSIONS: usize = 50;
+/// Sources kept per session; the earliest are dropped first.
+const MAX_SOURCES: usize = 60;
+/// Turns kept per session, for the report; the earliest are dropped first.
+const MAX_TURNS: usize = 100;
+const MAX_HEADING_CHARS: usize = 80;
+
+pub type SessionId = String;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct SessionTurn {
+    /// 1-based; 0 is the research that started the session.
+    pub index: u32,
+    pub instruction: String,
+    pub reply: String,
+    /// Sources this turn's search added to the session.
+    pub new_sources: u32,
+    pub at: u64,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct SessionSource {
+    pub url: String,
+    pub title: String,
+    /// The turn whose search found it; 0 for the initial research.
+    pub turn: u32,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct SessionReport {
+    /// See `schema`.
+    pub schema_version: u32,
+    pub session_id: SessionId,
+    pub workspace: String,
+    pub topic: String,
+    /// Markdown: the initial overview, then each turn's answer under its instruction.
+    pub report: String,
+    pub sources: Vec<SessionSource>,
+    pub turns: Vec<SessionTurn>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Session {
+    pub id: SessionId,
+    /// Topic, model, overview and the sources with their snippets, for prompts.
+    pub conversation: Conversation,
+    /// The same sources, in the same order, with the turn that found each.
+    sources: Vec<SessionSource>,
+    turns: Vec<SessionTurn>,
+    created_at: u64,
+}
+
+impl Session {
+    /// Adds the `results` the session doesn't have yet, found by turn `turn`, and returns how
+    /// many were new.
+    pub fn add_sources(&mut self, results: Vec<SearchResult>, turn: u32) -> u32 {
+        let mut added = 0;
+        for result in results {
+            let key = citations::normalize_url(&result.url);
+            if self.sources.iter().any(|s| citations::normalize_url(&s.url) == key) {
+                continue;
+            }
+            self.sources.push(SessionSource {
+                url: result.url.clone(),
+                title: result.title.clone(),
+                turn,
+            });
+            self.conversation.sources.push(result);
+            added += 1;
+        }
+        let excess = self.sources.len().saturating_sub(MAX_SOURCES);
+        self.sources.drain(..excess);
+        self.conversation.sources.drain(..excess);
+        added
+    }
+
+    pub fn next_turn(&self) -> u32 {
+        self.turns.last().map_or(1, |turn| turn.index + 1)
+    }
+
+    pub fn record(&mut self, instruction: &str, reply: &str, new_sources: u32, at: u64) -> SessionTurn {
+        let turn = SessionTurn {
+            index: self.next_turn(),
+            instruction: instruction.to_string(),
+            reply: reply.to_string(),
+            new_sources,
+            at,
+        };
+        self.conversation.record(instruction, reply, at);
+        self.turns.push(turn.clone());
+        if self.turns.len() > MAX_TURNS {
+            self.turns.remove(0);
+        }
+        turn
+    }
+
+    pub fn report(&self) -> SessionReport {
+        let mut report = format!("# {}\n\n{}\n", self.conversation.topic, self.conversation.answer.trim());
+        for turn in &self.turns {
+            report.push_str(&format!(
+                "\n## {}\n\n{}\n",
+                truncate::chars(&turn.instruction.replace('\n', " "), MAX_HEADING_CHARS),
+                turn.reply.trim()
+            ));
+        }
+        SessionReport {
+            schema_version: SCHEMA_VERSION,
+            session_id: self.id.clone(),
+            workspace: self.conversation.workspace.clone(),
+            topic: self.conversation.topic.clone(),
+            report,
+            sources: self.sources.clone(),
+            turns: self.turns.clone(),
+            created_at: self.created_at,
+            updated_at: self.conversation.updated_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct SessionBook {
+    sessions: Vec<Session>,
+    next_id: u64,
+}
+
+impl SessionBook {
+    /// Starts a session from a research run's conversation and returns its ID.
+    pub fn create(&mut self, mut conversation: Conversation, now: u64) -> SessionId {
+        self.next_id += 1;
+        let id = format!("session-{}", self.next_id);
+        let results = std::mem::take(&mut conversation.sources);
+        let mut session = Session {
+            id: id.clone(),
+            conversation,
+            sources: Vec::new(),
+            turns: Vec::new(),
+            created_at: now,
+        };
+        session.add_sources(results, 0);
+        self.sessions.push(session);
+        if self.sessions.len() > MAX_SESSIONS {
+            if let Some(oldest) = self
+                .sessions
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, s)| s.conversation.updated_at)
+                .map(|(i, _)| i)
+            {
+                self.sessions.remove(oldest);
+            }
+        }
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Session> {
+        self.sessions.iter().find(|s| s.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Session> {
+        self.sessions.iter_mut().find(|s| s.id == id)
+    }
+}
@@ -0,0 +1,58 @@
+//! Load shedding: past a configured queue depth or amount of in-flight work, requests marked
+//! `priority: low` are rejected at once with `Overloaded` and a retry-after hint, instead of
+//! adding to work the worker is already behind on. Normal-priority requests always run.
+//!
+//! `LOAD_SHED_MAX_QUEUE_DEPTH` and `LOAD_SHED_MAX_IN_FLIGHT` are off when unset;
+//! `LOAD_SHED_RETRY_AFTER_SECONDS` (default 30) is the hint returned.
+
+use golem_rust::Schema;
+
+use crate::config;
+use crate::options::RequestPriority;
+
+const DEFAULT_RETRY_AFTER_SECONDS: u32 = 30;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct Overloaded {
+    /// The threshold that was exceeded, e.g. "queue depth 12 exceeds 10".
+    pub reason: String,
+    /// Same measures as `heartbeat`: due schedules and watches plus deferred notifications.
+    pub queue_depth: u32,
+    /// Deep research jobs and streams still running.
+    pub in_flight: u32,
+    pub retry_after_seconds: u32,
+}
+
+pub struct LoadLimits {
+    max_queue_depth: Option<u32>,
+    max_in_flight: Option<u32>,
+    retry_after_seconds: u32,
+}
+
+impl LoadLimits {
+    pub fn from_env() -> Self {
+        Self {
+            max_queue_depth: config::env_parse::<u32>("LOAD_SHED_MAX_QUEUE_DEPTH"),
+            max_in_flight: config::env_parse::<u32>("LOAD_SHED_MAX_IN_FLIGHT"),
+            retry_after_seconds: config::env_parse::<u32>("LOAD_SHED_RETRY_AFTER_SECONDS").unwrap_or(DEFAULT_RETRY_AFTER_SECONDS),
+        }
+    }
+
+    /// Fails for a low-priority request while either threshold is exceeded.
+    pub fn check(&self, priority: RequestPriority, queue_depth: u32, in_flight: u32) -> Result<(), Overloaded> {
+        if priority != RequestPriority::Low {
+            return Ok(());
+        }
+        let reason = match (self.max_queue_depth, self.max_in_flight) {
+            (Some(max), _) if queue_depth > max => format!("queue depth {queue_depth} exceeds {max}"),
+            (_, Some(max)) if in_flight > max => format!("{in_flight} in-flight jobs and streams exceed {max}"),
+            _ => return Ok(()),
+        };
+        Err(Overloaded {
+            reason,
+            queue_depth,
+            in_flight,
+            retry_after_seconds: self.retry_after_seconds,
+        })
+    }
+}
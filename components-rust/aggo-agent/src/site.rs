@@ -0,0 +1,194 @@
+//! Sitemap-guided research on a single site: "what does vendor X say about Y".
+//!
+//! The site's sitemaps (from `robots.txt`, else `/sitemap.xml`) list its pages; those whose
+//! paths and titles best match the topic (BM25) are fetched and synthesized.
+//! `SITE_RESEARCH_MAX_PAGES` caps the pages read per call (default 5, at most 10).
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::config;
+use crate::http_cache::ResponseCache;
+use crate::{citations, compress, fetch, rank, SearchResult};
+
+const DEFAULT_MAX_PAGES: usize = 5;
+const MAX_PAGES: usize = 10;
+/// Sitemap documents (index and children) read per call.
+const MAX_SITEMAP_FETCHES: usize = 10;
+const MAX_SITEMAP_URLS: usize = 10_000;
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+pub struct SitemapEntry {
+    pub url: String,
+    /// `<news:title>` or `<image:title>`, when the sitemap carries one.
+    pub title: Option<String>,
+}
+
+pub fn max_pages() -> usize {
+    config::env_parse::<usize>("SITE_RESEARCH_MAX_PAGES")
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_PAGES)
+        .min(MAX_PAGES)
+}
+
+/// Accepts `example.com`, `www.example.com` or a URL on the site.
+pub fn normalize_domain(domain: &str) -> Result<String, String> {
+    let domain = domain.trim();
+    let url = if domain.contains("://") {
+        domain.to_string()
+    } else {
+        format!("https://{domain}")
+    };
+    citations::host(&url).ok_or_else(|| format!("Invalid domain {domain:?}: expected e.g. example.com"))
+}
+
+/// Page URLs listed in the site's sitemaps, following sitemap indexes.
+pub fn sitemap_entries(domain: &str, cache: &ResponseCache, now: u64) -> Result<Vec<SitemapEntry>, String> {
+    let mut queue: VecDeque<String> = sitemap_locations(domain).into();
+    let mut entries = Vec::new();
+    let mut fetched = 0;
+
+    while let Some(sitemap) = queue.pop_front() {
+        if fetched >= MAX_SITEMAP_FETCHES || entries.len() >= MAX_SITEMAP_URLS {
+            break;
+        }
+        if sitemap.ends_with(".gz") {
+            log::info!("Skipping compressed sitemap {sitemap}");
+            continue;
+        }
+        fetched += 1;
+        let body = match cache.fetch_text(&sitemap, FETCH_TIMEOUT, now) {
+            Ok(body) => body,
+            Err(err) => {
+                log::warn!("Skipping sitemap: {err}");
+                continue;
+            }
+        };
+
+        for child in elements(&body, "sitemap") {
+            if let Some(loc) = text_of(child, "loc") {
+                queue.push_back(loc);
+            }
+        }
+        for url in elements(&body, "url") {
+            let Some(loc) = text_of(url, "loc") else {
+                continue;
+            };
+            if !on_site(&loc, domain) {
+                continue;
+            }
+            let title = text_of(url, "news:title").or_else(|| text_of(url, "image:title"));
+            entries.push(SitemapEntry { url: loc, title });
+        }
+    }
+
+    entries.truncate(MAX_SITEMAP_URLS);
+    if entries.is_empty() {
+        return Err(format!(
+            "No sitemap pages found for {domain} (checked robots.txt and /sitemap.xml)"
+        ));
+    }
+    Ok(entries)
+}
+
+/// The `limit` entries that best match the topic; entries with no matching term are dropped.
+pub fn select(entries: Vec<SitemapEntry>, topic: &str, limit: usize) -> Vec<SitemapEntry> {
+    let query = compress::terms(topic);
+    let documents: Vec<Vec<String>> = entries
+        .iter()
+        .map(|entry| {
+            let path = entry.url.split_once("://").map_or(entry.url.as_str(), |(_, rest)| rest);
+            let path = path.find('/').map_or("", |i| &path[i..]);
+            let mut words: Vec<String> = compress::words(path).collect();
+            words.extend(compress::words(entry.title.as_deref().unwrap_or("")));
+            words
+        })
+        .collect();
+    let scores = rank::bm25(&documents, &query);
+
+    let mut ranked: Vec<(f64, SitemapEntry)> = scores
+        .into_iter()
+        .zip(entries)
+        .filter(|(score, _)| *score > 0.0)
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+    ranked.into_iter().take(limit).map(|(_, entry)| entry).collect()
+}
+
+pub fn site_prompt(domain: &str, topic: &str, format_instructions: &str, pages: &[SearchResult]) -> String {
+    let pages_json = serde_json::to_string(pages).unwrap_or_else(|_| "[]".to_string());
+    format!(
+        "I'm researching what {domain} says about \"{topic}\".\n\
+         Below are pages from that site in json format. Summarize the site's own position, claims and details on the topic, \
+         citing the page URL for each point. Say plainly where the pages don't cover something rather than filling gaps from elsewhere.\n\
+         {format_instructions}\n\
+         Pages: {pages_json}"
+    )
+}
+
+fn sitemap_locations(domain: &str) -> Vec<String> {
+    let from_robots: Vec<String> = fetch::fetch_text(&format!("https://{domain}/robots.txt"), FETCH_TIMEOUT)
+        .map(|(_, robots)| {
+            robots
+                .lines()
+                .filter_map(|line| {
+                    let (key, value) = line.split_once(':')?;
+                    key.trim()
+                        .eq_ignore_ascii_case("sitemap")
+                        .then(|| value.trim().to_string())
+                })
+                .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if from_robots.is_empty() {
+        vec![format!("https://{domain}/sitemap.xml")]
+    } else {
+        from_robots
+    }
+}
+
+/// Whether `url` is on `domain` or one of its subdomains.
+fn on_site(url: &str, domain: &str) -> bool {
+    citations::host(url).is_some_and(|host| host == domain || host.ends_with(&format!(".{domain}")))
+}
+
+/// Inner text of every `<name>...</name>` element, in order.
+pub fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let open = format!("<{name}");
+    let close = format!("</{name}>");
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(at) = rest.find(&open) {
+        let after = &rest[at + open.len()..];
+        if !after.starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            rest = after;
+            continue;
+        }
+        let Some(start) = after.find('>') else {
+            break;
+        };
+        let inner = &after[start + 1..];
+        let end = inner.find(&close).unwrap_or(inner.len());
+        found.push(&inner[..end]);
+        rest = &inner[end..];
+    }
+    found
+}
+
+/// Entity-decoded text of the first `<name>` element (CDATA unwrapped), if not empty.
+pub fn text_of(xml: &str, name: &str) -> Option<String> {
+    let inner = elements(xml, name).into_iter().next()?.trim();
+    let inner = inner
+        .strip_prefix("<![CDATA[")
+        .and_then(|s| s.strip_suffix("]]>"))
+        .unwrap_or(inner);
+    let text = fetch::decode_entities(inner.trim());
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
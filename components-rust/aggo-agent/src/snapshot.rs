@@ -0,0 +1,82 @@
+//! Worker snapshots.
+//!
+//! Without a snapshot Golem rebuilds the agent by replaying its oplog, which re-runs every
+//! ingest, embedding call and model readiness check before the first request. `save_snapshot`
+//! and `load_snapshot` let restarts, transfers and snapshot-based updates start from a copy of
+//! the state instead. The copy is kept compact by leaving out what is cheap to derive: corpus
+//! keyword tokens are rebuilt from the chunk boundaries on load, while embeddings, resolved
+//! context windows and ready models are kept so no provider call is repeated.
+//!
+//! Configuration read from the environment (providers, connectors, budgets per lane, prompt
+//! settings) is not part of the snapshot; `new()` reads it before the snapshot is loaded. An
+//! LLM stream that was open when the snapshot was taken cannot be restored and fails on its
+//! next `advance_stream`.
+
+use crate::budget::BudgetBook;
+use crate::cache::ReportCache;
+use crate::conversation::Conversation;
+use crate::corpus::Corpus;
+use crate::history::ReportHistory;
+use crate::http_cache::ResponseCache;
+use crate::jobs::JobBook;
+use crate::llm_cache::LlmCache;
+use crate::notify::Outbox;
+use crate::page_cache::PageCache;
+use crate::quota::QuotaBook;
+use crate::safety::ModerationQueue;
+use crate::schedule::ScheduleBook;
+use crate::schema::SCHEMA_VERSION;
+use crate::search_cache::SearchCache;
+use crate::sessions::SessionBook;
+use crate::stream::StreamBook;
+use crate::templates::TemplateRegistry;
+use crate::usage::UsageLedger;
+use crate::watch::WatchBook;
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AgentSnapshot {
+    /// See `schema`; version 0 is a snapshot from before versioning.
+    pub schema_version: u32,
+    pub templates: TemplateRegistry,
+    pub schedules: ScheduleBook,
+    pub outbox: Outbox,
+    pub history: ReportHistory,
+    pub jobs: JobBook,
+    pub streams: StreamBook,
+    pub watches: WatchBook,
+    pub corpus: Corpus,
+    pub usage: UsageLedger,
+    pub budgets: BudgetBook,
+    pub ready_models: Vec<String>,
+    pub context_windows: Vec<(String, u32)>,
+    pub report_cache: ReportCache,
+    pub page_cache: PageCache,
+    pub http_cache: ResponseCache,
+    pub search_cache: SearchCache,
+    pub llm_cache: LlmCache,
+    pub quotas: QuotaBook,
+    pub moderation: ModerationQueue,
+    pub conversation: Option<Conversation>,
+    pub sessions: SessionBook,
+}
+
+impl AgentSnapshot {
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        serde_json::to_vec(self).map_err(|err| format!("Could not serialize the agent state: {err}"))
+    }
+
+    /// Parses a snapshot and rebuilds the indices it leaves out.
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut snapshot: Self =
+            serde_json::from_slice(bytes).map_err(|err| format!("Not a valid agent snapshot: {err}"))?;
+        if snapshot.schema_version > SCHEMA_VERSION {
+            log::warn!(
+                "Loading a snapshot with schema version {} (this component writes {SCHEMA_VERSION}); newer fields are dropped",
+                snapshot.schema_version
+            );
+        }
+        snapshot.corpus.restore_index();
+        Ok(snapshot)
+    }
+}
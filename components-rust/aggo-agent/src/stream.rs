@@ -0,0 +1,253 @@
+//! Streamed research reports.
+//!
+//! `research_stream` searches, starts a streaming LLM call and returns right away. Each
+//! `advance_stream` invocation, which the agent triggers on itself, reads the next batch of
+//! stream events, so callers polling `get_stream_chunks` between invocations see the report
+//! grow chunk by chunk instead of waiting for the whole answer.
+
+use golem_rust::golem_ai::golem::llm::llm;
+use golem_rust::Schema;
+
+use crate::options::DEFAULT_LANGUAGE;
+use crate::schema::SCHEMA_VERSION;
+use crate::usage::RunUsage;
+
+/// Finished streams kept in state; their reports also live in the report history.
+const MAX_FINISHED_STREAMS: usize = 100;
+
+#[derive(Clone, Debug, PartialEq, Schema, serde::Serialize, serde::Deserialize)]
+pub enum StreamStatus {
+    Streaming,
+    Completed,
+    Failed(String),
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ResearchStream {
+    /// See `schema`; version 0 is a record from before versioning.
+    #[serde(default)]
+    pub schema_version: u32,
+    pub id: String,
+    pub workspace: String,
+    pub topic: String,
+    pub model: String,
+    pub status: StreamStatus,
+    /// Text chunks received so far.
+    pub chunk_count: u32,
+    pub created_at: u64,
+    pub updated_at: u64,
+    /// History ID of the stored report, once completed.
+    pub report_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct StreamChunk {
+    pub index: u32,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct StreamUpdate {
+    pub stream_id: String,
+    pub status: StreamStatus,
+    /// Chunks from the requested index on, in order.
+    pub chunks: Vec<StreamChunk>,
+    /// Index to ask for on the next poll.
+    pub next_index: u32,
+    pub report_id: Option<String>,
+}
+
+/// How a batch of stream events ended the stream, if it did.
+pub enum StreamEnd {
+    Finished,
+    Failed(String),
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Stream {
+    pub info: ResearchStream,
+    pub chunks: Vec<String>,
+    pub seed: Option<u64>,
+    /// Report language, for the footer date.
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Retrieval notes appended to the finished report.
+    pub notes: Vec<String>,
+    /// LLM usage, added to the workspace once the stream ends.
+    pub usage: RunUsage,
+    /// The open LLM stream; `None` once it has ended. Not part of snapshots: a stream open at
+    /// snapshot time is reported as lost by its next `advance_stream`.
+    #[serde(skip)]
+    pub chat: Option<llm::ChatStream>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct StreamBook {
+    streams: Vec<Stream>,
+    next_id: u64,
+}
+
+impl StreamBook {
+    #[allow(clippy::too_many_arguments)]
+    pub fn create(
+        &mut self,
+        workspace: &str,
+        topic: &str,
+        model: &str,
+        language: &str,
+        seed: Option<u64>,
+        chat: llm::ChatStream,
+        notes: Vec<String>,
+        now: u64,
+    ) -> ResearchStream {
+        self.next_id += 1;
+        let info = ResearchStream {
+            schema_version: SCHEMA_VERSION,
+            id: format!("stream-{}", self.next_id),
+            workspace: workspace.to_string(),
+            topic: topic.to_string(),
+            model: model.to_string(),
+            status: StreamStatus::Streaming,
+            chunk_count: 0,
+            created_at: now,
+            updated_at: now,
+            report_id: None,
+        };
+        self.streams.push(Stream {
+            info: info.clone(),
+            chunks: Vec::new(),
+            seed,
+            language: language.to_string(),
+            notes,
+            usage: RunUsage::default(),
+            chat: Some(chat),
+        });
+        self.enforce_limit();
+        info
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Stream> {
+        self.streams.iter().find(|s| s.info.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Stream> {
+        self.streams.iter_mut().find(|s| s.info.id == id)
+    }
+
+    /// Streams still receiving text.
+    pub fn active(&self) -> usize {
+        self.streams
+            .iter()
+            .filter(|s| s.info.status == StreamStatus::Streaming)
+            .count()
+    }
+
+    pub fn update(&self, id: &str, from_index: u32) -> Option<StreamUpdate> {
+        let stream = self.get(id)?;
+        Some(StreamUpdate {
+            stream_id: stream.info.id.clone(),
+            status: stream.info.status.clone(),
+            chunks: stream
+                .chunks
+                .iter()
+                .enumerate()
+                .skip(from_index as usize)
+                .map(|(index, text)| StreamChunk {
+                    index: index as u32,
+                    text: text.clone(),
+                })
+                .collect(),
+            next_index: stream.chunks.len() as u32,
+            report_id: stream.info.report_id.clone(),
+        })
+    }
+
+    /// A copy of the book for a snapshot, without the open LLM streams.
+    pub fn snapshot(&self) -> StreamBook {
+        StreamBook {
+            streams: self
+                .streams
+                .iter()
+                .map(|s| Stream {
+                    info: s.info.clone(),
+                    chunks: s.chunks.clone(),
+                    seed: s.seed,
+                    language: s.language.clone(),
+                    notes: s.notes.clone(),
+                    usage: s.usage,
+                    chat: None,
+                })
+                .collect(),
+            next_id: self.next_id,
+        }
+    }
+
+    fn enforce_limit(&mut self) {
+        let finished = self
+            .streams
+            .iter()
+            .filter(|s| s.info.status != StreamStatus::Streaming)
+            .count();
+        let mut excess = finished.saturating_sub(MAX_FINISHED_STREAMS);
+        self.streams.retain(|s| {
+            if excess > 0 && s.info.status != StreamStatus::Streaming {
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+impl Stream {
+    /// Appends the text deltas in `events` as chunks and notes the usage of a finished stream.
+    pub fn push_events(&mut self, events: Vec<llm::StreamEvent>, now: u64) -> Option<StreamEnd> {
+        self.info.updated_at = now;
+        for event in events {
+            match event {
+                llm::StreamEvent::Delta(delta) => {
+                    let text: String = delta
+                        .content
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|part| match part {
+                            llm::ContentPart::Text(text) => Some(text),
+                            _ => None,
+                        })
+                        .collect();
+                    if !text.is_empty() {
+                        self.chunks.push(text);
+                    }
+                }
+                llm::StreamEvent::Finish(metadata) => {
+                    self.usage.add_llm_response(metadata.usage.as_ref());
+                    return Some(self.end(StreamStatus::Completed, StreamEnd::Finished));
+                }
+                llm::StreamEvent::Error(err) => {
+                    self.usage.add_llm_response(None);
+                    let message = format!("LLM stream failed (model: {:?}): {}", self.info.model, err.message);
+                    return Some(self.end(StreamStatus::Failed(message.clone()), StreamEnd::Failed(message)));
+                }
+            }
+        }
+        self.info.chunk_count = self.chunks.len() as u32;
+        None
+    }
+
+    /// The answer received so far.
+    pub fn text(&self) -> String {
+        self.chunks.concat()
+    }
+
+    fn end(&mut self, status: StreamStatus, end: StreamEnd) -> StreamEnd {
+        self.info.chunk_count = self.chunks.len() as u32;
+        self.info.status = status;
+        self.chat = None;
+        end
+    }
+}
+
+fn default_language() -> String {
+    DEFAULT_LANGUAGE.to_string()
+}
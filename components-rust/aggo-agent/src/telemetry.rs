@@ -0,0 +1,248 @@
+//! Opt-in pipeline telemetry, exported as OTLP/HTTP JSON traces.
+//!
+//! With `OTEL_EXPORTER_OTLP_ENDPOINT` set (e.g. `http://collector:4318`), each research run is
+//! sent to `<endpoint>/v1/traces` as one trace: a root span for the run and a child span per
+//! stage (each web search provider, each LLM call) with its latency and outcome. Spans from
+//! calls outside a run, such as `search`, are sent on their own as they finish.
+//! `OTEL_EXPORTER_OTLP_HEADERS` (`key=value,...`) adds headers, e.g. a collector API key, and
+//! `OTEL_SERVICE_NAME` overrides the service name (default `aggo-agent`).
+//!
+//! Events are anonymized: they carry stage names, provider and model names, counts, token
+//! usage, durations and error kinds, never topics, queries, URLs, workspaces or text. Export
+//! failures are logged and never fail the run.
+
+use std::cell::RefCell;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+const DEFAULT_SERVICE_NAME: &str = "aggo-agent";
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(5);
+/// OTLP span kind `INTERNAL`.
+const SPAN_KIND_INTERNAL: u32 = 1;
+const STATUS_OK: u32 = 1;
+const STATUS_ERROR: u32 = 2;
+
+/// A span attribute value; only numbers and names, never content.
+pub enum Value {
+    Str(String),
+    Int(u64),
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(value.to_string())
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Int(value)
+    }
+}
+
+struct Span {
+    trace_id: String,
+    span_id: String,
+    parent_span_id: Option<String>,
+    name: String,
+    start: u64,
+    end: u64,
+    attributes: Vec<(&'static str, Value)>,
+    /// The error kind, if the stage failed.
+    error: Option<String>,
+}
+
+struct Run {
+    trace_id: String,
+    span_id: String,
+    name: &'static str,
+    start: u64,
+    spans: Vec<Span>,
+}
+
+#[derive(Default)]
+pub struct Telemetry {
+    /// `None` turns telemetry off.
+    endpoint: Option<String>,
+    headers: Vec<(String, String)>,
+    service_name: String,
+    run: RefCell<Option<Run>>,
+}
+
+impl Telemetry {
+    pub fn from_env() -> Self {
+        let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .ok()
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty());
+        let headers = std::env::var("OTEL_EXPORTER_OTLP_HEADERS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .filter(|(key, _)| !key.is_empty())
+            .collect();
+        let service_name = std::env::var("OTEL_SERVICE_NAME")
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string());
+        Self {
+            endpoint,
+            headers,
+            service_name,
+            run: RefCell::new(None),
+        }
+    }
+
+    /// Starts collecting the stage spans of a run, replacing any run that was not finished.
+    pub fn start_run(&self, name: &'static str) {
+        if self.endpoint.is_none() {
+            return;
+        }
+        *self.run.borrow_mut() = Some(Run {
+            trace_id: random_hex(16),
+            span_id: random_hex(8),
+            name,
+            start: now_nanos(),
+            spans: Vec::new(),
+        });
+    }
+
+    /// Records a finished stage that began at `start` (see `now_nanos`).
+    pub fn stage(&self, name: &str, start: u64, error: Option<&str>, attributes: Vec<(&'static str, Value)>) {
+        if self.endpoint.is_none() {
+            return;
+        }
+        let mut span = Span {
+            trace_id: random_hex(16),
+            span_id: random_hex(8),
+            parent_span_id: None,
+            name: name.to_string(),
+            start,
+            end: now_nanos(),
+            attributes,
+            error: error.map(str::to_string),
+        };
+        let mut run = self.run.borrow_mut();
+        match run.as_mut() {
+            Some(run) => {
+                span.trace_id = run.trace_id.clone();
+                span.parent_span_id = Some(run.span_id.clone());
+                run.spans.push(span);
+            }
+            None => {
+                drop(run);
+                self.export(vec![span]);
+            }
+        }
+    }
+
+    /// Ends the current run and exports it with its stages.
+    pub fn finish_run(&self, error: Option<&str>) {
+        let Some(run) = self.run.borrow_mut().take() else {
+            return;
+        };
+        let stages = run.spans.len() as u64;
+        let mut spans = run.spans;
+        spans.push(Span {
+            trace_id: run.trace_id,
+            span_id: run.span_id,
+            parent_span_id: None,
+            name: run.name.to_string(),
+            start: run.start,
+            end: now_nanos(),
+            attributes: vec![("aggo.stages", Value::Int(stages))],
+            error: error.map(str::to_string),
+        });
+        self.export(spans);
+    }
+
+    fn export(&self, spans: Vec<Span>) {
+        let Some(endpoint) = &self.endpoint else {
+            return;
+        };
+        let body = serde_json::json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", &Value::Str(self.service_name.clone()))],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": DEFAULT_SERVICE_NAME },
+                    "spans": spans.iter().map(span_json).collect::<Vec<_>>(),
+                }],
+            }],
+        });
+
+        let mut request = golem_wasi_http::Client::new()
+            .post(format!("{endpoint}/v1/traces"))
+            .json(&body)
+            .timeout(EXPORT_TIMEOUT);
+        for (key, value) in &self.headers {
+            request = request.header(key.as_str(), value.as_str());
+        }
+        match request.send() {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => log::warn!("Telemetry export was rejected: HTTP {}", response.status()),
+            Err(err) => log::warn!("Telemetry export failed: {err}"),
+        }
+    }
+}
+
+/// Unix time in nanoseconds, as OTLP wants it.
+pub fn now_nanos() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+fn span_json(span: &Span) -> serde_json::Value {
+    let mut attributes: Vec<serde_json::Value> = span
+        .attributes
+        .iter()
+        .map(|(key, value)| attribute(key, value))
+        .collect();
+    let status = match &span.error {
+        Some(kind) => {
+            attributes.push(attribute("error.type", &Value::Str(kind.clone())));
+            serde_json::json!({ "code": STATUS_ERROR })
+        }
+        None => serde_json::json!({ "code": STATUS_OK }),
+    };
+    let mut json = serde_json::json!({
+        "traceId": span.trace_id,
+        "spanId": span.span_id,
+        "name": span.name,
+        "kind": SPAN_KIND_INTERNAL,
+        // 64-bit integers are strings in OTLP JSON.
+        "startTimeUnixNano": span.start.to_string(),
+        "endTimeUnixNano": span.end.to_string(),
+        "attributes": attributes,
+        "status": status,
+    });
+    if let Some(parent) = &span.parent_span_id {
+        json["parentSpanId"] = parent.as_str().into();
+    }
+    json
+}
+
+fn attribute(key: &str, value: &Value) -> serde_json::Value {
+    let value = match value {
+        Value::Str(text) => serde_json::json!({ "stringValue": text }),
+        Value::Int(number) => serde_json::json!({ "intValue": number.to_string() }),
+    };
+    serde_json::json!({ "key": key, "value": value })
+}
+
+/// `bytes` random bytes as lowercase hex, for trace and span IDs.
+fn random_hex(bytes: usize) -> String {
+    let mut hex = String::with_capacity(bytes * 2);
+    while hex.len() < bytes * 2 {
+        // `RandomState` is seeded from the host's random source.
+        let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+        hex.push_str(&format!("{random:016x}"));
+    }
+    hex.truncate(bytes * 2);
+    hex
+}
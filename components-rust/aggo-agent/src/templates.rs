@@ -0,0 +1,140 @@
+use golem_rust::Schema;
+
+use crate::notify::NotificationPrefs;
+use crate::ResearchOptions;
+
+/// A named, parameterized research recipe.
+///
+/// `topic` may contain `{param}` placeholders that are filled from the caller's params, e.g.
+/// `"{company} pricing changes in {year}"`.
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct ResearchTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    pub topic: String,
+    /// Source profile: restricts retrieval to these domains (rendered as `site:` operators).
+    pub source_domains: Option<Vec<String>>,
+    /// Depth, style, sections and exclusions applied to every run of the recipe.
+    pub options: ResearchOptions,
+    /// Default delivery for schedules created from this template; returned with credentials
+    /// redacted.
+    pub delivery: Option<NotificationPrefs>,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct TemplateParam {
+    pub name: String,
+    pub value: String,
+}
+
+/// Recipes known to this agent: seeded from `RESEARCH_TEMPLATES` (a JSON array of
+/// `ResearchTemplate`) and extended at runtime via `define_template`.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct TemplateRegistry {
+    templates: Vec<ResearchTemplate>,
+}
+
+impl TemplateRegistry {
+    pub fn from_env() -> Self {
+        let mut registry = Self::default();
+
+        let Ok(raw) = std::env::var("RESEARCH_TEMPLATES") else {
+            return registry;
+        };
+        if raw.trim().is_empty() {
+            return registry;
+        }
+
+        match serde_json::from_str::<Vec<ResearchTemplate>>(&raw) {
+            Ok(templates) => {
+                for template in templates {
+                    if let Err(err) = registry.define(template) {
+                        log::warn!("Skipping template from RESEARCH_TEMPLATES: {err}");
+                    }
+                }
+            }
+            Err(err) => log::warn!("RESEARCH_TEMPLATES is not a valid JSON template list: {err}"),
+        }
+
+        registry
+    }
+
+    /// Adds or replaces a template by name.
+    pub fn define(&mut self, mut template: ResearchTemplate) -> Result<ResearchTemplate, String> {
+        template.name = template.name.trim().to_string();
+        if template.name.is_empty() {
+            return Err("Template name must not be empty".to_string());
+        }
+        if template.topic.trim().is_empty() {
+            return Err(format!("Template {:?} has an empty topic", template.name));
+        }
+
+        match self.templates.iter_mut().find(|t| t.name == template.name) {
+            Some(existing) => {
+                if let Some(stored) = &existing.delivery {
+                    template.delivery = template.delivery.map(|delivery| delivery.keep_credentials(stored));
+                }
+                *existing = template.clone();
+            }
+            None => self.templates.push(template.clone()),
+        }
+
+        Ok(template.redacted())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ResearchTemplate> {
+        self.templates.iter().find(|t| t.name == name.trim())
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.templates.len();
+        self.templates.retain(|t| t.name != name.trim());
+        self.templates.len() != before
+    }
+
+    pub fn list(&self) -> Vec<ResearchTemplate> {
+        self.templates.iter().map(ResearchTemplate::redacted).collect()
+    }
+}
+
+impl ResearchTemplate {
+    /// This template as returned to callers; `get` keeps the real delivery credentials.
+    fn redacted(&self) -> Self {
+        Self {
+            delivery: self.delivery.as_ref().map(NotificationPrefs::redacted),
+            ..self.clone()
+        }
+    }
+
+    /// Fills the topic placeholders and appends the source profile as `site:` operators.
+    pub fn render_topic(&self, params: &[TemplateParam]) -> Result<String, String> {
+        let mut topic = self.topic.clone();
+        for param in params {
+            topic = topic.replace(&format!("{{{}}}", param.name.trim()), param.value.trim());
+        }
+
+        if let Some(start) = topic.find('{') {
+            if let Some(len) = topic[start..].find('}') {
+                return Err(format!(
+                    "Template {:?} is missing a value for parameter {}",
+                    self.name,
+                    &topic[start..start + len + 1]
+                ));
+            }
+        }
+
+        let domains: Vec<String> = self
+            .source_domains
+            .iter()
+            .flatten()
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty())
+            .collect();
+        if !domains.is_empty() {
+            let sites: Vec<String> = domains.iter().map(|d| format!("site:{d}")).collect();
+            topic = format!("{} {}", topic.trim(), sites.join(" OR "));
+        }
+
+        Ok(topic)
+    }
+}
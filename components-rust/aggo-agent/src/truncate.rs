@@ -0,0 +1,170 @@
+//! Length limits on text from providers, pages and models, shared by prompt assembly, snippet
+//! trimming, stored documents and logged or returned errors.
+//!
+//! Limits count characters, not bytes, and cuts never fall inside a UTF-8 sequence, so
+//! non-ASCII text can't make a cut panic. Cuts also keep user-perceived characters whole: a
+//! letter stays with its combining accents, an emoji with its modifiers and joined emoji, and
+//! a flag's two regional indicators stay together. (This is a close approximation of Unicode
+//! grapheme clusters that needs no segmentation tables.)
+
+const ELLIPSIS: &str = "…";
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+/// `text` trimmed and cut to at most `max_chars` characters, ending in `…` if cut.
+pub fn chars(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    let cut = prefix(text, max_chars);
+    if cut.len() == text.len() {
+        text.to_string()
+    } else {
+        format!("{}{ELLIPSIS}", cut.trim_end())
+    }
+}
+
+/// The start of `text`, at most `max_chars` characters, without a marker.
+pub fn prefix(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((cut, _)) => &text[..cluster_start(text, cut)],
+        None => text,
+    }
+}
+
+/// The start of `text`, at most `max_bytes` bytes.
+pub fn prefix_bytes(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut cut = max_bytes;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    &text[..cluster_start(text, cut)]
+}
+
+/// `index` (a character boundary of `text`) moved back to the start of the user-perceived
+/// character it falls inside, if any. A cluster that starts the text (runaway combining marks)
+/// is cut at `index` instead, so the cut never comes out empty.
+fn cluster_start(text: &str, cut: usize) -> usize {
+    let mut index = cut;
+    loop {
+        let (before, after) = text.split_at(index);
+        let (Some(previous), Some(next)) = (before.chars().next_back(), after.chars().next()) else {
+            return index;
+        };
+        let inside = extends_previous(next)
+            || previous == ZERO_WIDTH_JOINER
+            || (is_regional_indicator(previous) && is_regional_indicator(next) && odd_indicator_run(before));
+        if !inside {
+            return index;
+        }
+        index -= previous.len_utf8();
+        if index == 0 {
+            return cut;
+        }
+    }
+}
+
+/// Characters that attach to the one before them: combining marks, joiners, variation
+/// selectors and emoji skin tones.
+fn extends_previous(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0300..=0x036f
+            | 0x0483..=0x0489
+            | 0x0591..=0x05bd
+            | 0x0610..=0x061a
+            | 0x064b..=0x065f
+            | 0x0670
+            | 0x06d6..=0x06dc
+            | 0x06df..=0x06e4
+            | 0x0900..=0x0903
+            | 0x093a..=0x094f
+            | 0x0e31
+            | 0x0e34..=0x0e3a
+            | 0x0e47..=0x0e4e
+            | 0x1ab0..=0x1aff
+            | 0x1dc0..=0x1dff
+            | 0x200c..=0x200d
+            | 0x20d0..=0x20ff
+            | 0x3099..=0x309a
+            | 0xfe00..=0xfe0f
+            | 0xfe20..=0xfe2f
+            | 0x1f3fb..=0x1f3ff
+            | 0xe0020..=0xe007f
+    )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1f1e6..=0x1f1ff)
+}
+
+/// Whether `text` ends in an odd number of regional indicators, i.e. in the first half of a
+/// flag.
+fn odd_indicator_run(text: &str) -> bool {
+    text.chars().rev().take_while(|c| is_regional_indicator(*c)).count() % 2 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chars_trims_and_marks_cuts() {
+        assert_eq!(chars("  short  ", 10), "short");
+        assert_eq!(chars("one two three", 8), "one two…");
+        assert_eq!(chars("one two three", 4), "one…");
+        assert_eq!(chars("日本語のテキスト", 3), "日本語…");
+    }
+
+    #[test]
+    fn prefix_counts_characters_not_bytes() {
+        assert_eq!(prefix("héllo", 2), "hé");
+        assert_eq!(prefix("日本語", 2), "日本");
+        assert_eq!(prefix("日本語", 3), "日本語");
+        assert_eq!(prefix("", 3), "");
+    }
+
+    #[test]
+    fn prefix_bytes_cuts_on_a_char_boundary() {
+        assert_eq!(prefix_bytes("héllo", 2), "h");
+        assert_eq!(prefix_bytes("héllo", 3), "hé");
+        assert_eq!(prefix_bytes("日本語", 4), "日");
+        assert_eq!(prefix_bytes("abc", 10), "abc");
+    }
+
+    #[test]
+    fn keeps_combining_marks_with_their_letter() {
+        let text = "ae\u{301}\u{302}x";
+        assert_eq!(prefix(text, 2), "a");
+        assert_eq!(prefix(text, 3), "a");
+        assert_eq!(prefix(text, 4), "ae\u{301}\u{302}");
+        assert_eq!(prefix_bytes(text, 3), "a");
+    }
+
+    #[test]
+    fn keeps_emoji_sequences_whole() {
+        // Skin tone modifier.
+        assert_eq!(prefix("a👍🏽b", 2), "a");
+        assert_eq!(prefix("a👍🏽b", 3), "a👍🏽");
+        // Joined emoji: man, ZWJ, woman.
+        assert_eq!(prefix("a👨\u{200d}👩b", 2), "a");
+        assert_eq!(prefix("a👨\u{200d}👩b", 3), "a");
+        assert_eq!(prefix("a👨\u{200d}👩b", 4), "a👨\u{200d}👩");
+    }
+
+    #[test]
+    fn keeps_flag_pairs_together() {
+        let flags = "a🇩🇪🇫🇷";
+        assert_eq!(prefix(flags, 2), "a");
+        assert_eq!(prefix(flags, 3), "a🇩🇪");
+        assert_eq!(prefix(flags, 4), "a🇩🇪");
+        assert_eq!(prefix(flags, 5), flags);
+    }
+
+    #[test]
+    fn never_cuts_to_nothing() {
+        // Runaway combining marks at the start are cut mid-cluster rather than emptied.
+        assert_eq!(prefix("\u{301}\u{301}\u{301}", 2), "\u{301}\u{301}");
+        assert_eq!(prefix("🇩🇪", 1), "🇩");
+    }
+}
@@ -0,0 +1,141 @@
+//! Per-workspace, per-month usage accounting for internal chargeback.
+//!
+//! Cost is estimated when each run is recorded, using the prices configured at that time:
+//! `LLM_PRICE_PER_1K_INPUT_TOKENS`, `LLM_PRICE_PER_1K_OUTPUT_TOKENS` and
+//! `SEARCH_PRICE_PER_CALL` (all USD, default 0).
+
+use golem_rust::Schema;
+
+use crate::clock;
+use crate::config;
+
+/// Provider usage of a single research run.
+#[derive(Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct RunUsage {
+    pub llm_calls: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub search_calls: u32,
+}
+
+impl RunUsage {
+    pub fn add_llm_response(&mut self, usage: Option<&golem_rust::golem_ai::golem::llm::llm::Usage>) {
+        self.llm_calls += 1;
+        if let Some(usage) = usage {
+            self.input_tokens += u64::from(usage.input_tokens.unwrap_or(0));
+            self.output_tokens += u64::from(usage.output_tokens.unwrap_or(0));
+        }
+    }
+
+    pub fn estimated_cost_usd(&self) -> f64 {
+        let input = self.input_tokens as f64 / 1000.0 * price("LLM_PRICE_PER_1K_INPUT_TOKENS");
+        let output = self.output_tokens as f64 / 1000.0 * price("LLM_PRICE_PER_1K_OUTPUT_TOKENS");
+        let search = f64::from(self.search_calls) * price("SEARCH_PRICE_PER_CALL");
+        input + output + search
+    }
+}
+
+#[derive(Clone, Debug, Default, Schema, serde::Serialize, serde::Deserialize)]
+pub struct UsageReport {
+    pub workspace: String,
+    /// `YYYY-MM` (UTC).
+    pub period: String,
+    pub research_count: u32,
+    pub llm_calls: u32,
+    pub search_calls: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    pub estimated_cost_usd: f64,
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct UsageLedger {
+    buckets: Vec<UsageReport>,
+}
+
+impl UsageLedger {
+    /// Records a finished research run and its provider usage.
+    pub fn record(&mut self, workspace: &str, at: u64, run: &RunUsage) {
+        self.add(workspace, at, run);
+        self.count_research(workspace, at);
+    }
+
+    /// Adds provider usage without counting a research run, for work done in several steps.
+    pub fn add(&mut self, workspace: &str, at: u64, run: &RunUsage) {
+        let bucket = self.bucket_mut(workspace, at);
+        bucket.llm_calls += run.llm_calls;
+        bucket.search_calls += run.search_calls;
+        bucket.input_tokens += run.input_tokens;
+        bucket.output_tokens += run.output_tokens;
+        bucket.total_tokens = bucket.input_tokens + bucket.output_tokens;
+        bucket.estimated_cost_usd += run.estimated_cost_usd();
+    }
+
+    pub fn count_research(&mut self, workspace: &str, at: u64) {
+        self.bucket_mut(workspace, at).research_count += 1;
+    }
+
+    fn bucket_mut(&mut self, workspace: &str, at: u64) -> &mut UsageReport {
+        let period = clock::month_key(at);
+        let index = match self
+            .buckets
+            .iter()
+            .position(|b| b.workspace == workspace && b.period == period)
+        {
+            Some(index) => index,
+            None => {
+                self.buckets.push(UsageReport {
+                    workspace: workspace.to_string(),
+                    period,
+                    ..UsageReport::default()
+                });
+                self.buckets.len() - 1
+            }
+        };
+        &mut self.buckets[index]
+    }
+
+    /// Usage for one workspace and `YYYY-MM` period; an all-zero report if nothing ran.
+    pub fn report(&self, workspace: &str, period: &str) -> UsageReport {
+        self.buckets
+            .iter()
+            .find(|b| b.workspace == workspace && b.period == period)
+            .cloned()
+            .unwrap_or_else(|| UsageReport {
+                workspace: workspace.to_string(),
+                period: period.to_string(),
+                ..UsageReport::default()
+            })
+    }
+}
+
+/// Validates a `YYYY-MM` period; empty means the current month.
+pub fn normalize_period(period: &str, now: u64) -> Result<String, String> {
+    let period = period.trim();
+    if period.is_empty() {
+        return Ok(clock::month_key(now));
+    }
+
+    let valid = match period.split_once('-') {
+        Some((year, month)) => {
+            year.len() == 4
+                && year.chars().all(|c| c.is_ascii_digit())
+                && month.len() == 2
+                && month.parse::<u32>().is_ok_and(|m| (1..=12).contains(&m))
+        }
+        None => false,
+    };
+
+    if valid {
+        Ok(period.to_string())
+    } else {
+        Err(format!("Invalid period {period:?}: expected YYYY-MM"))
+    }
+}
+
+fn price(var: &str) -> f64 {
+    config::env_parse::<f64>(var)
+        .filter(|p| p.is_finite() && *p >= 0.0)
+        .unwrap_or(0.0)
+}
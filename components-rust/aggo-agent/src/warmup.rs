@@ -0,0 +1,26 @@
+//! LLM warmup: a tiny completion sent before a model's first real request, so a cold Ollama
+//! model load (often tens of seconds) doesn't eat into a research call's timeout.
+//!
+//! A worker runs its invocations one at a time, so the first request needing a model warms it
+//! and requests queued behind it find it warm: concurrent first requests cause one model load,
+//! not one each. `LLM_WARMUP` turns it on or off; it defaults to on with Ollama only.
+
+use golem_rust::Schema;
+
+use crate::ollama;
+
+/// Asks for a one-word answer so the completion itself costs next to nothing.
+pub const PROMPT: &str = "Reply with the single word \"ready\".";
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct LlmWarmup {
+    pub model: String,
+    /// How long the warmup completion took; mostly model load time when the model was cold.
+    pub duration_ms: u64,
+}
+
+pub fn enabled() -> bool {
+    std::env::var("LLM_WARMUP")
+        .map(|v| !matches!(v.trim().to_ascii_lowercase().as_str(), "false" | "0" | "off" | "no"))
+        .unwrap_or_else(|_| ollama::is_configured())
+}
@@ -0,0 +1,258 @@
+//! Page-level monitoring: watched URLs are re-fetched on an interval and their extracted
+//! text is diffed against the previous snapshot. Changes get an LLM summary; unchanged pages
+//! cost one fetch and no LLM call.
+//!
+//! Checks run from `run_due_schedules`, in the same background lane as scheduled research.
+//! They are conditional GETs with the validators (ETag / Last-Modified) of the response the
+//! snapshot came from, so a server that supports them answers an unchanged page with a bodiless
+//! 304.
+
+use golem_rust::Schema;
+
+use crate::fetch::Validators;
+use crate::schema::SCHEMA_VERSION;
+use crate::truncate;
+
+const MIN_INTERVAL_MINUTES: u32 = 5;
+const MAX_INTERVAL_MINUTES: u32 = 30 * 24 * 60;
+/// Snapshot text kept per watch; the rest of a very long page is not compared.
+const MAX_SNAPSHOT_CHARS: usize = 100_000;
+/// Changed lines shown to the model per side.
+const MAX_DIFF_LINES: usize = 80;
+const MAX_RECENT_CHANGES: usize = 10;
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct PageChange {
+    pub detected_at: u64,
+    pub added_lines: u32,
+    pub removed_lines: u32,
+    pub summary: String,
+}
+
+#[derive(Clone, Debug, Schema, serde::Serialize, serde::Deserialize)]
+pub struct WatchInfo {
+    /// See `schema`; version 0 is a record from before versioning.
+    #[serde(default)]
+    pub schema_version: u32,
+    pub id: String,
+    pub workspace: String,
+    pub url: String,
+    pub interval_minutes: u32,
+    pub next_check_at: u64,
+    pub last_checked_at: Option<u64>,
+    /// Why the last check failed, e.g. the page was unreachable; cleared by the next success.
+    pub last_error: Option<String>,
+    /// Most recent changes first, up to 10.
+    pub recent_changes: Vec<PageChange>,
+}
+
+/// Line-level difference between two snapshots.
+#[derive(Clone, Debug, Default)]
+pub struct TextDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl TextDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct WatchBook {
+    watches: Vec<Watch>,
+    next_id: u64,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct Watch {
+    info: WatchInfo,
+    snapshot: String,
+    /// Of the response `snapshot` was taken from.
+    #[serde(default)]
+    validators: Validators,
+}
+
+impl WatchBook {
+    pub fn add(
+        &mut self,
+        workspace: &str,
+        url: &str,
+        interval_minutes: u32,
+        snapshot: &str,
+        validators: Validators,
+        now: u64,
+    ) -> WatchInfo {
+        self.next_id += 1;
+        let info = WatchInfo {
+            schema_version: SCHEMA_VERSION,
+            id: format!("watch-{}", self.next_id),
+            workspace: workspace.to_string(),
+            url: url.to_string(),
+            interval_minutes,
+            next_check_at: now + u64::from(interval_minutes) * 60,
+            last_checked_at: Some(now),
+            last_error: None,
+            recent_changes: Vec::new(),
+        };
+        self.watches.push(Watch {
+            info: info.clone(),
+            snapshot: cap(snapshot),
+            validators,
+        });
+        info
+    }
+
+    pub fn list(&self, workspace: &str) -> Vec<WatchInfo> {
+        self.watches
+            .iter()
+            .filter(|w| w.info.workspace == workspace)
+            .map(|w| w.info.clone())
+            .collect()
+    }
+
+    pub fn remove(&mut self, workspace: &str, id: &str) -> bool {
+        let before = self.watches.len();
+        self.watches
+            .retain(|w| !(w.info.id == id && w.info.workspace == workspace));
+        self.watches.len() != before
+    }
+
+    /// Due watches, most overdue first.
+    pub fn due(&self, now: u64) -> Vec<WatchInfo> {
+        let mut due: Vec<WatchInfo> = self
+            .watches
+            .iter()
+            .filter(|w| w.info.next_check_at <= now)
+            .map(|w| w.info.clone())
+            .collect();
+        due.sort_by_key(|w| w.next_check_at);
+        due
+    }
+
+    pub fn next_check_at(&self) -> Option<u64> {
+        self.watches.iter().map(|w| w.info.next_check_at).min()
+    }
+
+    /// Diff of `text` against the watch's current snapshot.
+    pub fn diff(&self, id: &str, text: &str) -> TextDiff {
+        match self.watches.iter().find(|w| w.info.id == id) {
+            Some(watch) => diff_lines(&watch.snapshot, &cap(text)),
+            None => TextDiff::default(),
+        }
+    }
+
+    pub fn validators(&self, id: &str) -> Validators {
+        self.watches
+            .iter()
+            .find(|w| w.info.id == id)
+            .map(|w| w.validators.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_snapshot(&mut self, id: &str, text: &str, validators: Validators) {
+        if let Some(watch) = self.find_by_id(id) {
+            watch.snapshot = cap(text);
+            watch.validators = validators;
+        }
+    }
+
+    /// Records a finished check, successful or not, and moves the watch to its next slot.
+    pub fn record_check(&mut self, id: &str, now: u64, outcome: Result<Option<PageChange>, String>) {
+        let Some(watch) = self.find_by_id(id) else {
+            return;
+        };
+        watch.info.last_checked_at = Some(now);
+        watch.info.next_check_at = now + u64::from(watch.info.interval_minutes) * 60;
+        match outcome {
+            Ok(change) => {
+                watch.info.last_error = None;
+                if let Some(change) = change {
+                    watch.info.recent_changes.insert(0, change);
+                    watch.info.recent_changes.truncate(MAX_RECENT_CHANGES);
+                }
+            }
+            Err(err) => watch.info.last_error = Some(err),
+        }
+    }
+
+    fn find_by_id(&mut self, id: &str) -> Option<&mut Watch> {
+        self.watches.iter_mut().find(|w| w.info.id == id)
+    }
+}
+
+pub fn validate_interval(interval_minutes: u32) -> Result<u32, String> {
+    if (MIN_INTERVAL_MINUTES..=MAX_INTERVAL_MINUTES).contains(&interval_minutes) {
+        Ok(interval_minutes)
+    } else {
+        Err(format!(
+            "Interval must be between {MIN_INTERVAL_MINUTES} minutes and {} days",
+            MAX_INTERVAL_MINUTES / (24 * 60)
+        ))
+    }
+}
+
+/// Lines added and removed between two snapshots, compared as multisets so reordered but
+/// otherwise unchanged lines don't count as changes.
+pub fn diff_lines(old: &str, new: &str) -> TextDiff {
+    let old_lines: Vec<&str> = old.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let new_lines: Vec<&str> = new.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+
+    TextDiff {
+        added: unmatched(&new_lines, &old_lines),
+        removed: unmatched(&old_lines, &new_lines),
+    }
+}
+
+/// Lines of `lines` left over after pairing each with an equal line of `other`.
+fn unmatched(lines: &[&str], other: &[&str]) -> Vec<String> {
+    let mut remaining: Vec<&str> = other.to_vec();
+    remaining.sort_unstable();
+    let mut used = vec![false; remaining.len()];
+    lines
+        .iter()
+        .filter(|line| {
+            let start = remaining.partition_point(|l| l < *line);
+            let slot = (start..remaining.len())
+                .take_while(|&i| remaining[i] == **line)
+                .find(|&i| !used[i]);
+            match slot {
+                Some(i) => {
+                    used[i] = true;
+                    false
+                }
+                None => true,
+            }
+        })
+        .map(|line| line.to_string())
+        .collect()
+}
+
+pub fn change_prompt(url: &str, diff: &TextDiff) -> String {
+    let section = |lines: &[String]| {
+        let mut shown: Vec<String> = lines.iter().take(MAX_DIFF_LINES).map(|l| format!("- {l}")).collect();
+        if lines.len() > MAX_DIFF_LINES {
+            shown.push(format!("(and {} more lines)", lines.len() - MAX_DIFF_LINES));
+        }
+        if shown.is_empty() {
+            "(none)".to_string()
+        } else {
+            shown.join("\n")
+        }
+    };
+
+    format!(
+        "The web page {url} changed since it was last checked. Below are the lines that were removed and added.\n\
+         Summarize what changed in 2 to 4 sentences for someone monitoring this page. Focus on substantive changes \
+         (facts, prices, dates, policies, announcements) and ignore cosmetic ones such as timestamps or navigation; \
+         if every change is cosmetic, say so in one sentence.\n\n\
+         Removed:\n{}\n\nAdded:\n{}",
+        section(&diff.removed),
+        section(&diff.added)
+    )
+}
+
+fn cap(text: &str) -> String {
+    truncate::prefix(text, MAX_SNAPSHOT_CHARS).to_string()
+}
@@ -0,0 +1,113 @@
+//! Wikipedia grounding: with `ResearchOptions.use_wikipedia`, the summary of the topic's
+//! Wikipedia article is fetched before web search and placed first among the sources, so the
+//! model has an encyclopedic anchor for niche topics where web results are thin or off-target.
+//!
+//! The topic is looked up as an article title first (redirects followed) and through Wikipedia's
+//! title search when there is no such article. Disambiguation pages are skipped. The wiki used
+//! is the one for the request's `language`.
+
+use std::time::Duration;
+
+use crate::http_cache::ResponseCache;
+use crate::http_util;
+use crate::{usage, SearchResult};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const USER_AGENT: &str = "aggo-research-agent/0.1 (https://github.com/haymant/AGGO-AGENT)";
+
+/// The article summary for `topic` as a source, or `None` when Wikipedia has no article for it.
+pub fn summary(
+    topic: &str,
+    language: &str,
+    cache: &ResponseCache,
+    now: u64,
+    run_usage: &mut usage::RunUsage,
+) -> Result<Option<SearchResult>, String> {
+    run_usage.search_calls += 1;
+    if let Some(result) = page_summary(topic, language, cache, now)? {
+        return Ok(Some(result));
+    }
+    match search_title(topic, language, cache, now)? {
+        Some(title) => page_summary(&title, language, cache, now),
+        None => Ok(None),
+    }
+}
+
+fn page_summary(title: &str, language: &str, cache: &ResponseCache, now: u64) -> Result<Option<SearchResult>, String> {
+    let url = format!(
+        "https://{language}.wikipedia.org/api/rest_v1/page/summary/{}",
+        encode_title(title)
+    );
+    let request = || {
+        golem_wasi_http::Client::new()
+            .get(&url)
+            .header("User-Agent", USER_AGENT)
+            .timeout(http_util::timeout(TIMEOUT))
+    };
+    let (status, body) = cache
+        .send(&url, request, now)
+        .map_err(|err| format!("Wikipedia summary request failed: {err}"))?;
+    if status == 404 {
+        return Ok(None);
+    }
+    if !(200..300).contains(&status) {
+        return Err(format!("Wikipedia summary request failed: HTTP {status}"));
+    }
+    let body: serde_json::Value =
+        serde_json::from_str(&body).map_err(|err| format!("Wikipedia returned an invalid summary: {err}"))?;
+
+    if body["type"].as_str() == Some("disambiguation") {
+        return Ok(None);
+    }
+    let Some(extract) = body["extract"].as_str().map(str::trim).filter(|e| !e.is_empty()) else {
+        return Ok(None);
+    };
+    let title = body["title"].as_str().unwrap_or(title);
+    let snippet = match body["description"].as_str().filter(|d| !d.trim().is_empty()) {
+        Some(description) => format!("{description}. {extract}"),
+        None => extract.to_string(),
+    };
+    Ok(Some(SearchResult {
+        url: body["content_urls"]["desktop"]["page"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or(url),
+        title: format!("{title} - Wikipedia"),
+        snippet,
+    }))
+}
+
+/// The best-matching article title for `topic`.
+fn search_title(topic: &str, language: &str, cache: &ResponseCache, now: u64) -> Result<Option<String>, String> {
+    let url = format!("https://{language}.wikipedia.org/w/rest.php/v1/search/title");
+    let request = || {
+        golem_wasi_http::Client::new()
+            .get(&url)
+            .query(&[("q", topic), ("limit", "1")])
+            .header("User-Agent", USER_AGENT)
+            .timeout(http_util::timeout(TIMEOUT))
+    };
+    let (status, body) = cache
+        .send(&format!("{url}?q={topic}&limit=1"), request, now)
+        .map_err(|err| format!("Wikipedia title search failed: {err}"))?;
+    if !(200..300).contains(&status) {
+        return Err(format!("Wikipedia title search failed: HTTP {status}"));
+    }
+    let body: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|err| format!("Wikipedia title search returned invalid JSON: {err}"))?;
+    Ok(body["pages"][0]["key"].as_str().map(str::to_string))
+}
+
+/// An article title as a URL path segment: spaces become underscores, everything outside the
+/// unreserved set is percent-encoded.
+fn encode_title(title: &str) -> String {
+    title
+        .trim()
+        .replace(' ', "_")
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
@@ -0,0 +1,26 @@
+//! Workspaces partition reports and schedules so one deployed agent can serve several teams.
+//! Callers supply the workspace ID; calls without one use `DEFAULT_WORKSPACE`.
+
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+const MAX_WORKSPACE_LEN: usize = 64;
+
+/// Trims and validates a caller-supplied workspace ID. Empty means the default workspace.
+pub fn normalize(workspace: &str) -> Result<String, String> {
+    let workspace = workspace.trim();
+    if workspace.is_empty() {
+        return Ok(DEFAULT_WORKSPACE.to_string());
+    }
+    if workspace.len() > MAX_WORKSPACE_LEN {
+        return Err(format!("Workspace ID must be at most {MAX_WORKSPACE_LEN} characters"));
+    }
+    if !workspace
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "Invalid workspace ID {workspace:?}: use letters, digits, '-', '_' or '.'"
+        ));
+    }
+    Ok(workspace.to_string())
+}